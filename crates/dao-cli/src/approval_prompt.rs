@@ -0,0 +1,161 @@
+use std::env;
+use std::io;
+use std::io::IsTerminal;
+use std::io::Write;
+
+use dao_core::reducer::diff_artifact_text;
+use dao_core::state::DiffArtifact;
+use dao_core::state::DiffLineKind;
+
+/// Diff lines shown before the approval prompt; the rest is available via `dao cat`.
+const APPROVAL_DIFF_PREVIEW_LINES: usize = 40;
+
+/// Environment variable that approves every prompt when stdin is not a terminal.
+const AUTO_APPROVE_ENV: &str = "DAO_AUTO_APPROVE";
+
+/// Where the answer to an approval prompt comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalSource {
+    /// `--approve` (`true`) or `--deny` (`false`).
+    Flag(bool),
+    /// No terminal, but `DAO_AUTO_APPROVE=1` is set.
+    AutoApprove,
+    /// No terminal and no other answer, so the prompt is denied instead of
+    /// waiting on stdin.
+    NoTerminal,
+    /// Ask on stdin.
+    Prompt,
+}
+
+/// Decides how to answer an approval prompt. An explicit flag always wins;
+/// without one, a terminal is asked and anything else is denied unless
+/// `auto_approve` (the value of `DAO_AUTO_APPROVE`) is `1`.
+pub fn approval_source(
+    stdin_is_tty: bool,
+    flag: Option<bool>,
+    auto_approve: Option<&str>,
+) -> ApprovalSource {
+    if let Some(approve) = flag {
+        return ApprovalSource::Flag(approve);
+    }
+    if stdin_is_tty {
+        return ApprovalSource::Prompt;
+    }
+    if auto_approve.map(str::trim) == Some("1") {
+        ApprovalSource::AutoApprove
+    } else {
+        ApprovalSource::NoTerminal
+    }
+}
+
+/// Answers the approval prompt for `tool`, printing how the decision was made
+/// or showing the pending diff and reading a `y/N` answer from stdin.
+pub fn prompt_approval(
+    tool: &str,
+    diff: Option<&DiffArtifact>,
+    preset: Option<bool>,
+) -> io::Result<bool> {
+    let auto_approve = env::var(AUTO_APPROVE_ENV).ok();
+    match approval_source(io::stdin().is_terminal(), preset, auto_approve.as_deref()) {
+        ApprovalSource::Flag(approve) => {
+            let (decision, flag) = if approve {
+                ("approved", "--approve")
+            } else {
+                ("denied", "--deny")
+            };
+            println!("approval required for {tool}: {decision} ({flag})");
+            return Ok(approve);
+        }
+        ApprovalSource::AutoApprove => {
+            println!("approval required for {tool}: approved ({AUTO_APPROVE_ENV}=1)");
+            return Ok(true);
+        }
+        ApprovalSource::NoTerminal => {
+            println!(
+                "approval required for {tool}: denied (stdin is not a terminal; pass --approve or set {AUTO_APPROVE_ENV}=1)"
+            );
+            return Ok(false);
+        }
+        ApprovalSource::Prompt => {}
+    }
+    if let Some(diff) = diff.filter(|diff| !diff.files.is_empty()) {
+        println!("pending changes ({} files):", diff.files.len());
+        for file in &diff.files {
+            let (added, removed) = file.hunks.iter().flat_map(|hunk| hunk.lines.iter()).fold(
+                (0, 0),
+                |(added, removed), line| match line.kind {
+                    DiffLineKind::Add => (added + 1, removed),
+                    DiffLineKind::Remove => (added, removed + 1),
+                    DiffLineKind::Context => (added, removed),
+                },
+            );
+            println!("  {}  +{added} -{removed}", file.path);
+        }
+        let text = diff_artifact_text(diff);
+        let lines = text.lines().collect::<Vec<_>>();
+        for line in lines.iter().take(APPROVAL_DIFF_PREVIEW_LINES) {
+            println!("{line}");
+        }
+        if lines.len() > APPROVAL_DIFF_PREVIEW_LINES {
+            println!(
+                "... {} more lines (dao cat --artifact diff)",
+                lines.len() - APPROVAL_DIFF_PREVIEW_LINES
+            );
+        }
+    }
+    print!("approval required for {tool} [y/N]: ");
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(matches!(line.trim(), "y" | "Y" | "yes" | "YES"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_answer_the_prompt_with_or_without_a_terminal() {
+        for stdin_is_tty in [true, false] {
+            for auto_approve in [None, Some("1"), Some("0")] {
+                assert_eq!(
+                    approval_source(stdin_is_tty, Some(true), auto_approve),
+                    ApprovalSource::Flag(true)
+                );
+                assert_eq!(
+                    approval_source(stdin_is_tty, Some(false), auto_approve),
+                    ApprovalSource::Flag(false)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_terminal_without_a_flag_is_asked() {
+        for auto_approve in [None, Some("1"), Some("0")] {
+            assert_eq!(
+                approval_source(true, None, auto_approve),
+                ApprovalSource::Prompt
+            );
+        }
+    }
+
+    #[test]
+    fn without_a_terminal_only_auto_approve_one_approves() {
+        assert_eq!(
+            approval_source(false, None, Some("1")),
+            ApprovalSource::AutoApprove
+        );
+        assert_eq!(
+            approval_source(false, None, Some(" 1\n")),
+            ApprovalSource::AutoApprove
+        );
+        for auto_approve in [None, Some(""), Some("0"), Some("true"), Some("yes")] {
+            assert_eq!(
+                approval_source(false, None, auto_approve),
+                ApprovalSource::NoTerminal,
+                "{auto_approve:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use dao_core::approval_summary::approval_summary_file_name;
+use dao_core::approval_summary::approval_summary_markdown;
+use dao_core::persistence::PersistedShellEvent;
+use dao_core::persistence::ShellEventStore;
+use dao_core::state::ShellState;
+
+/// Renders the approval summary of `run_id` from the repository's event log
+/// and writes it to `.dao/approval-<run>.md`.
+pub fn write_approval_summary(
+    repo: &Path,
+    state: &ShellState,
+    run_id: u64,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dao_dir = repo.join(".dao");
+    let store = ShellEventStore::open(dao_dir.join("workflow-events.jsonl"))?;
+    let summary = approval_summary_markdown(state, run_id, &store.load()?);
+    let path = dao_dir.join(approval_summary_file_name(run_id));
+    fs::write(&path, summary)?;
+    Ok(path)
+}
+
+/// Appends `ApprovalReopened` for a request reopened in the UI, so `dao resume`
+/// prompts for it again.
+pub fn record_approval_reopened(
+    repo: &Path,
+    request_id: String,
+    run_id: u64,
+    timestamp_ms: u64,
+) -> std::io::Result<u64> {
+    let store = ShellEventStore::open(repo.join(".dao").join("workflow-events.jsonl"))?;
+    store.append(PersistedShellEvent::ApprovalReopened {
+        request_id,
+        run_id,
+        timestamp_ms,
+    })
+}
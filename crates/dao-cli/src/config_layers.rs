@@ -0,0 +1,796 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use dao_core::config::Config;
+use toml::Table;
+use toml::Value;
+
+/// Tables that a layer replaces wholesale instead of merging key by key:
+/// `auto_open` drops the default states it leaves out, and `chat.context` is a
+/// tagged enum whose fields depend on the strategy.
+const REPLACED_TABLES: &[&str] = &["auto_open", "chat.context"];
+
+/// Keys a repository's `.dao/config.toml` cannot set: they decide what runs
+/// without approval, and a cloned repository is not trusted to choose that.
+/// `risk` belongs here because its weights and thresholds set each gate's risk.
+const TRUSTED_ONLY_KEYS: &[&str] = &["tools", "policy.tiers", "risk"];
+
+/// Environment variables layered over the config files.
+const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("DAO_MODEL", "model.default_model"),
+    ("DAO_PROVIDER", "model.default_provider"),
+];
+
+/// Where the effective value of a config key came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    User(PathBuf),
+    Repo(PathBuf),
+    Env(&'static str),
+    /// The file given with `--config`, read in place of the user file.
+    Explicit(PathBuf),
+    Flag(&'static str),
+    /// Probed from the tools installed on this machine.
+    Detected,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::User(path) => write!(f, "user {}", path.display()),
+            Self::Repo(path) => write!(f, "repo {}", path.display()),
+            Self::Env(name) => write!(f, "env {name}"),
+            Self::Explicit(path) => write!(f, "--config {}", path.display()),
+            Self::Flag(flag) => write!(f, "flag {flag}"),
+            Self::Detected => write!(f, "detected"),
+        }
+    }
+}
+
+/// Command-line values that sit on top of every other layer.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    /// Config file to read instead of the user file, from `--config`.
+    pub config: Option<PathBuf>,
+    pub model: Option<String>,
+    pub provider: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    pub config: Config,
+    /// Effective value of every leaf key, by dotted path.
+    pub values: BTreeMap<String, Value>,
+    /// Layer that set each key; keys absent here still hold their default.
+    pub sources: BTreeMap<String, ConfigSource>,
+    /// Config files that were read, lowest layer first.
+    pub files: Vec<PathBuf>,
+    /// Trusted-only keys dropped from the repo layer, as `file: key`.
+    pub ignored: Vec<String>,
+}
+
+impl ResolvedConfig {
+    pub fn source_of(&self, key: &str) -> &ConfigSource {
+        self.sources.get(key).unwrap_or(&ConfigSource::Default)
+    }
+
+    /// Provider a session starts with: a configured one, else `saved` (the
+    /// last `/provider` choice), else the detected one. The flag is set only
+    /// when the detected provider is used.
+    pub fn session_provider(&self, saved: Option<&str>) -> Option<(String, bool)> {
+        let provider = self.config.model.default_provider.as_deref();
+        if self.source_of("model.default_provider") != &ConfigSource::Detected {
+            return provider.map(|provider| (provider.to_string(), false));
+        }
+        match saved {
+            Some(saved) => Some((saved.to_string(), false)),
+            None => provider.map(|provider| (provider.to_string(), true)),
+        }
+    }
+}
+
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("dao").join("config.toml"))
+}
+
+pub fn repo_config_path(repo: &Path) -> PathBuf {
+    repo.join(".dao").join("config.toml")
+}
+
+/// Resolves the config as defaults → user file → repo `.dao/config.toml` →
+/// `DAO_*` environment → `--config` file → flags, later layers winning. A
+/// `--config` file replaces the user file rather than sitting on top of it.
+pub fn resolve_config(
+    repo: Option<&Path>,
+    overrides: &ConfigOverrides,
+) -> Result<ResolvedConfig, Box<dyn std::error::Error>> {
+    let env_values: Vec<_> = ENV_OVERRIDES
+        .iter()
+        .filter_map(|(name, key)| {
+            let value = env::var(name).ok().filter(|value| !value.is_empty())?;
+            Some((*name, *key, value))
+        })
+        .collect();
+    let mut resolved = resolve_config_from(user_config_path(), repo, &env_values, overrides)?;
+    for ignored in &resolved.ignored {
+        eprintln!("warning: ignoring {ignored}; set it in the user config or --config");
+    }
+    apply_detected_provider(&mut resolved, installed_provider());
+    Ok(resolved)
+}
+
+/// Fills in `detected` as the provider when no layer set one.
+fn apply_detected_provider(resolved: &mut ResolvedConfig, detected: Option<&str>) {
+    if resolved.config.model.default_provider.is_some() {
+        return;
+    }
+    if let Some(provider) = detected {
+        let key = "model.default_provider".to_string();
+        resolved.config.model.default_provider = Some(provider.to_string());
+        resolved
+            .values
+            .insert(key.clone(), Value::String(provider.to_string()));
+        resolved.sources.insert(key, ConfigSource::Detected);
+    }
+}
+
+/// `resolve_config` with the user file location and the set `DAO_*`
+/// variables, as `(variable, key, value)`, passed in.
+fn resolve_config_from(
+    user_path: Option<PathBuf>,
+    repo: Option<&Path>,
+    env_values: &[(&'static str, &'static str, String)],
+    overrides: &ConfigOverrides,
+) -> Result<ResolvedConfig, Box<dyn std::error::Error>> {
+    let mut layers = Vec::new();
+    if let Some(path) = user_path
+        .filter(|_| overrides.config.is_none())
+        .filter(|path| path.exists())
+    {
+        layers.push((ConfigSource::User(path.clone()), read_layer(&path)?));
+    }
+    let mut ignored = Vec::new();
+    if let Some(path) = repo.map(repo_config_path).filter(|path| path.exists()) {
+        let mut layer = read_layer(&path)?;
+        for key in TRUSTED_ONLY_KEYS {
+            if remove_key(&mut layer, key) {
+                ignored.push(format!("{}: {key}", path.display()));
+            }
+        }
+        layers.push((ConfigSource::Repo(path), layer));
+    }
+    for (name, key, value) in env_values {
+        layers.push((
+            ConfigSource::Env(name),
+            single_key_layer(key, value.clone()),
+        ));
+    }
+    if let Some(path) = &overrides.config {
+        let layer = read_layer(path).map_err(|err| format!("--config {err}"))?;
+        layers.push((ConfigSource::Explicit(path.clone()), layer));
+    }
+    if let Some(model) = &overrides.model {
+        layers.push((
+            ConfigSource::Flag("--model"),
+            single_key_layer("model.default_model", model.clone()),
+        ));
+    }
+    if let Some(provider) = &overrides.provider {
+        layers.push((
+            ConfigSource::Flag("--provider"),
+            single_key_layer("model.default_provider", provider.clone()),
+        ));
+    }
+    let files = layers
+        .iter()
+        .filter_map(|(source, _)| match source {
+            ConfigSource::User(path) | ConfigSource::Repo(path) | ConfigSource::Explicit(path) => {
+                Some(path.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    let mut resolved = resolve_layers(layers)?;
+    resolved.files = files;
+    resolved.ignored = ignored;
+    Ok(resolved)
+}
+
+/// Which chat providers this machine can reach.
+pub trait ProviderAvailability {
+    fn ollama(&self) -> bool;
+    fn codex(&self) -> bool;
+    fn gemini_key(&self) -> bool;
+}
+
+/// Looks for the provider CLIs on `PATH` and for `GEMINI_API_KEY`, without
+/// running anything.
+struct InstalledProviders;
+
+impl ProviderAvailability for InstalledProviders {
+    fn ollama(&self) -> bool {
+        on_path("ollama")
+    }
+
+    fn codex(&self) -> bool {
+        on_path("codex")
+    }
+
+    fn gemini_key(&self) -> bool {
+        env::var("GEMINI_API_KEY").is_ok_and(|key| !key.trim().is_empty())
+    }
+}
+
+/// First available provider, in the order `ollama`, `codex`, `gemini`.
+pub fn detect_provider(available: &impl ProviderAvailability) -> Option<&'static str> {
+    if available.ollama() {
+        Some("ollama")
+    } else if available.codex() {
+        Some("codex")
+    } else if available.gemini_key() {
+        Some("gemini")
+    } else {
+        None
+    }
+}
+
+/// `detect_provider` for this machine, probed once per process.
+pub fn installed_provider() -> Option<&'static str> {
+    static INSTALLED: OnceLock<Option<&'static str>> = OnceLock::new();
+    *INSTALLED.get_or_init(|| detect_provider(&InstalledProviders))
+}
+
+fn on_path(program: &str) -> bool {
+    let Some(paths) = env::var_os("PATH") else {
+        return false;
+    };
+    env::split_paths(&paths).any(|dir| {
+        let candidate = dir.join(program);
+        candidate.is_file() || (cfg!(windows) && candidate.with_extension("exe").is_file())
+    })
+}
+
+fn read_layer(path: &Path) -> Result<Table, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    content
+        .parse::<Table>()
+        .map_err(|err| format!("{}: {err}", path.display()).into())
+}
+
+/// Removes the dotted `key` from `table`, reporting whether it was set.
+fn remove_key(table: &mut Table, key: &str) -> bool {
+    match key.split_once('.') {
+        Some((parent, rest)) => match table.get_mut(parent) {
+            Some(Value::Table(inner)) => remove_key(inner, rest),
+            _ => false,
+        },
+        None => table.remove(key).is_some(),
+    }
+}
+
+fn single_key_layer(key: &str, value: String) -> Table {
+    let mut table = Table::new();
+    let mut parts = key.rsplit('.');
+    let leaf = parts.next().unwrap_or(key);
+    table.insert(leaf.to_string(), Value::String(value));
+    for part in parts {
+        let mut parent = Table::new();
+        parent.insert(part.to_string(), Value::Table(table));
+        table = parent;
+    }
+    table
+}
+
+pub fn resolve_layers(
+    layers: Vec<(ConfigSource, Table)>,
+) -> Result<ResolvedConfig, Box<dyn std::error::Error>> {
+    let mut merged = match Value::try_from(Config::default())? {
+        Value::Table(table) => table,
+        _ => Table::new(),
+    };
+    let mut sources = BTreeMap::new();
+    for (source, layer) in layers {
+        merge_table(&mut merged, layer, "", &source, &mut sources);
+    }
+    let config: Config = Value::Table(merged.clone()).try_into()?;
+    let mut values = BTreeMap::new();
+    flatten(&merged, "", &mut values);
+    Ok(ResolvedConfig {
+        config,
+        values,
+        sources,
+        files: Vec::new(),
+        ignored: Vec::new(),
+    })
+}
+
+/// Parses the config file at `path` over the defaults, without reading any
+/// other layer. Keys the config does not know, such as a misspelled section,
+/// are errors here even though normal loading ignores them.
+pub fn validate_config_file(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+    let layer = read_layer(path)?;
+    let config = resolve_layers(vec![(
+        ConfigSource::Repo(path.to_path_buf()),
+        layer.clone(),
+    )])
+    .map(|resolved| resolved.config)
+    .map_err(|err| format!("{}: {err}", path.display()))?;
+    let known = match Value::try_from(&config)? {
+        Value::Table(table) => table,
+        _ => Table::new(),
+    };
+    let mut unknown = Vec::new();
+    unknown_keys(&layer, &known, "", &mut unknown);
+    if !unknown.is_empty() {
+        return Err(format!("{}: unknown key {}", path.display(), unknown.join(", ")).into());
+    }
+    Ok(config)
+}
+
+/// Collects the dotted keys of `layer` missing from `known`, the parsed config
+/// serialized back. Every key the config reads survives that round trip.
+fn unknown_keys(layer: &Table, known: &Table, prefix: &str, out: &mut Vec<String>) {
+    for (key, value) in layer {
+        let path = join_key(prefix, key);
+        match (value, known.get(key)) {
+            (_, None) => out.push(path),
+            (Value::Table(inner), Some(Value::Table(known))) => {
+                unknown_keys(inner, known, &path, out);
+            }
+            (Value::Array(items), Some(Value::Array(known))) => {
+                for (index, (item, known)) in items.iter().zip(known).enumerate() {
+                    if let (Value::Table(item), Value::Table(known)) = (item, known) {
+                        unknown_keys(item, known, &format!("{path}[{index}]"), out);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn merge_table(
+    base: &mut Table,
+    layer: Table,
+    prefix: &str,
+    source: &ConfigSource,
+    sources: &mut BTreeMap<String, ConfigSource>,
+) {
+    for (key, value) in layer {
+        let path = join_key(prefix, &key);
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(table))
+                if !REPLACED_TABLES.contains(&path.as_str()) =>
+            {
+                merge_table(existing, table, &path, source, sources);
+            }
+            (None, Value::Table(table)) if !REPLACED_TABLES.contains(&path.as_str()) => {
+                let mut fresh = Table::new();
+                merge_table(&mut fresh, table, &path, source, sources);
+                base.insert(key, Value::Table(fresh));
+            }
+            (_, value) => {
+                sources.retain(|existing, _| !is_under(existing, &path));
+                sources.insert(path, source.clone());
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn flatten(table: &Table, prefix: &str, out: &mut BTreeMap<String, Value>) {
+    for (key, value) in table {
+        let path = join_key(prefix, key);
+        match value {
+            Value::Table(inner) if !REPLACED_TABLES.contains(&path.as_str()) => {
+                flatten(inner, &path, out);
+            }
+            _ => {
+                out.insert(path, value.clone());
+            }
+        }
+    }
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{prefix}.{key}")
+    }
+}
+
+fn is_under(key: &str, parent: &str) -> bool {
+    key == parent
+        || key
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use dao_core::actions::ShellAction;
+    use dao_core::chat_context::ChatContextStrategy;
+    use dao_core::config::RiskConfig;
+    use dao_core::reducer::reduce;
+    use dao_core::state::{
+        ApprovalGateRequirement, ApprovalRiskClass, ExplanationDepth, PersonaOutputFormat,
+        Personality, PolicyTier, ShellState,
+    };
+
+    use super::*;
+
+    fn layer(text: &str) -> Table {
+        text.parse::<Table>().expect("toml layer")
+    }
+
+    #[test]
+    fn later_layers_win_and_record_their_source() {
+        let user = PathBuf::from("/home/me/.config/dao/config.toml");
+        let repo = PathBuf::from("/work/repo/.dao/config.toml");
+        let resolved = resolve_layers(vec![
+            (
+                ConfigSource::User(user.clone()),
+                layer(
+                    "[model]\ndefault_model = \"llama3\"\ndefault_provider = \"ollama\"\n[risk]\nline_weight = 1.5\n",
+                ),
+            ),
+            (
+                ConfigSource::Repo(repo.clone()),
+                layer("[model]\ndefault_model = \"qwen\"\n[chat.context]\nstrategy = \"diff_only\"\n[auto_open]\nReviewReady = \"Diff\"\n"),
+            ),
+            (
+                ConfigSource::Flag("--model"),
+                single_key_layer("model.default_model", "phi3".to_string()),
+            ),
+        ])
+        .expect("resolve");
+
+        assert_eq!(resolved.config.model.default_model.as_deref(), Some("phi3"));
+        assert_eq!(
+            resolved.config.model.default_provider.as_deref(),
+            Some("ollama")
+        );
+        assert_eq!(resolved.config.risk.line_weight, 1.5);
+        assert_eq!(resolved.config.chat.context, ChatContextStrategy::DiffOnly);
+        assert_eq!(resolved.config.auto_open.0.len(), 1);
+
+        assert_eq!(
+            resolved.source_of("model.default_model"),
+            &ConfigSource::Flag("--model")
+        );
+        assert_eq!(
+            resolved.source_of("model.default_provider"),
+            &ConfigSource::User(user)
+        );
+        assert_eq!(
+            resolved.source_of("chat.context"),
+            &ConfigSource::Repo(repo.clone())
+        );
+        assert_eq!(resolved.source_of("auto_open"), &ConfigSource::Repo(repo));
+        assert_eq!(
+            resolved.source_of("risk.file_weight"),
+            &ConfigSource::Default
+        );
+        assert!(resolved.values.contains_key("risk.destructive_threshold"));
+    }
+
+    #[test]
+    fn persona_section_sets_personality_and_policy_overrides() {
+        let resolved = resolve_layers(vec![(
+            ConfigSource::Repo(PathBuf::from("/work/repo/.dao/config.toml")),
+            layer("[persona]\npersonality = \"Friendly\"\ntier_ceiling = \"Strict\"\nexplanation_depth = \"Brief\"\n"),
+        )])
+        .expect("resolve");
+        let persona = resolved.config.persona.clone();
+        assert_eq!(persona.personality, Some(Personality::Friendly));
+
+        let mut state = ShellState::new(
+            "project".to_string(),
+            persona.personality.unwrap_or(Personality::Pragmatic),
+            resolved.config,
+        );
+        let defaults = state.sm.persona_policy.clone();
+        for action in persona.override_actions() {
+            reduce(&mut state, ShellAction::Runtime(action));
+        }
+
+        assert_eq!(state.sm.personality, Personality::Friendly);
+        assert_eq!(state.sm.persona_policy.tier_ceiling, PolicyTier::Strict);
+        assert_eq!(
+            state.sm.persona_policy.explanation_depth,
+            ExplanationDepth::Brief
+        );
+        assert_eq!(
+            state.sm.persona_policy.output_format,
+            defaults.output_format
+        );
+        assert_eq!(state.sm.persona_policy.tab_order, defaults.tab_order);
+        assert_eq!(
+            state.sm.persona_policy.output_format,
+            PersonaOutputFormat::ImpactFirst
+        );
+    }
+
+    struct Available {
+        ollama: bool,
+        codex: bool,
+        gemini_key: bool,
+    }
+
+    impl ProviderAvailability for Available {
+        fn ollama(&self) -> bool {
+            self.ollama
+        }
+
+        fn codex(&self) -> bool {
+            self.codex
+        }
+
+        fn gemini_key(&self) -> bool {
+            self.gemini_key
+        }
+    }
+
+    #[test]
+    fn detection_prefers_ollama_then_codex_then_gemini() {
+        let cases = [
+            ((true, true, true), Some("ollama")),
+            ((true, false, false), Some("ollama")),
+            ((false, true, true), Some("codex")),
+            ((false, false, true), Some("gemini")),
+            ((false, false, false), None),
+        ];
+        for ((ollama, codex, gemini_key), expected) in cases {
+            let available = Available {
+                ollama,
+                codex,
+                gemini_key,
+            };
+            assert_eq!(
+                detect_provider(&available),
+                expected,
+                "ollama={ollama} codex={codex} gemini_key={gemini_key}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_detected_provider_gives_way_to_a_saved_choice() {
+        let mut detected = resolve_layers(Vec::new()).expect("resolve");
+        apply_detected_provider(&mut detected, Some("ollama"));
+        assert_eq!(
+            detected.session_provider(None),
+            Some(("ollama".to_string(), true))
+        );
+        assert_eq!(
+            detected.session_provider(Some("gemini")),
+            Some(("gemini".to_string(), false))
+        );
+
+        let mut configured = resolve_layers(vec![(
+            ConfigSource::Flag("--provider"),
+            layer("[model]\ndefault_provider = \"codex\"\n"),
+        )])
+        .expect("resolve");
+        apply_detected_provider(&mut configured, Some("ollama"));
+        assert_eq!(
+            configured.session_provider(Some("gemini")),
+            Some(("codex".to_string(), false))
+        );
+
+        let mut nothing = resolve_layers(Vec::new()).expect("resolve");
+        apply_detected_provider(&mut nothing, None);
+        assert_eq!(nothing.session_provider(None), None);
+    }
+
+    #[test]
+    fn validate_accepts_a_good_file_and_reports_a_malformed_one() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let good = dir.path().join("good.toml");
+        fs::write(
+            &good,
+            "[model]\ndefault_model = \"qwen\"\n[risk]\nline_weight = 2.0\n",
+        )
+        .expect("write good");
+        let config = validate_config_file(&good).expect("valid config");
+        assert_eq!(config.model.default_model.as_deref(), Some("qwen"));
+        assert_eq!(config.risk.line_weight, 2.0);
+
+        let syntax = dir.path().join("syntax.toml");
+        fs::write(&syntax, "[model\ndefault_model = \"qwen\"\n").expect("write syntax");
+        let err = validate_config_file(&syntax).expect_err("unclosed table");
+        assert!(err.to_string().starts_with(&syntax.display().to_string()));
+
+        let typed = dir.path().join("typed.toml");
+        fs::write(&typed, "[risk]\nline_weight = \"heavy\"\n").expect("write typed");
+        let err = validate_config_file(&typed).expect_err("string weight");
+        assert!(err.to_string().starts_with(&typed.display().to_string()));
+
+        let misspelled = dir.path().join("misspelled.toml");
+        fs::write(
+            &misspelled,
+            "[rsik]\nline_weight = 2.0\n[model]\ndefualt_model = \"qwen\"\n",
+        )
+        .expect("write misspelled");
+        let err = validate_config_file(&misspelled).expect_err("unknown keys");
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{}: unknown key model.defualt_model, rsik",
+                misspelled.display()
+            )
+        );
+    }
+
+    #[test]
+    fn explicit_config_replaces_the_user_file_and_beats_env() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let user = dir.path().join("user.toml");
+        fs::write(
+            &user,
+            "[model]\ndefault_model = \"user\"\n[risk]\nline_weight = 3.0\n",
+        )
+        .expect("write user");
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join(".dao")).expect("repo dir");
+        fs::write(
+            repo_config_path(&repo),
+            "[model]\ndefault_provider = \"codex\"\n",
+        )
+        .expect("write repo");
+        let explicit = dir.path().join("ci.toml");
+        fs::write(&explicit, "[model]\ndefault_model = \"ci\"\n").expect("write ci");
+        let env_values = [("DAO_MODEL", "model.default_model", "env".to_string())];
+
+        let without = resolve_config_from(
+            Some(user.clone()),
+            Some(&repo),
+            &env_values,
+            &ConfigOverrides::default(),
+        )
+        .expect("resolve");
+        assert_eq!(without.config.model.default_model.as_deref(), Some("env"));
+        assert_eq!(without.config.risk.line_weight, 3.0);
+
+        let overrides = ConfigOverrides {
+            config: Some(explicit.clone()),
+            ..ConfigOverrides::default()
+        };
+        let resolved =
+            resolve_config_from(Some(user.clone()), Some(&repo), &env_values, &overrides)
+                .expect("resolve");
+        assert_eq!(resolved.config.model.default_model.as_deref(), Some("ci"));
+        assert_eq!(
+            resolved.source_of("model.default_model"),
+            &ConfigSource::Explicit(explicit.clone())
+        );
+        assert_eq!(resolved.config.risk.line_weight, 0.0);
+        assert_eq!(
+            resolved.config.model.default_provider.as_deref(),
+            Some("codex")
+        );
+        assert_eq!(resolved.files, vec![repo_config_path(&repo), explicit]);
+
+        let flagged = ConfigOverrides {
+            model: Some("phi3".to_string()),
+            ..overrides
+        };
+        let resolved =
+            resolve_config_from(Some(user), Some(&repo), &env_values, &flagged).expect("resolve");
+        assert_eq!(resolved.config.model.default_model.as_deref(), Some("phi3"));
+    }
+
+    #[test]
+    fn repo_config_cannot_loosen_policy_tiers() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join(".dao")).expect("repo dir");
+        fs::write(
+            repo_config_path(&repo),
+            "[policy.tiers.Strict]\nDestructive = \"Allow\"\n[model]\ndefault_model = \"qwen\"\n",
+        )
+        .expect("write repo");
+
+        let resolved = resolve_config_from(None, Some(&repo), &[], &ConfigOverrides::default())
+            .expect("resolve");
+        assert_eq!(
+            resolved
+                .config
+                .policy
+                .tiers
+                .requirement(PolicyTier::Strict, ApprovalRiskClass::Destructive),
+            ApprovalGateRequirement::Deny
+        );
+        assert_eq!(resolved.config.model.default_model.as_deref(), Some("qwen"));
+        assert_eq!(
+            resolved.ignored,
+            vec![format!(
+                "{}: policy.tiers",
+                repo_config_path(&repo).display()
+            )]
+        );
+    }
+
+    #[test]
+    fn repo_config_cannot_lower_diff_risk() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join(".dao")).expect("repo dir");
+        fs::write(
+            repo_config_path(&repo),
+            "[risk]\ndeleted_files_destructive = false\nsensitive_paths = []\n",
+        )
+        .expect("write repo");
+
+        let resolved = resolve_config_from(None, Some(&repo), &[], &ConfigOverrides::default())
+            .expect("resolve");
+        assert!(resolved.config.risk.deleted_files_destructive);
+        assert_eq!(
+            resolved.config.risk.sensitive_paths,
+            RiskConfig::default().sensitive_paths
+        );
+        assert_eq!(
+            resolved.ignored,
+            vec![format!("{}: risk", repo_config_path(&repo).display())]
+        );
+
+        let user = dir.path().join("user.toml");
+        fs::write(&user, "[risk]\ndeleted_files_destructive = false\n").expect("write user");
+        let resolved =
+            resolve_config_from(Some(user), Some(&repo), &[], &ConfigOverrides::default())
+                .expect("resolve");
+        assert!(!resolved.config.risk.deleted_files_destructive);
+    }
+
+    #[test]
+    fn repo_config_cannot_register_tools() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let tool = "[[tools]]\nname = \"lint\"\ncommand = \"true\"\noutput = \"verify\"\n";
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join(".dao")).expect("repo dir");
+        fs::write(
+            repo_config_path(&repo),
+            format!("[model]\ndefault_model = \"qwen\"\n{tool}"),
+        )
+        .expect("write repo");
+
+        let resolved = resolve_config_from(None, Some(&repo), &[], &ConfigOverrides::default())
+            .expect("resolve");
+        assert!(resolved.config.tools.is_empty());
+        assert_eq!(resolved.config.model.default_model.as_deref(), Some("qwen"));
+        assert_eq!(
+            resolved.ignored,
+            vec![format!("{}: tools", repo_config_path(&repo).display())]
+        );
+
+        let user = dir.path().join("user.toml");
+        fs::write(&user, tool).expect("write user");
+        let resolved =
+            resolve_config_from(Some(user), Some(&repo), &[], &ConfigOverrides::default())
+                .expect("resolve");
+        assert_eq!(resolved.config.tools.len(), 1);
+    }
+
+    #[test]
+    fn unreadable_explicit_config_is_an_error() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let missing = dir.path().join("missing.toml");
+        let overrides = ConfigOverrides {
+            config: Some(missing.clone()),
+            ..ConfigOverrides::default()
+        };
+
+        let err = resolve_config_from(None, None, &[], &overrides).expect_err("missing file");
+        assert!(err
+            .to_string()
+            .starts_with(&format!("--config {}:", missing.display())));
+    }
+}
@@ -1,111 +1,319 @@
 use std::env;
 use std::fs;
 use std::io;
+use std::io::IsTerminal;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
-
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use approval_prompt::prompt_approval;
+use approval_summary::write_approval_summary;
+use config_layers::resolve_config;
+use config_layers::ConfigOverrides;
+use config_layers::ConfigSource;
 use dao_core::actions::RuntimeAction;
 use dao_core::actions::ShellAction;
 use dao_core::config::Config;
+use dao_core::config::ExternalToolConfig;
+use dao_core::config::ExternalToolOutputKind;
+use dao_core::config::PersonaConfig;
+use dao_core::conversion::diff_files_from_unified;
+use dao_core::conversion::payload_to_step_result;
+use dao_core::conversion::StepResult;
+use dao_core::persistence;
+use dao_core::persistence::cap_tool_logs;
+use dao_core::persistence::last_recorded_commit;
+use dao_core::persistence::load_chat_history;
+use dao_core::persistence::merge_chat_history;
 use dao_core::persistence::replay_latest_workflow;
 use dao_core::persistence::replay_workflow_from;
+use dao_core::persistence::replay_workflow_run;
+use dao_core::persistence::workflow_runs;
+use dao_core::persistence::workflow_stats;
+use dao_core::persistence::write_atomic;
+use dao_core::persistence::LoadedShellState;
 use dao_core::persistence::PersistedExecutionMode;
 use dao_core::persistence::PersistedPersonaPolicy;
 use dao_core::persistence::PersistedShellEvent;
 use dao_core::persistence::PersistedShellEventRecord;
 use dao_core::persistence::PersistedShellSnapshot;
 use dao_core::persistence::PersistedWorkflowStatus;
+use dao_core::persistence::PruneTarget;
 use dao_core::persistence::ReplayedWorkflowRun;
 use dao_core::persistence::ShellEventStore;
+use dao_core::persistence::WorkflowArtifactFlags;
+use dao_core::persistence::WorkflowRunExport;
+use dao_core::persistence::CHAT_HISTORY_CAP;
 use dao_core::policy_simulation::simulate_tool;
+use dao_core::reducer::diff_artifact_text;
+use dao_core::reducer::plan_artifact_text;
 use dao_core::reducer::reduce;
+use dao_core::reducer::system_artifact_text;
+use dao_core::reducer::verify_artifact_text;
 use dao_core::state::ApprovalAction;
 use dao_core::state::ApprovalDecisionKind;
 use dao_core::state::ApprovalDecisionRecord;
 use dao_core::state::ApprovalGateRequirement;
 use dao_core::state::ApprovalRequestRecord;
-use dao_core::state::ArtifactError;
+use dao_core::state::ApprovalRiskClass;
 use dao_core::state::DiffArtifact;
-use dao_core::state::DiffFile;
-use dao_core::state::DiffFileStatus;
-use dao_core::state::DiffHunk;
-use dao_core::state::DiffLine;
 use dao_core::state::DiffLineKind;
-use dao_core::state::ErrorKind;
 use dao_core::state::LogEntry;
 use dao_core::state::LogLevel;
 use dao_core::state::LogSource;
 use dao_core::state::Personality;
-use dao_core::state::PlanArtifact;
-use dao_core::state::PlanStep;
+use dao_core::state::PolicyGateState;
 use dao_core::state::PolicyTier;
 use dao_core::state::ShellState;
-use dao_core::state::StepStatus;
-use dao_core::state::SystemArtifact;
-use dao_core::state::VerifyArtifact;
-use dao_core::state::VerifyCheck;
-use dao_core::state::VerifyCheckStatus;
 use dao_core::state::VerifyOverall;
 use dao_core::state::ARTIFACT_SCHEMA_V1;
 use dao_core::tool_registry::ToolId;
 use dao_core::tool_registry::ToolRegistry;
 use dao_core::workflow::workflow_template;
+use dao_core::workflow::WorkflowTemplate;
 use dao_core::workflow::WorkflowTemplateId;
 use dao_core::ReviewPolicy;
+use dao_exec::cache::repo_fingerprint;
+use dao_exec::cache::CachingToolExecutor;
 use dao_exec::contracts::ToolInvocation;
 use dao_exec::contracts::ToolInvocationStatus;
+use dao_exec::executor::diff_since_commit;
+use dao_exec::executor::execute_with_retries;
+use dao_exec::executor::execute_with_timeout;
 use dao_exec::executor::RuntimeToolExecutor;
 use dao_exec::executor::ToolExecutionContext;
+use dao_exec::executor::ToolExecutionOutcome;
 use dao_exec::executor::ToolExecutionPayload;
 use dao_exec::executor::ToolExecutor;
+use dao_exec::external::ExternalTool;
+use dao_exec::external::ExternalToolOutput;
+use dao_exec::scan::ScanFilters;
 
+mod approval_prompt;
+mod approval_summary;
+mod config_layers;
 mod ui;
 
-fn main() {
-    if let Err(err) = run() {
-        eprintln!("error: {err}");
+/// Process exit codes. Scripts depend on these values, so they are part of the
+/// CLI contract: keep them in sync with the README table and
+/// `tests/exit_codes.rs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DaoExit {
+    /// The command finished, including `resume` with nothing to resume.
+    Success = 0,
+    /// Usage errors, I/O failures and any other error.
+    Failure = 1,
+    /// Persisted workflow state cannot be resumed.
+    MalformedState = 2,
+    /// `run` or `resume` stopped at a policy block, a denied approval or a
+    /// failing tool; `status` found an approval awaiting a decision.
+    WorkflowBlocked = 3,
+}
+
+impl DaoExit {
+    fn for_error(err: &dyn std::error::Error) -> Self {
         if err.to_string().starts_with("malformed resume state") {
-            std::process::exit(2);
+            Self::MalformedState
+        } else {
+            Self::Failure
+        }
+    }
+
+    fn for_workflow(end: WorkflowEnd) -> Self {
+        match end {
+            WorkflowEnd::Completed | WorkflowEnd::Stopped => Self::Success,
+            WorkflowEnd::Blocked => Self::WorkflowBlocked,
+        }
+    }
+}
+
+impl From<DaoExit> for ExitCode {
+    fn from(exit: DaoExit) -> Self {
+        ExitCode::from(exit as u8)
+    }
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(exit) => exit.into(),
+        Err(err) => {
+            eprintln!("error: {err}");
+            DaoExit::for_error(err.as_ref()).into()
         }
-        std::process::exit(1);
     }
 }
 
-fn run() -> Result<(), Box<dyn std::error::Error>> {
+fn run() -> Result<DaoExit, Box<dyn std::error::Error>> {
     let mut args = env::args().skip(1);
     let Some(command) = args.next() else {
         print_help();
-        return Ok(());
+        return Ok(DaoExit::Success);
     };
 
     match command.as_str() {
         "--help" | "-h" | "help" => {
             print_help();
-            Ok(())
+            Ok(DaoExit::Success)
         }
         "--version" | "-V" | "version" => {
-            println!("dao {}", env!("CARGO_PKG_VERSION"));
-            Ok(())
+            print_version(args.collect::<Vec<_>>()).map(|()| DaoExit::Success)
         }
         "run" => {
-            let (repo, policy, model, provider, intent) = parse_cli_args(args.collect::<Vec<_>>())?;
-            run_workflow(repo, policy, model, provider, intent)
+            let args = parse_cli_args(args.collect::<Vec<_>>())?;
+            if args.preset_approval.is_some() {
+                return Err("--approve and --deny are only supported by resume".into());
+            }
+            if args.watch {
+                return watch_workflow(args).map(|()| DaoExit::Success);
+            }
+            let repo = args.repo.clone();
+            let config_path = args.config.clone();
+            let end = run_workflow(
+                args.repo,
+                args.policy,
+                args.config,
+                args.model,
+                args.provider,
+                args.personality,
+                args.intent,
+                args.no_cache,
+                args.timeout,
+                args.retries,
+                args.explain_gate,
+                args.template
+                    .unwrap_or(WorkflowTemplateId::ScanPlanDiffVerify),
+                args.log_format,
+            )?;
+            if end == WorkflowEnd::Completed && io::stdout().is_terminal() {
+                start_ui(
+                    repo,
+                    config_path,
+                    None,
+                    None,
+                    None,
+                    args.no_history,
+                    args.color_enabled,
+                )?;
+            }
+            Ok(DaoExit::for_workflow(end))
         }
-        "replay" => replay_workflow(args.collect::<Vec<_>>()),
+        "replay" => replay_workflow(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
+        "runs" => list_workflow_runs(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
+        "risk" => assess_diff_risk(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
+        "changes" => show_changes_since_commit(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
+        "cat" => cat_artifact(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
+        "status" => print_workflow_status(args.collect::<Vec<_>>()),
+        "compact" => compact_event_log(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
+        "prune" => prune_event_log(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
+        "export" => export_workflow_run(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
+        "simulate" => simulate_policy(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
+        "approval-summary" => {
+            write_approval_summary_command(args.collect::<Vec<_>>()).map(|()| DaoExit::Success)
+        }
+        "prompt" => print_prompt_status(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
+        "config" => show_config(args.collect::<Vec<_>>()).map(|()| DaoExit::Success),
         "resume" => {
-            let (repo, policy, model, provider, intent) = parse_cli_args(args.collect::<Vec<_>>())?;
-            resume_workflow(repo, policy, model, provider, intent)
+            let args = parse_cli_args(args.collect::<Vec<_>>())?;
+            if args.watch {
+                return Err("--watch is only supported by run".into());
+            }
+            let repo = args.repo.clone();
+            let config_path = args.config.clone();
+            let end = resume_workflow(
+                args.repo,
+                args.policy,
+                args.config,
+                args.model,
+                args.provider,
+                args.personality,
+                args.intent,
+                args.no_cache,
+                args.timeout,
+                args.retries,
+                args.explain_gate,
+                args.preset_approval,
+                args.template,
+                args.log_format,
+            )?;
+            if end == WorkflowEnd::Completed && io::stdout().is_terminal() {
+                start_ui(
+                    repo,
+                    config_path,
+                    None,
+                    None,
+                    None,
+                    args.no_history,
+                    args.color_enabled,
+                )?;
+            }
+            Ok(DaoExit::for_workflow(end))
         }
         "ui" => {
-            let (repo, _, model, provider, _) = parse_cli_args(args.collect::<Vec<_>>())?;
-            start_ui(repo, model, provider)
+            let args = parse_cli_args(args.collect::<Vec<_>>())?;
+            start_ui(
+                args.repo,
+                args.config,
+                args.model,
+                args.provider,
+                args.personality,
+                args.no_history,
+                args.color_enabled,
+            )
+            .map(|()| DaoExit::Success)
         }
         "chat" => {
-            let (message, model, provider) = parse_chat_args(args.collect::<Vec<_>>())?;
+            let chat = parse_chat_args(args.collect::<Vec<_>>())?;
+            let repo = env::current_dir()?;
+            let resolved = resolve_config(Some(&repo), &chat.overrides)?;
+            let saved = if resolved.source_of("model.default_provider") == &ConfigSource::Detected {
+                load_shell_state(&repo)?.and_then(|state| state.sm.model_provider)
+            } else {
+                None
+            };
+            let provider = resolved.session_provider(saved.as_deref());
+            if let Some((provider, true)) = &provider {
+                eprintln!("No provider configured; using {provider}, detected on this machine");
+            }
+            let provider = provider.map(|(provider, _)| provider);
+            let model = &resolved.config.model;
+            if chat.json {
+                if chat.message.is_empty() {
+                    return Err("--json requires a message".into());
+                }
+                let reply = dao_exec::ShellAdapter::chat_reply(
+                    provider.as_deref(),
+                    model.default_model.as_deref(),
+                    &chat.message,
+                )?;
+                println!("{}", serde_json::to_string(&reply)?);
+                return Ok(DaoExit::Success);
+            }
+            if chat.stream {
+                if chat.message.is_empty() {
+                    return Err("--stream requires a message".into());
+                }
+                dao_exec::ShellAdapter::chat_streamed(
+                    provider.as_deref(),
+                    model.default_model.as_deref(),
+                    &chat.message,
+                    chat.stream_meta,
+                    &mut io::stdout().lock(),
+                )?;
+                return Ok(DaoExit::Success);
+            }
             // If message is empty, ShellAdapter::chat will start interactive mode
-            dao_exec::ShellAdapter::chat(provider.as_deref(), model.as_deref(), &message);
-            Ok(())
+            dao_exec::ShellAdapter::chat(
+                provider.as_deref(),
+                model.default_model.as_deref(),
+                &chat.message,
+            );
+            Ok(DaoExit::Success)
         }
         _ => {
             print_help();
@@ -114,26 +322,80 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
     }
 }
 
-fn parse_cli_args(
-    args: Vec<String>,
-) -> Result<
-    (
-        PathBuf,
-        Option<PathBuf>,
-        Option<String>,
-        Option<String>,
-        Option<String>,
-    ),
-    Box<dyn std::error::Error>,
-> {
+struct CliArgs {
+    repo: PathBuf,
+    policy: Option<PathBuf>,
+    /// Config file read in place of the user file, from `--config`.
+    config: Option<PathBuf>,
+    model: Option<String>,
+    provider: Option<String>,
+    personality: Option<Personality>,
+    intent: Option<String>,
+    watch: bool,
+    no_cache: bool,
+    /// Per-tool deadline, from `--timeout-secs`.
+    timeout: Option<Duration>,
+    /// Extra attempts for a failed tool, from `--retries`.
+    retries: u32,
+    explain_gate: bool,
+    no_history: bool,
+    /// False under `--no-color` or a non-empty `NO_COLOR`.
+    color_enabled: bool,
+    /// Fixed answer for approval prompts, from `--approve` or `--deny`.
+    preset_approval: Option<bool>,
+    template: Option<WorkflowTemplateId>,
+    log_format: LogFormat,
+}
+
+fn parse_cli_args(args: Vec<String>) -> Result<CliArgs, Box<dyn std::error::Error>> {
     let mut repo = None;
     let mut policy = None;
+    let mut config = None;
     let mut model = None;
     let mut provider = None;
+    let mut personality = None;
     let mut intent_words = Vec::new();
+    let mut watch = false;
+    let mut no_cache = false;
+    let mut timeout = None;
+    let mut retries = 0;
+    let mut explain_gate = false;
+    let mut no_history = false;
+    let mut no_color = false;
+    let mut preset_approval = None;
+    let mut template = None;
+    let mut log_format = LogFormat::Text;
     let mut i = 0;
     while i < args.len() {
         match args[i].as_str() {
+            "--watch" => {
+                watch = true;
+                i += 1;
+            }
+            "--no-cache" => {
+                no_cache = true;
+                i += 1;
+            }
+            "--explain-gate" => {
+                explain_gate = true;
+                i += 1;
+            }
+            "--no-history" => {
+                no_history = true;
+                i += 1;
+            }
+            "--no-color" => {
+                no_color = true;
+                i += 1;
+            }
+            "--approve" | "--deny" => {
+                let approve = args[i] == "--approve";
+                if preset_approval.is_some_and(|preset| preset != approve) {
+                    return Err("--approve and --deny are mutually exclusive".into());
+                }
+                preset_approval = Some(approve);
+                i += 1;
+            }
             "--repo" => {
                 let Some(value) = args.get(i + 1) else {
                     return Err("--repo requires a path".into());
@@ -141,6 +403,43 @@ fn parse_cli_args(
                 repo = Some(PathBuf::from(value));
                 i += 2;
             }
+            "--timeout-secs" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--timeout-secs requires a number of seconds".into());
+                };
+                let secs = value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|&secs| secs > 0)
+                    .ok_or_else(|| format!("invalid --timeout-secs value: {value}"))?;
+                timeout = Some(Duration::from_secs(secs));
+                i += 2;
+            }
+            "--retries" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--retries requires a number of attempts".into());
+                };
+                retries = value
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid --retries value: {value}"))?;
+                i += 2;
+            }
+            "--log-format" => {
+                log_format = match args.get(i + 1).map(String::as_str) {
+                    Some("text") => LogFormat::Text,
+                    Some("json") => LogFormat::Json,
+                    Some(other) => return Err(format!("unsupported log format: {other}").into()),
+                    None => return Err("--log-format requires text or json".into()),
+                };
+                i += 2;
+            }
+            "--template" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--template requires a name".into());
+                };
+                template = Some(parse_template(value)?);
+                i += 2;
+            }
             "--policy" => {
                 let Some(value) = args.get(i + 1) else {
                     return Err("--policy requires a path".into());
@@ -148,6 +447,13 @@ fn parse_cli_args(
                 policy = Some(PathBuf::from(value));
                 i += 2;
             }
+            "--config" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--config requires a path".into());
+                };
+                config = Some(PathBuf::from(value));
+                i += 2;
+            }
             "--model" => {
                 let Some(value) = args.get(i + 1) else {
                     return Err("--model requires a name".into());
@@ -162,6 +468,13 @@ fn parse_cli_args(
                 provider = Some(value.clone());
                 i += 2;
             }
+            "--personality" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--personality requires a name".into());
+                };
+                personality = Some(parse_personality(value)?);
+                i += 2;
+            }
             other => {
                 if other.starts_with('-') {
                     return Err(format!("unsupported argument: {other}").into());
@@ -171,41 +484,87 @@ fn parse_cli_args(
             }
         }
     }
+    let color_enabled = !no_color && env::var_os("NO_COLOR").map_or(true, |value| value.is_empty());
+    if !color_enabled && log_format == LogFormat::Text {
+        log_format = LogFormat::Plain;
+    }
     let intent = if intent_words.is_empty() {
         None
     } else {
         Some(intent_words.join(" "))
     };
-    Ok((
-        repo.unwrap_or_else(|| PathBuf::from(".")),
+    Ok(CliArgs {
+        repo: repo.unwrap_or_else(|| PathBuf::from(".")),
         policy,
+        config,
         model,
         provider,
+        personality,
         intent,
-    ))
+        watch,
+        no_cache,
+        timeout,
+        retries,
+        explain_gate,
+        no_history,
+        color_enabled,
+        preset_approval,
+        template,
+        log_format,
+    })
 }
 
-fn parse_chat_args(
-    args: Vec<String>,
-) -> Result<(String, Option<String>, Option<String>), Box<dyn std::error::Error>> {
-    let mut model = None;
-    let mut provider = None;
+struct ChatArgs {
+    message: String,
+    overrides: ConfigOverrides,
+    /// Print the reply token by token instead of handing the terminal over.
+    stream: bool,
+    /// With `stream`, also print provider meta lines to stderr.
+    stream_meta: bool,
+    /// Print the finished reply as one JSON object.
+    json: bool,
+}
+
+fn parse_chat_args(args: Vec<String>) -> Result<ChatArgs, Box<dyn std::error::Error>> {
+    let mut overrides = ConfigOverrides::default();
     let mut words = Vec::new();
+    let mut stream = false;
+    let mut stream_meta = false;
+    let mut json = false;
     let mut i = 0;
     while i < args.len() {
         match args[i].as_str() {
+            "--stream" => {
+                stream = true;
+                i += 1;
+            }
+            "--stream-meta" => {
+                stream_meta = true;
+                i += 1;
+            }
+            "--json" => {
+                json = true;
+                i += 1;
+            }
             "--model" => {
                 let Some(value) = args.get(i + 1) else {
                     return Err("--model requires a name".into());
                 };
-                model = Some(value.clone());
+                overrides.model = Some(value.clone());
                 i += 2;
             }
             "--provider" => {
                 let Some(value) = args.get(i + 1) else {
                     return Err("--provider requires a name".into());
                 };
-                provider = Some(value.clone());
+                overrides.provider = Some(value.clone());
+                i += 2;
+            }
+            "--config" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--config requires a path".into());
+                };
+                overrides.config = Some(PathBuf::from(value));
                 i += 2;
             }
             other => {
@@ -217,11 +576,25 @@ fn parse_chat_args(
             }
         }
     }
-    Ok((words.join(" "), model, provider))
+    if stream_meta && !stream {
+        return Err("--stream-meta requires --stream".into());
+    }
+    if json && stream {
+        return Err("--json and --stream are mutually exclusive".into());
+    }
+    Ok(ChatArgs {
+        message: words.join(" "),
+        overrides,
+        stream,
+        stream_meta,
+        json,
+    })
 }
 
 fn replay_workflow(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
     let mut last = false;
+    let mut stats = false;
+    let mut run_filter = None;
     let mut repo = None;
     let mut i = 0;
     while i < args.len() {
@@ -230,6 +603,21 @@ fn replay_workflow(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>>
                 last = true;
                 i += 1;
             }
+            "--stats" => {
+                stats = true;
+                i += 1;
+            }
+            "--run" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--run requires a run id".into());
+                };
+                run_filter = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| format!("invalid run id: {value}"))?,
+                );
+                i += 2;
+            }
             "--repo" => {
                 let Some(value) = args.get(i + 1) else {
                     return Err("--repo requires a path".into());
@@ -241,27 +629,37 @@ fn replay_workflow(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>>
         }
     }
 
-    if !last {
-        return Err("replay currently supports only --last".into());
+    if stats && (last || run_filter.is_some()) {
+        return Err("--stats cannot be combined with --last or --run".into());
+    }
+    if last && run_filter.is_some() {
+        return Err("replay accepts only one of --last or --run".into());
     }
 
     let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
-    let (store, snapshot_path) = open_store_for_repo(&repo)?;
+    if stats {
+        return print_workflow_stats(&repo);
+    }
+    let (store, snapshot_path) = open_store_read_only(&repo);
     let records = store.load()?;
-    let run = load_latest_run(&store, &snapshot_path)?;
+    let run = load_run(&store, &snapshot_path, run_filter)?;
 
     let Some(run) = run else {
+        if let Some(run_id) = run_filter {
+            return Err(format!("run {run_id} not found").into());
+        }
         println!("no workflow runs found");
         return Ok(());
     };
 
-    let template = workflow_template(WorkflowTemplateId::ScanPlanDiffVerify);
+    let template = run_template(&run);
     let current_step = template.steps.get(run.step_index).map(|step| step.step_id);
     let next_step = template.steps.get(run.step_index).map(|step| step.step_id);
-    let (system, plan, diff, verify) = artifact_flags(run.step_index);
+    let flags = WorkflowArtifactFlags::for_step(run.step_index);
     let last_log_seq = records.iter().map(|record| record.seq).max().unwrap_or(0);
 
     println!("run_id: {}", run.run_id);
+    println!("template: {}", run.template_id);
     println!("status: {}", persisted_status_label(run.status));
     println!("current_step: {}", current_step.unwrap_or("<completed>"));
     println!("next_step: {}", next_step.unwrap_or("<none>"));
@@ -279,140 +677,1163 @@ fn replay_workflow(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>>
 
     println!(
         "artifacts: system={} plan={} diff={} verify={}",
-        system, plan, diff, verify
+        flags.system, flags.plan, flags.diff, flags.verify
     );
+    match run.last_approval_ms {
+        Some(ms) => println!("last_approval_ms: {ms}"),
+        None => println!("last_approval_ms: none"),
+    }
     println!("last_log_seq: {last_log_seq}");
+    for output in &run.tool_outputs {
+        println!(
+            "tool_output: {} invocation_id={}",
+            output.tool_id, output.invocation_id
+        );
+        for line in &output.logs {
+            println!("  {line}");
+        }
+    }
     Ok(())
 }
 
-fn start_ui(
-    repo: PathBuf,
-    model: Option<String>,
-    provider: Option<String>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let repo = repo.canonicalize()?;
-    let mut config = load_config()?;
-    if let Some(model) = model {
-        config.model.default_model = Some(model);
-    }
-    if let Some(provider) = provider {
-        config.model.default_provider = Some(provider);
+/// Prints the latest run as `key=value` lines for wrapper scripts. Exits with
+/// `WorkflowBlocked` while an approval is awaiting a decision.
+fn print_workflow_status(args: Vec<String>) -> Result<DaoExit, Box<dyn std::error::Error>> {
+    let mut repo = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--repo" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--repo requires a path".into());
+                };
+                repo = Some(PathBuf::from(value));
+                i += 2;
+            }
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
     }
-    let mut state = load_shell_state(&repo)?.unwrap_or_else(|| {
-        ShellState::new(repo_name(&repo), Personality::Pragmatic, config.clone())
-    });
-    if let Some(model) = config.model.default_model.clone() {
-        reduce(
-            &mut state,
-            ShellAction::Runtime(RuntimeAction::SetModelSlug(Some(model))),
-        );
+
+    let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let (store, snapshot_path) = open_store_read_only(&repo);
+    let Some(run) = load_latest_run(&store, &snapshot_path)? else {
+        println!("status=none");
+        return Ok(DaoExit::Success);
+    };
+
+    println!("run_id={}", run.run_id);
+    println!("status={}", persisted_status_label(run.status));
+    println!(
+        "pending_request_id={}",
+        run.pending_request_id.as_deref().unwrap_or_default()
+    );
+    println!(
+        "pending_tool_id={}",
+        run.pending_tool_id.as_deref().unwrap_or_default()
+    );
+    println!(
+        "pending_invocation_id={}",
+        run.pending_invocation_id
+            .map(|id| id.to_string())
+            .unwrap_or_default()
+    );
+    println!(
+        "blocked_reason={}",
+        run.blocked_reason.as_deref().unwrap_or_default()
+    );
+    if let Some(latency) = run.last_approval_latency_ms {
+        println!("approval_latency_ms={latency}");
     }
-    if let Some(provider) = config.model.default_provider.clone() {
-        reduce(
-            &mut state,
-            ShellAction::Runtime(RuntimeAction::SetModelProvider(Some(provider))),
-        );
+    if run.status == PersistedWorkflowStatus::AwaitingApproval {
+        Ok(DaoExit::WorkflowBlocked)
+    } else {
+        Ok(DaoExit::Success)
     }
-    state.cwd = Some(repo.clone());
-    state.file_browser.current_path = repo.clone();
-    ui::run(state, repo)
 }
 
-fn run_workflow(
-    repo: PathBuf,
-    policy_path: Option<PathBuf>,
-    model: Option<String>,
-    provider: Option<String>,
-    intent: Option<String>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let repo = repo.canonicalize()?;
-    let (mut store, snapshot_path) = open_store_for_repo(&repo)?;
-
-    let records = store.load()?;
-    let prior_run_id = replay_latest_workflow(&records)
-        .map(|run| run.run_id)
-        .unwrap_or(0);
-    let run_id = prior_run_id.saturating_add(1);
+/// Snapshots the latest run, then drops every event the snapshot covers.
+fn compact_event_log(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut repo = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--repo" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--repo requires a path".into());
+                };
+                repo = Some(PathBuf::from(value));
+                i += 2;
+            }
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
+    }
 
-    let project_name = repo
-        .file_name()
-        .and_then(|name| name.to_str())
-        .unwrap_or("repo")
-        .to_string();
+    let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let (store, snapshot_path) = open_store_for_repo(&repo)?;
+    let Some(last_seq) = store.load()?.iter().map(|record| record.seq).max() else {
+        println!("event log is empty; nothing to compact");
+        return Ok(());
+    };
+    save_snapshots(&store, &snapshot_path, last_seq)?;
+    let dropped = store.compact(last_seq)?;
+    println!("compacted {dropped} events up to seq {last_seq}");
+    Ok(())
+}
 
-    let mut config = load_config()?;
-    if let Some(model) = model.clone() {
-        config.model.default_model = Some(model);
-    }
-    if let Some(provider) = provider.clone() {
-        config.model.default_provider = Some(provider);
+fn prune_event_log(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut repo = None;
+    let mut targets = Vec::new();
+    let mut yes = false;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--repo" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--repo requires a path".into());
+                };
+                repo = Some(PathBuf::from(value));
+                i += 2;
+            }
+            flag @ ("--run" | "--before") => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err(format!("{flag} requires a run id").into());
+                };
+                let run_id = value
+                    .parse::<u64>()
+                    .map_err(|_| format!("invalid run id: {value}"))?;
+                targets.push(if flag == "--run" {
+                    PruneTarget::Run(run_id)
+                } else {
+                    PruneTarget::Before(run_id)
+                });
+                i += 2;
+            }
+            "--all" => {
+                targets.push(PruneTarget::All);
+                i += 1;
+            }
+            "--yes" => {
+                yes = true;
+                i += 1;
+            }
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
     }
-    let mut state = ShellState::new(project_name, Personality::Pragmatic, config);
+    let [target] = targets[..] else {
+        return Err("prune requires exactly one of --run, --before, or --all".into());
+    };
 
-    if let Some(path) = policy_path {
-        println!("Loading review policy from {}", path.display());
-        let content = fs::read_to_string(&path)?;
-        let policy: ReviewPolicy = serde_yaml::from_str(&content)?;
-        reduce(
-            &mut state,
-            ShellAction::Runtime(RuntimeAction::SetReviewPolicy(policy)),
-        );
+    let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let (store, snapshot_path) = open_store_for_repo(&repo)?;
+    let records = store.load()?;
+    let pruned = records
+        .iter()
+        .filter(|record| target.selects(record.event.run_id()))
+        .count();
+    let mut runs = records
+        .iter()
+        .filter_map(|record| record.event.run_id())
+        .filter(|&run_id| target.selects(Some(run_id)))
+        .collect::<Vec<_>>();
+    runs.sort_unstable();
+    runs.dedup();
+    if pruned == 0 {
+        println!("no events match; nothing to prune");
+        return Ok(());
     }
-    let policy_tier = state.approval.policy_tier;
 
-    let seq = store.append(PersistedShellEvent::WorkflowRunStarted {
-        run_id,
-        template_id: "scan_plan_diff_verify".to_string(),
-        execution_mode: PersistedExecutionMode::Simulated,
-        policy_tier: policy_tier.label().to_string(),
-        persona_policy: PersistedPersonaPolicy {
-            tier_ceiling: state.sm.persona_policy.tier_ceiling.label().to_string(),
-            explanation_depth: state
-                .sm
-                .persona_policy
-                .explanation_depth
-                .label()
-                .to_string(),
-            output_format: state.sm.persona_policy.output_format.label().to_string(),
-        },
-    })?;
-    save_snapshots(&store, &snapshot_path, seq)?;
+    let what = match target {
+        PruneTarget::All => format!("all {pruned} events"),
+        _ => format!("{pruned} events of {} runs", runs.len()),
+    };
+    if !yes {
+        if !io::stdin().is_terminal() {
+            return Err(format!(
+                "prune would delete {what}; pass --yes when stdin is not a terminal"
+            )
+            .into());
+        }
+        print!("delete {what}? [y/N]: ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        io::stdin().read_line(&mut line)?;
+        if !matches!(line.trim(), "y" | "Y" | "yes" | "YES") {
+            println!("prune cancelled");
+            return Ok(());
+        }
+    }
 
-    execute_workflow(
-        &repo,
-        &mut store,
-        &snapshot_path,
-        &mut state,
-        run_id,
-        0,
-        1,
-        policy_tier,
-        model,
-        provider,
-        intent,
-        None,
-    )
+    let dropped = store.prune(target)?;
+    match store.load_snapshot()? {
+        Some(snapshot) => write_atomic(&snapshot_path, &serde_json::to_vec_pretty(&snapshot)?)?,
+        None if snapshot_path.exists() => fs::remove_file(&snapshot_path)?,
+        None => {}
+    }
+    let runs = runs
+        .iter()
+        .map(u64::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    println!("pruned {dropped} events (runs: {runs})");
+    Ok(())
 }
 
-fn resume_workflow(
-    repo: PathBuf,
-    policy_path: Option<PathBuf>,
+fn export_workflow_run(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut repo = None;
+    let mut run_filter = None;
+    let mut format = "json".to_string();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--repo" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--repo requires a path".into());
+                };
+                repo = Some(PathBuf::from(value));
+                i += 2;
+            }
+            "--run" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--run requires a run id".into());
+                };
+                run_filter = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| format!("invalid run id: {value}"))?,
+                );
+                i += 2;
+            }
+            "--format" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--format requires json or yaml".into());
+                };
+                if value != "json" && value != "yaml" {
+                    return Err(format!("unsupported format: {value}").into());
+                }
+                format = value.clone();
+                i += 2;
+            }
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
+    }
+
+    let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let (store, snapshot_path) = open_store_read_only(&repo);
+    let Some(run) = load_run(&store, &snapshot_path, run_filter)? else {
+        return Err(match run_filter {
+            Some(run_id) => format!("run not found: {run_id}").into(),
+            None => "run not found: no workflow runs recorded".into(),
+        });
+    };
+    let records = store.load()?;
+    let last_log_seq = records
+        .iter()
+        .filter(|record| record.event.run_id() == Some(run.run_id))
+        .map(|record| record.seq)
+        .max()
+        .unwrap_or(0);
+    let export = WorkflowRunExport {
+        version: 1,
+        artifacts: WorkflowArtifactFlags::for_step(run.step_index),
+        run,
+        last_log_seq,
+    };
+    match format.as_str() {
+        "yaml" => print!("{}", serde_yaml::to_string(&export)?),
+        _ => println!("{}", serde_json::to_string_pretty(&export)?),
+    }
+    Ok(())
+}
+
+fn print_workflow_stats(repo: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let (store, _) = open_store_read_only(repo);
+    let stats = workflow_stats(&store.load()?);
+    if stats.runs == 0 {
+        println!("no workflow runs found");
+        return Ok(());
+    }
+
+    println!("runs: {}", stats.runs);
+    let by_status = stats
+        .final_status
+        .iter()
+        .map(|(status, count)| format!("{}={count}", persisted_status_label(*status)))
+        .collect::<Vec<_>>();
+    println!("final_status: {}", by_status.join(" "));
+    match stats.avg_steps_to_completion {
+        Some(avg) => println!("avg_steps_to_completion: {avg:.1}"),
+        None => println!("avg_steps_to_completion: n/a"),
+    }
+    let resolved = stats.approvals_granted + stats.approvals_denied;
+    if resolved == 0 {
+        println!("approvals: none");
+    } else {
+        println!(
+            "approvals: granted={} denied={} grant_rate={:.0}%",
+            stats.approvals_granted,
+            stats.approvals_denied,
+            stats.approvals_granted as f64 * 100.0 / resolved as f64
+        );
+    }
+    if stats.blocked_reasons.is_empty() {
+        println!("blocked_reasons: none");
+    } else {
+        println!("blocked_reasons:");
+        for (reason, count) in stats.blocked_reasons.iter().take(5) {
+            println!("  {count:>4}  {reason}");
+        }
+    }
+    Ok(())
+}
+
+/// Prints one row per run in the event log, newest first.
+fn list_workflow_runs(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut repo = None;
+    let mut limit = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--repo" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--repo requires a path".into());
+                };
+                repo = Some(PathBuf::from(value));
+                i += 2;
+            }
+            "--limit" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--limit requires a number of runs".into());
+                };
+                limit = Some(
+                    value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&limit| limit > 0)
+                        .ok_or_else(|| format!("invalid --limit value: {value}"))?,
+                );
+                i += 2;
+            }
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
+    }
+
+    let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let (store, snapshot_path) = open_store_read_only(&repo);
+    let mut runs = workflow_runs(&store.load()?);
+    // Compaction leaves only the start of the snapshot's run in the log.
+    if let Some(snapshot_run) =
+        load_snapshot_preferred(&store, &snapshot_path)?.and_then(|snapshot| snapshot.workflow)
+    {
+        if let Some(run) = runs
+            .iter_mut()
+            .find(|run| run.run_id == snapshot_run.run_id)
+        {
+            if let Some(replayed) = load_run(&store, &snapshot_path, Some(run.run_id))? {
+                *run = replayed;
+            }
+        }
+    }
+    if runs.is_empty() {
+        println!("no workflow runs found");
+        return Ok(());
+    }
+    runs.truncate(limit.unwrap_or(runs.len()));
+
+    let width = runs
+        .iter()
+        .map(|run| run.template_id.len())
+        .max()
+        .unwrap_or(0)
+        .max("TEMPLATE".len());
+    println!(
+        "{:>5}  {:<width$}  {:<17}  STEPS",
+        "RUN", "TEMPLATE", "STATUS"
+    );
+    for run in &runs {
+        println!(
+            "{:>5}  {:<width$}  {:<17}  {}",
+            run.run_id,
+            run.template_id,
+            persisted_status_label(run.status),
+            run.step_index
+        );
+    }
+    Ok(())
+}
+
+fn assess_diff_risk(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut diff_path = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--diff" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--diff requires a path".into());
+                };
+                diff_path = Some(PathBuf::from(value));
+                i += 2;
+            }
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
+    }
+
+    let Some(diff_path) = diff_path else {
+        return Err("risk requires --diff FILE".into());
+    };
+    let text = fs::read_to_string(&diff_path)?;
+    let diff = DiffArtifact {
+        schema_version: ARTIFACT_SCHEMA_V1,
+        run_id: 0,
+        artifact_id: 0,
+        files: diff_files_from_unified(&text),
+        summary: String::new(),
+        error: None,
+    };
+    let config = load_config(None)?;
+    let assessment = diff.assess_risk(&config.risk);
+
+    println!("risk: {}", assessment.risk.label());
+    println!("score: {:.1}", assessment.score);
+    println!("factors:");
+    for factor in &assessment.factors {
+        println!("  - {factor}");
+    }
+    Ok(())
+}
+
+fn show_changes_since_commit(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut repo = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--repo" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--repo requires a path".into());
+                };
+                repo = Some(PathBuf::from(value));
+                i += 2;
+            }
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
+    }
+
+    let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let (store, _) = open_store_read_only(&repo);
+    let Some((commit_run_id, hash)) = last_recorded_commit(&store.load()?) else {
+        println!("no committed run recorded (run with an intent to auto-commit)");
+        return Ok(());
+    };
+
+    let unified_diff = diff_since_commit(&repo, &hash)?;
+    let (saved, warning) = load_shell_state_or_warn(&repo)?;
+    if let Some(warning) = warning {
+        eprintln!("warning: {warning}");
+    }
+    let mut state = match saved {
+        Some(state) => state,
+        None => ShellState::new(
+            repo_name(&repo),
+            Personality::Pragmatic,
+            load_config(Some(&repo))?,
+        ),
+    };
+    let (run_id, artifact_id) = match state.artifacts.diff.as_ref() {
+        Some(diff) if diff.run_id >= commit_run_id => {
+            (diff.run_id, diff.artifact_id.saturating_add(1))
+        }
+        _ => (commit_run_id, 1),
+    };
+    let diff = DiffArtifact {
+        schema_version: ARTIFACT_SCHEMA_V1,
+        run_id,
+        artifact_id,
+        files: diff_files_from_unified(&unified_diff),
+        summary: format!("Changes since {hash} (run {commit_run_id})"),
+        error: None,
+    };
+
+    println!("changes since {hash} (run {commit_run_id}):");
+    if diff.files.is_empty() {
+        println!("  <no changes>");
+    }
+    for file in &diff.files {
+        let (added, removed) = file.hunks.iter().flat_map(|hunk| &hunk.lines).fold(
+            (0usize, 0usize),
+            |(added, removed), line| match line.kind {
+                DiffLineKind::Add => (added + 1, removed),
+                DiffLineKind::Remove => (added, removed + 1),
+                DiffLineKind::Context => (added, removed),
+            },
+        );
+        println!("  {} (+{added} -{removed})", file.path);
+    }
+
+    reduce(
+        &mut state,
+        ShellAction::Runtime(RuntimeAction::SetDiffArtifact(diff)),
+    );
+    save_shell_state(&repo, &state)?;
+    println!(
+        "diff saved to {}",
+        store_path(&repo).join("state.json").display()
+    );
+    Ok(())
+}
+
+/// Runs each workflow step's policy gate against the saved diff without
+/// executing anything or touching the event log.
+fn simulate_policy(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut repo = None;
+    let mut policy_path = None;
+    let mut template = WorkflowTemplateId::ScanPlanDiffVerify;
+    let mut intent_words = Vec::new();
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--repo" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--repo requires a path".into());
+                };
+                repo = Some(PathBuf::from(value));
+                i += 2;
+            }
+            "--policy" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--policy requires a path".into());
+                };
+                policy_path = Some(PathBuf::from(value));
+                i += 2;
+            }
+            "--template" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--template requires a template name".into());
+                };
+                template = parse_template(value)?;
+                i += 2;
+            }
+            other if other.starts_with("--") => {
+                return Err(format!("unsupported argument: {other}").into())
+            }
+            other => {
+                intent_words.push(other.to_string());
+                i += 1;
+            }
+        }
+    }
+    let intent = if intent_words.is_empty() {
+        None
+    } else {
+        Some(intent_words.join(" "))
+    };
+
+    let Some(policy_path) = policy_path else {
+        return Err("simulate requires --policy PATH".into());
+    };
+    let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let policy: ReviewPolicy = serde_yaml::from_str(&fs::read_to_string(&policy_path)?)?;
+    let config = resolve_config(Some(&repo), &ConfigOverrides::default())?.config;
+    let mut pending_diff = load_shell_state(&repo)?.and_then(|saved| saved.artifacts.diff);
+    let mut state = ShellState::new("simulate".to_string(), Personality::Pragmatic, config);
+    let policy_id = policy.id.clone();
+    reduce(
+        &mut state,
+        ShellAction::Runtime(RuntimeAction::SetReviewPolicy(Box::new(policy))),
+    );
+
+    println!("policy: {policy_id}");
+    match &pending_diff {
+        Some(diff) => println!(
+            "diff: run {} ({} files, +{}/-{})",
+            diff.run_id,
+            diff.files_changed(),
+            diff.added_lines(),
+            diff.removed_lines()
+        ),
+        None => println!("diff: none"),
+    }
+    let steps = workflow_steps(workflow_template(template), &state.config.tools)?;
+    for (step_index, step) in steps.iter().enumerate() {
+        // A run has no diff before `compute_diff`, so earlier gates must not see it.
+        if step.builtin == Some(ToolId::ComputeDiff) {
+            if let Some(diff) = pending_diff.take() {
+                reduce(
+                    &mut state,
+                    ShellAction::Runtime(RuntimeAction::SetDiffArtifact(diff)),
+                );
+            }
+        }
+        let reason = intent.clone().unwrap_or_else(|| match step.builtin {
+            Some(tool_id) => simulate_tool(
+                state.approval.policy_tier,
+                tool_id,
+                &state.config.policy.tiers,
+            )
+            .reason
+            .to_string(),
+            None => format!("external tool {}", step.tool),
+        });
+        let risk = step_gate_risk(step_index, step, &state);
+        reduce(
+            &mut state,
+            ShellAction::Runtime(RuntimeAction::AssessPolicyGate {
+                run_id: 0,
+                action: ApprovalAction::Execute,
+                risk,
+                reason,
+            }),
+        );
+        let Some(gate) = state.approval.last_gate.as_ref() else {
+            return Err(format!("no policy gate was assessed for step {}", step.step_id).into());
+        };
+        let outcome = match gate.requirement {
+            ApprovalGateRequirement::Allow => "allowed",
+            ApprovalGateRequirement::RequireApproval => "approval_required",
+            ApprovalGateRequirement::Deny => "blocked",
+        };
+        let rule = gate.matched_rule.as_deref().unwrap_or("(default)");
+        println!(
+            "{} ({}): {outcome} [{rule}] {}",
+            step.step_id, step.tool, gate.reason
+        );
+    }
+    Ok(())
+}
+
+fn cat_artifact(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut repo = None;
+    let mut artifact = None;
+    let mut run_filter = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--repo" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--repo requires a path".into());
+                };
+                repo = Some(PathBuf::from(value));
+                i += 2;
+            }
+            "--artifact" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--artifact requires plan|diff|system|verify".into());
+                };
+                artifact = Some(value.clone());
+                i += 2;
+            }
+            "--run" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--run requires a run id".into());
+                };
+                run_filter = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| format!("invalid run id: {value}"))?,
+                );
+                i += 2;
+            }
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
+    }
+
+    let Some(artifact) = artifact else {
+        return Err("cat requires --artifact plan|diff|system|verify".into());
+    };
+    let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let Some(state) = load_shell_state(&repo)? else {
+        return Err(format!("no saved state in {}", store_path(&repo).display()).into());
+    };
+
+    let artifacts = &state.artifacts;
+    let (run_id, text) = match artifact.as_str() {
+        "plan" => artifacts
+            .plan
+            .as_ref()
+            .map(|plan| (plan.run_id, plan_artifact_text(plan))),
+        "diff" => artifacts
+            .diff
+            .as_ref()
+            .map(|diff| (diff.run_id, diff_artifact_text(diff))),
+        "system" => artifacts
+            .system
+            .as_ref()
+            .map(|system| (system.run_id, system_artifact_text(system))),
+        "verify" => artifacts
+            .verify
+            .as_ref()
+            .map(|verify| (verify.run_id, verify_artifact_text(verify))),
+        other => {
+            return Err(
+                format!("unknown artifact: {other} (expected plan|diff|system|verify)").into(),
+            )
+        }
+    }
+    .ok_or_else(|| format!("no {artifact} artifact saved"))?;
+
+    // Only the latest artifact of each kind is saved, and the event log does
+    // not record artifact contents, so an older run cannot be rebuilt.
+    if let Some(wanted) = run_filter {
+        if wanted != run_id {
+            let (store, snapshot_path) = open_store_read_only(&repo);
+            if load_run(&store, &snapshot_path, Some(wanted))?.is_none() {
+                return Err(format!("run not found: {wanted}").into());
+            }
+            return Err(format!(
+                "{artifact} artifact for run {wanted} is no longer saved; only the latest (run {run_id}) is kept"
+            )
+            .into());
+        }
+    }
+    print!("{text}");
+    Ok(())
+}
+
+fn write_approval_summary_command(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut repo = None;
+    let mut run_filter = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--repo" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--repo requires a path".into());
+                };
+                repo = Some(PathBuf::from(value));
+                i += 2;
+            }
+            "--run" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--run requires a run id".into());
+                };
+                run_filter = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| format!("invalid run id: {value}"))?,
+                );
+                i += 2;
+            }
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
+    }
+
+    let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let Some(state) = load_shell_state(&repo)? else {
+        return Err(format!("no saved state in {}", store_path(&repo).display()).into());
+    };
+    let Some(run_id) = state.artifacts.diff.as_ref().map(|diff| diff.run_id) else {
+        return Err("no diff artifact saved".into());
+    };
+    if let Some(wanted) = run_filter {
+        if wanted != run_id {
+            return Err(format!(
+                "diff artifact for run {wanted} is not available (latest saved is run {run_id})"
+            )
+            .into());
+        }
+    }
+    let path = write_approval_summary(&repo, &state, run_id)?;
+    println!("approval summary: {}", path.display());
+    Ok(())
+}
+
+/// Prints a one-line status for shell prompts, e.g. `dao:run#7 diffing ⚠1`.
+/// Prints nothing when the repository has no `.dao` directory.
+fn print_prompt_status(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut repo = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--repo" => {
+                let Some(value) = args.get(i + 1) else {
+                    return Err("--repo requires a path".into());
+                };
+                repo = Some(PathBuf::from(value));
+                i += 2;
+            }
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
+    }
+    let repo = repo.unwrap_or_else(|| PathBuf::from("."));
+    let (store, snapshot_path) = open_store_read_only(&repo);
+    let Some(run) = load_latest_run(&store, &snapshot_path)? else {
+        return Ok(());
+    };
+
+    let template = run_template(&run);
+    let phase = match run.status {
+        PersistedWorkflowStatus::Running => {
+            match template.steps.get(run.step_index).map(|step| step.step_id) {
+                Some("scan") => "scanning",
+                Some("plan") => "planning",
+                Some("diff") => "diffing",
+                Some("verify") => "verifying",
+                Some("test") => "testing",
+                _ => "running",
+            }
+        }
+        PersistedWorkflowStatus::AwaitingApproval => "approval",
+        PersistedWorkflowStatus::Blocked => "blocked",
+        PersistedWorkflowStatus::Completed => "done",
+        PersistedWorkflowStatus::Failed => "failed",
+    };
+    let mut line = format!("dao:run#{} {phase}", run.run_id);
+    if run.pending_request_id.is_some() {
+        line.push_str(" \u{26a0}1");
+    }
+    println!("{line}");
+    Ok(())
+}
+
+/// Logs a problem found while loading saved files, before the UI starts.
+fn log_startup_warning(state: &mut ShellState, context: &str, message: String) {
+    reduce(
+        state,
+        ShellAction::Runtime(RuntimeAction::AppendStructuredLog(LogEntry {
+            seq: 0,
+            id: 0,
+            level: LogLevel::Warn,
+            ts_ms: None,
+            source: LogSource::Runtime,
+            context: Some(context.to_string()),
+            message,
+            run_id: 0,
+        })),
+    );
+}
+
+fn start_ui(
+    repo: PathBuf,
+    config_path: Option<PathBuf>,
     model: Option<String>,
     provider: Option<String>,
-    intent: Option<String>,
+    personality: Option<Personality>,
+    no_history: bool,
+    color_enabled: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let repo = repo.canonicalize()?;
-    let (mut store, snapshot_path) = open_store_for_repo(&repo)?;
+    let resolved = resolve_config(
+        Some(&repo),
+        &ConfigOverrides {
+            config: config_path,
+            model,
+            provider,
+        },
+    )?;
+    let (saved, state_warning) = load_shell_state_or_warn(&repo)?;
+    let provider = resolved.session_provider(
+        saved
+            .as_ref()
+            .and_then(|state| state.sm.model_provider.as_deref()),
+    );
+    let config = resolved.config;
+    let mut state = saved.unwrap_or_else(|| {
+        ShellState::new(repo_name(&repo), Personality::Pragmatic, config.clone())
+    });
+    if let Some(warning) = state_warning {
+        log_startup_warning(&mut state, "state", warning);
+    }
+    let personality = personality.or(config.persona.personality);
+    state.artifacts.logs.apply_config(&config.logging);
+    // The saved config is whatever was resolved last session; the layers win.
+    state.config = config.clone();
+    if no_history {
+        state.interaction.chat_history.clear();
+    } else {
+        let path = chat_history_path(&repo);
+        let saved = match load_chat_history(&path) {
+            Ok(saved) => saved,
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                let backup = persistence::move_aside(&path)?;
+                log_startup_warning(
+                    &mut state,
+                    "chat-history",
+                    format!(
+                        "{}: {err}; moved to {} and starting with an empty history",
+                        path.display(),
+                        backup.display()
+                    ),
+                );
+                Vec::new()
+            }
+            Err(err) => return Err(format!("{}: {err}", path.display()).into()),
+        };
+        state.interaction.chat_history =
+            merge_chat_history(&saved, &state.interaction.chat_history, CHAT_HISTORY_CAP);
+    }
+    if let Some(personality) = personality {
+        reduce(
+            &mut state,
+            ShellAction::Runtime(RuntimeAction::SetPersonality(personality)),
+        );
+    }
+    apply_persona_config(&mut state, &config.persona);
+    if let Some(model) = config.model.default_model.clone() {
+        reduce(
+            &mut state,
+            ShellAction::Runtime(RuntimeAction::SetModelSlug(Some(model))),
+        );
+    }
+    if let Some((provider, detected)) = provider {
+        if detected {
+            reduce(
+                &mut state,
+                ShellAction::Runtime(RuntimeAction::AppendLog(format!(
+                    "No default provider configured; using {provider}, detected on this machine"
+                ))),
+            );
+        }
+        reduce(
+            &mut state,
+            ShellAction::Runtime(RuntimeAction::SetModelProvider(Some(provider))),
+        );
+    }
+    state.cwd = Some(repo.clone());
+    state.file_browser.current_path = repo.clone();
+    state.file_browser.refresh_entries();
+    ui::run(state, repo, color_enabled)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_workflow(
+    repo: PathBuf,
+    policy_path: Option<PathBuf>,
+    config_path: Option<PathBuf>,
+    model: Option<String>,
+    provider: Option<String>,
+    personality: Option<Personality>,
+    intent: Option<String>,
+    no_cache: bool,
+    timeout: Option<Duration>,
+    retries: u32,
+    explain_gate: bool,
+    template: WorkflowTemplateId,
+    log_format: LogFormat,
+) -> Result<WorkflowEnd, Box<dyn std::error::Error>> {
+    let repo = repo.canonicalize()?;
+    let (store, snapshot_path) = open_store_for_repo(&repo)?;
+
+    // Pruned runs leave their ids in the snapshot's high-water mark.
+    let prior_run_id = load_latest_run(&store, &snapshot_path)?
+        .map(|run| run.run_id)
+        .unwrap_or(0)
+        .max(
+            load_snapshot_preferred(&store, &snapshot_path)?
+                .map_or(0, |snapshot| snapshot.highest_run_id()),
+        );
+    let run_id = prior_run_id.saturating_add(1);
+
+    let project_name = repo
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("repo")
+        .to_string();
+
+    let config = resolve_config(
+        Some(&repo),
+        &ConfigOverrides {
+            config: config_path,
+            model: model.clone(),
+            provider: provider.clone(),
+        },
+    )?
+    .config;
+    let persona = config.persona.clone();
+    let mut state = ShellState::new(
+        project_name,
+        personality
+            .or(persona.personality)
+            .unwrap_or(Personality::Pragmatic),
+        config,
+    );
+    apply_persona_config(&mut state, &persona);
+
+    if let Some(path) = policy_path {
+        log_format.banner(format_args!(
+            "Loading review policy from {}",
+            path.display()
+        ));
+        let content = fs::read_to_string(&path)?;
+        let policy: ReviewPolicy = serde_yaml::from_str(&content)?;
+        reduce(
+            &mut state,
+            ShellAction::Runtime(RuntimeAction::SetReviewPolicy(Box::new(policy))),
+        );
+    }
+    let policy_tier = state.approval.policy_tier;
+
+    let seq = emit_event(
+        &store,
+        log_format,
+        PersistedShellEvent::WorkflowRunStarted {
+            run_id,
+            template_id: template.as_str().to_string(),
+            execution_mode: PersistedExecutionMode::Simulated,
+            policy_tier: policy_tier.label().to_string(),
+            persona_policy: PersistedPersonaPolicy {
+                tier_ceiling: state.sm.persona_policy.tier_ceiling.label().to_string(),
+                explanation_depth: state
+                    .sm
+                    .persona_policy
+                    .explanation_depth
+                    .label()
+                    .to_string(),
+                output_format: state.sm.persona_policy.output_format.label().to_string(),
+            },
+        },
+    )?;
+    save_snapshots(&store, &snapshot_path, seq)?;
+
+    execute_workflow(
+        &repo,
+        &store,
+        &snapshot_path,
+        &mut state,
+        run_id,
+        0,
+        1,
+        policy_tier,
+        model,
+        provider,
+        intent,
+        None,
+        no_cache,
+        timeout,
+        retries,
+        explain_gate,
+        None,
+        template,
+        log_format,
+    )
+}
+
+fn watch_workflow(args: CliArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let repo = args.repo.canonicalize()?;
+    let log_format = args.log_format;
+    loop {
+        // A failed iteration is reported and the next change retries it.
+        if let Err(err) = run_workflow(
+            repo.clone(),
+            args.policy.clone(),
+            args.config.clone(),
+            args.model.clone(),
+            args.provider.clone(),
+            args.personality,
+            args.intent.clone(),
+            args.no_cache,
+            args.timeout,
+            args.retries,
+            args.explain_gate,
+            args.template
+                .unwrap_or(WorkflowTemplateId::ScanPlanDiffVerify),
+            args.log_format,
+        ) {
+            eprintln!("error: {err}");
+        }
+        // Fingerprint before the banner so an edit made right after it counts.
+        let mut unreadable = false;
+        let baseline = watch_fingerprint(&repo, &mut unreadable);
+        log_format.banner(format_args!(
+            "watching {} for changes (Ctrl+C to stop)",
+            repo.display()
+        ));
+        wait_for_repo_change(&repo, baseline, unreadable);
+        log_format.banner(format_args!("change detected, re-running workflow"));
+    }
+}
+
+// Polling the tree's fingerprint needs no platform watcher or extra dependency,
+// and a twice-a-second walk is cheap next to a workflow run.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(750);
+
+/// `repo_fingerprint`, or `None` when the repository cannot be read. The error
+/// is reported once until a read succeeds again, tracked by `unreadable`.
+fn watch_fingerprint(repo: &Path, unreadable: &mut bool) -> Option<u64> {
+    match repo_fingerprint(repo) {
+        Ok(fingerprint) => {
+            *unreadable = false;
+            Some(fingerprint)
+        }
+        Err(err) => {
+            if !*unreadable {
+                eprintln!(
+                    "warning: cannot read {}: {err}; still watching",
+                    repo.display()
+                );
+            }
+            *unreadable = true;
+            None
+        }
+    }
+}
+
+/// Returns once the tree's fingerprint differs from `baseline` and has settled.
+/// Polls that cannot read the tree are skipped.
+fn wait_for_repo_change(repo: &Path, baseline: Option<u64>, mut unreadable: bool) {
+    loop {
+        thread::sleep(WATCH_POLL_INTERVAL);
+        let Some(mut current) = watch_fingerprint(repo, &mut unreadable) else {
+            continue;
+        };
+        if Some(current) == baseline {
+            continue;
+        }
+        // Wait for the tree to settle so a burst of saves triggers a single run.
+        loop {
+            thread::sleep(WATCH_DEBOUNCE);
+            match watch_fingerprint(repo, &mut unreadable) {
+                Some(settled) if settled == current => return,
+                Some(settled) => current = settled,
+                None => {}
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn resume_workflow(
+    repo: PathBuf,
+    policy_path: Option<PathBuf>,
+    config_path: Option<PathBuf>,
+    model: Option<String>,
+    provider: Option<String>,
+    personality: Option<Personality>,
+    intent: Option<String>,
+    no_cache: bool,
+    timeout: Option<Duration>,
+    retries: u32,
+    explain_gate: bool,
+    preset_approval: Option<bool>,
+    template: Option<WorkflowTemplateId>,
+    log_format: LogFormat,
+) -> Result<WorkflowEnd, Box<dyn std::error::Error>> {
+    let repo = repo.canonicalize()?;
+    let (store, snapshot_path) = open_store_for_repo(&repo)?;
     let records = store.load()?;
     let Some(run) = load_latest_run(&store, &snapshot_path)? else {
-        println!("nothing to resume");
-        return Ok(());
+        log_format.banner(format_args!("nothing to resume"));
+        return Ok(WorkflowEnd::Stopped);
     };
+    let Some(template_id) = WorkflowTemplateId::parse(&run.template_id) else {
+        return Err(format!(
+            "malformed resume state: unknown template {:?} for run {}",
+            run.template_id, run.run_id
+        )
+        .into());
+    };
+    if let Some(requested) = template.filter(|&requested| requested != template_id) {
+        return Err(format!(
+            "run {} uses template {}, not {}",
+            run.run_id,
+            template_id.as_str(),
+            requested.as_str()
+        )
+        .into());
+    }
 
     match run.status {
         PersistedWorkflowStatus::Completed | PersistedWorkflowStatus::Failed => {
-            println!("nothing to resume");
-            return Ok(());
+            log_format.banner(format_args!("nothing to resume"));
+            Ok(WorkflowEnd::Stopped)
         }
         PersistedWorkflowStatus::AwaitingApproval => {
             let Some(request_id) = run.pending_request_id.clone() else {
@@ -430,51 +1851,93 @@ fn resume_workflow(
                     "malformed resume state: awaiting approval without pending invocation".into(),
                 );
             };
-
-            let tool_id_enum = parse_tool_id(tool_id.as_str())?;
-            if !prompt_approval(tool_id_enum)? {
-                let seq = store.append(PersistedShellEvent::ApprovalResolved {
-                    request_id,
-                    run_id: run.run_id,
-                    decision: "denied".to_string(),
-                })?;
+
+            let saved = load_shell_state(&repo)?;
+            let diff = saved
+                .as_ref()
+                .and_then(|state| state.artifacts.diff.as_ref())
+                .filter(|diff| diff.run_id == run.run_id);
+            let approved = prompt_approval(&tool_id, diff, preset_approval)?;
+            let decided_at = now_ms();
+            if !approved {
+                let seq = emit_event(
+                    &store,
+                    log_format,
+                    PersistedShellEvent::ApprovalResolved {
+                        request_id,
+                        run_id: run.run_id,
+                        decision: "denied".to_string(),
+                        timestamp_ms: decided_at,
+                    },
+                )?;
                 save_snapshots(&store, &snapshot_path, seq)?;
-                let seq = store.append(PersistedShellEvent::WorkflowStatusChanged {
-                    run_id: run.run_id,
-                    status: PersistedWorkflowStatus::Blocked,
-                    step_index: run.step_index,
-                    reason: Some("approval denied".to_string()),
-                })?;
+                let seq = emit_event(
+                    &store,
+                    log_format,
+                    PersistedShellEvent::WorkflowStatusChanged {
+                        run_id: run.run_id,
+                        status: PersistedWorkflowStatus::Blocked,
+                        step_index: run.step_index,
+                        reason: Some("approval denied".to_string()),
+                    },
+                )?;
                 save_snapshots(&store, &snapshot_path, seq)?;
-                println!("workflow blocked: approval denied");
-                return Ok(());
+                log_format.banner(format_args!("workflow blocked: approval denied"));
+                return Ok(WorkflowEnd::Blocked);
             }
 
-            let seq = store.append(PersistedShellEvent::ApprovalResolved {
-                request_id,
-                run_id: run.run_id,
-                decision: "approved".to_string(),
-            })?;
+            let seq = emit_event(
+                &store,
+                log_format,
+                PersistedShellEvent::ApprovalResolved {
+                    request_id,
+                    run_id: run.run_id,
+                    decision: "approved".to_string(),
+                    timestamp_ms: decided_at,
+                },
+            )?;
             save_snapshots(&store, &snapshot_path, seq)?;
 
-            let seq = store.append(PersistedShellEvent::WorkflowResumed { run_id: run.run_id })?;
+            let seq = emit_event(
+                &store,
+                log_format,
+                PersistedShellEvent::WorkflowResumed { run_id: run.run_id },
+            )?;
             save_snapshots(&store, &snapshot_path, seq)?;
 
-            let mut state =
-                ShellState::new(repo_name(&repo), Personality::Pragmatic, load_config()?);
+            let config = resolve_config(
+                Some(&repo),
+                &ConfigOverrides {
+                    config: config_path,
+                    ..ConfigOverrides::default()
+                },
+            )?
+            .config;
+            let persona = config.persona.clone();
+            let mut state = ShellState::new(
+                repo_name(&repo),
+                personality
+                    .or(persona.personality)
+                    .unwrap_or(Personality::Pragmatic),
+                config,
+            );
+            apply_persona_config(&mut state, &persona);
             if let Some(path) = &policy_path {
-                println!("Loading review policy from {}", path.display());
+                log_format.banner(format_args!(
+                    "Loading review policy from {}",
+                    path.display()
+                ));
                 let content = fs::read_to_string(path)?;
                 let policy: ReviewPolicy = serde_yaml::from_str(&content)?;
                 reduce(
                     &mut state,
-                    ShellAction::Runtime(RuntimeAction::SetReviewPolicy(policy)),
+                    ShellAction::Runtime(RuntimeAction::SetReviewPolicy(Box::new(policy))),
                 );
             }
             let policy_tier = policy_tier_for_run(run.run_id, &records);
-            return execute_workflow(
+            execute_workflow(
                 &repo,
-                &mut store,
+                &store,
                 &snapshot_path,
                 &mut state,
                 run.run_id,
@@ -485,34 +1948,63 @@ fn resume_workflow(
                 provider,
                 intent,
                 Some(pending_invocation_id),
-            );
+                no_cache,
+                timeout,
+                retries,
+                explain_gate,
+                preset_approval,
+                template_id,
+                log_format,
+            )
         }
         PersistedWorkflowStatus::Running | PersistedWorkflowStatus::Blocked => {
             if matches!(run.status, PersistedWorkflowStatus::Blocked)
                 && run.blocked_reason.as_deref() != Some("interrupted")
             {
-                println!("nothing to resume");
-                return Ok(());
+                log_format.banner(format_args!("nothing to resume"));
+                return Ok(WorkflowEnd::Stopped);
             }
 
-            let seq = store.append(PersistedShellEvent::WorkflowResumed { run_id: run.run_id })?;
+            let seq = emit_event(
+                &store,
+                log_format,
+                PersistedShellEvent::WorkflowResumed { run_id: run.run_id },
+            )?;
             save_snapshots(&store, &snapshot_path, seq)?;
 
-            let mut state =
-                ShellState::new(repo_name(&repo), Personality::Pragmatic, load_config()?);
+            let config = resolve_config(
+                Some(&repo),
+                &ConfigOverrides {
+                    config: config_path,
+                    ..ConfigOverrides::default()
+                },
+            )?
+            .config;
+            let persona = config.persona.clone();
+            let mut state = ShellState::new(
+                repo_name(&repo),
+                personality
+                    .or(persona.personality)
+                    .unwrap_or(Personality::Pragmatic),
+                config,
+            );
+            apply_persona_config(&mut state, &persona);
             if let Some(path) = &policy_path {
-                println!("Loading review policy from {}", path.display());
+                log_format.banner(format_args!(
+                    "Loading review policy from {}",
+                    path.display()
+                ));
                 let content = fs::read_to_string(path)?;
                 let policy: ReviewPolicy = serde_yaml::from_str(&content)?;
                 reduce(
                     &mut state,
-                    ShellAction::Runtime(RuntimeAction::SetReviewPolicy(policy)),
+                    ShellAction::Runtime(RuntimeAction::SetReviewPolicy(Box::new(policy))),
                 );
             }
             let policy_tier = policy_tier_for_run(run.run_id, &records);
             execute_workflow(
                 &repo,
-                &mut store,
+                &store,
                 &snapshot_path,
                 &mut state,
                 run.run_id,
@@ -523,6 +2015,13 @@ fn resume_workflow(
                 provider,
                 intent,
                 None,
+                no_cache,
+                timeout,
+                retries,
+                explain_gate,
+                preset_approval,
+                template_id,
+                log_format,
             )
         }
     }
@@ -531,7 +2030,7 @@ fn resume_workflow(
 #[allow(clippy::too_many_arguments)]
 fn execute_workflow(
     repo: &Path,
-    store: &mut ShellEventStore,
+    store: &ShellEventStore,
     snapshot_path: &Path,
     state: &mut ShellState,
     run_id: u64,
@@ -542,30 +2041,62 @@ fn execute_workflow(
     _provider: Option<String>,
     intent: Option<String>,
     first_invocation_override: Option<u64>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let template = workflow_template(WorkflowTemplateId::ScanPlanDiffVerify);
-    let executor = RuntimeToolExecutor;
+    no_cache: bool,
+    timeout: Option<Duration>,
+    retries: u32,
+    explain_gate: bool,
+    preset_approval: Option<bool>,
+    template_id: WorkflowTemplateId,
+    log_format: LogFormat,
+) -> Result<WorkflowEnd, Box<dyn std::error::Error>> {
+    let template = workflow_template(template_id);
+    let steps = workflow_steps(template, &state.config.tools)?;
+    let external_tools = state
+        .config
+        .tools
+        .iter()
+        .map(|tool| ExternalTool {
+            name: tool.name.clone(),
+            command: tool.command.clone(),
+            output: match tool.output {
+                ExternalToolOutputKind::Diff => ExternalToolOutput::Diff,
+                ExternalToolOutputKind::Verify => ExternalToolOutput::Verify,
+            },
+        })
+        .collect::<Vec<_>>();
+    let executor: Arc<dyn ToolExecutor + Send + Sync> = if no_cache {
+        Arc::new(RuntimeToolExecutor)
+    } else {
+        Arc::new(CachingToolExecutor::new(
+            RuntimeToolExecutor,
+            store_path(repo).join("cache"),
+        ))
+    };
+    let scan_filters = ScanFilters {
+        include: state.config.scan.include.clone(),
+        exclude: state.config.scan.exclude.clone(),
+    };
     let context = ToolExecutionContext {
         cwd: repo,
         model: model.as_deref(),
         intent: intent.as_deref(),
+        scan_filters: &scan_filters,
+        external_tools: &external_tools,
+        cancel: None,
     };
     let mut next_invocation_id = start_next_invocation.max(1);
     let mut first_override = first_invocation_override;
 
-    for (step_index, step) in template.steps.iter().enumerate().skip(start_step) {
-        let spec = ToolRegistry::get(step.tool_id);
-        let sim = simulate_tool(policy_tier, step.tool_id);
-
-        let mut risk = spec.risk_class;
-        // If a diff exists and we are past the diff generation step, use the diff's calculated risk
-        if step_index > 2 {
-            if let Some(diff) = &state.artifacts.diff {
-                risk = diff.analyze_risk();
-            }
-        }
+    for (step_index, step) in steps.iter().enumerate().skip(start_step) {
+        let default_reason = match step.builtin {
+            Some(tool_id) => simulate_tool(policy_tier, tool_id, &state.config.policy.tiers)
+                .reason
+                .to_string(),
+            None => format!("external tool {}", step.tool),
+        };
 
-        let reason = intent.clone().unwrap_or_else(|| sim.reason.to_string());
+        let risk = step_gate_risk(step_index, step, state);
+        let reason = intent.clone().unwrap_or(default_reason);
 
         reduce(
             state,
@@ -582,17 +2113,27 @@ fn execute_workflow(
             .last_gate
             .as_ref()
             .expect("Gate state should be set by AssessPolicyGate");
+        if explain_gate {
+            print_gate_explanation(&step.step_id, &step.tool, step.risk, gate, state);
+        }
 
         if gate.requirement == ApprovalGateRequirement::Deny {
-            let seq = store.append(PersistedShellEvent::WorkflowStatusChanged {
-                run_id,
-                status: PersistedWorkflowStatus::Blocked,
-                step_index,
-                reason: Some(gate.reason.to_string()),
-            })?;
+            let seq = emit_event(
+                store,
+                log_format,
+                PersistedShellEvent::WorkflowStatusChanged {
+                    run_id,
+                    status: PersistedWorkflowStatus::Blocked,
+                    step_index,
+                    reason: Some(gate.reason.to_string()),
+                },
+            )?;
             save_snapshots(store, snapshot_path, seq)?;
-            println!("🛑 Policy Blocked at {}: {}", step.step_id, gate.reason);
-            return Ok(());
+            log_format.banner(format_args!(
+                "🛑 Policy Blocked at {}: {}",
+                step.step_id, gate.reason
+            ));
+            return Ok(WorkflowEnd::Blocked);
         }
 
         let invocation_id = if step_index == start_step {
@@ -603,57 +2144,85 @@ fn execute_workflow(
 
         if gate.requirement == ApprovalGateRequirement::RequireApproval && first_override.is_none()
         {
-            println!("⚠️  Approval Required: {}", gate.reason);
+            log_format.banner(format_args!("⚠️  Approval Required: {}", gate.reason));
             let request_id = format!("req-{run_id}-{invocation_id}");
+            let gate_risk = gate.risk;
+            let requested_at = now_ms();
             let request = ApprovalRequestRecord {
                 request_id: request_id.clone(),
                 run_id,
                 action: ApprovalAction::Execute,
-                risk: spec.risk_class,
+                risk: gate_risk,
                 reason: gate.reason.clone(),
-                preview: format!("workflow tool {}", step.tool_id.as_str()).into(),
-                created_at_ms: None,
+                preview: format!("workflow tool {}", step.tool),
+                created_at_ms: Some(requested_at),
             };
             reduce(
                 state,
                 ShellAction::Runtime(RuntimeAction::RequestApproval(request)),
             );
-            store.append(PersistedShellEvent::ApprovalRequested {
-                request_id: request_id.clone(),
-                run_id,
-                invocation_id,
-                tool_id: step.tool_id.as_str().to_string(),
-                risk: spec.risk_class.label().to_string(),
-                preview: format!("workflow tool {}", step.tool_id.as_str()),
-            })?;
-
-            if !prompt_approval(step.tool_id)? {
+            emit_event(
+                store,
+                log_format,
+                PersistedShellEvent::ApprovalRequested {
+                    request_id: request_id.clone(),
+                    run_id,
+                    invocation_id,
+                    tool_id: step.tool.clone(),
+                    risk: gate_risk.label().to_string(),
+                    preview: format!("workflow tool {}", step.tool),
+                    created_at_ms: requested_at,
+                },
+            )?;
+
+            // Persist the pending request so a running UI can show the preview.
+            save_shell_state(repo, state)?;
+            let diff = state
+                .artifacts
+                .diff
+                .as_ref()
+                .filter(|diff| diff.run_id == run_id);
+            let approved = prompt_approval(&step.tool, diff, preset_approval)?;
+            let decided_at = now_ms();
+            if !approved {
                 let decision = ApprovalDecisionRecord {
                     request_id: request_id.clone(),
                     run_id,
                     action: ApprovalAction::Execute,
                     decision: ApprovalDecisionKind::Denied,
-                    timestamp_ms: 0,
+                    timestamp_ms: decided_at,
                 };
                 reduce(
                     state,
                     ShellAction::Runtime(RuntimeAction::ResolveApproval(decision)),
                 );
-                let seq = store.append(PersistedShellEvent::ApprovalResolved {
-                    request_id,
-                    run_id,
-                    decision: "denied".to_string(),
-                })?;
+                let seq = emit_event(
+                    store,
+                    log_format,
+                    PersistedShellEvent::ApprovalResolved {
+                        request_id,
+                        run_id,
+                        decision: "denied".to_string(),
+                        timestamp_ms: decided_at,
+                    },
+                )?;
                 save_snapshots(store, snapshot_path, seq)?;
-                let seq = store.append(PersistedShellEvent::WorkflowStatusChanged {
-                    run_id,
-                    status: PersistedWorkflowStatus::Blocked,
-                    step_index,
-                    reason: Some("approval denied".to_string()),
-                })?;
+                let seq = emit_event(
+                    store,
+                    log_format,
+                    PersistedShellEvent::WorkflowStatusChanged {
+                        run_id,
+                        status: PersistedWorkflowStatus::Blocked,
+                        step_index,
+                        reason: Some("approval denied".to_string()),
+                    },
+                )?;
                 save_snapshots(store, snapshot_path, seq)?;
-                println!("workflow blocked: approval denied at {}", step.step_id);
-                return Ok(());
+                log_format.banner(format_args!(
+                    "workflow blocked: approval denied at {}",
+                    step.step_id
+                ));
+                return Ok(WorkflowEnd::Blocked);
             }
 
             let decision = ApprovalDecisionRecord {
@@ -661,346 +2230,363 @@ fn execute_workflow(
                 run_id,
                 action: ApprovalAction::Execute,
                 decision: ApprovalDecisionKind::Approved,
-                timestamp_ms: 0,
+                timestamp_ms: decided_at,
             };
             reduce(
                 state,
                 ShellAction::Runtime(RuntimeAction::ResolveApproval(decision)),
             );
-            let seq = store.append(PersistedShellEvent::ApprovalResolved {
-                request_id,
-                run_id,
-                decision: "approved".to_string(),
-            })?;
+            let seq = emit_event(
+                store,
+                log_format,
+                PersistedShellEvent::ApprovalResolved {
+                    request_id,
+                    run_id,
+                    decision: "approved".to_string(),
+                    timestamp_ms: decided_at,
+                },
+            )?;
             save_snapshots(store, snapshot_path, seq)?;
         }
 
         let invocation = ToolInvocation {
             run_id,
             invocation_id,
-            tool_id: step.tool_id.as_str().to_string(),
+            tool_id: step.tool.clone(),
             requested_tier: policy_tier.label().to_string(),
         };
-        store.append(PersistedShellEvent::ToolInvocationIssued {
-            run_id,
-            invocation_id,
-            tool_id: step.tool_id.as_str().to_string(),
-        })?;
-
-        let outcome = executor.execute(invocation, &context);
+        emit_event(
+            store,
+            log_format,
+            PersistedShellEvent::ToolInvocationIssued {
+                run_id,
+                invocation_id,
+                tool_id: step.tool.clone(),
+            },
+        )?;
+
+        let step_retries = if step.retryable { retries } else { 0 };
+        let outcome = execute_tool(
+            &executor,
+            invocation,
+            &context,
+            timeout,
+            step_retries,
+            log_format,
+        );
         next_invocation_id = next_invocation_id.max(invocation_id.saturating_add(1));
+        let Some(outcome) = outcome else {
+            block_on_timeout(
+                store,
+                snapshot_path,
+                log_format,
+                run_id,
+                invocation_id,
+                step,
+                step_index,
+            )?;
+            return Ok(WorkflowEnd::Blocked);
+        };
 
+        let mut step_result = payload_to_step_result(step.artifact_tool, outcome.payload);
+        // External checks add to the built-in verify result instead of replacing it.
+        if let (None, StepResult::Verify(artifact)) = (step.builtin, &mut step_result) {
+            if let Some(previous) = state
+                .artifacts
+                .verify
+                .as_ref()
+                .filter(|verify| verify.run_id == run_id)
+            {
+                let mut checks = previous.checks.clone();
+                checks.append(&mut artifact.checks);
+                artifact.checks = checks;
+                if previous.overall == VerifyOverall::Failing {
+                    artifact.overall = VerifyOverall::Failing;
+                }
+            }
+        }
         apply_execution_outcome(
             state,
             run_id,
             invocation_id,
-            payload_to_result(step.tool_id, outcome.payload),
+            step_result,
             &outcome.result.logs,
         );
 
-        store.append(PersistedShellEvent::ToolResultRecorded {
-            run_id,
-            invocation_id,
-            tool_id: step.tool_id.as_str().to_string(),
-            status: status_label(outcome.result.status).to_string(),
-        })?;
+        emit_event(
+            store,
+            log_format,
+            PersistedShellEvent::ToolResultRecorded {
+                run_id,
+                invocation_id,
+                tool_id: step.tool.clone(),
+                status: status_label(outcome.result.status).to_string(),
+                cache_hit: outcome.result.cache_hit,
+                logs: cap_tool_logs(&outcome.result.logs),
+            },
+        )?;
 
         let workflow_status = match outcome.result.status {
             ToolInvocationStatus::Succeeded => PersistedWorkflowStatus::Running,
             ToolInvocationStatus::Failed => PersistedWorkflowStatus::Failed,
             ToolInvocationStatus::Blocked => PersistedWorkflowStatus::Blocked,
         };
-        let seq = store.append(PersistedShellEvent::WorkflowStatusChanged {
-            run_id,
-            status: workflow_status,
-            step_index: step_index.saturating_add(1),
-            reason: if outcome.result.status == ToolInvocationStatus::Succeeded {
-                None
-            } else {
-                Some("tool execution did not succeed".to_string())
+        let seq = emit_event(
+            store,
+            log_format,
+            PersistedShellEvent::WorkflowStatusChanged {
+                run_id,
+                status: workflow_status,
+                step_index: step_index.saturating_add(1),
+                reason: if outcome.result.status == ToolInvocationStatus::Succeeded {
+                    None
+                } else {
+                    Some("tool execution did not succeed".to_string())
+                },
             },
-        })?;
+        )?;
         save_snapshots(store, snapshot_path, seq)?;
 
         if outcome.result.status != ToolInvocationStatus::Succeeded {
-            println!(
+            log_format.banner(format_args!(
                 "workflow ended at {} with status {}",
                 step.step_id,
                 status_label(outcome.result.status)
-            );
-            return Ok(());
+            ));
+            return Ok(WorkflowEnd::Blocked);
         }
     }
 
     // Auto-commit if the workflow completed successfully and we have an intent
     if intent.is_some() {
-        println!("Committing changes...");
+        log_format.banner(format_args!("Committing changes..."));
         let invocation = ToolInvocation {
             run_id,
             invocation_id: next_invocation_id,
             tool_id: "git_commit".to_string(),
             requested_tier: policy_tier.label().to_string(),
         };
-        store.append(PersistedShellEvent::ToolInvocationIssued {
-            run_id,
-            invocation_id: next_invocation_id,
-            tool_id: "git_commit".to_string(),
-        })?;
-
-        let outcome = executor.execute(invocation, &context);
+        emit_event(
+            store,
+            log_format,
+            PersistedShellEvent::ToolInvocationIssued {
+                run_id,
+                invocation_id: next_invocation_id,
+                tool_id: "git_commit".to_string(),
+            },
+        )?;
+
+        // A commit is never re-run without a fresh approval.
+        let Some(outcome) = execute_tool(&executor, invocation, &context, timeout, 0, log_format)
+        else {
+            let seq = emit_event(
+                store,
+                log_format,
+                PersistedShellEvent::WorkflowStatusChanged {
+                    run_id,
+                    status: PersistedWorkflowStatus::Blocked,
+                    step_index: steps.len(),
+                    reason: Some("timeout".to_string()),
+                },
+            )?;
+            save_snapshots(store, snapshot_path, seq)?;
+            log_format.banner(format_args!("workflow blocked: git_commit timed out"));
+            return Ok(WorkflowEnd::Blocked);
+        };
+        if let ToolExecutionPayload::Commit { hash, .. } = &outcome.payload {
+            if outcome.result.status == ToolInvocationStatus::Succeeded && !hash.is_empty() {
+                emit_event(
+                    store,
+                    log_format,
+                    PersistedShellEvent::CommitRecorded {
+                        run_id,
+                        hash: hash.clone(),
+                    },
+                )?;
+            }
+        }
         apply_execution_outcome(
             state,
             run_id,
             next_invocation_id,
-            payload_to_result(ToolId::ScanRepo, outcome.payload), // Use ScanRepo as placeholder since Unknown doesn't exist
+            payload_to_step_result(ToolId::ScanRepo, outcome.payload), // Use ScanRepo as placeholder since Unknown doesn't exist
             &outcome.result.logs,
         );
     }
 
     save_shell_state(repo, state)?;
-    let seq = store.append(PersistedShellEvent::WorkflowStatusChanged {
-        run_id,
-        status: PersistedWorkflowStatus::Completed,
-        step_index: template.steps.len(),
-        reason: None,
-    })?;
+    let seq = emit_event(
+        store,
+        log_format,
+        PersistedShellEvent::WorkflowStatusChanged {
+            run_id,
+            status: PersistedWorkflowStatus::Completed,
+            step_index: steps.len(),
+            reason: None,
+        },
+    )?;
     save_snapshots(store, snapshot_path, seq)?;
 
-    println!("workflow {run_id} completed");
-    println!(
+    log_format.banner(format_args!("workflow {run_id} completed"));
+    let awaiting_commit = intent.is_none()
+        && state
+            .artifacts
+            .diff
+            .as_ref()
+            .is_some_and(|diff| diff.run_id == run_id && !diff.files.is_empty());
+    if awaiting_commit {
+        let path = write_approval_summary(repo, state, run_id)?;
+        log_format.banner(format_args!("approval summary: {}", path.display()));
+    }
+    log_format.banner(format_args!(
         "events: {}",
         store_path(repo).join("workflow-events.jsonl").display()
-    );
-    println!(
+    ));
+    log_format.banner(format_args!(
         "snapshot: {}",
         store_path(repo).join("snapshot.json").display()
-    );
+    ));
 
-    // Auto-open UI after workflow completion
-    start_ui(repo.to_path_buf(), None, None)?;
-    Ok(())
+    Ok(WorkflowEnd::Completed)
 }
 
-enum StepResult {
-    System(SystemArtifact),
-    Plan(PlanArtifact),
-    Diff(DiffArtifact),
-    Verify(VerifyArtifact),
-    Commit(SystemArtifact),
+/// Wait before the first retry of a failed tool; it doubles for each retry.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Runs one tool, on a worker thread when a deadline is set, and retries it up
+/// to `retries` times while it fails. Each failed attempt is announced and kept
+/// at the front of the final outcome's logs. `None` means the tool was still
+/// running when the deadline passed.
+fn execute_tool(
+    executor: &Arc<dyn ToolExecutor + Send + Sync>,
+    invocation: ToolInvocation,
+    context: &ToolExecutionContext<'_>,
+    timeout: Option<Duration>,
+    retries: u32,
+    log_format: LogFormat,
+) -> Option<ToolExecutionOutcome> {
+    let tool = invocation.tool_id.clone();
+    let mut attempt_logs = Vec::new();
+    let mut outcome = execute_with_retries(
+        retries,
+        RETRY_BACKOFF,
+        || match timeout {
+            Some(timeout) => execute_with_timeout(executor, invocation.clone(), context, timeout),
+            None => Some(executor.execute(invocation.clone(), context)),
+        },
+        |retry, wait, failed| {
+            let reason = failed.result.logs.last().map_or("", String::as_str);
+            let line = format!(
+                "attempt {retry} of {} failed: {reason}; retrying in {} ms",
+                retries + 1,
+                wait.as_millis()
+            );
+            log_format.banner(format_args!("{tool} {line}"));
+            attempt_logs.push(line);
+        },
+    )?;
+    outcome.result.logs.splice(0..0, attempt_logs);
+    Some(outcome)
 }
 
-fn payload_to_result(tool_id: ToolId, payload: ToolExecutionPayload) -> StepResult {
-    match (tool_id, payload) {
-        (
-            ToolId::ScanRepo,
-            ToolExecutionPayload::System {
-                summary,
-                detected_stack,
-                entrypoints,
-                risk_flags,
-            },
-        ) => StepResult::System(SystemArtifact {
-            schema_version: ARTIFACT_SCHEMA_V1,
-            run_id: 0,
-            artifact_id: 0,
-            repo_root: String::new(),
-            detected_stack,
-            entrypoints,
-            risk_flags,
-            summary,
-            error: None,
-        }),
-        (ToolId::GeneratePlan, ToolExecutionPayload::Plan { steps }) => {
-            StepResult::Plan(PlanArtifact {
-                schema_version: ARTIFACT_SCHEMA_V1,
-                run_id: 0,
-                artifact_id: 0,
-                title: "Workflow plan".to_string(),
-                steps: steps
-                    .into_iter()
-                    .enumerate()
-                    .map(|(idx, label)| PlanStep {
-                        id: format!("step-{}", idx.saturating_add(1)),
-                        label,
-                        status: StepStatus::Pending,
-                    })
-                    .collect(),
-                assumptions: Vec::new(),
-                error: None,
-            })
-        }
-        (ToolId::ComputeDiff, ToolExecutionPayload::Diff { unified_diff }) => {
-            let files = legacy_diff_files_from_text(&unified_diff);
-            StepResult::Diff(DiffArtifact {
-                schema_version: ARTIFACT_SCHEMA_V1,
-                run_id: 0,
-                artifact_id: 0,
-                files,
-                summary: "Diff preview".to_string(),
-                error: None,
-            })
-        }
-        (ToolId::Verify, ToolExecutionPayload::Verify { checks, passing }) => {
-            StepResult::Verify(VerifyArtifact {
-                schema_version: ARTIFACT_SCHEMA_V1,
-                run_id: 0,
-                artifact_id: 0,
-                checks: checks
-                    .into_iter()
-                    .map(|check| VerifyCheck {
-                        name: check,
-                        status: if passing {
-                            VerifyCheckStatus::Pass
-                        } else {
-                            VerifyCheckStatus::Fail
-                        },
-                        details: None,
-                    })
-                    .collect(),
-                overall: if passing {
-                    VerifyOverall::Passing
-                } else {
-                    VerifyOverall::Failing
-                },
-                error: if passing {
-                    None
-                } else {
-                    Some(ArtifactError {
-                        kind: ErrorKind::Runtime,
-                        message: "verification failed".into(),
-                    })
-                },
-            })
-        }
-        (_, ToolExecutionPayload::Commit { hash, message }) => StepResult::Commit(SystemArtifact {
-            schema_version: ARTIFACT_SCHEMA_V1,
-            run_id: 0,
-            artifact_id: 0,
-            repo_root: String::new(),
-            detected_stack: Vec::new(),
-            entrypoints: Vec::new(),
-            risk_flags: Vec::new(),
-            summary: format!("Committed {}: {}", hash, message),
-            error: None,
-        }),
-        (_, _) => StepResult::Plan(PlanArtifact {
-            schema_version: ARTIFACT_SCHEMA_V1,
-            run_id: 0,
-            artifact_id: 0,
-            title: "Workflow plan".to_string(),
-            steps: Vec::new(),
-            assumptions: Vec::new(),
-            error: Some(ArtifactError {
-                kind: ErrorKind::Unknown,
-                message: "payload mismatch".into(),
-            }),
-        }),
-    }
+/// Records a step whose tool missed its deadline. The step index is left at
+/// the timed-out step so `resume` runs it again.
+fn block_on_timeout(
+    store: &ShellEventStore,
+    snapshot_path: &Path,
+    log_format: LogFormat,
+    run_id: u64,
+    invocation_id: u64,
+    step: &WorkflowStep,
+    step_index: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    emit_event(
+        store,
+        log_format,
+        PersistedShellEvent::ToolResultRecorded {
+            run_id,
+            invocation_id,
+            tool_id: step.tool.clone(),
+            status: status_label(ToolInvocationStatus::Blocked).to_string(),
+            cache_hit: false,
+            logs: Vec::new(),
+        },
+    )?;
+    let seq = emit_event(
+        store,
+        log_format,
+        PersistedShellEvent::WorkflowStatusChanged {
+            run_id,
+            status: PersistedWorkflowStatus::Blocked,
+            step_index,
+            reason: Some("timeout".to_string()),
+        },
+    )?;
+    save_snapshots(store, snapshot_path, seq)?;
+    log_format.banner(format_args!(
+        "workflow blocked: {} timed out at {}",
+        step.tool, step.step_id
+    ));
+    Ok(())
 }
 
-fn legacy_diff_files_from_text(text: &str) -> Vec<DiffFile> {
-    let mut files = Vec::new();
-    let mut current_file: Option<DiffFile> = None;
-    let mut current_hunk: Option<DiffHunk> = None;
-
-    let finish_hunk = |file: &mut Option<DiffFile>, hunk: &mut Option<DiffHunk>| {
-        if let Some(hunk_value) = hunk.take() {
-            if let Some(file_value) = file.as_mut() {
-                file_value.hunks.push(hunk_value);
-            }
-        }
-    };
-
-    let finish_file =
-        |files: &mut Vec<DiffFile>, file: &mut Option<DiffFile>, hunk: &mut Option<DiffHunk>| {
-            finish_hunk(file, hunk);
-            if let Some(file_value) = file.take() {
-                files.push(file_value);
-            }
-        };
-
-    for line in text.lines() {
-        if let Some(path) = line.strip_prefix("+++ b/") {
-            finish_file(&mut files, &mut current_file, &mut current_hunk);
-            current_file = Some(DiffFile {
-                path: path.to_string(),
-                status: DiffFileStatus::Modified,
-                hunks: Vec::new(),
-            });
-            continue;
-        }
-
-        if let Some(path) = line.strip_prefix("+++ ") {
-            finish_file(&mut files, &mut current_file, &mut current_hunk);
-            current_file = Some(DiffFile {
-                path: path.to_string(),
-                status: DiffFileStatus::Modified,
-                hunks: Vec::new(),
-            });
-            continue;
-        }
-
-        if let Some(header) = line.strip_prefix("@@") {
-            finish_hunk(&mut current_file, &mut current_hunk);
-            current_hunk = Some(DiffHunk {
-                header: format!("@@{header}"),
-                lines: Vec::new(),
-            });
-            continue;
-        }
-
-        let kind = if line.starts_with('+') {
-            Some(DiffLineKind::Add)
-        } else if line.starts_with('-') {
-            Some(DiffLineKind::Remove)
-        } else if !line.is_empty() {
-            Some(DiffLineKind::Context)
-        } else {
-            None
-        };
+/// How `run` and `resume` report progress: human-readable banners on stdout,
+/// or one JSON line per persisted event on stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    Text,
+    /// `Text` without emoji, when color is disabled.
+    Plain,
+    Json,
+}
 
-        if let Some(kind) = kind {
-            if current_hunk.is_none() {
-                current_hunk = Some(DiffHunk {
-                    header: "@@".to_string(),
-                    lines: Vec::new(),
-                });
-            }
-            if let Some(hunk) = current_hunk.as_mut() {
-                hunk.lines.push(DiffLine {
-                    kind,
-                    text: line.to_string(),
-                });
-            }
+impl LogFormat {
+    fn banner(self, message: std::fmt::Arguments) {
+        match self {
+            Self::Text => println!("{message}"),
+            Self::Plain => println!("{}", strip_emoji(&message.to_string())),
+            Self::Json => {}
         }
     }
+}
 
-    finish_file(&mut files, &mut current_file, &mut current_hunk);
-
-    if files.is_empty() {
-        files.push(DiffFile {
-            path: "<patch>".to_string(),
-            status: DiffFileStatus::Modified,
-            hunks: vec![DiffHunk {
-                header: "@@".to_string(),
-                lines: text
-                    .lines()
-                    .map(|line| DiffLine {
-                        kind: if line.starts_with('+') {
-                            DiffLineKind::Add
-                        } else if line.starts_with('-') {
-                            DiffLineKind::Remove
-                        } else {
-                            DiffLineKind::Context
-                        },
-                        text: line.to_string(),
-                    })
-                    .collect(),
-            }],
-        });
+/// Drops pictographs and their joiners and variation selectors, and the
+/// spacing left in front of the text.
+fn strip_emoji(text: &str) -> String {
+    text.chars()
+        .filter(|&c| {
+            !matches!(
+                c,
+                '\u{200d}' | '\u{fe0f}' | '\u{2190}'..='\u{2bff}' | '\u{1f000}'..='\u{1faff}'
+            )
+        })
+        .collect::<String>()
+        .trim_start()
+        .to_string()
+}
+
+/// Appends `event` to the log; in JSON mode the stored record, timestamp
+/// first, is echoed to stderr as well.
+fn emit_event(
+    store: &ShellEventStore,
+    log_format: LogFormat,
+    event: PersistedShellEvent,
+) -> io::Result<u64> {
+    let record = store.append_record(event)?;
+    if log_format == LogFormat::Json {
+        let line = serde_json::to_string(&record).map_err(io::Error::other)?;
+        eprintln!("{line}");
     }
+    Ok(record.seq)
+}
 
-    files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WorkflowEnd {
+    Completed,
+    /// Nothing ran, e.g. there was nothing to resume.
+    Stopped,
+    /// A policy gate, an approval denial or a failing tool ended the run.
+    Blocked,
 }
 
 fn apply_execution_outcome(
@@ -1058,6 +2644,7 @@ fn apply_execution_outcome(
             state,
             ShellAction::Runtime(RuntimeAction::AppendStructuredLog(LogEntry {
                 seq: 0,
+                id: 0,
                 level: LogLevel::Info,
                 ts_ms: None,
                 source: LogSource::Runtime,
@@ -1080,10 +2667,22 @@ fn open_store_for_repo(
     Ok((store, snapshot_path))
 }
 
+/// Opens the store for a command that only reads it. A repo without `.dao`
+/// reads as having no runs, and the directory is left uncreated.
+fn open_store_read_only(repo: &Path) -> (ShellEventStore, PathBuf) {
+    let dao_dir = store_path(repo);
+    let store = ShellEventStore::open_read_only(dao_dir.join("workflow-events.jsonl"));
+    (store, dao_dir.join("snapshot.json"))
+}
+
 fn store_path(repo: &Path) -> PathBuf {
     repo.join(".dao")
 }
 
+fn chat_history_path(repo: &Path) -> PathBuf {
+    store_path(repo).join("chat-history.json")
+}
+
 fn load_latest_run(
     store: &ShellEventStore,
     snapshot_path: &Path,
@@ -1097,6 +2696,33 @@ fn load_latest_run(
     Ok(replay_latest_workflow(&records))
 }
 
+/// Loads `run_id`, or the latest run when `None`. The snapshot is only a
+/// starting point when it holds the requested run.
+fn load_run(
+    store: &ShellEventStore,
+    snapshot_path: &Path,
+    run_id: Option<u64>,
+) -> Result<Option<ReplayedWorkflowRun>, Box<dyn std::error::Error>> {
+    let Some(run_id) = run_id else {
+        return load_latest_run(store, snapshot_path);
+    };
+    if let Some(snapshot) = load_snapshot_preferred(store, snapshot_path)? {
+        if snapshot
+            .workflow
+            .as_ref()
+            .is_some_and(|run| run.run_id == run_id)
+        {
+            let tail = store
+                .load_since(snapshot.seq)?
+                .into_iter()
+                .filter(|record| record.event.run_id() == Some(run_id))
+                .collect::<Vec<_>>();
+            return Ok(replay_workflow_from(snapshot.workflow, &tail));
+        }
+    }
+    Ok(replay_workflow_run(run_id, &store.load()?))
+}
+
 fn load_snapshot_preferred(
     store: &ShellEventStore,
     snapshot_path: &Path,
@@ -1112,18 +2738,46 @@ fn load_snapshot_preferred(
 fn save_shell_state(repo: &Path, state: &ShellState) -> Result<(), Box<dyn std::error::Error>> {
     let path = store_path(repo).join("state.json");
     let bytes = serde_json::to_vec_pretty(state)?;
-    fs::write(path, bytes)?;
+    write_atomic(&path, &bytes)?;
     Ok(())
 }
 
+/// Reads `.dao/state.json` for a command that does not save it; a file that
+/// cannot be used is left in place and reported as no saved state.
 fn load_shell_state(repo: &Path) -> Result<Option<ShellState>, Box<dyn std::error::Error>> {
     let path = store_path(repo).join("state.json");
-    if !path.exists() {
-        return Ok(None);
-    }
-    let bytes = fs::read(path)?;
-    let state: ShellState = serde_json::from_slice(&bytes)?;
-    Ok(Some(state))
+    Ok(match persistence::read_shell_state(&path)? {
+        LoadedShellState::Loaded(state) => Some(*state),
+        LoadedShellState::Unusable { reason } | LoadedShellState::Discarded { reason, .. } => {
+            eprintln!("warning: {}: {reason}; ignoring it", path.display());
+            None
+        }
+        LoadedShellState::Missing => None,
+    })
+}
+
+/// Loads `.dao/state.json` before saving over it; a file that cannot be used
+/// is moved aside and the returned warning says why, leaving the caller to
+/// start from a fresh state.
+fn load_shell_state_or_warn(
+    repo: &Path,
+) -> Result<(Option<ShellState>, Option<String>), Box<dyn std::error::Error>> {
+    let path = store_path(repo).join("state.json");
+    Ok(match persistence::load_shell_state(&path)? {
+        LoadedShellState::Missing => (None, None),
+        LoadedShellState::Loaded(state) => (Some(*state), None),
+        LoadedShellState::Unusable { reason } => {
+            (None, Some(format!("{}: {reason}", path.display())))
+        }
+        LoadedShellState::Discarded { backup, reason } => (
+            None,
+            Some(format!(
+                "{}: {reason}; moved to {} and starting fresh",
+                path.display(),
+                backup.display()
+            )),
+        ),
+    })
 }
 
 fn save_snapshots(
@@ -1131,7 +2785,11 @@ fn save_snapshots(
     snapshot_path: &Path,
     seq: u64,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let workflow = replay_latest_workflow(&store.load()?).map(|mut run| {
+    let max_run_id = load_snapshot_preferred(store, snapshot_path)?
+        .map_or(0, |snapshot| snapshot.highest_run_id());
+    // Replays from the previous snapshot so a compacted log still carries the
+    // current run forward.
+    let workflow = load_latest_run(store, snapshot_path)?.map(|mut run| {
         if run.status == PersistedWorkflowStatus::Running {
             run.status = PersistedWorkflowStatus::Blocked;
             run.blocked_reason = Some("interrupted".to_string());
@@ -1141,13 +2799,24 @@ fn save_snapshots(
     let snapshot = PersistedShellSnapshot {
         version: 1,
         seq,
+        max_run_id: workflow
+            .as_ref()
+            .map_or(0, |run| run.run_id)
+            .max(max_run_id),
         workflow,
     };
     store.save_snapshot(&snapshot)?;
-    fs::write(snapshot_path, serde_json::to_vec_pretty(&snapshot)?)?;
+    write_atomic(snapshot_path, &serde_json::to_vec_pretty(&snapshot)?)?;
     Ok(())
 }
 
+/// Wall-clock time in ms since the epoch, for audit timestamps.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
 fn repo_name(repo: &Path) -> String {
     repo.file_name()
         .and_then(|name| name.to_str())
@@ -1155,25 +2824,173 @@ fn repo_name(repo: &Path) -> String {
         .to_string()
 }
 
-fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
-    if let Some(config_dir) = dirs::config_dir() {
-        let config_path = config_dir.join("dao").join("config.toml");
-        if config_path.exists() {
-            let content = fs::read_to_string(config_path)?;
-            let config: Config = toml::from_str(&content)?;
-            return Ok(config);
+fn show_config(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let as_toml = match args.first().map(String::as_str) {
+        Some("show") => false,
+        Some("--show") => true,
+        Some("--validate") => {
+            let Some(path) = args.get(1) else {
+                return Err("--validate requires a path".into());
+            };
+            config_layers::validate_config_file(Path::new(path))?;
+            println!("{path}: ok");
+            return Ok(());
+        }
+        _ => return Err(CONFIG_USAGE.into()),
+    };
+    let mut repo = None;
+    let mut overrides = ConfigOverrides::default();
+    let mut i = 1;
+    while i < args.len() {
+        let flag = args[i].as_str();
+        let Some(value) = args.get(i + 1).cloned() else {
+            return Err(format!("{flag} requires a value").into());
+        };
+        match flag {
+            "--repo" => repo = Some(PathBuf::from(value)),
+            "--config" => overrides.config = Some(PathBuf::from(value)),
+            "--model" => overrides.model = Some(value),
+            "--provider" => overrides.provider = Some(value),
+            other => return Err(format!("unsupported argument: {other}").into()),
+        }
+        i += 2;
+    }
+
+    let repo = repo.unwrap_or_else(|| PathBuf::from(".")).canonicalize()?;
+    let resolved = resolve_config(Some(&repo), &overrides)?;
+    if as_toml {
+        if resolved.files.is_empty() {
+            println!("# no config file found; built-in defaults");
+        }
+        for path in &resolved.files {
+            println!("# from {}", path.display());
+        }
+        print!("{}", toml::to_string_pretty(&resolved.config)?);
+        return Ok(());
+    }
+    println!(
+        "# layers (later wins): default < user < repo < env < --config < flag; an unset provider is detected"
+    );
+    let user = config_layers::user_config_path();
+    let repo_path = config_layers::repo_config_path(&repo);
+    for (label, path) in [
+        ("user", user.as_deref()),
+        ("repo", Some(repo_path.as_path())),
+    ] {
+        match path {
+            Some(path) if path.exists() => println!("# {label}: {}", path.display()),
+            Some(path) => println!("# {label}: {} (not found)", path.display()),
+            None => println!("# {label}: (no config directory)"),
+        }
+    }
+    for (key, value) in &resolved.values {
+        println!("{key} = {value}  # {}", resolved.source_of(key));
+    }
+    Ok(())
+}
+
+const CONFIG_USAGE: &str = "usage: dao config show|--show [--repo PATH] [--config PATH] [--model NAME] [--provider NAME] | dao config --validate PATH";
+
+fn load_config(repo: Option<&Path>) -> Result<Config, Box<dyn std::error::Error>> {
+    Ok(resolve_config(repo, &ConfigOverrides::default())?.config)
+}
+
+/// Applies the `[persona]` policy overrides to a newly built state.
+fn apply_persona_config(state: &mut ShellState, persona: &PersonaConfig) {
+    for action in persona.override_actions() {
+        reduce(state, ShellAction::Runtime(action));
+    }
+}
+
+fn parse_personality(raw: &str) -> Result<Personality, Box<dyn std::error::Error>> {
+    Personality::parse(raw)
+        .ok_or_else(|| format!("unknown personality: {raw} (expected friendly|pragmatic)").into())
+}
+
+/// A step of a workflow run: a built-in tool from the template, or an external
+/// tool from `[[tools]]` config appended after the built-ins.
+struct WorkflowStep {
+    step_id: String,
+    /// Tool id recorded in events and shown at the approval prompt.
+    tool: String,
+    builtin: Option<ToolId>,
+    risk: ApprovalRiskClass,
+    /// Whether `--retries` may re-run the tool after a failure: read-only
+    /// built-ins and external tools marked `idempotent`.
+    retryable: bool,
+    /// Built-in tool whose artifact the step's payload becomes.
+    artifact_tool: ToolId,
+}
+
+fn parse_template(raw: &str) -> Result<WorkflowTemplateId, Box<dyn std::error::Error>> {
+    WorkflowTemplateId::parse(&raw.replace('-', "_")).ok_or_else(|| {
+        let accepted = WorkflowTemplateId::ALL
+            .iter()
+            .map(|id| id.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("unknown template: {raw} (expected one of: {accepted})").into()
+    })
+}
+
+/// Template a persisted run was started with. Runs recorded before templates
+/// were selectable, or with an unknown id, use the default template.
+fn run_template(run: &ReplayedWorkflowRun) -> &'static WorkflowTemplate {
+    workflow_template(
+        WorkflowTemplateId::parse(&run.template_id)
+            .unwrap_or(WorkflowTemplateId::ScanPlanDiffVerify),
+    )
+}
+
+fn workflow_steps(
+    template: &WorkflowTemplate,
+    tools: &[ExternalToolConfig],
+) -> Result<Vec<WorkflowStep>, Box<dyn std::error::Error>> {
+    let mut steps = template
+        .steps
+        .iter()
+        .map(|step| WorkflowStep {
+            step_id: step.step_id.to_string(),
+            tool: step.tool_id.as_str().to_string(),
+            builtin: Some(step.tool_id),
+            risk: ToolRegistry::risk(step.tool_id),
+            retryable: ToolRegistry::risk(step.tool_id) == ApprovalRiskClass::ReadOnly,
+            artifact_tool: step.tool_id,
+        })
+        .collect::<Vec<_>>();
+    for tool in tools {
+        if tool.name.is_empty() || steps.iter().any(|step| step.tool == tool.name) {
+            return Err(format!("invalid external tool name: {:?}", tool.name).into());
         }
+        steps.push(WorkflowStep {
+            step_id: tool.name.clone(),
+            tool: tool.name.clone(),
+            builtin: None,
+            risk: tool.gate_risk(),
+            retryable: tool.idempotent,
+            artifact_tool: match tool.output {
+                ExternalToolOutputKind::Diff => ToolId::ComputeDiff,
+                ExternalToolOutputKind::Verify => ToolId::Verify,
+            },
+        });
     }
-    Ok(Config::default())
+    Ok(steps)
 }
 
-fn parse_tool_id(raw: &str) -> Result<ToolId, Box<dyn std::error::Error>> {
-    match raw {
-        "scan_repo" => Ok(ToolId::ScanRepo),
-        "generate_plan" => Ok(ToolId::GeneratePlan),
-        "compute_diff" => Ok(ToolId::ComputeDiff),
-        "verify" => Ok(ToolId::Verify),
-        _ => Err(format!("unknown tool id in replay state: {raw}").into()),
+/// Risk a step is gated with: its tool's risk or, for built-in steps after the
+/// diff is generated, the diff's calculated risk when that is higher. `verify`
+/// only checks the diff, so it takes the diff's risk as is.
+fn step_gate_risk(step_index: usize, step: &WorkflowStep, state: &ShellState) -> ApprovalRiskClass {
+    match &state.artifacts.diff {
+        Some(diff) if step.builtin.is_some() && step_index > 2 => {
+            let diff_risk = diff.assess_risk(&state.config.risk).risk;
+            if step.builtin == Some(ToolId::Verify) {
+                diff_risk
+            } else {
+                step.risk.max(diff_risk)
+            }
+        }
+        _ => step.risk,
     }
 }
 
@@ -1197,15 +3014,6 @@ fn policy_tier_for_run(run_id: u64, records: &[PersistedShellEventRecord]) -> Po
     PolicyTier::Balanced
 }
 
-fn artifact_flags(step_index: usize) -> (bool, bool, bool, bool) {
-    (
-        step_index >= 1,
-        step_index >= 2,
-        step_index >= 3,
-        step_index >= 4,
-    )
-}
-
 fn persisted_status_label(status: PersistedWorkflowStatus) -> &'static str {
     match status {
         PersistedWorkflowStatus::Running => "running",
@@ -1216,12 +3024,27 @@ fn persisted_status_label(status: PersistedWorkflowStatus) -> &'static str {
     }
 }
 
-fn prompt_approval(tool_id: ToolId) -> io::Result<bool> {
-    print!("approval required for {} [y/N]: ", tool_id.as_str());
-    io::stdout().flush()?;
-    let mut line = String::new();
-    io::stdin().read_line(&mut line)?;
-    Ok(matches!(line.trim(), "y" | "Y" | "yes" | "YES"))
+fn print_gate_explanation(
+    step_id: &str,
+    tool: &str,
+    tool_risk: ApprovalRiskClass,
+    gate: &PolicyGateState,
+    state: &ShellState,
+) {
+    println!("gate {step_id} ({tool}):");
+    println!("  tier: {}", state.approval.policy_tier.label());
+    println!("  tool risk: {}", tool_risk.label());
+    match state.approval.risk_override {
+        Some(risk) => println!("  risk: {} (override)", risk.label()),
+        None => println!("  risk: {}", gate.risk.label()),
+    }
+    match (&state.approval.active_policy, &gate.matched_rule) {
+        (Some(policy), Some(rule)) => println!("  policy: {} rule {rule}", policy.id),
+        (Some(policy), None) => println!("  policy: {} (no rule matched, default)", policy.id),
+        (None, _) => println!("  policy: none (tier defaults)"),
+    }
+    println!("  requirement: {}", gate.requirement.label());
+    println!("  reason: {}", gate.reason);
 }
 
 fn status_label(status: ToolInvocationStatus) -> &'static str {
@@ -1232,6 +3055,29 @@ fn status_label(status: ToolInvocationStatus) -> &'static str {
     }
 }
 
+fn print_version(args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    match args.as_slice() {
+        [] => println!("dao {}", env!("CARGO_PKG_VERSION")),
+        [flag] if flag == "--json" => {
+            let features = env!("DAO_FEATURES")
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .collect::<Vec<_>>();
+            let info = serde_json::json!({
+                "name": "dao",
+                "version": env!("CARGO_PKG_VERSION"),
+                "git_commit": env!("DAO_GIT_COMMIT"),
+                "rustc": env!("DAO_RUSTC_VERSION"),
+                "target": format!("{}-{}", env::consts::ARCH, env::consts::OS),
+                "features": features,
+            });
+            println!("{}", serde_json::to_string_pretty(&info)?);
+        }
+        [other, ..] => return Err(format!("unsupported argument: {other}").into()),
+    }
+    Ok(())
+}
+
 fn print_help() {
     println!(
         r#"
@@ -1244,11 +3090,34 @@ fn print_help() {
     );
     println!("dao {}", env!("CARGO_PKG_VERSION"));
     println!("Usage:");
-    println!("  dao run --repo PATH [--policy PATH] [--model NAME] [--provider NAME]");
-    println!("  dao replay --last --repo PATH");
-    println!("  dao resume --repo PATH [--policy PATH] [--model NAME] [--provider NAME]");
-    println!("  dao ui [--repo PATH] [--model NAME] [--provider NAME]");
-    println!("  dao chat [--model NAME] [--provider NAME] [message]");
+    println!(
+        "  dao run --repo PATH [--template NAME] [--policy PATH] [--config PATH] [--model NAME] [--provider NAME] [--personality NAME] [--watch] [--no-cache] [--timeout-secs N] [--retries N] [--explain-gate] [--no-color] [--log-format text|json] [intent]"
+    );
+    println!("  dao replay [--last | --run N] --repo PATH");
+    println!("  dao replay --stats --repo PATH");
+    println!("  dao runs [--repo PATH] [--limit N]");
+    println!("  dao risk --diff FILE");
+    println!("  dao changes [--repo PATH]");
+    println!("  dao prompt [--repo PATH]");
+    println!("  dao cat [--repo PATH] --artifact plan|diff|system|verify [--run N]");
+    println!("  dao status [--repo PATH]");
+    println!("  dao compact [--repo PATH]");
+    println!("  dao prune [--repo PATH] (--run N | --before N | --all) [--yes]");
+    println!("  dao export [--repo PATH] [--run N] [--format json|yaml]");
+    println!("  dao simulate --policy PATH [--repo PATH] [--template NAME] [intent]");
+    println!("  dao approval-summary [--repo PATH] [--run N]");
+    println!(
+        "  dao config show|--show [--repo PATH] [--config PATH] [--model NAME] [--provider NAME]"
+    );
+    println!("  dao config --validate PATH");
+    println!(
+        "  dao resume --repo PATH [--policy PATH] [--config PATH] [--model NAME] [--provider NAME] [--personality NAME] [--no-cache] [--timeout-secs N] [--retries N] [--explain-gate] [--no-color] [--log-format text|json] [--approve | --deny]"
+    );
+    println!(
+        "  dao ui [--repo PATH] [--config PATH] [--model NAME] [--provider NAME] [--personality NAME] [--no-history] [--no-color]"
+    );
+    println!("  dao chat [--config PATH] [--model NAME] [--provider NAME] [--stream [--stream-meta] | --json] [message]");
     println!("  dao --help");
     println!("  dao --version");
+    println!("  dao version --json");
 }
@@ -1,19 +1,25 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
 use std::fs;
-use std::io::{self, BufRead, BufReader};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::sync::OnceLock;
 use std::sync::{mpsc, Arc};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crossterm::event::{
-    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers, MouseButton,
-    MouseEventKind,
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers,
+    KeyboardEnhancementFlags, MouseButton, MouseEventKind, PopKeyboardEnhancementFlags,
+    PushKeyboardEnhancementFlags,
 };
 use crossterm::execute;
 use crossterm::terminal::{
-    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+    disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+    LeaveAlternateScreen,
 };
 use ratatui::backend::{Backend, CrosstermBackend};
 use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
@@ -25,14 +31,30 @@ use ratatui::widgets::{
 use ratatui::Terminal;
 
 use dao_core::actions::RuntimeAction;
+use dao_core::actions::RuntimeFlag;
 use dao_core::actions::{filtered_palette_indices, ShellAction, UserAction, PALETTE_ITEMS};
+use dao_core::chat_context::{next_chat_summary, ExtractiveSummarizer};
+use dao_core::persistence::{decode_shell_state, save_chat_history, write_atomic};
 use dao_core::reducer::{reduce, DaoEffect, AVAILABLE_MODELS};
 use dao_core::state::{
-    DiffLineKind, JourneyState, LogLevel, ShellOverlay, ShellState, ShellTab, StepStatus, UiTheme,
+    ApprovalDecisionKind, ApprovalRequestRecord, ChatRole, DiffFile, DiffFileStatus, DiffLineKind,
+    JourneyState, JourneyStep, KeymapPreset, LogEntry, LogLevel, LogSource, ShellOverlay,
+    ShellState, ShellTab, StepStatus, TelemetrySnapshot, UiTheme, VerifyOverall, VerifyStatus,
+    FILE_PREVIEW_MAX_LINES,
 };
 
+use crate::apply_execution_outcome;
+use crate::approval_summary::record_approval_reopened;
+use crate::approval_summary::write_approval_summary;
+use dao_core::conversion::{payload_to_step_result, StepResult};
+use dao_core::tool_registry::ToolId;
+use dao_exec::contracts::ToolInvocation;
+use dao_exec::executor::{
+    RuntimeToolExecutor, ToolExecutionContext, ToolExecutionOutcome, ToolExecutor,
+};
+use dao_exec::scan::ScanFilters;
 use syntect::easy::HighlightLines;
-use syntect::highlighting::ThemeSet;
+use syntect::highlighting::{Theme, ThemeSet};
 use syntect::parsing::SyntaxSet;
 
 static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
@@ -46,10 +68,95 @@ fn get_theme_set() -> &'static ThemeSet {
     THEME_SET.get_or_init(ThemeSet::load_defaults)
 }
 
+/// Bundled syntect theme used when a UI theme maps to a missing name.
+const FALLBACK_SYNTECT_THEME: &str = "base16-ocean.dark";
+
+static EMPTY_THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Looks up `name`, falling back to `FALLBACK_SYNTECT_THEME`, then to any
+/// theme in the set, then to an unstyled theme. The flag is set when the
+/// named theme was missing.
+fn resolve_syntect_theme<'a>(themes: &'a ThemeSet, name: &str) -> (&'a Theme, bool) {
+    if let Some(theme) = themes.themes.get(name) {
+        return (theme, false);
+    }
+    let fallback = themes
+        .themes
+        .get(FALLBACK_SYNTECT_THEME)
+        .or_else(|| themes.themes.values().next())
+        .unwrap_or_else(|| EMPTY_THEME.get_or_init(Theme::default));
+    (fallback, true)
+}
+
+const TELEMETRY_CSV_HEADER: &str = "ts_ms,cpu,mem_used_mb,mem_total_mb,process_mem_mb,tps";
+
+/// Telemetry rows buffered while the UI runs; written out when `TuiGuard` drops.
+struct TelemetryCsv {
+    path: PathBuf,
+    rows: Vec<String>,
+}
+
+static TELEMETRY_CSV: Mutex<Option<TelemetryCsv>> = Mutex::new(None);
+
+fn telemetry_csv_row(sample: &TelemetrySnapshot) -> String {
+    format!(
+        "{},{:.1},{},{},{},{}",
+        sample.sample_ts_ms.unwrap_or(0),
+        sample.cpu_percent,
+        sample.mem_used_mb,
+        sample.mem_total_mb,
+        sample.process_mem_mb,
+        sample
+            .tokens_per_second
+            .map(|tps| format!("{tps:.1}"))
+            .unwrap_or_default()
+    )
+}
+
+fn record_telemetry_sample(sample: &TelemetrySnapshot) {
+    if let Ok(mut csv) = TELEMETRY_CSV.lock() {
+        if let Some(csv) = csv.as_mut() {
+            csv.rows.push(telemetry_csv_row(sample));
+        }
+    }
+}
+
+fn flush_telemetry_csv() -> io::Result<()> {
+    let Some(csv) = TELEMETRY_CSV.lock().ok().and_then(|mut csv| csv.take()) else {
+        return Ok(());
+    };
+    if csv.rows.is_empty() {
+        return Ok(());
+    }
+    if let Some(parent) = csv.path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let is_new = fs::metadata(&csv.path).map_or(true, |meta| meta.len() == 0);
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&csv.path)?;
+    if is_new {
+        writeln!(file, "{TELEMETRY_CSV_HEADER}")?;
+    }
+    for row in &csv.rows {
+        writeln!(file, "{row}")?;
+    }
+    Ok(())
+}
+
+/// Whether the terminal was asked to report modified keys such as
+/// Shift+Enter, so the guard can undo it.
+static KEYBOARD_ENHANCED: OnceLock<bool> = OnceLock::new();
+
 struct TuiGuard;
 
 impl Drop for TuiGuard {
     fn drop(&mut self) {
+        let _ = flush_telemetry_csv();
+        if KEYBOARD_ENHANCED.get().copied().unwrap_or(false) {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
         let _ = disable_raw_mode();
         let _ = execute!(
             io::stdout(),
@@ -60,7 +167,21 @@ impl Drop for TuiGuard {
     }
 }
 
-pub fn run(mut state: ShellState, repo: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+pub fn run(
+    mut state: ShellState,
+    repo: PathBuf,
+    color_enabled: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _ = COLOR_ENABLED.set(color_enabled);
+    load_custom_theme(&mut state, &repo);
+    if state.config.telemetry.record {
+        if let Ok(mut csv) = TELEMETRY_CSV.lock() {
+            *csv = Some(TelemetryCsv {
+                path: repo.join(".dao").join("telemetry.csv"),
+                rows: Vec::new(),
+            });
+        }
+    }
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(
@@ -70,6 +191,13 @@ pub fn run(mut state: ShellState, repo: PathBuf) -> Result<(), Box<dyn std::erro
         crossterm::cursor::Hide
     )?;
     let _guard = TuiGuard; // Ensures terminal is restored on exit or panic
+    if supports_keyboard_enhancement().unwrap_or(false) {
+        execute!(
+            io::stdout(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+        let _ = KEYBOARD_ENHANCED.set(true);
+    }
 
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
@@ -80,59 +208,66 @@ pub fn run(mut state: ShellState, repo: PathBuf) -> Result<(), Box<dyn std::erro
 enum UiEvent {
     Token(String),
     StreamMeta(String),
-    Finished { elapsed_ms: u64, bytes: usize },
+    ChatFailed(String),
+    Finished(StreamStats),
     AuthOutput(String),
     AuthFinished { provider: String, success: bool },
+    ToolResult(ToolRunResult),
+}
+
+/// How a streamed reply went. Providers stream text in chunks of any size,
+/// so `chunks` is not a token count.
+struct StreamStats {
+    elapsed_ms: u64,
+    chunks: usize,
+    bytes: usize,
+}
+
+struct ToolRunResult {
+    tool_id: ToolId,
+    run_id: u64,
+    artifact_id: u64,
+    outcome: ToolExecutionOutcome,
 }
 
 fn resolved_model_slug(state: &ShellState) -> &str {
-    let provider = resolved_provider(state);
-    state
-        .sm
-        .model_slug
-        .as_deref()
-        .or(state.config.model.default_model.as_deref())
-        .unwrap_or(match provider {
-            "codex" => "gpt-5",
-            "gemini" => "gemini-2.5-pro",
-            _ => "phi3:mini-128k",
-        })
+    chat_target(state).1
 }
 
 fn resolved_provider(state: &ShellState) -> &str {
-    state
-        .sm
-        .model_provider
-        .as_deref()
-        .or(state.config.model.default_provider.as_deref())
-        .unwrap_or("ollama")
+    chat_target(state).0
+}
+
+/// Provider and model for chats from the UI, resolved like `dao chat`: the
+/// session's choice first, then config, then the provider defaults.
+fn chat_target(state: &ShellState) -> (&'static str, &str) {
+    dao_exec::resolve_chat_target(
+        state
+            .sm
+            .model_provider
+            .as_deref()
+            .or(state.config.model.default_provider.as_deref()),
+        state
+            .sm
+            .model_slug
+            .as_deref()
+            .or(state.config.model.default_model.as_deref()),
+    )
 }
 
 fn chat_line_count(state: &ShellState) -> usize {
     let filter = state.selection.log_search.trim().to_ascii_lowercase();
     let mut lines = 0_usize;
-    let mut last_role = "";
-    for entry in state.artifacts.logs.iter().filter(|l| {
-        l.source == dao_core::state::LogSource::Shell
-            || l.source == dao_core::state::LogSource::Runtime
-    }) {
-        let (role, text) = if entry.message.starts_with("> ") {
-            ("you", entry.message.trim_start_matches("> "))
-        } else if let Some(content) = entry.message.strip_prefix("[assistant] ") {
-            ("assistant", content)
-        } else if let Some(content) = entry.message.strip_prefix("[meta] ") {
-            ("meta", content)
-        } else {
-            ("system", entry.message.as_str())
-        };
-        if !filter.is_empty() && !text.to_ascii_lowercase().contains(&filter) {
+    let mut last_role = None;
+    for entry in state.artifacts.chat.iter() {
+        if !message_matches_filter(&entry.text, &filter) {
             continue;
         }
-        if role != last_role {
+        if last_role != Some(entry.role) {
             lines += 1; // role label
-            last_role = role;
+            last_role = Some(entry.role);
         }
-        lines += text.split('\n').count();
+        lines += entry.text.split('\n').count();
         lines += 1; // spacer
     }
     if state.interaction.is_thinking && !state.interaction.live_assistant_preview.is_empty() {
@@ -149,42 +284,31 @@ const CHAT_COMMAND_SUGGESTIONS: &[&str] = &[
     "/auth [codex]",
     "/login [codex]",
     "/search <text|clear>",
+    "/logsearch <text|clear>",
     "/streammeta <on|off|toggle|status>",
     "/models",
     "/model <name>",
     "/provider <ollama|codex|gemini>",
-    "/tab <chat|overview|telemetry|system|plan|diff|explain|logs|files|1-9>",
-    "/theme <classic|cyberpunk|neon-noir|solar-flare|forest-zen|next|prev>",
+    "/tab <chat|overview|telemetry|system|plan|diff|explain|logs|files|approvals|1-10>",
+    "/theme <classic|cyberpunk|neon-noir|solar-flare|forest-zen|midnight-slate|custom|next|prev>",
+    "/tier <strict|balanced|permissive|next>",
+    "/personality <friendly|pragmatic>",
+    "/context <diff|recent [N]|summarize [N]>",
+    "/risk <readonly|edit|refactor|execution|destructive|clear>",
     "/panel <journey|context|actions>",
     "/telemetry",
     "/copylast",
     "/copydiff",
     "/copychat",
     "/copylogs",
+    "/verify",
+    "/approve",
+    "/reopen",
+    "/approvalsummary",
     "/focus",
     "/clear",
 ];
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum ChatRole {
-    User,
-    Assistant,
-    Meta,
-    System,
-}
-
-fn parse_chat_role(message: &str) -> (ChatRole, String) {
-    if message.starts_with("> ") {
-        (ChatRole::User, message.trim_start_matches("> ").to_string())
-    } else if let Some(content) = message.strip_prefix("[assistant] ") {
-        (ChatRole::Assistant, content.to_string())
-    } else if let Some(content) = message.strip_prefix("[meta] ") {
-        (ChatRole::Meta, content.to_string())
-    } else {
-        (ChatRole::System, message.to_string())
-    }
-}
-
 fn message_matches_filter(message: &str, filter_lower: &str) -> bool {
     if filter_lower.is_empty() {
         return true;
@@ -197,7 +321,6 @@ fn role_style(role: ChatRole, palette: UiPalette) -> Style {
         ChatRole::User => palette.accent,
         ChatRole::Assistant => palette.success,
         ChatRole::Meta => palette.muted,
-        ChatRole::System => palette.warning,
     };
     Style::default().fg(color)
 }
@@ -295,6 +418,54 @@ fn append_emphasis_spans(
     }
 }
 
+const CODE_TAB_WIDTH: usize = 4;
+
+/// Expands tabs to the next tab stop and drops ANSI escape sequences and other
+/// control characters that models sometimes leave in code output.
+fn sanitize_code_line(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut column = 0;
+    let mut chars = raw.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\t' => {
+                let pad = CODE_TAB_WIDTH - column % CODE_TAB_WIDTH;
+                out.extend(std::iter::repeat(' ').take(pad));
+                column += pad;
+            }
+            '\u{1b}' => match chars.next() {
+                // CSI: parameters up to a final byte in @..~
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC: terminated by BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\u{7}' {
+                            break;
+                        }
+                        if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            ch if ch.is_control() => {}
+            ch => {
+                out.push(ch);
+                column += 1;
+            }
+        }
+    }
+    out
+}
+
 fn render_code_block(
     out: &mut Vec<Line<'static>>,
     lines: &[String],
@@ -306,10 +477,15 @@ fn render_code_block(
         Style::default().fg(palette.muted),
     )));
     for raw in lines {
+        let line = sanitize_code_line(raw);
+        let body = line.trim_start_matches(' ');
+        // The chat pane wraps with trimming, which drops leading spaces;
+        // non-breaking spaces keep the indentation.
+        let indent = "\u{a0}".repeat(line.len() - body.len());
         let spans = vec![
             Span::styled("  ".to_string(), Style::default().fg(palette.muted)),
             Span::styled(
-                raw.to_string(),
+                format!("{indent}{body}"),
                 Style::default()
                     .fg(palette.accent_alt)
                     .bg(palette.selected_bg),
@@ -329,11 +505,177 @@ fn render_code_block(
     )));
 }
 
+const LIST_MARKERS: [&str; 3] = ["•", "◦", "▪"];
+
+/// Leading indentation of a list item, as non-breaking spaces so the chat
+/// pane's trimming wrap keeps it, plus the marker for its nesting depth.
+fn nested_list_prefix(raw: &str) -> (String, usize) {
+    let indent = raw
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { CODE_TAB_WIDTH } else { 1 })
+        .sum::<usize>()
+        .min(12);
+    ("\u{a0}".repeat(indent), indent / 2)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ColumnAlign {
+    Left,
+    Center,
+    Right,
+}
+
+fn split_table_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = line.strip_suffix('|').unwrap_or(line);
+    line.split('|')
+        .map(|cell| cell.trim().to_string())
+        .collect()
+}
+
+fn parse_table_separator(cells: &[String]) -> Option<Vec<ColumnAlign>> {
+    cells
+        .iter()
+        .map(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => ColumnAlign::Center,
+                (false, true) => ColumnAlign::Right,
+                _ => ColumnAlign::Left,
+            })
+        })
+        .collect()
+}
+
+fn fit_cell(cell: &str, width: usize, align: ColumnAlign) -> String {
+    let len = cell.chars().count();
+    if len > width {
+        let mut cut = cell
+            .chars()
+            .take(width.saturating_sub(1))
+            .collect::<String>();
+        cut.push('…');
+        return cut;
+    }
+    let pad = width - len;
+    match align {
+        ColumnAlign::Left => format!("{cell}{}", " ".repeat(pad)),
+        ColumnAlign::Right => format!("{}{cell}", " ".repeat(pad)),
+        ColumnAlign::Center => {
+            format!("{}{cell}{}", " ".repeat(pad / 2), " ".repeat(pad - pad / 2))
+        }
+    }
+}
+
+/// Renders a pipe table with columns aligned within `width`. Returns false,
+/// leaving `out` untouched, when the lines are not a well-formed table.
+fn render_table(
+    out: &mut Vec<Line<'static>>,
+    lines: &[&str],
+    width: usize,
+    base: Style,
+    strong: Style,
+    palette: UiPalette,
+) -> bool {
+    let rows = lines
+        .iter()
+        .map(|line| split_table_row(line))
+        .collect::<Vec<_>>();
+    if rows.len() < 2 {
+        return false;
+    }
+    let columns = rows[0].len();
+    let Some(aligns) = parse_table_separator(&rows[1]) else {
+        return false;
+    };
+    if aligns.len() != columns || rows.iter().any(|row| row.len() != columns) {
+        return false;
+    }
+
+    let mut widths = (0..columns)
+        .map(|col| {
+            rows.iter()
+                .enumerate()
+                .filter(|(idx, _)| *idx != 1)
+                .map(|(_, row)| row[col].chars().count())
+                .max()
+                .unwrap_or(0)
+                .max(1)
+        })
+        .collect::<Vec<_>>();
+    // "│ " + cells joined by " │ " + " │"
+    let overhead = 3 * columns + 1;
+    while widths.iter().sum::<usize>() + overhead > width {
+        let Some(widest) = (0..columns).max_by_key(|&col| widths[col]) else {
+            break;
+        };
+        if widths[widest] <= 3 {
+            break;
+        }
+        widths[widest] -= 1;
+    }
+
+    let border = Style::default().fg(palette.muted);
+    for (idx, row) in rows.iter().enumerate() {
+        if idx == 1 {
+            let rule = widths
+                .iter()
+                .map(|w| "─".repeat(w + 2))
+                .collect::<Vec<_>>()
+                .join("┼");
+            out.push(Line::from(Span::styled(format!("├{rule}┤"), border)));
+            continue;
+        }
+        let style = if idx == 0 { strong } else { base };
+        let mut spans = vec![Span::styled("│ ".to_string(), border)];
+        for (col, cell) in row.iter().enumerate() {
+            if col > 0 {
+                spans.push(Span::styled(" │ ".to_string(), border));
+            }
+            spans.push(Span::styled(
+                fit_cell(cell, widths[col], aligns[col]),
+                style,
+            ));
+        }
+        spans.push(Span::styled(" │".to_string(), border));
+        out.push(Line::from(spans));
+    }
+    true
+}
+
+#[allow(clippy::too_many_arguments)]
+fn flush_table(
+    out: &mut Vec<Line<'static>>,
+    table_lines: &mut Vec<&str>,
+    width: usize,
+    base: Style,
+    code_inline: Style,
+    strong: Style,
+    italic: Style,
+    palette: UiPalette,
+) {
+    if table_lines.is_empty() {
+        return;
+    }
+    if !render_table(out, table_lines, width, base, strong, palette) {
+        for line in table_lines.iter() {
+            push_with_inline_code(out, "  ", line, base, code_inline, strong, italic);
+        }
+    }
+    table_lines.clear();
+}
+
 fn render_chat_message(
     out: &mut Vec<Line<'static>>,
     role: ChatRole,
     message: &str,
     palette: UiPalette,
+    width: usize,
 ) {
     let base = role_style(role, palette);
     let code_inline = Style::default()
@@ -345,9 +687,25 @@ fn render_chat_message(
     let mut in_code = false;
     let mut code_lang = String::new();
     let mut code_lines: Vec<String> = Vec::new();
+    let mut table_lines: Vec<&str> = Vec::new();
 
     for raw in message.split('\n') {
         let trimmed = raw.trim_start();
+        if !in_code && role == ChatRole::Assistant && trimmed.starts_with('|') {
+            table_lines.push(raw);
+            continue;
+        }
+        flush_table(
+            out,
+            &mut table_lines,
+            width,
+            base,
+            code_inline,
+            strong,
+            italic,
+            palette,
+        );
+
         if let Some(rest) = trimmed.strip_prefix("```") {
             if !in_code {
                 in_code = true;
@@ -367,7 +725,7 @@ fn render_chat_message(
             continue;
         }
 
-        if role == ChatRole::Meta || role == ChatRole::System {
+        if role == ChatRole::Meta {
             push_with_inline_code(out, "  ", raw, base, code_inline, strong, italic);
             continue;
         }
@@ -391,13 +749,11 @@ fn render_chat_message(
         if let Some(item) = trimmed
             .strip_prefix("- ")
             .or_else(|| trimmed.strip_prefix("* "))
+            .or_else(|| trimmed.strip_prefix("• "))
         {
-            push_with_inline_code(out, "  • ", item, base, code_inline, strong, italic);
-            continue;
-        }
-
-        if let Some(item) = trimmed.strip_prefix("• ") {
-            push_with_inline_code(out, "  • ", item, base, code_inline, strong, italic);
+            let (indent, depth) = nested_list_prefix(raw);
+            let prefix = format!("  {indent}{} ", LIST_MARKERS[depth % LIST_MARKERS.len()]);
+            push_with_inline_code(out, &prefix, item, base, code_inline, strong, italic);
             continue;
         }
 
@@ -417,8 +773,9 @@ fn render_chat_message(
         if let Some(dot) = trimmed.find(". ") {
             if trimmed[..dot].chars().all(|c| c.is_ascii_digit()) {
                 let (n, text) = trimmed.split_at(dot + 2);
+                let (indent, _) = nested_list_prefix(raw);
                 let mut spans = vec![Span::styled(
-                    "  ".to_string(),
+                    format!("  {indent}"),
                     Style::default().fg(palette.accent_alt),
                 )];
                 spans.push(Span::styled(
@@ -436,29 +793,35 @@ fn render_chat_message(
         push_with_inline_code(out, "  ", raw, base, code_inline, strong, italic);
     }
 
+    flush_table(
+        out,
+        &mut table_lines,
+        width,
+        base,
+        code_inline,
+        strong,
+        italic,
+        palette,
+    );
     if in_code {
         render_code_block(out, &code_lines, code_lang.as_str(), palette);
     }
 }
 
-fn build_chat_lines(state: &ShellState, palette: UiPalette) -> Vec<Line<'static>> {
+fn build_chat_lines(state: &ShellState, palette: UiPalette, width: usize) -> Vec<Line<'static>> {
     let filter = state.selection.log_search.trim().to_ascii_lowercase();
-    let mut grouped: Vec<(ChatRole, Vec<String>)> = Vec::new();
-    for entry in state.artifacts.logs.iter().filter(|l| {
-        l.source == dao_core::state::LogSource::Shell
-            || l.source == dao_core::state::LogSource::Runtime
-    }) {
-        let (role, text) = parse_chat_role(&entry.message);
-        if !message_matches_filter(&text, &filter) {
+    let mut grouped: Vec<(ChatRole, Vec<&str>)> = Vec::new();
+    for entry in state.artifacts.chat.iter() {
+        if !message_matches_filter(&entry.text, &filter) {
             continue;
         }
         if let Some((last_role, lines)) = grouped.last_mut() {
-            if *last_role == role {
-                lines.push(text);
+            if *last_role == entry.role {
+                lines.push(&entry.text);
                 continue;
             }
         }
-        grouped.push((role, vec![text]));
+        grouped.push((entry.role, vec![&entry.text]));
     }
 
     let mut out = Vec::new();
@@ -467,7 +830,6 @@ fn build_chat_lines(state: &ShellState, palette: UiPalette) -> Vec<Line<'static>
             ChatRole::User => ("[You]", palette.accent),
             ChatRole::Assistant => ("[Assistant]", palette.success),
             ChatRole::Meta => ("[Meta]", palette.muted),
-            ChatRole::System => ("[System]", palette.warning),
         };
         out.push(Line::from(Span::styled(
             label.to_string(),
@@ -475,7 +837,7 @@ fn build_chat_lines(state: &ShellState, palette: UiPalette) -> Vec<Line<'static>
         )));
 
         for message in messages {
-            render_chat_message(&mut out, role, &message, palette);
+            render_chat_message(&mut out, role, message, palette, width);
             out.push(Line::from(""));
         }
     }
@@ -492,6 +854,7 @@ fn build_chat_lines(state: &ShellState, palette: UiPalette) -> Vec<Line<'static>
             ChatRole::Assistant,
             &state.interaction.live_assistant_preview,
             palette,
+            width,
         );
         out.push(Line::from(""));
     }
@@ -499,7 +862,7 @@ fn build_chat_lines(state: &ShellState, palette: UiPalette) -> Vec<Line<'static>
     out
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 struct UiPalette {
     accent: Color,
     accent_alt: Color,
@@ -512,9 +875,18 @@ struct UiPalette {
     selected_bg: Color,
 }
 
-fn palette_for(theme: UiTheme) -> UiPalette {
-    match theme {
-        UiTheme::Classic => UiPalette {
+struct ThemeStyle {
+    theme: UiTheme,
+    palette: UiPalette,
+    syntect: &'static str,
+}
+
+/// Palette and syntect theme for each `UiTheme`; the first row is the
+/// fallback.
+const THEME_STYLES: &[ThemeStyle] = &[
+    ThemeStyle {
+        theme: UiTheme::Classic,
+        palette: UiPalette {
             accent: Color::Cyan,
             accent_alt: Color::Blue,
             success: Color::Green,
@@ -525,7 +897,11 @@ fn palette_for(theme: UiTheme) -> UiPalette {
             panel_bg: Color::Black,
             selected_bg: Color::DarkGray,
         },
-        UiTheme::Cyberpunk => UiPalette {
+        syntect: "base16-ocean.dark",
+    },
+    ThemeStyle {
+        theme: UiTheme::Cyberpunk,
+        palette: UiPalette {
             accent: Color::Magenta,
             accent_alt: Color::Cyan,
             success: Color::LightGreen,
@@ -536,7 +912,11 @@ fn palette_for(theme: UiTheme) -> UiPalette {
             panel_bg: Color::Black,
             selected_bg: Color::Rgb(58, 0, 58),
         },
-        UiTheme::NeonNoir => UiPalette {
+        syntect: "base16-eighties.dark",
+    },
+    ThemeStyle {
+        theme: UiTheme::NeonNoir,
+        palette: UiPalette {
             accent: Color::LightBlue,
             accent_alt: Color::LightCyan,
             success: Color::LightGreen,
@@ -547,7 +927,11 @@ fn palette_for(theme: UiTheme) -> UiPalette {
             panel_bg: Color::Black,
             selected_bg: Color::Rgb(18, 28, 42),
         },
-        UiTheme::SolarFlare => UiPalette {
+        syntect: "base16-mocha.dark",
+    },
+    ThemeStyle {
+        theme: UiTheme::SolarFlare,
+        palette: UiPalette {
             accent: Color::LightYellow,
             accent_alt: Color::LightRed,
             success: Color::Green,
@@ -558,7 +942,11 @@ fn palette_for(theme: UiTheme) -> UiPalette {
             panel_bg: Color::Black,
             selected_bg: Color::Rgb(42, 28, 0),
         },
-        UiTheme::ForestZen => UiPalette {
+        syntect: "base16-ocean.dark",
+    },
+    ThemeStyle {
+        theme: UiTheme::ForestZen,
+        palette: UiPalette {
             accent: Color::LightGreen,
             accent_alt: Color::Green,
             success: Color::Green,
@@ -569,19 +957,166 @@ fn palette_for(theme: UiTheme) -> UiPalette {
             panel_bg: Color::Black,
             selected_bg: Color::Rgb(8, 32, 10),
         },
+        syntect: "base16-ocean.dark",
+    },
+    ThemeStyle {
+        theme: UiTheme::MidnightSlate,
+        palette: UiPalette {
+            accent: Color::Rgb(122, 162, 247),
+            accent_alt: Color::Rgb(187, 154, 247),
+            success: Color::Rgb(158, 206, 106),
+            warning: Color::Rgb(224, 175, 104),
+            danger: Color::Rgb(247, 118, 142),
+            muted: Color::Rgb(86, 95, 137),
+            border: Color::Rgb(65, 72, 104),
+            panel_bg: Color::Rgb(26, 27, 38),
+            selected_bg: Color::Rgb(41, 46, 66),
+        },
+        syntect: "base16-ocean.dark",
+    },
+];
+
+/// Palette for `UiTheme::Custom`, loaded once at startup.
+static CUSTOM_PALETTE: OnceLock<UiPalette> = OnceLock::new();
+
+/// False under `--no-color` or `NO_COLOR`; set once at startup.
+static COLOR_ENABLED: OnceLock<bool> = OnceLock::new();
+
+/// Every role in the terminal's own colors, used when color is disabled.
+const MONOCHROME_PALETTE: UiPalette = UiPalette {
+    accent: Color::Reset,
+    accent_alt: Color::Reset,
+    success: Color::Reset,
+    warning: Color::Reset,
+    danger: Color::Reset,
+    muted: Color::Reset,
+    border: Color::Reset,
+    panel_bg: Color::Reset,
+    selected_bg: Color::Reset,
+};
+
+/// A custom theme file: every `UiPalette` field as a `#rrggbb` color.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CustomPaletteFile {
+    accent: String,
+    accent_alt: String,
+    success: String,
+    warning: String,
+    danger: String,
+    muted: String,
+    border: String,
+    panel_bg: String,
+    selected_bg: String,
+}
+
+fn parse_hex_color(field: &str, value: &str) -> Result<Color, String> {
+    let hex = value.trim().trim_start_matches('#');
+    let invalid = || format!("{field}: '{value}' is not a #rrggbb color");
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
     }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    Ok(Color::Rgb(channel(0)?, channel(2)?, channel(4)?))
 }
 
-fn syntect_theme_name(theme: UiTheme) -> &'static str {
-    match theme {
-        UiTheme::Classic => "base16-ocean.dark",
-        UiTheme::Cyberpunk => "base16-eighties.dark",
-        UiTheme::NeonNoir => "base16-mocha.dark",
-        UiTheme::SolarFlare => "base16-ocean.dark",
-        UiTheme::ForestZen => "base16-ocean.dark",
+fn parse_custom_palette(text: &str) -> Result<UiPalette, String> {
+    let file: CustomPaletteFile = toml::from_str(text).map_err(|err| err.message().to_string())?;
+    Ok(UiPalette {
+        accent: parse_hex_color("accent", &file.accent)?,
+        accent_alt: parse_hex_color("accent_alt", &file.accent_alt)?,
+        success: parse_hex_color("success", &file.success)?,
+        warning: parse_hex_color("warning", &file.warning)?,
+        danger: parse_hex_color("danger", &file.danger)?,
+        muted: parse_hex_color("muted", &file.muted)?,
+        border: parse_hex_color("border", &file.border)?,
+        panel_bg: parse_hex_color("panel_bg", &file.panel_bg)?,
+        selected_bg: parse_hex_color("selected_bg", &file.selected_bg)?,
+    })
+}
+
+/// Loads `[ui] custom_theme_path`, logging why when it can't be used.
+fn load_custom_theme(state: &mut ShellState, repo: &Path) {
+    let Some(path) = state.config.ui.custom_theme_path.clone() else {
+        return;
+    };
+    let path = repo.join(path);
+    let loaded = fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|text| parse_custom_palette(&text));
+    match loaded {
+        Ok(palette) => {
+            let _ = CUSTOM_PALETTE.set(palette);
+        }
+        Err(err) => {
+            reduce(
+                state,
+                ShellAction::Runtime(RuntimeAction::AppendLog(format!(
+                    "[meta] Custom theme {} not loaded: {err}",
+                    path.display()
+                ))),
+            );
+        }
+    }
+}
+
+fn theme_style(theme: UiTheme) -> &'static ThemeStyle {
+    THEME_STYLES
+        .iter()
+        .find(|style| style.theme == theme)
+        .unwrap_or(&THEME_STYLES[0])
+}
+
+fn color_enabled() -> bool {
+    COLOR_ENABLED.get().copied().unwrap_or(true)
+}
+
+fn palette_for(theme: UiTheme) -> UiPalette {
+    select_palette(theme, color_enabled())
+}
+
+fn select_palette(theme: UiTheme, color_enabled: bool) -> UiPalette {
+    if !color_enabled {
+        return MONOCHROME_PALETTE;
+    }
+    if theme == UiTheme::Custom {
+        if let Some(palette) = CUSTOM_PALETTE.get() {
+            return *palette;
+        }
+    }
+    theme_style(theme).palette
+}
+
+/// Foreground for syntax-highlighted text; the terminal default without color.
+fn syntax_color(style: syntect::highlighting::Style) -> Color {
+    if !color_enabled() {
+        return Color::Reset;
+    }
+    Color::Rgb(style.foreground.r, style.foreground.g, style.foreground.b)
+}
+
+/// Body text on overlay panels.
+fn overlay_text_color() -> Color {
+    if color_enabled() {
+        Color::White
+    } else {
+        Color::Reset
     }
 }
 
+/// The selected row of an overlay list; reverse video without color.
+fn selected_row_style(palette: UiPalette) -> Style {
+    if color_enabled() {
+        Style::default().fg(Color::Black).bg(palette.accent)
+    } else {
+        Style::default().add_modifier(Modifier::REVERSED)
+    }
+}
+
+fn syntect_theme_name(theme: UiTheme) -> &'static str {
+    theme_style(theme).syntect
+}
+
 fn tab_by_index(state: &ShellState, one_based_index: usize) -> Option<ShellTab> {
     if one_based_index == 0 {
         return None;
@@ -589,6 +1124,119 @@ fn tab_by_index(state: &ShellState, one_based_index: usize) -> Option<ShellTab>
     state.ordered_tabs().get(one_based_index - 1).copied()
 }
 
+/// Vertical layout of the whole screen: header, tabs, content, input, the
+/// action bar when shown, and the footer.
+fn screen_chunks(state: &ShellState, area: Rect) -> std::rc::Rc<[Rect]> {
+    let (header_h, tabs_h) = if state.customization.focus_mode {
+        (0, 0)
+    } else {
+        (3, 3)
+    };
+    let action_bar_h = if !state.customization.focus_mode && state.customization.show_action_bar {
+        2
+    } else {
+        0
+    };
+
+    let mut constraints = vec![
+        Constraint::Length(header_h),                         // Header
+        Constraint::Length(tabs_h),                           // Tabs
+        Constraint::Min(0),                                   // Content
+        Constraint::Length(state.customization.input_height), // Input
+    ];
+    if action_bar_h > 0 {
+        constraints.push(Constraint::Length(action_bar_h)); // Action bar
+    }
+    constraints.push(Constraint::Length(1)); // Footer
+
+    Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints(constraints)
+        .split(area)
+}
+
+/// Text columns inside the main panel's borders.
+fn content_width<B: Backend>(state: &ShellState, terminal: &Terminal<B>) -> io::Result<usize> {
+    let size = terminal.size()?;
+    let chunks = screen_chunks(state, Rect::new(0, 0, size.width, size.height));
+    let main_area = resolve_main_content_area(state, chunks[2]);
+    Ok(usize::from(main_area.width.saturating_sub(2)))
+}
+
+/// Rows `text` takes when word-wrapped to `width` columns with leading
+/// whitespace trimmed, as `Wrap { trim: true }` renders it. Words longer than
+/// the width are broken across rows.
+fn wrapped_line_count(text: &str, width: usize) -> usize {
+    if width == 0 {
+        return text.split('\n').count();
+    }
+    text.split('\n')
+        .map(|line| {
+            let mut rows = 1;
+            let mut col = 0;
+            for word in line.split_whitespace() {
+                let mut len = word.chars().count();
+                if col > 0 && col + 1 + len <= width {
+                    col += 1 + len;
+                    continue;
+                }
+                if col > 0 {
+                    rows += 1;
+                }
+                while len > width {
+                    rows += 1;
+                    len -= width;
+                }
+                col = len;
+            }
+            rows
+        })
+        .sum()
+}
+
+fn line_text(line: &Line) -> String {
+    line.spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect()
+}
+
+/// Rendered rows of the Chat or Logs view at `width`, counting wrapped lines.
+fn scrollable_rows(state: &ShellState, width: usize) -> usize {
+    let palette = palette_for(state.customization.theme);
+    let lines = if state.routing.tab == ShellTab::Chat {
+        build_chat_lines(state, palette, width)
+    } else {
+        build_log_lines(state, palette)
+    };
+    lines
+        .iter()
+        .map(|line| wrapped_line_count(&line_text(line), width))
+        .sum()
+}
+
+fn build_log_lines(state: &ShellState, palette: UiPalette) -> Vec<Line<'static>> {
+    let filter = state.selection.log_filter();
+    state
+        .artifacts
+        .logs
+        .iter()
+        .filter(|l| filter.matches(l))
+        .map(|l| {
+            let text = format!("[{:?}] {}", l.level, l.message);
+            if state.selection.log_bookmarks.contains(&l.id) {
+                Line::from(vec![
+                    Span::styled("★ ", Style::default().fg(palette.warning)),
+                    Span::raw(text),
+                ])
+            } else {
+                Line::from(text)
+            }
+        })
+        .collect()
+}
+
 fn resolve_main_content_area(state: &ShellState, content_area: Rect) -> Rect {
     let show_journey = !state.customization.focus_mode && state.customization.show_journey;
     let show_context = !state.customization.focus_mode && state.customization.show_overview;
@@ -678,6 +1326,7 @@ fn command_stdout(cmd: &str, args: &[&str]) -> Option<String> {
     Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
+#[cfg(not(target_os = "linux"))]
 fn parse_macos_cpu_percent() -> Option<f32> {
     let out = command_stdout("top", &["-l", "1", "-n", "0"])?;
     let cpu_line = out.lines().find(|l| l.contains("CPU usage:"))?;
@@ -694,6 +1343,7 @@ fn parse_macos_cpu_percent() -> Option<f32> {
     Some((100.0 - idle_pct).clamp(0.0, 100.0))
 }
 
+#[cfg(not(target_os = "linux"))]
 fn parse_macos_memory_mb() -> Option<(u64, u64)> {
     let total_bytes = command_stdout("sysctl", &["-n", "hw.memsize"])?
         .parse::<u64>()
@@ -735,24 +1385,153 @@ fn parse_process_mem_mb() -> Option<u64> {
     Some(kb / 1024)
 }
 
+/// `/proc/stat` CPU counters from the previous sample; CPU usage is the
+/// delta between two samples.
+#[cfg(target_os = "linux")]
+static LAST_PROC_STAT: std::sync::Mutex<Option<ProcStatCpu>> = std::sync::Mutex::new(None);
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ProcStatCpu {
+    idle: u64,
+    total: u64,
+}
+
+/// Parses the aggregate `cpu` line of `/proc/stat`. Idle time includes iowait.
+#[cfg(target_os = "linux")]
+fn parse_proc_stat_cpu(text: &str) -> Option<ProcStatCpu> {
+    let line = text.lines().find(|l| l.starts_with("cpu "))?;
+    let fields = line
+        .split_whitespace()
+        .skip(1)
+        .map(|v| v.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if fields.len() < 4 {
+        return None;
+    }
+    let idle = fields[3] + fields.get(4).copied().unwrap_or(0);
+    Some(ProcStatCpu {
+        idle,
+        total: fields.iter().sum(),
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_percent_between(prev: ProcStatCpu, next: ProcStatCpu) -> Option<f32> {
+    let total = next.total.checked_sub(prev.total)?;
+    let idle = next.idle.checked_sub(prev.idle)?;
+    if total == 0 {
+        return None;
+    }
+    let busy = total.saturating_sub(idle) as f32 / total as f32;
+    Some((busy * 100.0).clamp(0.0, 100.0))
+}
+
+#[cfg(target_os = "linux")]
+fn parse_linux_cpu_percent() -> Option<f32> {
+    let next = parse_proc_stat_cpu(&std::fs::read_to_string("/proc/stat").ok()?)?;
+    let mut last = LAST_PROC_STAT
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let prev = last.replace(next)?;
+    cpu_percent_between(prev, next)
+}
+
+/// Used and total memory from `/proc/meminfo`, in MB. Kernels without
+/// `MemAvailable` fall back to `MemFree + Buffers + Cached`.
+#[cfg(target_os = "linux")]
+fn parse_proc_meminfo(text: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| {
+        text.lines()
+            .find_map(|l| l.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|v| v.split_whitespace().next()?.parse::<u64>().ok())
+    };
+    let total_kb = field("MemTotal")?;
+    let available_kb = match field("MemAvailable") {
+        Some(kb) => kb,
+        None => field("MemFree")? + field("Buffers").unwrap_or(0) + field("Cached").unwrap_or(0),
+    };
+    let used_kb = total_kb.saturating_sub(available_kb);
+    Some((used_kb / 1024, total_kb / 1024))
+}
+
+#[cfg(target_os = "linux")]
+fn parse_linux_memory_mb() -> Option<(u64, u64)> {
+    parse_proc_meminfo(&std::fs::read_to_string("/proc/meminfo").ok()?)
+}
+
+/// Resident set size from `/proc/self/statm`, which counts pages.
+#[cfg(target_os = "linux")]
+fn parse_linux_process_mem_mb() -> Option<u64> {
+    const PAGE_SIZE: u64 = 4096;
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages = statm.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(pages * PAGE_SIZE / (1024 * 1024))
+}
+
+/// Artifact id one past every artifact already recorded for `run_id`, so a
+/// re-run from the UI replaces the current one.
+fn next_artifact_id(state: &ShellState, run_id: u64) -> u64 {
+    let artifacts = &state.artifacts;
+    [
+        artifacts.system.as_ref().map(|a| (a.run_id, a.artifact_id)),
+        artifacts.plan.as_ref().map(|a| (a.run_id, a.artifact_id)),
+        artifacts.diff.as_ref().map(|a| (a.run_id, a.artifact_id)),
+        artifacts.verify.as_ref().map(|a| (a.run_id, a.artifact_id)),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|(artifact_run, _)| *artifact_run == run_id)
+    .map(|(_, artifact_id)| artifact_id)
+    .max()
+    .unwrap_or(0)
+    .saturating_add(1)
+}
+
+/// Rate of `count` over `elapsed_ms`; a zero duration reports the raw count.
+fn per_second(count: u64, elapsed_ms: u64) -> f32 {
+    if elapsed_ms == 0 {
+        count as f32
+    } else {
+        count as f32 / (elapsed_ms as f32 / 1000.0)
+    }
+}
+
+fn mem_ratio(used_mb: u64, total_mb: u64) -> f64 {
+    if total_mb == 0 {
+        0.0
+    } else {
+        (used_mb as f64 / total_mb as f64).clamp(0.0, 1.0)
+    }
+}
+
 fn update_system_telemetry(state: &mut ShellState) {
-    let cpu = parse_macos_cpu_percent().unwrap_or(state.telemetry.latest.cpu_percent);
-    let (mem_used_mb, mem_total_mb) = parse_macos_memory_mb().unwrap_or((
+    #[cfg(target_os = "linux")]
+    let (cpu, memory, process_mem) = (
+        parse_linux_cpu_percent(),
+        parse_linux_memory_mb(),
+        parse_linux_process_mem_mb().or_else(parse_process_mem_mb),
+    );
+    #[cfg(not(target_os = "linux"))]
+    let (cpu, memory, process_mem) = (
+        parse_macos_cpu_percent(),
+        parse_macos_memory_mb(),
+        parse_process_mem_mb(),
+    );
+    let cpu = cpu.unwrap_or(state.telemetry.latest.cpu_percent);
+    let (mem_used_mb, mem_total_mb) = memory.unwrap_or((
         state.telemetry.latest.mem_used_mb,
         state.telemetry.latest.mem_total_mb.max(1),
     ));
-    let process_mem_mb = parse_process_mem_mb().unwrap_or(state.telemetry.latest.process_mem_mb);
-    let mem_ratio = if mem_total_mb == 0 {
-        0.0
-    } else {
-        (mem_used_mb as f64 / mem_total_mb as f64).clamp(0.0, 1.0)
-    };
+    let process_mem_mb = process_mem.unwrap_or(state.telemetry.latest.process_mem_mb);
+    let mem_ratio = mem_ratio(mem_used_mb, mem_total_mb);
 
     state.telemetry.latest.cpu_percent = cpu;
     state.telemetry.latest.mem_used_mb = mem_used_mb;
     state.telemetry.latest.mem_total_mb = mem_total_mb;
     state.telemetry.latest.process_mem_mb = process_mem_mb;
     state.telemetry.latest.sample_ts_ms = Some(now_ms());
+    record_telemetry_sample(&state.telemetry.latest);
 
     push_sample(&mut state.telemetry.cpu_history, cpu.round() as u64, 240);
     push_sample(
@@ -897,7 +1676,81 @@ fn update_gpu_telemetry(state: &mut ShellState) {
     };
 }
 
-#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+/// Parses `nvidia-smi --query-gpu=utilization.gpu,memory.used,memory.total
+/// --format=csv,noheader,nounits`, one line per GPU. Reports the busiest GPU's
+/// utilization and memory summed across GPUs.
+#[cfg(target_os = "linux")]
+fn parse_nvidia_smi_csv(text: &str) -> Option<(f32, u64, u64, usize)> {
+    let mut util = 0.0_f32;
+    let mut used_mb = 0_u64;
+    let mut total_mb = 0_u64;
+    let mut gpus = 0;
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let mut fields = line.split(',').map(str::trim);
+        let (Some(gpu_util), Some(used), Some(total)) = (
+            fields.next().and_then(|v| v.parse::<f32>().ok()),
+            fields.next().and_then(|v| v.parse::<u64>().ok()),
+            fields.next().and_then(|v| v.parse::<u64>().ok()),
+        ) else {
+            continue;
+        };
+        util = util.max(gpu_util);
+        used_mb += used;
+        total_mb += total;
+        gpus += 1;
+    }
+    (gpus > 0).then_some((util.clamp(0.0, 100.0), used_mb, total_mb, gpus))
+}
+
+#[cfg(target_os = "linux")]
+fn read_sysfs_u64(path: &str) -> Option<u64> {
+    std::fs::read_to_string(path)
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+}
+
+#[cfg(target_os = "linux")]
+fn update_gpu_telemetry(state: &mut ShellState) {
+    let smi = command_stdout(
+        "nvidia-smi",
+        &[
+            "--query-gpu=utilization.gpu,memory.used,memory.total",
+            "--format=csv,noheader,nounits",
+        ],
+    );
+    if let Some((util, used_mb, total_mb, gpus)) = smi.as_deref().and_then(parse_nvidia_smi_csv) {
+        state.telemetry.latest.gpu_util_percent = Some(util);
+        state.telemetry.latest.gpu_mem_used_mb = Some(used_mb);
+        state.telemetry.latest.gpu_mem_total_mb = Some(total_mb);
+        state.telemetry.latest.gpu_status = Some(if gpus == 1 {
+            "Live (nvidia-smi)".to_string()
+        } else {
+            format!("Live (nvidia-smi; busiest of {gpus} GPUs, memory summed)")
+        });
+        return;
+    }
+
+    const DRM_DEVICE: &str = "/sys/class/drm/card0/device";
+    let util = read_sysfs_u64(&format!("{DRM_DEVICE}/gpu_busy_percent"))
+        .map(|v| (v as f32).clamp(0.0, 100.0));
+    let used_mb =
+        read_sysfs_u64(&format!("{DRM_DEVICE}/mem_info_vram_used")).map(|v| v / (1024 * 1024));
+    let total_mb =
+        read_sysfs_u64(&format!("{DRM_DEVICE}/mem_info_vram_total")).map(|v| v / (1024 * 1024));
+
+    state.telemetry.latest.gpu_util_percent = util;
+    state.telemetry.latest.gpu_mem_used_mb = used_mb;
+    state.telemetry.latest.gpu_mem_total_mb = total_mb;
+    state.telemetry.latest.gpu_status = if util.is_some() || used_mb.is_some() {
+        Some("Live (sysfs card0)".to_string())
+    } else {
+        Some("N/A (no nvidia-smi or DRM counters on this host)".to_string())
+    };
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
 fn update_gpu_telemetry(state: &mut ShellState) {
     state.telemetry.latest.gpu_util_percent = None;
     state.telemetry.latest.gpu_mem_used_mb = None;
@@ -905,6 +1758,117 @@ fn update_gpu_telemetry(state: &mut ShellState) {
     state.telemetry.latest.gpu_status = Some("N/A (unsupported on this OS)".to_string());
 }
 
+/// Rows the Diff tab renders for the hunk at `index` of `file`, including
+/// expanded context.
+fn diff_hunk_rows(state: &ShellState, file: &DiffFile, index: usize) -> usize {
+    let expanded = state
+        .selection
+        .hunk_expansions
+        .iter()
+        .find(|e| e.file == file.path && e.hunk == index)
+        .map_or(0, |e| e.before.len() + e.after.len());
+    1 + file.hunks[index].lines.len() + expanded
+}
+
+/// Header the Diff tab draws for a binary file, which has no hunks to show.
+fn binary_file_label(file: &DiffFile) -> String {
+    let change = match file.status {
+        DiffFileStatus::Added => "added",
+        DiffFileStatus::Deleted => "deleted",
+        _ => "changed",
+    };
+    format!("{} (binary, {change})", file.path)
+}
+
+/// File shown at `row` of the Diff tab; rows past the end map to the last file.
+fn diff_file_at_row(state: &ShellState, row: u16) -> Option<String> {
+    let diff = state.shown_diff()?;
+    let row = usize::from(row);
+    let mut current = 0;
+    for file in &diff.files {
+        current += 1;
+        if !state.selection.collapsed_diff_files.contains(&file.path) {
+            current += (0..file.hunks.len())
+                .map(|index| diff_hunk_rows(state, file, index))
+                .sum::<usize>();
+        }
+        if row < current {
+            return Some(file.path.clone());
+        }
+    }
+    diff.files.last().map(|file| file.path.clone())
+}
+
+/// File whose header line the Diff tab draws at screen `row`, following its
+/// layout: a header line per file, then that file's hunk rows unless collapsed.
+fn diff_file_path_at_row(state: &ShellState, main_area: Rect, row: u16) -> Option<String> {
+    let diff = state.shown_diff()?;
+    let top = main_area.y.saturating_add(1);
+    let bottom = main_area.y + main_area.height.saturating_sub(1);
+    if row < top || row >= bottom {
+        return None;
+    }
+    let target = usize::from(row - top) + usize::from(state.selection.log_scroll);
+    let mut current = 0;
+    for file in &diff.files {
+        if current == target {
+            return Some(file.path.clone());
+        }
+        current += 1;
+        if !state.selection.collapsed_diff_files.contains(&file.path) {
+            current += (0..file.hunks.len())
+                .map(|index| diff_hunk_rows(state, file, index))
+                .sum::<usize>();
+        }
+        if current > target {
+            return None;
+        }
+    }
+    None
+}
+
+/// Hunk shown at `row` of the Diff tab, counting rows the way it renders them.
+/// A file header row maps to that file's first hunk; collapsed files have none.
+fn diff_hunk_at_row(state: &ShellState, row: u16) -> Option<(String, usize)> {
+    let diff = state.shown_diff()?;
+    let row = usize::from(row);
+    let mut current = 0;
+    let mut last = None;
+    for file in &diff.files {
+        let file_header = current;
+        current += 1;
+        if state.selection.collapsed_diff_files.contains(&file.path) {
+            if row == file_header {
+                return None;
+            }
+            continue;
+        }
+        for index in 0..file.hunks.len() {
+            let end = current + diff_hunk_rows(state, file, index);
+            if row < end && (row >= current || (index == 0 && row == file_header)) {
+                return Some((file.path.clone(), index));
+            }
+            last = Some((file.path.clone(), index));
+            current = end;
+        }
+    }
+    last
+}
+
+/// Log entry a bookmark toggle applies to: the top visible line, or the
+/// newest entry while the view follows the tail.
+fn anchored_log_id(state: &ShellState) -> Option<u64> {
+    let filter = state.selection.log_filter();
+    let mut visible = state.artifacts.logs.iter().filter(|l| filter.matches(l));
+    if state.selection.log_stick_to_bottom {
+        visible.next_back().map(|l| l.id)
+    } else {
+        visible
+            .nth(usize::from(state.selection.log_scroll))
+            .map(|l| l.id)
+    }
+}
+
 enum KeyHandlerResult {
     Continue(Vec<DaoEffect>),
     Exit,
@@ -933,6 +1897,32 @@ fn handle_help_keys(key: event::KeyEvent, state: &mut ShellState) -> KeyHandlerR
     KeyHandlerResult::Continue(effects)
 }
 
+fn handle_approval_preview_keys(key: event::KeyEvent, state: &mut ShellState) -> KeyHandlerResult {
+    let effects = match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('A') => {
+            reduce(state, ShellAction::User(UserAction::CloseOverlay))
+        }
+        KeyCode::Up | KeyCode::Char('k') => reduce(
+            state,
+            ShellAction::User(UserAction::ScrollApprovalPreview(-1)),
+        ),
+        KeyCode::Down | KeyCode::Char('j') => reduce(
+            state,
+            ShellAction::User(UserAction::ScrollApprovalPreview(1)),
+        ),
+        KeyCode::PageUp => reduce(
+            state,
+            ShellAction::User(UserAction::ScrollApprovalPreview(-10)),
+        ),
+        KeyCode::PageDown => reduce(
+            state,
+            ShellAction::User(UserAction::ScrollApprovalPreview(10)),
+        ),
+        _ => Vec::new(),
+    };
+    KeyHandlerResult::Continue(effects)
+}
+
 fn handle_action_palette_keys(key: event::KeyEvent, state: &mut ShellState) -> KeyHandlerResult {
     let effects = match key.code {
         KeyCode::Esc => reduce(state, ShellAction::User(UserAction::CloseOverlay)),
@@ -959,9 +1949,26 @@ fn handle_model_selection_keys(key: event::KeyEvent, state: &mut ShellState) ->
 
 fn handle_chat_focus_keys(key: event::KeyEvent, state: &mut ShellState) -> KeyHandlerResult {
     let effects = match key.code {
+        KeyCode::Esc if state.interaction.is_thinking => {
+            reduce(state, ShellAction::User(UserAction::CancelChat))
+        }
         KeyCode::Esc => reduce(state, ShellAction::User(UserAction::SetChatFocus(false))),
+        // Alt+Enter for terminals that cannot tell Shift+Enter from Enter.
+        KeyCode::Enter
+            if key
+                .modifiers
+                .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+        {
+            reduce(state, ShellAction::User(UserAction::ChatInputNewline))
+        }
         KeyCode::Enter => reduce(state, ShellAction::User(UserAction::ChatSubmit)),
         KeyCode::Backspace => reduce(state, ShellAction::User(UserAction::ChatBackspace)),
+        KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            reduce(state, ShellAction::User(UserAction::ChatDeleteWord))
+        }
+        KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+            reduce(state, ShellAction::User(UserAction::ChatClearLine))
+        }
         KeyCode::Char(c) => reduce(state, ShellAction::User(UserAction::ChatInput(c))),
         KeyCode::Up => reduce(state, ShellAction::User(UserAction::ChatHistoryUp)),
         KeyCode::Down => reduce(state, ShellAction::User(UserAction::ChatHistoryDown)),
@@ -1009,10 +2016,42 @@ fn handle_global_keys<B: Backend>(
                 ShellAction::User(UserAction::ToggleFocusMode),
             ));
         }
+        KeyCode::Char('m') if state.routing.tab == ShellTab::Logs => {
+            if let Some(id) = anchored_log_id(state) {
+                effects.extend(reduce(
+                    state,
+                    ShellAction::User(UserAction::ToggleLogBookmark(id)),
+                ));
+            }
+        }
+        KeyCode::Char('n') if state.routing.tab == ShellTab::Logs => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::JumpLogBookmark { forward: true }),
+            ));
+        }
+        KeyCode::Char('N') if state.routing.tab == ShellTab::Logs => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::JumpLogBookmark { forward: false }),
+            ));
+        }
+        KeyCode::Char('{') if state.routing.tab == ShellTab::Logs => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::JumpLogRunBoundary { forward: false }),
+            ));
+        }
+        KeyCode::Char('}') if state.routing.tab == ShellTab::Logs => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::JumpLogRunBoundary { forward: true }),
+            ));
+        }
         KeyCode::Char('[') => {
             effects.extend(reduce(
                 state,
-                ShellAction::User(UserAction::SetTheme(state.customization.theme.prev())),
+                ShellAction::User(UserAction::SetTheme(state.cycled_theme(false))),
             ));
         }
         KeyCode::Char(']') => {
@@ -1036,6 +2075,12 @@ fn handle_global_keys<B: Backend>(
                 ShellAction::User(UserAction::ToggleActionBar),
             ));
         }
+        KeyCode::Char('A') => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::ShowApprovalPreview),
+            ));
+        }
         KeyCode::Char('+') | KeyCode::Char('=') => {
             effects.extend(reduce(state, ShellAction::User(UserAction::ResizeInput(1))));
         }
@@ -1054,6 +2099,24 @@ fn handle_global_keys<B: Backend>(
         KeyCode::Char('?') => {
             effects.extend(reduce(state, ShellAction::User(UserAction::ShowHelp)));
         }
+        KeyCode::Right
+            if state.routing.tab == ShellTab::Diff
+                && key.modifiers.contains(KeyModifiers::SHIFT) =>
+        {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::ScrollDiffHorizontal(8)),
+            ));
+        }
+        KeyCode::Left
+            if state.routing.tab == ShellTab::Diff
+                && key.modifiers.contains(KeyModifiers::SHIFT) =>
+        {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::ScrollDiffHorizontal(-8)),
+            ));
+        }
         KeyCode::Right | KeyCode::Tab => {
             effects.extend(reduce(state, ShellAction::User(UserAction::NextTab)));
         }
@@ -1065,21 +2128,15 @@ fn handle_global_keys<B: Backend>(
                 effects.extend(reduce(state, ShellAction::User(UserAction::PlanStepUp)));
             } else if state.routing.tab == ShellTab::FileBrowser {
                 effects.extend(reduce(state, ShellAction::User(UserAction::FileBrowserUp)));
+                effects.extend(reduce(
+                    state,
+                    ShellAction::User(UserAction::LoadFilePreview),
+                ));
             } else if (state.routing.tab == ShellTab::Logs || state.routing.tab == ShellTab::Chat)
                 && state.selection.log_stick_to_bottom
             {
                 let content_area_h = content_height(state, terminal)?;
-                let log_count = if state.routing.tab == ShellTab::Chat {
-                    chat_line_count(state)
-                } else {
-                    let filter = state.selection.log_level_filter;
-                    state
-                        .artifacts
-                        .logs
-                        .iter()
-                        .filter(|l| filter.map_or(true, |f| l.level >= f))
-                        .count()
-                };
+                let log_count = scrollable_rows(state, content_width(state, terminal)?);
                 let current_scroll = (log_count as u16).saturating_sub(content_area_h);
                 let new_scroll = current_scroll.saturating_sub(3);
                 effects.extend(reduce(
@@ -1102,30 +2159,33 @@ fn handle_global_keys<B: Backend>(
                     state,
                     ShellAction::User(UserAction::FileBrowserDown),
                 ));
-            } else if state.routing.tab == ShellTab::Diff || state.routing.tab == ShellTab::Explain
-            {
-                effects.extend(reduce(state, ShellAction::User(UserAction::ScrollLogs(3))));
-            } else if (state.routing.tab == ShellTab::Logs || state.routing.tab == ShellTab::Chat)
-                && !state.selection.log_stick_to_bottom
-            {
-                effects.extend(reduce(state, ShellAction::User(UserAction::ScrollLogs(3))));
-            }
-        }
-        KeyCode::Enter => {
-            if state.routing.tab == ShellTab::FileBrowser {
                 effects.extend(reduce(
                     state,
-                    ShellAction::User(UserAction::FileBrowserEnter),
+                    ShellAction::User(UserAction::LoadFilePreview),
                 ));
+            } else if state.routing.tab == ShellTab::Diff
+                || state.routing.tab == ShellTab::Explain
+                || ((state.routing.tab == ShellTab::Logs || state.routing.tab == ShellTab::Chat)
+                    && !state.selection.log_stick_to_bottom)
+            {
+                effects.extend(reduce(state, ShellAction::User(UserAction::ScrollLogs(3))));
             }
         }
-        KeyCode::Backspace => {
-            if state.routing.tab == ShellTab::FileBrowser {
-                effects.extend(reduce(
-                    state,
-                    ShellAction::User(UserAction::FileBrowserBack),
-                ));
-            }
+        KeyCode::Enter if state.routing.tab == ShellTab::FileBrowser => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::FileBrowserEnter),
+            ));
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::LoadFilePreview),
+            ));
+        }
+        KeyCode::Backspace if state.routing.tab == ShellTab::FileBrowser => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::FileBrowserBack),
+            ));
         }
         KeyCode::Char(' ') => {
             if state.routing.tab == ShellTab::Plan {
@@ -1133,6 +2193,17 @@ fn handle_global_keys<B: Backend>(
                     state,
                     ShellAction::User(UserAction::TogglePlanStepExpansion),
                 ));
+            } else if state.routing.tab == ShellTab::Diff {
+                if let Some(path) = diff_file_at_row(state, state.selection.log_scroll) {
+                    effects.extend(reduce(
+                        state,
+                        ShellAction::User(UserAction::SelectDiffFile { path: path.clone() }),
+                    ));
+                    effects.extend(reduce(
+                        state,
+                        ShellAction::User(UserAction::ToggleDiffFileCollapse { path }),
+                    ));
+                }
             } else if (state.routing.tab == ShellTab::Logs || state.routing.tab == ShellTab::Chat)
                 && !state.selection.log_stick_to_bottom
             {
@@ -1146,17 +2217,7 @@ fn handle_global_keys<B: Backend>(
                 && state.selection.log_stick_to_bottom
             {
                 let content_area_h = content_height(state, terminal)?;
-                let log_count = if state.routing.tab == ShellTab::Chat {
-                    chat_line_count(state)
-                } else {
-                    let filter = state.selection.log_level_filter;
-                    state
-                        .artifacts
-                        .logs
-                        .iter()
-                        .filter(|l| filter.map_or(true, |f| l.level >= f))
-                        .count()
-                };
+                let log_count = scrollable_rows(state, content_width(state, terminal)?);
                 let current_scroll = (log_count as u16).saturating_sub(content_area_h);
                 let new_scroll = current_scroll.saturating_sub(10);
                 effects.extend(reduce(
@@ -1180,31 +2241,29 @@ fn handle_global_keys<B: Backend>(
                     state,
                     ShellAction::User(UserAction::PlanStepPageDown),
                 ));
-            } else if state.routing.tab == ShellTab::Diff || state.routing.tab == ShellTab::Explain
-            {
-                effects.extend(reduce(state, ShellAction::User(UserAction::ScrollLogs(10))));
-            } else if (state.routing.tab == ShellTab::Logs || state.routing.tab == ShellTab::Chat)
-                && !state.selection.log_stick_to_bottom
-            {
-                effects.extend(reduce(state, ShellAction::User(UserAction::ScrollLogs(10))));
-            }
-        }
-        KeyCode::Home => {
-            if state.routing.tab == ShellTab::Logs
-                || state.routing.tab == ShellTab::Chat
-                || state.routing.tab == ShellTab::Diff
-                || state.routing.tab == ShellTab::Explain
-            {
-                effects.extend(reduce(
-                    state,
-                    ShellAction::User(UserAction::SetLogScroll(0)),
-                ));
-                effects.extend(reduce(
-                    state,
-                    ShellAction::User(UserAction::SetLogStickToBottom(false)),
-                ));
+            } else if state.routing.tab == ShellTab::Diff
+                || state.routing.tab == ShellTab::Explain
+                || ((state.routing.tab == ShellTab::Logs || state.routing.tab == ShellTab::Chat)
+                    && !state.selection.log_stick_to_bottom)
+            {
+                effects.extend(reduce(state, ShellAction::User(UserAction::ScrollLogs(10))));
             }
         }
+        KeyCode::Home
+            if state.routing.tab == ShellTab::Logs
+                || state.routing.tab == ShellTab::Chat
+                || state.routing.tab == ShellTab::Diff
+                || state.routing.tab == ShellTab::Explain =>
+        {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::SetLogScroll(0)),
+            ));
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::SetLogStickToBottom(false)),
+            ));
+        }
         KeyCode::End => {
             if state.routing.tab == ShellTab::Logs || state.routing.tab == ShellTab::Chat {
                 effects.extend(reduce(
@@ -1239,14 +2298,54 @@ fn handle_global_keys<B: Backend>(
                 ShellAction::User(UserAction::ToggleActionPalette),
             ));
         }
-        KeyCode::Char('y') => {
-            if state.routing.tab == ShellTab::Diff {
+        // Loosening the gate takes a modifier, so a stray `p` cannot do it.
+        KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::ALT) => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::CyclePolicyTier),
+            ));
+        }
+        // Consumed on the Plan tab even with no step selected, so the keys
+        // never fall through to a global binding.
+        KeyCode::Char(c @ ('d' | 'x' | 'p')) if state.routing.tab == ShellTab::Plan => {
+            let status = match c {
+                'd' => StepStatus::Done,
+                'x' => StepStatus::Failed,
+                _ => StepStatus::Pending,
+            };
+            if let Some(id) = state.selection.selected_plan_step.clone() {
+                effects.extend(reduce(
+                    state,
+                    ShellAction::User(UserAction::SetPlanStepStatus { id, status }),
+                ));
+            }
+        }
+        KeyCode::Char('d') if state.routing.tab == ShellTab::FileBrowser => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::DiffSelectedFile),
+            ));
+        }
+        KeyCode::Char('e') if state.routing.tab == ShellTab::Diff => {
+            if let Some((file, hunk)) = diff_hunk_at_row(state, state.selection.log_scroll) {
                 effects.extend(reduce(
                     state,
-                    ShellAction::User(UserAction::CopyDiffToClipboard),
+                    ShellAction::User(UserAction::ExpandHunkContext { file, hunk }),
                 ));
             }
         }
+        KeyCode::Char('y') if state.routing.tab == ShellTab::Diff => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::CopyDiffToClipboard),
+            ));
+        }
+        KeyCode::Char('y') if state.routing.tab == ShellTab::Plan => {
+            effects.extend(reduce(
+                state,
+                ShellAction::User(UserAction::CopySelectedPlanStep),
+            ));
+        }
         KeyCode::Char('s') => {
             effects.extend(reduce(
                 state,
@@ -1332,10 +2431,47 @@ fn handle_global_keys<B: Backend>(
     Ok(KeyHandlerResult::Continue(effects))
 }
 
+/// Rewrites keys of the active keymap preset into the standard bindings that
+/// `handle_global_keys` matches on, so every preset shares one set of handlers.
+#[derive(Default)]
+struct KeymapResolver {
+    /// The vim preset saw a lone `g` and waits for the second one.
+    pending_g: bool,
+}
+
+impl KeymapResolver {
+    /// `None` when the key only starts a sequence.
+    fn resolve(&mut self, preset: KeymapPreset, key: event::KeyEvent) -> Option<event::KeyEvent> {
+        let pending_g = std::mem::take(&mut self.pending_g);
+        if preset != KeymapPreset::Vim
+            || key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return Some(key);
+        }
+        let code = match key.code {
+            KeyCode::Char('h') => KeyCode::Left,
+            KeyCode::Char('j') => KeyCode::Down,
+            KeyCode::Char('k') => KeyCode::Up,
+            KeyCode::Char('l') => KeyCode::Right,
+            KeyCode::Char('g') if pending_g => KeyCode::Home,
+            KeyCode::Char('g') => {
+                self.pending_g = true;
+                return None;
+            }
+            KeyCode::Char('G') => KeyCode::End,
+            _ => return Some(key),
+        };
+        Some(event::KeyEvent::new(code, KeyModifiers::NONE))
+    }
+}
+
 fn handle_key_event<B: Backend>(
     key: event::KeyEvent,
     state: &mut ShellState,
     terminal: &mut Terminal<B>,
+    keymap: &mut KeymapResolver,
 ) -> io::Result<KeyHandlerResult> {
     if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
         return Ok(KeyHandlerResult::Exit);
@@ -1346,11 +2482,15 @@ fn handle_key_event<B: Backend>(
         ShellOverlay::Help => Ok(handle_help_keys(key, state)),
         ShellOverlay::ActionPalette { .. } => Ok(handle_action_palette_keys(key, state)),
         ShellOverlay::ModelSelection { .. } => Ok(handle_model_selection_keys(key, state)),
+        ShellOverlay::ApprovalPreview { .. } => Ok(handle_approval_preview_keys(key, state)),
         ShellOverlay::None => {
             if state.interaction.focus_in_chat {
                 Ok(handle_chat_focus_keys(key, state))
             } else {
-                handle_global_keys(key, state, terminal)
+                match keymap.resolve(state.customization.keymap_preset, key) {
+                    Some(key) => handle_global_keys(key, state, terminal),
+                    None => Ok(KeyHandlerResult::Continue(Vec::new())),
+                }
             }
         }
         _ => Ok(KeyHandlerResult::Continue(Vec::new())),
@@ -1366,33 +2506,7 @@ fn handle_mouse_event<B: Backend>(
     match mouse.kind {
         MouseEventKind::Down(MouseButton::Left) => {
             if let Ok(size) = terminal.size() {
-                let rect = Rect::new(0, 0, size.width, size.height);
-                let (header_h, tabs_h) = if state.customization.focus_mode {
-                    (0, 0)
-                } else {
-                    (3, 3)
-                };
-                let action_bar_h =
-                    if !state.customization.focus_mode && state.customization.show_action_bar {
-                        2
-                    } else {
-                        0
-                    };
-                let mut constraints = vec![
-                    Constraint::Length(header_h),
-                    Constraint::Length(tabs_h),
-                    Constraint::Min(0),
-                    Constraint::Length(state.customization.input_height),
-                ];
-                if action_bar_h > 0 {
-                    constraints.push(Constraint::Length(action_bar_h));
-                }
-                constraints.push(Constraint::Length(1));
-                let chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .margin(1)
-                    .constraints(constraints)
-                    .split(rect);
+                let chunks = screen_chunks(state, Rect::new(0, 0, size.width, size.height));
 
                 let tabs_area = chunks[1];
                 if mouse.row >= tabs_area.y && mouse.row < tabs_area.y + tabs_area.height {
@@ -1444,56 +2558,48 @@ fn handle_mouse_event<B: Backend>(
                         ));
                     }
                 }
+                if in_main && state.routing.tab == ShellTab::Diff {
+                    if let Some(path) = diff_file_path_at_row(state, main_area, mouse.row) {
+                        effects.extend(reduce(
+                            state,
+                            ShellAction::User(UserAction::SelectDiffFile { path }),
+                        ));
+                    }
+                }
             }
         }
-        MouseEventKind::ScrollDown => {
-            if state.routing.tab == ShellTab::Chat
-                || state.routing.tab == ShellTab::Logs
-                || state.routing.tab == ShellTab::Diff
+        MouseEventKind::ScrollDown
+            if state.routing.tab == ShellTab::Diff
                 || state.routing.tab == ShellTab::Explain
-            {
-                if state.routing.tab == ShellTab::Diff
-                    || state.routing.tab == ShellTab::Explain
-                    || !state.selection.log_stick_to_bottom
-                {
-                    effects.extend(reduce(state, ShellAction::User(UserAction::ScrollLogs(3))));
-                }
-            }
+                || ((state.routing.tab == ShellTab::Chat
+                    || state.routing.tab == ShellTab::Logs)
+                    && !state.selection.log_stick_to_bottom) =>
+        {
+            effects.extend(reduce(state, ShellAction::User(UserAction::ScrollLogs(3))));
         }
-        MouseEventKind::ScrollUp => {
+        MouseEventKind::ScrollUp
             if state.routing.tab == ShellTab::Chat
                 || state.routing.tab == ShellTab::Logs
                 || state.routing.tab == ShellTab::Diff
-                || state.routing.tab == ShellTab::Explain
+                || state.routing.tab == ShellTab::Explain =>
+        {
+            if (state.routing.tab == ShellTab::Logs || state.routing.tab == ShellTab::Chat)
+                && state.selection.log_stick_to_bottom
             {
-                if (state.routing.tab == ShellTab::Logs || state.routing.tab == ShellTab::Chat)
-                    && state.selection.log_stick_to_bottom
-                {
-                    let content_area_h = content_height(state, terminal)?;
-                    let log_count = if state.routing.tab == ShellTab::Chat {
-                        chat_line_count(state)
-                    } else {
-                        let filter = state.selection.log_level_filter;
-                        state
-                            .artifacts
-                            .logs
-                            .iter()
-                            .filter(|l| filter.map_or(true, |f| l.level >= f))
-                            .count()
-                    };
-                    let current_scroll = (log_count as u16).saturating_sub(content_area_h);
-                    let new_scroll = current_scroll.saturating_sub(3);
-                    effects.extend(reduce(
-                        state,
-                        ShellAction::User(UserAction::SetLogScroll(new_scroll)),
-                    ));
-                    effects.extend(reduce(
-                        state,
-                        ShellAction::User(UserAction::SetLogStickToBottom(false)),
-                    ));
-                } else {
-                    effects.extend(reduce(state, ShellAction::User(UserAction::ScrollLogs(-3))));
-                }
+                let content_area_h = content_height(state, terminal)?;
+                let log_count = scrollable_rows(state, content_width(state, terminal)?);
+                let current_scroll = (log_count as u16).saturating_sub(content_area_h);
+                let new_scroll = current_scroll.saturating_sub(3);
+                effects.extend(reduce(
+                    state,
+                    ShellAction::User(UserAction::SetLogScroll(new_scroll)),
+                ));
+                effects.extend(reduce(
+                    state,
+                    ShellAction::User(UserAction::SetLogStickToBottom(false)),
+                ));
+            } else {
+                effects.extend(reduce(state, ShellAction::User(UserAction::ScrollLogs(-3))));
             }
         }
         _ => {}
@@ -1501,33 +2607,157 @@ fn handle_mouse_event<B: Backend>(
     Ok(effects)
 }
 
+/// Adds a streamed token to the reply preview. Tokens still in flight when
+/// the stream was cancelled are dropped; returns whether `token` was kept.
+fn append_stream_token(preview: &mut String, token: &str, cancel: &AtomicBool) -> bool {
+    if token.is_empty() || cancel.load(Ordering::Relaxed) {
+        return false;
+    }
+    preview.push_str(token);
+    true
+}
+
+/// Moves the streamed reply preview into the log as an assistant message.
+fn finalize_assistant_preview(state: &mut ShellState) {
+    let final_text = std::mem::take(&mut state.interaction.live_assistant_preview);
+    if !final_text.trim().is_empty() {
+        reduce(
+            state,
+            ShellAction::Runtime(RuntimeAction::AppendLog(format!(
+                "[assistant] {}",
+                final_text
+            ))),
+        );
+    }
+    if let Some(summary) = next_chat_summary(state, &ExtractiveSummarizer) {
+        reduce(
+            state,
+            ShellAction::Runtime(RuntimeAction::SetChatSummary(Some(summary))),
+        );
+    }
+}
+
+/// Shortest gap between two reloads of `state.json` in `run_app`.
+const STATE_RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What `run_app` last saw of `state.json`, so that it re-reads the file at
+/// most once per `STATE_RELOAD_INTERVAL` and only reloads new content.
+#[derive(Debug, Default)]
+struct StateReloadGate {
+    modified: Option<SystemTime>,
+    hash: Option<u64>,
+    reloaded_at: Option<Instant>,
+}
+
+impl StateReloadGate {
+    /// Whether a file modified at `modified` is worth reading at `now`. A
+    /// change held back by the interval stays due until it is read.
+    fn is_due(&self, modified: SystemTime, now: Instant) -> bool {
+        self.modified != Some(modified)
+            && self.reloaded_at.map_or(true, |at| {
+                now.saturating_duration_since(at) >= STATE_RELOAD_INTERVAL
+            })
+    }
+
+    /// Records the file read at `modified`; true when its bytes differ from
+    /// the last ones loaded or written, so the state should be replaced.
+    fn should_reload(&mut self, modified: SystemTime, hash: u64, now: Instant) -> bool {
+        self.modified = Some(modified);
+        if self.hash == Some(hash) {
+            return false;
+        }
+        self.hash = Some(hash);
+        self.reloaded_at = Some(now);
+        true
+    }
+
+    /// Records a write by the UI itself, which never needs reloading.
+    fn record_write(&mut self, modified: SystemTime, hash: u64) {
+        self.modified = Some(modified);
+        self.hash = Some(hash);
+    }
+}
+
+fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     state: &mut ShellState,
     repo: &Path,
 ) -> io::Result<()> {
     let state_path = repo.join(".dao/state.json");
-    let mut last_mod = fs::metadata(&state_path).and_then(|m| m.modified()).ok();
+    let mut reload_gate = StateReloadGate {
+        modified: fs::metadata(&state_path).and_then(|m| m.modified()).ok(),
+        ..StateReloadGate::default()
+    };
     let (tx, rx) = mpsc::channel();
     let mut last_sample = Instant::now()
         .checked_sub(Duration::from_millis(1500))
         .unwrap_or_else(Instant::now);
+    let mut checked_syntect_theme = None;
+    let mut keymap = KeymapResolver::default();
+    // Replaced for every chat stream; set by Esc to stop the current one.
+    let mut chat_cancel = Arc::new(AtomicBool::new(false));
     let mut last_gpu_sample = Instant::now()
         .checked_sub(Duration::from_secs(4))
         .unwrap_or_else(Instant::now);
 
     loop {
         // Check for external updates to state.json
-        if let Ok(metadata) = fs::metadata(&state_path) {
-            if let Ok(modified) = metadata.modified() {
-                if last_mod != Some(modified) {
-                    if let Ok(bytes) = fs::read(&state_path) {
-                        if let Ok(new_state) = serde_json::from_slice::<ShellState>(&bytes) {
-                            // Preserve interaction state (e.g. chat input) so typing isn't interrupted
-                            let interaction = state.interaction.clone();
-                            *state = new_state;
-                            state.interaction = interaction;
-                            last_mod = Some(modified);
+        if let Ok(modified) = fs::metadata(&state_path).and_then(|m| m.modified()) {
+            let now = Instant::now();
+            if reload_gate.is_due(modified, now) {
+                if let Ok(bytes) = fs::read(&state_path) {
+                    if reload_gate.should_reload(modified, content_hash(&bytes), now) {
+                        match decode_shell_state(&bytes) {
+                            Ok(new_state) => {
+                                let previous_request = state
+                                    .approval
+                                    .pending
+                                    .as_ref()
+                                    .map(|pending| pending.request.request_id.clone());
+                                // Preserve interaction state (e.g. chat input and streaming
+                                // tokens) and telemetry histories so a reload interrupts neither
+                                let interaction = state.interaction.clone();
+                                let telemetry = std::mem::take(&mut state.telemetry);
+                                let log_bookmarks =
+                                    std::mem::take(&mut state.selection.log_bookmarks);
+                                *state = new_state;
+                                state.interaction = interaction;
+                                state.telemetry = telemetry;
+                                state.selection.log_bookmarks = log_bookmarks;
+                                match state.approval.pending.as_ref() {
+                                    Some(pending)
+                                        if previous_request.as_deref()
+                                            != Some(pending.request.request_id.as_str()) =>
+                                    {
+                                        reduce(
+                                            state,
+                                            ShellAction::User(UserAction::ShowApprovalPreview),
+                                        );
+                                    }
+                                    None if matches!(
+                                        state.interaction.overlay,
+                                        ShellOverlay::ApprovalPreview { .. }
+                                    ) =>
+                                    {
+                                        reduce(state, ShellAction::User(UserAction::CloseOverlay));
+                                    }
+                                    _ => {}
+                                }
+                            }
+                            Err(err) => {
+                                reduce(
+                                    state,
+                                    ShellAction::Runtime(RuntimeAction::AppendLog(format!(
+                                        "[meta] ignored state.json update: {err}"
+                                    ))),
+                                );
+                            }
                         }
                     }
                 }
@@ -1538,9 +2768,20 @@ fn run_app<B: Backend>(
         while let Ok(event) = rx.try_recv() {
             match event {
                 UiEvent::Token(token) => {
-                    if !token.is_empty() {
-                        state.interaction.live_assistant_preview.push_str(&token);
-                    }
+                    append_stream_token(
+                        &mut state.interaction.live_assistant_preview,
+                        &token,
+                        &chat_cancel,
+                    );
+                }
+                // Unlike stream meta lines, a failure is always shown.
+                UiEvent::ChatFailed(reason) => {
+                    reduce(
+                        state,
+                        ShellAction::Runtime(RuntimeAction::AppendLog(format!(
+                            "[meta] Chat failed: {reason}"
+                        ))),
+                    );
                 }
                 UiEvent::StreamMeta(line) => {
                     if state.interaction.stream_meta_enabled && !line.trim().is_empty() {
@@ -1553,25 +2794,17 @@ fn run_app<B: Backend>(
                         );
                     }
                 }
-                UiEvent::Finished { elapsed_ms, bytes } => {
-                    let final_text = std::mem::take(&mut state.interaction.live_assistant_preview);
-                    if !final_text.trim().is_empty() {
-                        reduce(
-                            state,
-                            ShellAction::Runtime(RuntimeAction::AppendLog(format!(
-                                "[assistant] {}",
-                                final_text
-                            ))),
-                        );
-                    }
-                    let tokens = (bytes / 4).max(1) as u64;
-                    let tps = if elapsed_ms == 0 {
-                        tokens as f32
-                    } else {
-                        tokens as f32 / (elapsed_ms as f32 / 1000.0)
-                    };
-                    state.telemetry.latest.tokens_generated = Some(tokens);
+                UiEvent::Finished(StreamStats {
+                    elapsed_ms,
+                    chunks,
+                    bytes,
+                }) => {
+                    finalize_assistant_preview(state);
+                    let tps = per_second(chunks as u64, elapsed_ms);
+                    state.telemetry.latest.tokens_generated = Some(chunks as u64);
                     state.telemetry.latest.tokens_per_second = Some(tps);
+                    state.telemetry.latest.stream_bytes_per_second =
+                        Some(per_second(bytes as u64, elapsed_ms));
                     push_sample(&mut state.telemetry.tps_history, tps.round() as u64, 240);
                     reduce(
                         state,
@@ -1599,6 +2832,46 @@ fn run_app<B: Backend>(
                         ))),
                     );
                 }
+                UiEvent::ToolResult(ToolRunResult {
+                    tool_id,
+                    run_id,
+                    artifact_id,
+                    outcome,
+                }) => {
+                    reduce(
+                        state,
+                        ShellAction::Runtime(RuntimeAction::SetRuntimeFlag {
+                            flag: RuntimeFlag::for_tool(tool_id),
+                            active: false,
+                            run_id,
+                        }),
+                    );
+                    let result = payload_to_step_result(tool_id, outcome.payload);
+                    if let StepResult::Verify(artifact) = &result {
+                        let (status, label) = if artifact.overall == VerifyOverall::Passing {
+                            (VerifyStatus::Pass, "passing")
+                        } else {
+                            (VerifyStatus::Fail, "failing")
+                        };
+                        reduce(
+                            state,
+                            ShellAction::Runtime(RuntimeAction::SetVerifyStatus(status)),
+                        );
+                        reduce(
+                            state,
+                            ShellAction::Runtime(RuntimeAction::AppendLog(format!(
+                                "[meta] Verification finished: {label}"
+                            ))),
+                        );
+                    }
+                    apply_execution_outcome(
+                        state,
+                        run_id,
+                        artifact_id,
+                        result,
+                        &outcome.result.logs,
+                    );
+                }
             }
         }
 
@@ -1611,30 +2884,72 @@ fn run_app<B: Backend>(
             last_gpu_sample = Instant::now();
         }
 
+        if checked_syntect_theme != Some(state.customization.theme) {
+            checked_syntect_theme = Some(state.customization.theme);
+            let name = syntect_theme_name(state.customization.theme);
+            if let (_, true) = resolve_syntect_theme(get_theme_set(), name) {
+                reduce(
+                    state,
+                    ShellAction::Runtime(RuntimeAction::AppendStructuredLog(LogEntry {
+                        seq: 0,
+                        id: 0,
+                        level: LogLevel::Warn,
+                        ts_ms: None,
+                        source: LogSource::Shell,
+                        context: Some("theme".to_string()),
+                        message: format!(
+                            "syntax theme {name} is missing; highlighting diffs with a fallback"
+                        ),
+                        run_id: state.journey_status.active_run_id,
+                    })),
+                );
+            }
+        }
+
         terminal.draw(|f| ui(f, state))?;
 
         if event::poll(Duration::from_millis(16))? {
             let mut effects = Vec::new();
             match event::read()? {
-                Event::Key(key) => match handle_key_event(key, state, terminal)? {
+                Event::Key(key) => match handle_key_event(key, state, terminal, &mut keymap)? {
                     KeyHandlerResult::Continue(e) => {
                         effects.extend(e);
                     }
-                    KeyHandlerResult::Exit => return Ok(()),
+                    KeyHandlerResult::Exit => {
+                        fs::create_dir_all(repo.join(".dao"))?;
+                        save_chat_history(
+                            &repo.join(".dao/chat-history.json"),
+                            &state.interaction.chat_history,
+                        )?;
+                        return Ok(());
+                    }
                 },
                 Event::Mouse(mouse) => effects.extend(handle_mouse_event(mouse, state, terminal)?),
                 _ => {}
             }
 
-            for effect in effects {
+            let mut effects = VecDeque::from(effects);
+            while let Some(effect) = effects.pop_front() {
                 match effect {
+                    DaoEffect::ApprovePending => {
+                        effects.extend(reduce(
+                            state,
+                            ShellAction::User(UserAction::ApprovePending {
+                                timestamp_ms: now_ms(),
+                            }),
+                        ));
+                    }
                     DaoEffect::SubmitChat { message, context } => {
                         let tx_clone = tx.clone();
                         let provider = resolved_provider(state).to_string();
                         let model = resolved_model_slug(state).to_string();
                         let response_bytes = Arc::new(AtomicUsize::new(0));
                         let response_bytes_clone = Arc::clone(&response_bytes);
+                        let response_chunks = Arc::new(AtomicUsize::new(0));
+                        let response_chunks_clone = Arc::clone(&response_chunks);
                         let started = Instant::now();
+                        chat_cancel = Arc::new(AtomicBool::new(false));
+                        let cancel = Arc::clone(&chat_cancel);
                         state.interaction.live_assistant_preview.clear();
                         reduce(
                             state,
@@ -1648,23 +2963,50 @@ fn run_app<B: Backend>(
                             Some(model.as_str()),
                             &message,
                             context.as_deref(),
-                            move |event| match event {
-                                dao_exec::ChatEvent::Token(msg) => {
-                                    response_bytes_clone.fetch_add(msg.len(), Ordering::Relaxed);
-                                    let _ = tx_clone.send(UiEvent::Token(msg));
-                                }
-                                dao_exec::ChatEvent::Meta(msg) => {
-                                    let _ = tx_clone.send(UiEvent::StreamMeta(msg));
+                            Arc::clone(&cancel),
+                            move |event| {
+                                // A cancelled stream was already finalized by the UI.
+                                if cancel.load(Ordering::Relaxed) {
+                                    return;
                                 }
-                                dao_exec::ChatEvent::Done => {
-                                    let _ = tx_clone.send(UiEvent::Finished {
-                                        elapsed_ms: started.elapsed().as_millis() as u64,
-                                        bytes: response_bytes.load(Ordering::Relaxed),
-                                    });
+                                match event {
+                                    dao_exec::ChatEvent::Token(msg) => {
+                                        response_bytes_clone
+                                            .fetch_add(msg.len(), Ordering::Relaxed);
+                                        response_chunks_clone.fetch_add(1, Ordering::Relaxed);
+                                        let _ = tx_clone.send(UiEvent::Token(msg));
+                                    }
+                                    dao_exec::ChatEvent::Meta(msg) => {
+                                        let _ = tx_clone.send(UiEvent::StreamMeta(msg));
+                                    }
+                                    dao_exec::ChatEvent::Failed(msg) => {
+                                        let _ = tx_clone.send(UiEvent::ChatFailed(msg));
+                                    }
+                                    dao_exec::ChatEvent::Done => {
+                                        let _ = tx_clone.send(UiEvent::Finished(StreamStats {
+                                            elapsed_ms: started.elapsed().as_millis() as u64,
+                                            chunks: response_chunks.load(Ordering::Relaxed),
+                                            bytes: response_bytes.load(Ordering::Relaxed),
+                                        }));
+                                    }
                                 }
                             },
                         );
                     }
+                    DaoEffect::CancelChat => {
+                        chat_cancel.store(true, Ordering::Relaxed);
+                        finalize_assistant_preview(state);
+                        reduce(
+                            state,
+                            ShellAction::Runtime(RuntimeAction::AppendLog(
+                                "[meta] Response cancelled".to_string(),
+                            )),
+                        );
+                        reduce(
+                            state,
+                            ShellAction::Runtime(RuntimeAction::SetThinking(false)),
+                        );
+                    }
                     DaoEffect::CopyToClipboard(text) => {
                         if let Ok(mut clipboard) = arboard::Clipboard::new() {
                             let _ = clipboard.set_text(text);
@@ -1742,6 +3084,120 @@ fn run_app<B: Backend>(
                             });
                         });
                     }
+                    DaoEffect::WriteApprovalSummary { run_id } => {
+                        let message = match write_approval_summary(repo, state, run_id) {
+                            Ok(path) => {
+                                format!("[meta] Approval summary written to {}", path.display())
+                            }
+                            Err(err) => format!("[meta] Approval summary failed: {err}"),
+                        };
+                        reduce(
+                            state,
+                            ShellAction::Runtime(RuntimeAction::AppendLog(message)),
+                        );
+                    }
+                    DaoEffect::RecordApprovalReopened { request_id, run_id } => {
+                        let message = match record_approval_reopened(
+                            repo,
+                            request_id,
+                            run_id,
+                            now_ms(),
+                        ) {
+                            Ok(_) => format!(
+                                "[meta] Run {run_id} awaits this approval again; run `dao resume` to answer it"
+                            ),
+                            Err(err) => {
+                                format!("[meta] Could not record the reopened approval: {err}")
+                            }
+                        };
+                        reduce(
+                            state,
+                            ShellAction::Runtime(RuntimeAction::AppendLog(message)),
+                        );
+                    }
+                    DaoEffect::DiffFile { path } => {
+                        let shown = path
+                            .strip_prefix(repo)
+                            .unwrap_or(&path)
+                            .display()
+                            .to_string();
+                        match dao_exec::diff_path(repo, &path) {
+                            Ok(text) if text.trim().is_empty() => {
+                                reduce(
+                                    state,
+                                    ShellAction::Runtime(RuntimeAction::AppendLog(format!(
+                                        "[meta] No changes in {shown}."
+                                    ))),
+                                );
+                            }
+                            Ok(text) => {
+                                reduce(
+                                    state,
+                                    ShellAction::Runtime(RuntimeAction::SetBrowseDiff {
+                                        path: shown,
+                                        text,
+                                    }),
+                                );
+                                reduce(
+                                    state,
+                                    ShellAction::User(UserAction::SelectTab(ShellTab::Diff)),
+                                );
+                            }
+                            Err(err) => {
+                                reduce(
+                                    state,
+                                    ShellAction::Runtime(RuntimeAction::AppendLog(format!(
+                                        "[meta] git diff for {shown} failed: {err}"
+                                    ))),
+                                );
+                            }
+                        }
+                    }
+                    DaoEffect::RunTool(run) => {
+                        let (tool_id, run_id) = (run.tool_id(), run.run_id());
+                        let tx_clone = tx.clone();
+                        let cwd = state.cwd.clone().unwrap_or_else(|| repo.to_path_buf());
+                        let artifact_id = next_artifact_id(state, run_id);
+                        let model = resolved_model_slug(state).to_string();
+                        let scan_filters = ScanFilters {
+                            include: state.config.scan.include.clone(),
+                            exclude: state.config.scan.exclude.clone(),
+                        };
+                        let invocation = ToolInvocation {
+                            run_id,
+                            invocation_id: artifact_id,
+                            tool_id: tool_id.as_str().to_string(),
+                            requested_tier: state.approval.policy_tier.label().to_string(),
+                        };
+                        std::thread::spawn(move || {
+                            let context = ToolExecutionContext {
+                                cwd: &cwd,
+                                model: Some(model.as_str()),
+                                intent: None,
+                                scan_filters: &scan_filters,
+                                external_tools: &[],
+                                cancel: None,
+                            };
+                            let outcome = RuntimeToolExecutor.execute(invocation, &context);
+                            let _ = tx_clone.send(UiEvent::ToolResult(ToolRunResult {
+                                tool_id,
+                                run_id,
+                                artifact_id,
+                                outcome,
+                            }));
+                        });
+                    }
+                    DaoEffect::PersistState => {
+                        if let Ok(bytes) = serde_json::to_vec_pretty(&*state) {
+                            if write_atomic(&state_path, &bytes).is_ok() {
+                                if let Ok(modified) =
+                                    fs::metadata(&state_path).and_then(|m| m.modified())
+                                {
+                                    reload_gate.record_write(modified, content_hash(&bytes));
+                                }
+                            }
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -1762,33 +3218,12 @@ fn get_spinner() -> &'static str {
 
 fn ui(f: &mut ratatui::Frame, state: &ShellState) {
     let palette = palette_for(state.customization.theme);
-    let (header_h, tabs_h) = if state.customization.focus_mode {
-        (0, 0)
-    } else {
-        (3, 3)
-    };
     let action_bar_h = if !state.customization.focus_mode && state.customization.show_action_bar {
         2
     } else {
         0
     };
-
-    let mut constraints = vec![
-        Constraint::Length(header_h),                         // Header
-        Constraint::Length(tabs_h),                           // Tabs
-        Constraint::Min(0),                                   // Content
-        Constraint::Length(state.customization.input_height), // Input
-    ];
-    if action_bar_h > 0 {
-        constraints.push(Constraint::Length(action_bar_h)); // Action bar
-    }
-    constraints.push(Constraint::Length(1)); // Footer
-
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints(constraints)
-        .split(f.area());
+    let chunks = screen_chunks(state, f.area());
     let content_idx = 2_usize;
     let input_idx = 3_usize;
     let action_idx = if action_bar_h > 0 {
@@ -1811,11 +3246,22 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
     } else {
         "idle".to_string()
     };
+    let tier = match state.approval.risk_override {
+        Some(risk) => format!(
+            "{} (risk override: {})",
+            state.approval.policy_tier.label(),
+            risk.label()
+        ),
+        None => state.approval.policy_tier.label().to_string(),
+    };
     let header_text = format!(
-        "DAO Cockpit | {} | {} | Journey:{} | Provider:{} | Model:{} | CPU:{}% RAM:{}% | Theme:{} | {}",
+        "DAO Cockpit | {} | {} | Tier:{} | Persona:{} | Journey:{} {}% | Provider:{} | Model:{} | CPU:{}% RAM:{}% | Theme:{} | {}",
         state.header.project_name,
         safety,
+        tier,
+        state.sm.personality.label(),
         journey,
+        state.journey_status.step.progress_percent(),
         provider,
         model,
         cpu,
@@ -1902,9 +3348,14 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
     }
 
     if state.routing.tab == ShellTab::Chat {
-        let chat_lines = build_chat_lines(state, palette);
+        let width = usize::from(main_area.width.saturating_sub(2));
+        let chat_lines = build_chat_lines(state, palette, width);
         let height = main_area.height.saturating_sub(2);
-        let content_height = chat_lines.len() as u16;
+        let rows: usize = chat_lines
+            .iter()
+            .map(|line| wrapped_line_count(&line_text(line), width))
+            .sum();
+        let content_height = u16::try_from(rows).unwrap_or(u16::MAX);
         let scroll = if state.selection.log_stick_to_bottom {
             content_height.saturating_sub(height)
         } else {
@@ -1934,10 +3385,12 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
         f.render_widget(p, main_area);
     } else if state.routing.tab == ShellTab::Plan {
         if let Some(plan) = &state.artifacts.plan {
+            let depths = plan.dependency_depths();
             let items: Vec<ListItem> = plan
                 .steps
                 .iter()
-                .map(|s| {
+                .zip(depths)
+                .map(|(s, depth)| {
                     let (symbol, color) = match s.status {
                         StepStatus::Pending => ("○", palette.muted),
                         StepStatus::Running => ("➤", palette.warning),
@@ -1945,10 +3398,23 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
                         StepStatus::Failed => ("✖", palette.danger),
                     };
 
-                    let mut lines = vec![Line::from(vec![
+                    let indent = if depth == 0 {
+                        String::new()
+                    } else {
+                        format!("{}└─ ", "   ".repeat(depth - 1))
+                    };
+                    let mut spans = vec![
+                        Span::styled(indent, Style::default().fg(palette.muted)),
                         Span::styled(format!("{} ", symbol), Style::default().fg(color)),
                         Span::raw(&s.label),
-                    ])];
+                    ];
+                    if !s.depends_on.is_empty() {
+                        spans.push(Span::styled(
+                            format!("  after {}", s.depends_on.join(", ")),
+                            Style::default().fg(palette.muted),
+                        ));
+                    }
+                    let mut lines = vec![Line::from(spans)];
 
                     if state.selection.expanded_plan_steps.contains(&s.id) {
                         lines.push(Line::from(vec![
@@ -1965,6 +3431,16 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
                                 Style::default().fg(palette.muted),
                             ),
                         ]));
+                        let unmet = plan.unmet_dependencies(&s.id);
+                        if !unmet.is_empty() {
+                            lines.push(Line::from(vec![
+                                Span::raw("      "),
+                                Span::styled(
+                                    format!("Waiting on: {}", unmet.join(", ")),
+                                    Style::default().fg(palette.warning),
+                                ),
+                            ]));
+                        }
                     }
 
                     ListItem::new(lines)
@@ -1993,23 +3469,32 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
             f.render_widget(p, main_area);
         }
     } else if state.routing.tab == ShellTab::Logs {
-        let filter = state.selection.log_level_filter;
-        let logs: Vec<Line> = state
-            .artifacts
-            .logs
-            .iter()
-            .filter(|l| filter.map_or(true, |f| l.level >= f))
-            .map(|l| Line::from(format!("[{:?}] {}", l.level, l.message)))
-            .collect();
-        let title = if let Some(f) = filter {
+        let filter = state.selection.log_filter();
+        let logs = build_log_lines(state, palette);
+        let mut title = if let Some(f) = filter.min_level {
             format!("Logs (Filter: {:?}+)", f)
         } else {
             "Logs".to_string()
         };
+        if !filter.text.is_empty() {
+            title.push_str(&format!(
+                " (Search: {})",
+                state.selection.logs_text_search.trim()
+            ));
+        }
+        if !state.selection.log_bookmarks.is_empty() {
+            title.push_str(&format!(" ★{}", state.selection.log_bookmarks.len()));
+        }
         let scroll = if state.selection.log_stick_to_bottom {
             let height = main_area.height.saturating_sub(2);
-            let content_height = logs.len() as u16;
-            content_height.saturating_sub(height)
+            let width = usize::from(main_area.width.saturating_sub(2));
+            let rows: usize = logs
+                .iter()
+                .map(|line| wrapped_line_count(&line_text(line), width))
+                .sum();
+            u16::try_from(rows)
+                .unwrap_or(u16::MAX)
+                .saturating_sub(height)
         } else {
             state.selection.log_scroll
         };
@@ -2019,18 +3504,42 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
             .scroll((scroll, 0));
         f.render_widget(p, main_area);
     } else if state.routing.tab == ShellTab::Diff {
-        if let Some(diff) = &state.artifacts.diff {
+        if let Some(diff) = state.shown_diff() {
             let ps = get_syntax_set();
             let ts = get_theme_set();
-            let theme = &ts.themes[syntect_theme_name(state.customization.theme)];
+            let (theme, _) =
+                resolve_syntect_theme(ts, syntect_theme_name(state.customization.theme));
             let mut lines = Vec::new();
 
             for file in &diff.files {
+                let mut header_style = Style::default()
+                    .add_modifier(Modifier::BOLD)
+                    .fg(palette.accent_alt);
+                if state.selection.selected_diff_file.as_deref() == Some(file.path.as_str()) {
+                    header_style = header_style.bg(palette.selected_bg);
+                }
+                if file.binary {
+                    lines.push(Line::from(Span::styled(
+                        binary_file_label(file),
+                        header_style,
+                    )));
+                    continue;
+                }
+                if state.selection.collapsed_diff_files.contains(&file.path) {
+                    lines.push(Line::from(Span::styled(
+                        format!(
+                            "{} (+{}/-{})",
+                            file.path,
+                            file.added_lines(),
+                            file.removed_lines()
+                        ),
+                        header_style,
+                    )));
+                    continue;
+                }
                 lines.push(Line::from(Span::styled(
                     format!("--- {} ({:?})", file.path, file.status),
-                    Style::default()
-                        .add_modifier(Modifier::BOLD)
-                        .fg(palette.accent_alt),
+                    header_style,
                 )));
 
                 let syntax = ps
@@ -2039,11 +3548,24 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
                     .unwrap_or_else(|| ps.find_syntax_plain_text());
                 let mut h = HighlightLines::new(syntax, theme);
 
-                for hunk in &file.hunks {
+                for (hunk_index, hunk) in file.hunks.iter().enumerate() {
                     lines.push(Line::from(Span::styled(
                         &hunk.header,
                         Style::default().fg(palette.accent),
                     )));
+                    let expansion = state
+                        .selection
+                        .hunk_expansions
+                        .iter()
+                        .find(|e| e.file == file.path && e.hunk == hunk_index);
+                    if let Some(expansion) = expansion {
+                        for text in &expansion.before {
+                            lines.push(Line::from(Span::styled(
+                                format!(" {text}"),
+                                Style::default().fg(palette.muted),
+                            )));
+                        }
+                    }
 
                     for line in &hunk.lines {
                         let text = &line.text;
@@ -2065,26 +3587,50 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
                         spans.push(Span::styled(prefix, Style::default().fg(prefix_color)));
 
                         for (style, text) in ranges {
-                            let fg = Color::Rgb(
-                                style.foreground.r,
-                                style.foreground.g,
-                                style.foreground.b,
-                            );
-                            spans.push(Span::styled(text, Style::default().fg(fg)));
+                            spans
+                                .push(Span::styled(text, Style::default().fg(syntax_color(style))));
                         }
                         lines.push(Line::from(spans));
                     }
+                    if let Some(expansion) = expansion {
+                        for text in &expansion.after {
+                            lines.push(Line::from(Span::styled(
+                                format!(" {text}"),
+                                Style::default().fg(palette.muted),
+                            )));
+                        }
+                    }
                 }
             }
+            let files = diff.files_changed();
+            let title = if state.file_browser.diff.is_some() {
+                format!(
+                    "{} (+{}/-{}, not part of the run)",
+                    diff.summary,
+                    diff.added_lines(),
+                    diff.removed_lines()
+                )
+            } else {
+                format!(
+                    "Diff ({} file{}, +{}/-{})",
+                    files,
+                    if files == 1 { "" } else { "s" },
+                    diff.added_lines(),
+                    diff.removed_lines()
+                )
+            };
             let p = Paragraph::new(lines)
-                .block(content_block)
-                .wrap(Wrap { trim: false })
-                .scroll((state.selection.log_scroll, 0));
+                .block(content_block.title(title))
+                .scroll((state.selection.log_scroll, state.selection.diff_h_scroll));
             f.render_widget(p, main_area);
         } else {
             let p = Paragraph::new("No diff artifact.").block(content_block);
             f.render_widget(p, main_area);
         }
+    } else if state.routing.tab == ShellTab::FileBrowser {
+        render_file_browser(f, main_area, state, palette, content_block);
+    } else if state.routing.tab == ShellTab::Approvals {
+        render_approvals(f, main_area, state, palette, content_block);
     } else if state.routing.tab == ShellTab::Overview {
         render_overview(f, main_area, state, palette);
     } else if state.routing.tab == ShellTab::Telemetry {
@@ -2139,6 +3685,22 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
             }
             lines.push(Line::from(""));
 
+            let filters = &state.config.scan;
+            if !filters.include.is_empty() || !filters.exclude.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    "Scan Filters:",
+                    Style::default().fg(palette.accent),
+                )));
+                for (label, patterns) in
+                    [("include", &filters.include), ("exclude", &filters.exclude)]
+                {
+                    if !patterns.is_empty() {
+                        lines.push(Line::from(format!("  {label}: {}", patterns.join(", "))));
+                    }
+                }
+                lines.push(Line::from(""));
+            }
+
             if !sys.risk_flags.is_empty() {
                 lines.push(Line::from(Span::styled(
                     "Risk Flags:",
@@ -2173,9 +3735,10 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
 
     // Input
     let input_block_title = if state.interaction.is_thinking {
-        format!("Chat Input {} (Thinking...)", get_spinner())
+        format!("Chat Input {} (Thinking... Esc to stop)", get_spinner())
     } else {
-        "Chat Input (Press 'i' to focus, 'Esc' to exit, Enter to send)".to_string()
+        "Chat Input (Press 'i' to focus, 'Esc' to exit, Enter to send, Shift+Enter for a new line)"
+            .to_string()
     };
     let input_border_style = if state.interaction.focus_in_chat {
         Style::default().fg(palette.accent)
@@ -2187,23 +3750,38 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
         .title(input_block_title)
         .style(Style::default().bg(palette.panel_bg))
         .border_style(input_border_style);
-    let input_text = if state.interaction.chat_input.is_empty() {
-        if !state.interaction.focus_in_chat {
+    let input_lines = if state.interaction.chat_input.is_empty() {
+        let hint = if !state.interaction.focus_in_chat {
             Span::styled(
                 "Press 'i' to type command...",
                 Style::default().fg(palette.muted),
             )
-        } else if (now_ms() / 500).is_multiple_of(2) {
+        } else if (now_ms() / 500) % 2 == 0 {
             Span::styled("▌", Style::default().fg(palette.accent))
         } else {
             Span::raw("")
-        }
-    } else if state.interaction.focus_in_chat && (now_ms() / 500).is_multiple_of(2) {
-        Span::raw(format!("{}▌", state.interaction.chat_input))
+        };
+        vec![Line::from(hint)]
     } else {
-        Span::raw(&state.interaction.chat_input)
+        let mut lines = state
+            .interaction
+            .chat_input
+            .split('\n')
+            .map(Line::raw)
+            .collect::<Vec<_>>();
+        if state.interaction.focus_in_chat && (now_ms() / 500) % 2 == 0 {
+            if let Some(last) = lines.last_mut() {
+                last.spans.push(Span::raw("▌"));
+            }
+        }
+        lines
     };
-    let input = Paragraph::new(input_text).block(input_block);
+    // Keep the line being typed in view once the input outgrows its box.
+    let visible_rows = state.customization.input_height.saturating_sub(2).max(1);
+    let input_scroll = (input_lines.len() as u16).saturating_sub(visible_rows);
+    let input = Paragraph::new(input_lines)
+        .block(input_block)
+        .scroll((input_scroll, 0));
     f.render_widget(input, chunks[input_idx]);
 
     if state.interaction.focus_in_chat
@@ -2243,13 +3821,15 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
 
     // Footer
     let footer_text = if state.interaction.focus_in_chat {
-        "In Chat: /help /search /streammeta /auth /status /tab /theme /panel /provider /model /copylast /copydiff /copychat /copylogs | Esc exits input"
+        "In Chat: /help /search /logsearch /streammeta /auth /status /tab /theme /tier /personality /context /risk /panel /provider /model /copylast /copydiff /copychat /copylogs /verify /approve /approvalsummary | Esc stops a reply or exits input"
     } else if state.routing.tab == ShellTab::Chat {
         "Chat: i focus, / commands, /search filter, Up/Down scroll, Home/End/G nav, mouse click focus"
     } else if state.routing.tab == ShellTab::Telemetry {
         "Telemetry refreshes every 500ms | CPU/RAM/Process/Tokens/GPU live"
+    } else if state.routing.tab == ShellTab::Logs {
+        "Logs: Up/Down scroll | { } prev/next run boundary | f level filter | /logsearch text | Home/End/G nav | [ ] theme | q quit"
     } else {
-        "Shortcuts: ? help | / palette | [ ] theme | j/o/a rails | 1..9 tabs | arrows+mouse nav | q quit"
+        "Shortcuts: ? help | / palette | [ ] theme | Alt+p tier | j/o/a rails | 1..9 tabs | arrows+mouse nav | q quit"
     };
     let footer = Paragraph::new(footer_text).style(Style::default().fg(palette.muted));
     f.render_widget(footer, chunks[footer_idx]);
@@ -2261,7 +3841,11 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
         let block = Block::default()
             .title("Confirm Reset")
             .borders(Borders::ALL)
-            .style(Style::default().bg(palette.panel_bg).fg(Color::White))
+            .style(
+                Style::default()
+                    .bg(palette.panel_bg)
+                    .fg(overlay_text_color()),
+            )
             .border_style(Style::default().fg(palette.warning));
         let text = Paragraph::new("Are you sure you want to reset the session?\nThis will clear all artifacts and logs.\n\n[Y] Confirm  [N] Cancel")
             .block(block)
@@ -2275,7 +3859,11 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
         let block = Block::default()
             .title("Keybindings")
             .borders(Borders::ALL)
-            .style(Style::default().bg(palette.panel_bg).fg(Color::White))
+            .style(
+                Style::default()
+                    .bg(palette.panel_bg)
+                    .fg(overlay_text_color()),
+            )
             .border_style(Style::default().fg(palette.border));
 
         let help_text = vec![
@@ -2292,6 +3880,7 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
             Line::from("  Home/End Jump top/bottom (logs/chat/diff/explain)"),
             Line::from("  z        Toggle focus mode"),
             Line::from("  [ / ]    Previous/next theme"),
+            Line::from("  Alt+p    Cycle policy tier"),
             Line::from("  j/o/a    Toggle journey/context/action rails"),
             Line::from("  +/-      Resize input"),
             Line::from("  Ctrl+Up/Down Resize input"),
@@ -2302,19 +3891,32 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
             )),
             Line::from("  i        Focus chat input"),
             Line::from("  Esc      Unfocus chat input"),
+            Line::from("  Esc      Stop a streaming reply"),
             Line::from("  Enter    Submit message"),
+            Line::from("  S-Enter  New line in message (Alt+Enter also works)"),
+            Line::from("  C-w      Delete the last word of the message"),
+            Line::from("  C-u      Clear the line being typed"),
             Line::from("  v        Review changes"),
             Line::from("  Up/Down  Scroll chat"),
             Line::from("  PgUp/Dn  Scroll chat page"),
             Line::from("  End/G    Jump to latest"),
             Line::from("  /help    Show slash commands"),
             Line::from("  /search  Filter chat history"),
+            Line::from("  /logsearch Filter the Logs tab by text"),
+            Line::from("  /tier    Set policy tier"),
+            Line::from("  /personality Switch personality"),
+            Line::from("  /context Choose how much chat history is sent"),
+            Line::from("  /risk    Override risk class for the current change"),
             Line::from("  /streammeta Show provider stream metadata"),
             Line::from("  /auth    Start Codex device login flow"),
             Line::from("  /copylast Copy latest assistant response"),
             Line::from("  /copydiff Copy full diff"),
             Line::from("  /copychat Copy full chat transcript"),
             Line::from("  /copylogs Copy all logs"),
+            Line::from("  /verify  Re-run the verify step"),
+            Line::from("  /approve Approve the pending request"),
+            Line::from("  /reopen  Reopen the latest denied request"),
+            Line::from("  /approvalsummary Write .dao/approval-<run>.md for review"),
             Line::from("  Mouse    Click input to focus, click plan step to select"),
             Line::from(""),
             Line::from(Span::styled(
@@ -2324,6 +3926,10 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
             Line::from("  Up/Down  Scroll logs"),
             Line::from("  PgUp/Dn  Scroll logs page"),
             Line::from("  f        Filter log level"),
+            Line::from("  A        Show pending approval with diff"),
+            Line::from("  { }      Jump to previous/next run start or end"),
+            Line::from("  m        Bookmark the top log line (newest when following)"),
+            Line::from("  n / N    Jump to next/previous bookmark"),
             Line::from("  End      Scroll to bottom"),
             Line::from(""),
             Line::from(Span::styled(
@@ -2337,6 +3943,11 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
                 Style::default().add_modifier(Modifier::BOLD),
             )),
             Line::from("  y        Copy Diff (in Diff view)"),
+            Line::from("  e        Expand context around the top hunk (in Diff view)"),
+            Line::from("  Space    Collapse/expand the top file (in Diff view)"),
+            Line::from("  S-Left/Right Scroll sideways (in Diff view)"),
+            Line::from("  d/x/p    Mark plan step done/failed/pending (in Plan view)"),
+            Line::from("  y        Copy the selected plan step (in Plan view)"),
             Line::from("  s        Show System view"),
             Line::from(""),
             Line::from(Span::styled(
@@ -2380,9 +3991,9 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
             .map(|(i, &idx)| {
                 let item = &PALETTE_ITEMS[idx];
                 let style = if i == *selected {
-                    Style::default().fg(Color::Black).bg(palette.accent)
+                    selected_row_style(palette)
                 } else {
-                    Style::default().fg(Color::White)
+                    Style::default().fg(overlay_text_color())
                 };
                 ListItem::new(item.label).style(style)
             })
@@ -2414,9 +4025,9 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
             .enumerate()
             .map(|(i, &model_name)| {
                 let style = if i == *selected {
-                    Style::default().fg(Color::Black).bg(palette.accent)
+                    selected_row_style(palette)
                 } else {
-                    Style::default().fg(Color::White)
+                    Style::default().fg(overlay_text_color())
                 };
                 ListItem::new(model_name).style(style)
             })
@@ -2424,6 +4035,266 @@ fn ui(f: &mut ratatui::Frame, state: &ShellState) {
         let list = List::new(items);
         f.render_widget(list, layout[0]);
     }
+
+    if let ShellOverlay::ApprovalPreview { scroll } = state.interaction.overlay {
+        render_approval_preview(f, state, palette, scroll);
+    }
+}
+
+fn render_approval_preview(
+    f: &mut ratatui::Frame,
+    state: &ShellState,
+    palette: UiPalette,
+    scroll: u16,
+) {
+    let Some(pending) = state.approval.pending.as_ref() else {
+        return;
+    };
+    let area = centered_rect(80, 80, f.area());
+    f.render_widget(Clear, area);
+
+    let lines = approval_preview_lines(state, &pending.request, palette);
+    let block = Block::default()
+        .title(
+            "Approval pending - answer y/N in the terminal running dao | Up/Down scroll, Esc close",
+        )
+        .borders(Borders::ALL)
+        .style(Style::default().bg(palette.panel_bg))
+        .border_style(Style::default().fg(palette.warning));
+    let p = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+    f.render_widget(p, area);
+}
+
+fn approval_preview_lines<'a>(
+    state: &'a ShellState,
+    request: &'a ApprovalRequestRecord,
+    palette: UiPalette,
+) -> Vec<Line<'a>> {
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Request ", Style::default().fg(palette.muted)),
+            Span::raw(request.request_id.as_str()),
+            Span::styled("  Risk ", Style::default().fg(palette.muted)),
+            Span::styled(request.risk.label(), Style::default().fg(palette.warning)),
+        ]),
+        Line::from(vec![
+            Span::styled("Action ", Style::default().fg(palette.muted)),
+            Span::raw(request.preview.as_str()),
+        ]),
+        Line::from(vec![
+            Span::styled("Reason ", Style::default().fg(palette.muted)),
+            Span::raw(request.reason.as_str()),
+        ]),
+        Line::from(""),
+    ];
+    match state
+        .artifacts
+        .diff
+        .as_ref()
+        .filter(|diff| diff.run_id == request.run_id)
+    {
+        Some(diff) => {
+            // Every file is shown in full, whatever is collapsed in the Diff
+            // tab: an approval covers the whole diff.
+            for file in &diff.files {
+                lines.push(Line::from(Span::styled(
+                    format!("--- {} ({:?})", file.path, file.status),
+                    Style::default()
+                        .add_modifier(Modifier::BOLD)
+                        .fg(palette.accent_alt),
+                )));
+                for hunk in &file.hunks {
+                    lines.push(Line::from(Span::styled(
+                        hunk.header.as_str(),
+                        Style::default().fg(palette.accent),
+                    )));
+                    for line in &hunk.lines {
+                        let color = match line.kind {
+                            DiffLineKind::Add => palette.success,
+                            DiffLineKind::Remove => palette.danger,
+                            DiffLineKind::Context => palette.muted,
+                        };
+                        lines.push(Line::from(Span::styled(
+                            line.text.as_str(),
+                            Style::default().fg(color),
+                        )));
+                    }
+                }
+            }
+        }
+        None => lines.push(Line::from(Span::styled(
+            "No diff recorded for this run.",
+            Style::default().fg(palette.muted),
+        ))),
+    }
+    lines
+}
+
+fn render_file_browser(
+    f: &mut ratatui::Frame,
+    area: Rect,
+    state: &ShellState,
+    palette: UiPalette,
+    block: Block,
+) {
+    let browser = &state.file_browser;
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(area);
+
+    let items: Vec<ListItem> = browser
+        .entries
+        .iter()
+        .map(|name| {
+            let style = if name.ends_with('/') {
+                Style::default().fg(palette.accent)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Line::from(Span::styled(name.clone(), style)))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            block
+                .clone()
+                .title(browser.current_path.display().to_string()),
+        )
+        .highlight_style(Style::default().bg(palette.selected_bg));
+    let mut list_state = ListState::default();
+    if !browser.entries.is_empty() {
+        list_state.select(Some(browser.selected));
+    }
+    f.render_stateful_widget(list, panes[0], &mut list_state);
+
+    let Some(preview) = &browser.preview else {
+        let p = Paragraph::new(Span::styled(
+            "Enter previews a file or opens a directory.",
+            Style::default().fg(palette.muted),
+        ))
+        .block(block.title("Preview"));
+        f.render_widget(p, panes[1]);
+        return;
+    };
+    let name = std::path::Path::new(&preview.path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| preview.path.clone());
+    let mut lines = Vec::new();
+    if preview.binary {
+        lines.push(Line::from(Span::styled(
+            "(binary file)",
+            Style::default().fg(palette.muted),
+        )));
+    } else if preview.too_large {
+        lines.push(Line::from(Span::styled(
+            "(file too large to preview)",
+            Style::default().fg(palette.muted),
+        )));
+    } else {
+        let ps = get_syntax_set();
+        let (theme, _) = resolve_syntect_theme(
+            get_theme_set(),
+            syntect_theme_name(state.customization.theme),
+        );
+        let syntax = ps
+            .find_syntax_for_file(&preview.path)
+            .unwrap_or(None)
+            .unwrap_or_else(|| ps.find_syntax_plain_text());
+        let mut h = HighlightLines::new(syntax, theme);
+        for text in &preview.lines {
+            let ranges: Vec<(syntect::highlighting::Style, &str)> =
+                h.highlight_line(text, ps).unwrap_or_default();
+            let spans: Vec<Span> = ranges
+                .into_iter()
+                .map(|(style, text)| {
+                    Span::styled(text.to_string(), Style::default().fg(syntax_color(style)))
+                })
+                .collect();
+            lines.push(Line::from(spans));
+        }
+        if preview.truncated {
+            lines.push(Line::from(Span::styled(
+                format!("... (first {FILE_PREVIEW_MAX_LINES} lines)"),
+                Style::default().fg(palette.muted),
+            )));
+        }
+    }
+    let p = Paragraph::new(lines).block(block.title(name));
+    f.render_widget(p, panes[1]);
+}
+
+/// `HH:MM:SS` (UTC) for a millisecond timestamp; `-` when unset.
+fn format_clock_ms(timestamp_ms: u64) -> String {
+    if timestamp_ms == 0 {
+        return "-".to_string();
+    }
+    let secs = (timestamp_ms / 1000) % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs % 3600) / 60,
+        secs % 60
+    )
+}
+
+fn render_approvals(
+    f: &mut ratatui::Frame,
+    area: Rect,
+    state: &ShellState,
+    palette: UiPalette,
+    block: Block,
+) {
+    let approval = &state.approval;
+    let mut items = Vec::new();
+    if let Some(pending) = &approval.pending {
+        items.push(ListItem::new(Line::from(vec![
+            Span::styled("pending   ", Style::default().fg(palette.warning)),
+            Span::raw(format!(
+                "{}  {}  {}",
+                pending.request.request_id,
+                pending.request.run_id,
+                pending.request.action.label()
+            )),
+        ])));
+    }
+    for record in &approval.history {
+        let color = match record.decision {
+            ApprovalDecisionKind::Approved => palette.success,
+            ApprovalDecisionKind::Denied => palette.danger,
+        };
+        items.push(ListItem::new(Line::from(vec![
+            Span::styled(
+                format!("{:<10}", record.decision.label()),
+                Style::default().fg(color),
+            ),
+            Span::raw(format!(
+                "{}  {}  {}  ",
+                record.request_id,
+                record.run_id,
+                record.action.label()
+            )),
+            Span::styled(
+                format_clock_ms(record.timestamp_ms),
+                Style::default().fg(palette.muted),
+            ),
+        ])));
+    }
+    if items.is_empty() {
+        let p = Paragraph::new(Span::styled(
+            "No approval decisions this session.",
+            Style::default().fg(palette.muted),
+        ))
+        .block(block.title("Approvals"));
+        f.render_widget(p, area);
+        return;
+    }
+    let title = format!("Approvals ({} decided)", approval.history.len());
+    f.render_widget(List::new(items).block(block.title(title)), area);
 }
 
 fn render_overview(f: &mut ratatui::Frame, area: Rect, state: &ShellState, palette: UiPalette) {
@@ -2597,10 +4468,13 @@ fn render_telemetry(f: &mut ratatui::Frame, area: Rect, state: &ShellState, pale
     let tps = state.telemetry.latest.tokens_per_second.unwrap_or(0.0);
     let tps_pct = tps.clamp(0.0, 100.0) as u16;
     let tps_gauge = Gauge::default()
-        .block(Block::default().borders(Borders::ALL).title("Tokens / sec"))
+        .block(Block::default().borders(Borders::ALL).title("Chunks / sec"))
         .gauge_style(Style::default().fg(palette.accent_alt))
         .percent(tps_pct)
-        .label(format!("{tps:.1} tok/s"));
+        .label(match state.telemetry.latest.stream_bytes_per_second {
+            Some(bps) => format!("{tps:.1} chunks/s ({bps:.0} B/s)"),
+            None => format!("{tps:.1} chunks/s"),
+        });
     f.render_widget(tps_gauge, mid[1]);
 
     let charts = Layout::default()
@@ -2624,7 +4498,11 @@ fn render_telemetry(f: &mut ratatui::Frame, area: Rect, state: &ShellState, pale
     f.render_widget(mem_spark, charts[1]);
 
     let tps_spark = Sparkline::default()
-        .block(Block::default().borders(Borders::ALL).title("TPS Trend"))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Chunks/s Trend"),
+        )
         .data(&state.telemetry.tps_history)
         .style(Style::default().fg(palette.accent_alt));
     f.render_widget(tps_spark, charts[2]);
@@ -2638,7 +4516,7 @@ fn render_telemetry(f: &mut ratatui::Frame, area: Rect, state: &ShellState, pale
             Span::raw(resolved_model_slug(state)),
         ]),
         Line::from(vec![
-            Span::styled("Generated Tokens: ", Style::default().fg(palette.accent)),
+            Span::styled("Streamed Chunks: ", Style::default().fg(palette.accent)),
             Span::raw(
                 state
                     .telemetry
@@ -2697,24 +4575,7 @@ fn render_telemetry(f: &mut ratatui::Frame, area: Rect, state: &ShellState, pale
 }
 
 fn render_journey_rail(f: &mut ratatui::Frame, area: Rect, state: &ShellState, palette: UiPalette) {
-    let steps = [
-        ("Idea", 0_u8),
-        ("Understand", 1),
-        ("Plan", 2),
-        ("Preview", 3),
-        ("Approve", 4),
-        ("Verify", 5),
-        ("Learn", 6),
-    ];
-    let current_step = match state.journey_status.step {
-        dao_core::state::JourneyStep::Idea => 0_u8,
-        dao_core::state::JourneyStep::Understand => 1,
-        dao_core::state::JourneyStep::Plan => 2,
-        dao_core::state::JourneyStep::Preview => 3,
-        dao_core::state::JourneyStep::Approve => 4,
-        dao_core::state::JourneyStep::Verify => 5,
-        dao_core::state::JourneyStep::Learn => 6,
-    };
+    let current_step = state.journey_status.step.ordinal();
 
     let mut lines = vec![
         Line::from(vec![
@@ -2723,7 +4584,8 @@ fn render_journey_rail(f: &mut ratatui::Frame, area: Rect, state: &ShellState, p
         ]),
         Line::from(""),
     ];
-    for (label, idx) in steps {
+    for step in JourneyStep::all() {
+        let idx = step.ordinal();
         let (marker, color) = if idx < current_step {
             ("●", palette.success)
         } else if idx == current_step {
@@ -2733,7 +4595,7 @@ fn render_journey_rail(f: &mut ratatui::Frame, area: Rect, state: &ShellState, p
         };
         lines.push(Line::from(vec![
             Span::styled(format!("{marker} "), Style::default().fg(color)),
-            Span::styled(label, Style::default().fg(color)),
+            Span::styled(step.rail_label(), Style::default().fg(color)),
         ]));
     }
     lines.push(Line::from(""));
@@ -2770,6 +4632,28 @@ fn render_context_rail(f: &mut ratatui::Frame, area: Rect, state: &ShellState, p
             Span::styled("Keymap: ", Style::default().fg(palette.accent)),
             Span::raw(state.customization.keymap_preset.label()),
         ]),
+        Line::from(vec![
+            Span::styled("Policy Tier: ", Style::default().fg(palette.accent)),
+            Span::raw(state.approval.policy_tier.label()),
+        ]),
+        Line::from(vec![
+            Span::styled("Gate: ", Style::default().fg(palette.accent)),
+            Span::raw(
+                state
+                    .approval
+                    .last_gate
+                    .as_ref()
+                    .map(|gate| format!("{} ({})", gate.requirement.label(), gate.risk.label()))
+                    .unwrap_or_else(|| "none".to_string()),
+            ),
+        ]),
+        Line::from(vec![
+            Span::styled("Risk Override: ", Style::default().fg(palette.accent)),
+            match state.approval.risk_override {
+                Some(risk) => Span::styled(risk.label(), Style::default().fg(palette.warning)),
+                None => Span::raw("none"),
+            },
+        ]),
         Line::from(vec![
             Span::styled("Chat Lines: ", Style::default().fg(palette.accent)),
             Span::raw(chat_lines.to_string()),
@@ -2788,6 +4672,7 @@ fn render_context_rail(f: &mut ratatui::Frame, area: Rect, state: &ShellState, p
         Line::from("  o      context rail"),
         Line::from("  a      action bar"),
         Line::from("  z      focus mode"),
+        Line::from("  Alt+p  policy tier"),
         Line::from(""),
         Line::from(Span::styled(
             "Slash Utility",
@@ -2797,9 +4682,16 @@ fn render_context_rail(f: &mut ratatui::Frame, area: Rect, state: &ShellState, p
         Line::from("  /copydiff"),
         Line::from("  /copychat"),
         Line::from("  /copylogs"),
+        Line::from("  /verify"),
+        Line::from("  /approve"),
+        Line::from("  /approvalsummary"),
         Line::from("  /streammeta <on|off>"),
         Line::from("  /auth <codex>"),
         Line::from("  /search <text|clear>"),
+        Line::from("  /logsearch <text|clear>"),
+        Line::from("  /tier <name>"),
+        Line::from("  /personality <name>"),
+        Line::from("  /risk <class|clear>"),
         Line::from("  /panel <name>"),
     ];
 
@@ -2871,3 +4763,560 @@ fn centered_rect(
         ])
         .split(popup_layout[1])[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_texts(lines: &[Line<'_>]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    fn render_assistant(message: &str, width: usize) -> Vec<String> {
+        let mut out = Vec::new();
+        render_chat_message(
+            &mut out,
+            ChatRole::Assistant,
+            message,
+            palette_for(UiTheme::Classic),
+            width,
+        );
+        line_texts(&out)
+    }
+
+    #[test]
+    fn per_second_divides_streamed_chunks_by_elapsed_seconds() {
+        assert_eq!(per_second(50, 2_000), 25.0);
+        assert_eq!(per_second(3, 500), 6.0);
+        assert_eq!(per_second(0, 1_000), 0.0);
+        assert_eq!(per_second(7, 0), 7.0);
+    }
+
+    #[test]
+    fn telemetry_csv_row_lists_sample_fields_in_header_order() {
+        let mut sample = TelemetrySnapshot {
+            cpu_percent: 12.345,
+            mem_used_mb: 2048,
+            mem_total_mb: 8192,
+            process_mem_mb: 64,
+            sample_ts_ms: Some(1_700_000_000_000),
+            ..TelemetrySnapshot::default()
+        };
+        assert_eq!(
+            telemetry_csv_row(&sample),
+            "1700000000000,12.3,2048,8192,64,"
+        );
+
+        sample.tokens_per_second = Some(41.25);
+        let row = telemetry_csv_row(&sample);
+        assert_eq!(row, "1700000000000,12.3,2048,8192,64,41.2");
+        assert_eq!(
+            row.split(',').count(),
+            TELEMETRY_CSV_HEADER.split(',').count()
+        );
+    }
+
+    #[test]
+    fn nested_list_items_keep_indentation_and_vary_marker() {
+        assert_eq!(
+            render_assistant("- top\n  - child\n    * grandchild\n  1. step", 80),
+            vec![
+                "  • top",
+                "  \u{a0}\u{a0}◦ child",
+                "  \u{a0}\u{a0}\u{a0}\u{a0}▪ grandchild",
+                "  \u{a0}\u{a0}1. step",
+            ]
+        );
+    }
+
+    #[test]
+    fn pipe_table_aligns_columns_and_fits_width() {
+        let table = "| Name | Count |\n|:-----|------:|\n| alpha | 3 |\n| b | 12 |";
+        assert_eq!(
+            render_assistant(table, 80),
+            vec![
+                "│ Name  │ Count │",
+                "├───────┼───────┤",
+                "│ alpha │     3 │",
+                "│ b     │    12 │",
+            ]
+        );
+        assert_eq!(
+            render_assistant(table, 14),
+            vec![
+                "│ Name │ Co… │",
+                "├──────┼─────┤",
+                "│ alp… │   3 │",
+                "│ b    │  12 │",
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_table_falls_back_to_raw_lines() {
+        assert_eq!(
+            render_assistant("| a | b |\n| not a separator |\nafter", 80),
+            vec!["  | a | b |", "  | not a separator |", "  after"]
+        );
+    }
+
+    #[test]
+    fn code_block_expands_tabs_and_strips_ansi_escapes() {
+        let lines = vec![
+            "fn main() {".to_string(),
+            "\tlet x\t= 1;".to_string(),
+            "\u{1b}[31mred\u{1b}[0m \u{1b}]0;title\u{7}done\r".to_string(),
+        ];
+        let mut out = Vec::new();
+        render_code_block(&mut out, &lines, "rust", palette_for(UiTheme::Classic));
+
+        assert_eq!(
+            line_texts(&out),
+            vec![
+                "  ```rust",
+                "  fn main() {",
+                "  \u{a0}\u{a0}\u{a0}\u{a0}let x   = 1;",
+                "  red done",
+                "  ```",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_syntect_theme_falls_back_instead_of_panicking() {
+        let themes = get_theme_set();
+        let (theme, fell_back) = resolve_syntect_theme(themes, "base16-eighties.dark");
+        assert!(!fell_back);
+        assert_eq!(theme.name, themes.themes["base16-eighties.dark"].name);
+
+        let (theme, fell_back) = resolve_syntect_theme(themes, "no-such-theme");
+        assert!(fell_back);
+        assert_eq!(theme.name, themes.themes[FALLBACK_SYNTECT_THEME].name);
+
+        let empty = ThemeSet::default();
+        let (theme, fell_back) = resolve_syntect_theme(&empty, "no-such-theme");
+        assert!(fell_back);
+        assert_eq!(theme.name, None);
+    }
+
+    #[test]
+    fn every_theme_has_a_palette_and_a_bundled_syntect_theme() {
+        let themes = get_theme_set();
+        for theme in UiTheme::all().filter(|theme| *theme != UiTheme::Custom) {
+            let styles: Vec<&ThemeStyle> = THEME_STYLES
+                .iter()
+                .filter(|style| style.theme == theme)
+                .collect();
+            assert_eq!(styles.len(), 1, "{theme:?} needs exactly one style row");
+            assert!(
+                themes.themes.contains_key(styles[0].syntect),
+                "{theme:?} uses missing syntect theme {}",
+                styles[0].syntect
+            );
+        }
+        assert_eq!(THEME_STYLES.len(), UiTheme::all().count() - 1);
+    }
+
+    #[test]
+    fn diff_file_path_at_row_hits_only_file_headers() {
+        use dao_core::state::{DiffArtifact, DiffHunk, DiffLine, Personality, ARTIFACT_SCHEMA_V1};
+        let file = |path: &str, lines: usize| DiffFile {
+            path: path.to_string(),
+            old_path: None,
+            status: DiffFileStatus::Modified,
+            binary: false,
+            hunks: vec![DiffHunk {
+                header: "@@ -1 +1 @@".to_string(),
+                lines: (0..lines)
+                    .map(|n| DiffLine {
+                        kind: DiffLineKind::Add,
+                        text: format!("+{n}"),
+                    })
+                    .collect(),
+            }],
+        };
+        let mut state = ShellState::new(
+            "project".to_string(),
+            Personality::Pragmatic,
+            dao_core::config::Config::default(),
+        );
+        state.artifacts.diff = Some(DiffArtifact {
+            schema_version: ARTIFACT_SCHEMA_V1,
+            run_id: 1,
+            artifact_id: 1,
+            files: vec![file("src/a.rs", 2), file("src/b.rs", 1)],
+            summary: String::new(),
+            error: None,
+        });
+        // Border on row 10; a.rs header, hunk header, 2 lines, then b.rs.
+        let area = Rect::new(0, 10, 80, 20);
+        let at = |state: &ShellState, row| diff_file_path_at_row(state, area, row);
+        assert_eq!(at(&state, 10), None);
+        assert_eq!(at(&state, 11).as_deref(), Some("src/a.rs"));
+        assert_eq!(at(&state, 12), None);
+        assert_eq!(at(&state, 14), None);
+        assert_eq!(at(&state, 15).as_deref(), Some("src/b.rs"));
+        assert_eq!(at(&state, 16), None);
+
+        state.selection.collapsed_diff_files = vec!["src/a.rs".to_string()];
+        assert_eq!(at(&state, 12).as_deref(), Some("src/b.rs"));
+
+        state.selection.collapsed_diff_files.clear();
+        state.selection.log_scroll = 4;
+        assert_eq!(at(&state, 11).as_deref(), Some("src/b.rs"));
+    }
+
+    #[test]
+    fn approval_preview_shows_collapsed_files_in_full() {
+        use dao_core::state::{
+            ApprovalAction, ApprovalRiskClass, DiffArtifact, DiffHunk, DiffLine, Personality,
+            ARTIFACT_SCHEMA_V1,
+        };
+        let mut state = ShellState::new(
+            "project".to_string(),
+            Personality::Pragmatic,
+            dao_core::config::Config::default(),
+        );
+        state.artifacts.diff = Some(DiffArtifact {
+            schema_version: ARTIFACT_SCHEMA_V1,
+            run_id: 1,
+            artifact_id: 1,
+            files: vec![DiffFile {
+                path: "src/a.rs".to_string(),
+                old_path: None,
+                status: DiffFileStatus::Modified,
+                binary: false,
+                hunks: vec![DiffHunk {
+                    header: "@@ -1 +1 @@".to_string(),
+                    lines: vec![DiffLine {
+                        kind: DiffLineKind::Add,
+                        text: "+secret = true".to_string(),
+                    }],
+                }],
+            }],
+            summary: String::new(),
+            error: None,
+        });
+        state.selection.collapsed_diff_files = vec!["src/a.rs".to_string()];
+        let request = ApprovalRequestRecord {
+            request_id: "req-1".to_string(),
+            run_id: 1,
+            action: ApprovalAction::Patch,
+            risk: ApprovalRiskClass::PatchOnly,
+            reason: "apply".to_string(),
+            preview: "apply diff".to_string(),
+            created_at_ms: None,
+        };
+
+        let lines = line_texts(&approval_preview_lines(
+            &state,
+            &request,
+            palette_for(UiTheme::Classic),
+        ));
+        assert!(lines.contains(&"--- src/a.rs (Modified)".to_string()));
+        assert!(lines.contains(&"+secret = true".to_string()));
+    }
+
+    fn key(code: KeyCode) -> event::KeyEvent {
+        event::KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn plan_tab_consumes_a_bare_p_but_alt_p_still_cycles_the_tier() {
+        use dao_core::state::{Personality, PolicyTier};
+        let mut terminal =
+            Terminal::new(ratatui::backend::TestBackend::new(80, 24)).expect("terminal");
+        let mut state = ShellState::new(
+            "project".to_string(),
+            Personality::Pragmatic,
+            dao_core::config::Config::default(),
+        );
+        state.routing.tab = ShellTab::Plan;
+        assert_eq!(state.selection.selected_plan_step, None);
+
+        handle_global_keys(key(KeyCode::Char('p')), &mut state, &mut terminal).expect("key");
+        assert_eq!(state.approval.policy_tier, PolicyTier::Balanced);
+
+        let alt_p = event::KeyEvent::new(KeyCode::Char('p'), KeyModifiers::ALT);
+        handle_global_keys(alt_p, &mut state, &mut terminal).expect("key");
+        assert_eq!(state.approval.policy_tier, PolicyTier::Permissive);
+    }
+
+    #[test]
+    fn vim_keymap_resolves_to_the_standard_navigation_keys() {
+        let mut keymap = KeymapResolver::default();
+        for (vim, standard) in [
+            ('h', KeyCode::Left),
+            ('j', KeyCode::Down),
+            ('k', KeyCode::Up),
+            ('l', KeyCode::Right),
+        ] {
+            assert_eq!(
+                keymap.resolve(KeymapPreset::Vim, key(KeyCode::Char(vim))),
+                Some(key(standard))
+            );
+            assert_eq!(
+                keymap.resolve(KeymapPreset::Standard, key(standard)),
+                Some(key(standard))
+            );
+        }
+
+        assert_eq!(
+            keymap.resolve(KeymapPreset::Vim, key(KeyCode::Char('g'))),
+            None
+        );
+        assert_eq!(
+            keymap.resolve(KeymapPreset::Vim, key(KeyCode::Char('g'))),
+            Some(key(KeyCode::Home))
+        );
+        let shift_g = event::KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(
+            keymap.resolve(KeymapPreset::Vim, shift_g),
+            Some(key(KeyCode::End))
+        );
+
+        assert_eq!(
+            keymap.resolve(KeymapPreset::Vim, key(KeyCode::Char('g'))),
+            None
+        );
+        assert_eq!(
+            keymap.resolve(KeymapPreset::Vim, key(KeyCode::Char('q'))),
+            Some(key(KeyCode::Char('q')))
+        );
+        assert!(!keymap.pending_g);
+    }
+
+    #[test]
+    fn non_vim_presets_keep_their_bindings() {
+        let mut keymap = KeymapResolver::default();
+        for preset in [
+            KeymapPreset::Standard,
+            KeymapPreset::Mac,
+            KeymapPreset::Windows,
+        ] {
+            for code in [
+                KeyCode::Char('j'),
+                KeyCode::Char('g'),
+                KeyCode::Char('G'),
+                KeyCode::Up,
+            ] {
+                assert_eq!(keymap.resolve(preset, key(code)), Some(key(code)));
+            }
+        }
+        let ctrl_h = event::KeyEvent::new(KeyCode::Char('h'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.resolve(KeymapPreset::Vim, ctrl_h), Some(ctrl_h));
+    }
+
+    #[test]
+    fn wrapped_line_count_matches_word_wrapping() {
+        let text = "the quick brown fox";
+        assert_eq!(wrapped_line_count(text, 80), 1);
+        assert_eq!(wrapped_line_count(text, 19), 1);
+        // "the quick" / "brown fox"
+        assert_eq!(wrapped_line_count(text, 10), 2);
+        // "the" / "quick" / "brown" / "fox"
+        assert_eq!(wrapped_line_count(text, 5), 4);
+        assert_eq!(wrapped_line_count(text, 0), 1);
+    }
+
+    #[test]
+    fn wrapped_line_count_handles_newlines_long_words_and_blank_lines() {
+        assert_eq!(wrapped_line_count("one\n\ntwo", 10), 3);
+        assert_eq!(wrapped_line_count("", 10), 1);
+        assert_eq!(wrapped_line_count("   indented", 8), 1);
+        // 25 characters over width 10: three rows.
+        assert_eq!(wrapped_line_count(&"x".repeat(25), 10), 3);
+        assert_eq!(wrapped_line_count(&"x".repeat(20), 10), 2);
+        // "ab" / "cccccccccc" / "ccccc de"
+        assert_eq!(
+            wrapped_line_count(&format!("ab {} de", "c".repeat(15)), 10),
+            3
+        );
+        assert_eq!(wrapped_line_count("a b\nlonger line here", 6), 1 + 3);
+    }
+
+    #[test]
+    fn hex_colors_parse_with_or_without_a_hash() {
+        assert_eq!(
+            parse_hex_color("accent", "#7aa2F7"),
+            Ok(Color::Rgb(122, 162, 247))
+        );
+        assert_eq!(
+            parse_hex_color("accent", "1a1b26"),
+            Ok(Color::Rgb(26, 27, 38))
+        );
+        for bad in ["#fff", "#12345g", "", "#1234567", "#ééé"] {
+            let err = parse_hex_color("border", bad).expect_err(bad);
+            assert!(err.starts_with("border: "), "{err}");
+        }
+    }
+
+    #[test]
+    fn custom_palette_requires_every_field() {
+        let full = "accent = \"#7aa2f7\"\naccent_alt = \"#bb9af7\"\nsuccess = \"#9ece6a\"\n\
+                    warning = \"#e0af68\"\ndanger = \"#f7768e\"\nmuted = \"#565f89\"\n\
+                    border = \"#414868\"\npanel_bg = \"#1a1b26\"\nselected_bg = \"#292e42\"\n";
+        let palette = parse_custom_palette(full).expect("full palette");
+        assert_eq!(palette.panel_bg, Color::Rgb(26, 27, 38));
+
+        let missing = full.replace("danger = \"#f7768e\"\n", "");
+        let err = parse_custom_palette(&missing).expect_err("danger is required");
+        assert!(err.contains("missing field `danger`"), "{err}");
+
+        let malformed = full.replace("#414868", "#41486");
+        let err = parse_custom_palette(&malformed).expect_err("bad hex");
+        assert_eq!(err, "border: '#41486' is not a #rrggbb color");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn proc_samples_parse_into_cpu_and_clamped_memory() {
+        let first = parse_proc_stat_cpu("cpu  100 0 100 700 100 0 0 0 0 0\ncpu0 1 2 3 4\n")
+            .expect("cpu line");
+        assert_eq!(
+            first,
+            ProcStatCpu {
+                idle: 800,
+                total: 1000
+            }
+        );
+        let next = ProcStatCpu {
+            idle: 1100,
+            total: 2000,
+        };
+        assert_eq!(cpu_percent_between(first, next), Some(70.0));
+        assert_eq!(cpu_percent_between(next, first), None);
+
+        let meminfo =
+            "MemTotal:       8192000 kB\nMemFree:  1024000 kB\nMemAvailable:   2048000 kB\n";
+        assert_eq!(parse_proc_meminfo(meminfo), Some((6000, 8000)));
+        let no_available = "MemTotal: 8192000 kB\nMemFree: 1024000 kB\nCached: 1024000 kB\n";
+        assert_eq!(parse_proc_meminfo(no_available), Some((6000, 8000)));
+        assert_eq!(parse_proc_meminfo("MemFree: 1024000 kB\n"), None);
+        assert_eq!(parse_proc_meminfo("MemTotal: 8192000 kB\n"), None);
+        let (used, total) =
+            parse_proc_meminfo("MemTotal: 1024 kB\nMemAvailable: 4096 kB\n").expect("meminfo");
+        assert_eq!(mem_ratio(used, total), 0.0);
+        assert_eq!(mem_ratio(12, 0), 0.0);
+        assert_eq!(mem_ratio(12, 8), 1.0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn nvidia_smi_csv_takes_the_busiest_gpu() {
+        assert_eq!(
+            parse_nvidia_smi_csv("37, 1024, 8192\n"),
+            Some((37.0, 1024, 8192, 1))
+        );
+        assert_eq!(
+            parse_nvidia_smi_csv("12, 500, 4096\n88, 2000, 4096\n[N/A], 0, 0\n"),
+            Some((88.0, 2500, 8192, 2))
+        );
+        assert_eq!(parse_nvidia_smi_csv("NVIDIA-SMI has failed\n"), None);
+    }
+
+    #[test]
+    fn state_reload_waits_for_the_interval_and_skips_unchanged_content() {
+        let start = Instant::now();
+        let t0 = UNIX_EPOCH + Duration::from_secs(100);
+        let t1 = t0 + Duration::from_secs(1);
+        let t2 = t1 + Duration::from_secs(1);
+        let mut gate = StateReloadGate {
+            modified: Some(t0),
+            ..StateReloadGate::default()
+        };
+
+        assert!(!gate.is_due(t0, start));
+        assert!(gate.is_due(t1, start));
+        assert!(gate.should_reload(t1, 7, start));
+
+        // A second change right after a reload waits out the interval.
+        assert!(!gate.is_due(t2, start + Duration::from_millis(100)));
+        let later = start + STATE_RELOAD_INTERVAL;
+        assert!(gate.is_due(t2, later));
+        // Same bytes under a new timestamp are not reloaded, and not re-read.
+        assert!(!gate.should_reload(t2, 7, later));
+        assert!(!gate.is_due(t2, later));
+
+        // The UI's own write is remembered and never reloaded.
+        let t3 = t2 + Duration::from_secs(1);
+        gate.record_write(t3, 9);
+        assert!(!gate.is_due(t3, later + STATE_RELOAD_INTERVAL));
+        let t4 = t3 + Duration::from_secs(1);
+        assert!(!gate.should_reload(t4, 9, later + STATE_RELOAD_INTERVAL));
+        assert!(gate.should_reload(t4, 11, later + STATE_RELOAD_INTERVAL));
+    }
+
+    #[test]
+    fn stream_tokens_stop_accumulating_once_cancelled() {
+        let cancel = AtomicBool::new(false);
+        let mut preview = String::new();
+        assert!(append_stream_token(&mut preview, "Hel", &cancel));
+        assert!(!append_stream_token(&mut preview, "", &cancel));
+        assert!(append_stream_token(&mut preview, "lo", &cancel));
+
+        cancel.store(true, Ordering::Relaxed);
+        assert!(!append_stream_token(&mut preview, " world", &cancel));
+        assert_eq!(preview, "Hello");
+    }
+
+    #[test]
+    fn disabling_color_maps_every_palette_role_to_reset() {
+        for style in THEME_STYLES {
+            let palette = select_palette(style.theme, false);
+            let roles = [
+                palette.accent,
+                palette.accent_alt,
+                palette.success,
+                palette.warning,
+                palette.danger,
+                palette.muted,
+                palette.border,
+                palette.panel_bg,
+                palette.selected_bg,
+            ];
+            assert!(
+                roles.iter().all(|&color| color == Color::Reset),
+                "{:?} keeps a color",
+                style.theme
+            );
+        }
+        assert_ne!(select_palette(UiTheme::Classic, true).accent, Color::Reset);
+    }
+
+    #[test]
+    fn shift_enter_starts_a_new_line_and_enter_submits() {
+        use dao_core::state::Personality;
+        let mut state = ShellState::new(
+            "project".to_string(),
+            Personality::Pragmatic,
+            dao_core::config::Config::default(),
+        );
+        state.interaction.focus_in_chat = true;
+        state.interaction.chat_input = "first".to_string();
+        let submits = |result: KeyHandlerResult| match result {
+            KeyHandlerResult::Continue(effects) => effects
+                .iter()
+                .any(|effect| matches!(effect, DaoEffect::SubmitChat { .. })),
+            KeyHandlerResult::Exit => panic!("chat keys never exit"),
+        };
+
+        for modifiers in [KeyModifiers::SHIFT, KeyModifiers::ALT] {
+            let newline = event::KeyEvent::new(KeyCode::Enter, modifiers);
+            assert!(!submits(handle_chat_focus_keys(newline, &mut state)));
+        }
+        assert_eq!(state.interaction.chat_input, "first\n\n");
+
+        let enter = event::KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert!(submits(handle_chat_focus_keys(enter, &mut state)));
+        assert!(state.interaction.chat_input.is_empty());
+    }
+}
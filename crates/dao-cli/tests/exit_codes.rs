@@ -0,0 +1,148 @@
+//! Pins the exit codes documented in the README. Scripts branch on these, so a
+//! change here is a breaking change to the CLI.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::process::Stdio;
+
+const SUCCESS: i32 = 0;
+const FAILURE: i32 = 1;
+const MALFORMED_STATE: i32 = 2;
+const WORKFLOW_BLOCKED: i32 = 3;
+
+fn dao(repo: &Path, args: &[&str]) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_dao"))
+        .args(args)
+        .current_dir(repo)
+        .stdin(Stdio::null())
+        .output()
+        .expect("run dao")
+        .status
+        .code()
+        .expect("exit code")
+}
+
+#[test]
+fn version_and_help_exit_successfully() {
+    let repo = tempfile::tempdir().expect("tempdir");
+    assert_eq!(dao(repo.path(), &["--version"]), SUCCESS);
+    assert_eq!(dao(repo.path(), &["help"]), SUCCESS);
+}
+
+#[test]
+fn usage_errors_exit_with_generic_failure() {
+    let repo = tempfile::tempdir().expect("tempdir");
+    assert_eq!(dao(repo.path(), &["frobnicate"]), FAILURE);
+    assert_eq!(dao(repo.path(), &["run", "--repo"]), FAILURE);
+}
+
+#[test]
+fn resume_without_runs_is_not_an_error() {
+    let repo = tempfile::tempdir().expect("tempdir");
+    assert_eq!(dao(repo.path(), &["resume", "--repo", "."]), SUCCESS);
+}
+
+#[test]
+fn resume_of_malformed_state_exits_with_malformed_state() {
+    let repo = tempfile::tempdir().expect("tempdir");
+    let store = repo.path().join(".dao");
+    fs::create_dir_all(&store).expect("mkdir");
+    fs::write(
+        store.join("workflow-events.jsonl"),
+        concat!(
+            r#"{"seq":1,"ts_ms":1,"kind":"workflow_run_started","run_id":1,"template_id":"scan_plan_diff_verify","execution_mode":"simulated","policy_tier":"balanced","persona_policy":{"tier_ceiling":"permissive","explanation_depth":"brief","output_format":"technical-first"}}"#,
+            "\n",
+            r#"{"seq":2,"ts_ms":2,"kind":"workflow_status_changed","run_id":1,"status":"awaiting_approval","step_index":3,"reason":null}"#,
+            "\n",
+        ),
+    )
+    .expect("write events");
+
+    assert_eq!(
+        dao(repo.path(), &["resume", "--repo", "."]),
+        MALFORMED_STATE
+    );
+}
+
+#[test]
+fn run_blocked_by_policy_exits_with_workflow_blocked() {
+    let repo = tempfile::tempdir().expect("tempdir");
+    fs::write(repo.path().join("a.txt"), "a\n").expect("write");
+    fs::write(
+        repo.path().join("block.yaml"),
+        r#"
+id: "block-all"
+version: "1"
+applies_to: {}
+defaults:
+  approval: {}
+rules:
+  - id: "block-everything"
+    when: "true"
+    then:
+      action: "block"
+      message: "Blocked by test policy."
+"#,
+    )
+    .expect("write policy");
+
+    assert_eq!(
+        dao(
+            repo.path(),
+            &["run", "--repo", ".", "--policy", "block.yaml", "--no-cache"]
+        ),
+        WORKFLOW_BLOCKED
+    );
+}
+
+#[test]
+fn status_exits_with_workflow_blocked_while_an_approval_is_awaiting() {
+    let repo = tempfile::tempdir().expect("tempdir");
+    assert_eq!(dao(repo.path(), &["status", "--repo", "."]), SUCCESS);
+    assert!(!repo.path().join(".dao").exists());
+
+    fs::create_dir_all(repo.path().join(".dao")).expect("mkdir");
+    fs::write(
+        repo.path().join(".dao/snapshot.json"),
+        r#"{"version":1,"seq":4,"workflow":{"run_id":2,"template_id":"scan_plan_diff_verify","execution_mode":"runtime","step_index":2,"status":"awaiting_approval","pending_request_id":"req-2-3","pending_tool_id":"compute_diff","pending_invocation_id":3,"next_invocation_id":4,"blocked_reason":null}}"#,
+    )
+    .expect("write snapshot");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_dao"))
+        .args(["status", "--repo", "."])
+        .current_dir(repo.path())
+        .stdin(Stdio::null())
+        .output()
+        .expect("run dao");
+    assert_eq!(output.status.code(), Some(WORKFLOW_BLOCKED));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        concat!(
+            "run_id=2\n",
+            "status=awaiting_approval\n",
+            "pending_request_id=req-2-3\n",
+            "pending_tool_id=compute_diff\n",
+            "pending_invocation_id=3\n",
+            "blocked_reason=\n",
+        )
+    );
+}
+
+#[test]
+fn chat_json_fails_when_the_provider_cannot_answer() {
+    let repo = tempfile::tempdir().expect("tempdir");
+    // No provider CLI can be found on an empty PATH.
+    let output = Command::new(env!("CARGO_BIN_EXE_dao"))
+        .args(["chat", "--provider", "ollama", "--json", "hello"])
+        .current_dir(repo.path())
+        .env("PATH", repo.path())
+        .env("HOME", repo.path())
+        .stdin(Stdio::null())
+        .output()
+        .expect("run dao");
+
+    assert_eq!(output.status.code(), Some(FAILURE));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Failed to start Ollama CLI"));
+}
@@ -0,0 +1,32 @@
+use std::process::Command;
+
+fn dao(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_dao"))
+        .args(args)
+        .output()
+        .expect("run dao");
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).expect("utf-8 stdout")
+}
+
+#[test]
+fn plain_version_is_unchanged() {
+    assert_eq!(
+        dao(&["--version"]),
+        format!("dao {}\n", env!("CARGO_PKG_VERSION"))
+    );
+}
+
+#[test]
+fn json_version_reports_build_metadata() {
+    let info: serde_json::Value =
+        serde_json::from_str(&dao(&["version", "--json"])).expect("json output");
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    assert!(info["git_commit"]
+        .as_str()
+        .is_some_and(|commit| !commit.is_empty()));
+    assert!(info["rustc"]
+        .as_str()
+        .is_some_and(|rustc| rustc.starts_with("rustc ") || rustc == "unknown"));
+    assert!(info["features"].is_array());
+}
@@ -0,0 +1,958 @@
+//! Drives the `dao` binary through run, replay and resume against throwaway git
+//! repositories. Planning goes through a local `.dao/plan.sh`, so no model is
+//! contacted.
+#![cfg(unix)]
+
+use std::fs;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use dao_core::persistence::PersistedShellEvent;
+use dao_core::persistence::PersistedShellEventRecord;
+use dao_core::persistence::PersistedWorkflowStatus;
+use dao_core::persistence::WorkflowArtifactFlags;
+use dao_core::persistence::WorkflowRunExport;
+use tempfile::TempDir;
+
+const REQUIRE_APPROVAL_POLICY: &str = r#"
+id: "review-everything"
+version: "1"
+applies_to: {}
+defaults:
+  approval: {}
+rules:
+  - id: "always-review"
+    when: "true"
+    then:
+      action: "require_approval"
+      message: "Every step needs a reviewer."
+"#;
+
+struct Sandbox {
+    repo: TempDir,
+    home: TempDir,
+}
+
+impl Sandbox {
+    fn new() -> Self {
+        let repo = tempfile::tempdir().expect("repo tempdir");
+        let home = tempfile::tempdir().expect("home tempdir");
+        let sandbox = Self { repo, home };
+        sandbox.git(&["init", "-q"]);
+        fs::write(sandbox.path().join("notes.txt"), "first\n").expect("write notes");
+        sandbox.git(&["add", "notes.txt"]);
+        sandbox.git(&["commit", "-q", "-m", "init"]);
+        fs::write(sandbox.path().join("notes.txt"), "first\nsecond\n").expect("edit notes");
+
+        let plan = sandbox.path().join(".dao/plan.sh");
+        fs::create_dir_all(plan.parent().expect("parent")).expect("mkdir .dao");
+        fs::write(
+            &plan,
+            "#!/bin/sh\necho 'Read notes'\necho 'Append a line'\n",
+        )
+        .expect("write plan script");
+        fs::set_permissions(&plan, fs::Permissions::from_mode(0o755)).expect("chmod");
+        fs::write(sandbox.path().join("review.yaml"), REQUIRE_APPROVAL_POLICY)
+            .expect("write policy");
+        sandbox
+    }
+
+    fn path(&self) -> &Path {
+        self.repo.path()
+    }
+
+    fn git(&self, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=dao", "-c", "user.email=dao@example.com"])
+            .args(args)
+            .current_dir(self.path())
+            .status()
+            .expect("run git");
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    fn dao(&self, args: &[&str], stdin: &str) -> Output {
+        self.dao_with_env(args, stdin, &[])
+    }
+
+    fn dao_with_env(&self, args: &[&str], stdin: &str, env: &[(&str, &str)]) -> Output {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_dao"))
+            .args(args)
+            .current_dir(self.path())
+            .env("HOME", self.home.path())
+            .env("XDG_CONFIG_HOME", self.home.path())
+            .env_remove("DAO_MODEL")
+            .env_remove("DAO_PROVIDER")
+            .env_remove("DAO_AUTO_APPROVE")
+            .envs(env.iter().copied())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("spawn dao");
+        child
+            .stdin
+            .take()
+            .expect("stdin")
+            .write_all(stdin.as_bytes())
+            .expect("write stdin");
+        child.wait_with_output().expect("wait for dao")
+    }
+
+    /// Writes what a run interrupted at the scan approval prompt leaves behind.
+    fn interrupt_at_scan_approval(&self) {
+        fs::write(
+            self.path().join(".dao/workflow-events.jsonl"),
+            concat!(
+                r#"{"seq":1,"ts_ms":1,"kind":"workflow_run_started","run_id":1,"template_id":"scan_plan_diff_verify","execution_mode":"simulated","policy_tier":"balanced","persona_policy":{"tier_ceiling":"permissive","explanation_depth":"brief","output_format":"technical-first"}}"#,
+                "\n",
+                r#"{"seq":2,"ts_ms":2,"kind":"approval_requested","request_id":"req-1-1","run_id":1,"invocation_id":1,"tool_id":"scan_repo","risk":"read_only","preview":"workflow tool scan_repo"}"#,
+                "\n",
+            ),
+        )
+        .expect("write events");
+    }
+
+    /// Decisions of the `ApprovalResolved` events, in log order.
+    fn approval_decisions(&self) -> Vec<String> {
+        self.events()
+            .lines()
+            .map(|line| serde_json::from_str::<PersistedShellEventRecord>(line).expect("record"))
+            .filter_map(|record| match record.event {
+                PersistedShellEvent::ApprovalResolved { decision, .. } => Some(decision),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Writes the user config, the only file `[[tools]]` is read from.
+    fn user_config(&self, text: &str) {
+        let dir = self.home.path().join("dao");
+        fs::create_dir_all(&dir).expect("user config dir");
+        fs::write(dir.join("config.toml"), text).expect("write user config");
+    }
+
+    fn events(&self) -> String {
+        fs::read_to_string(self.path().join(".dao/workflow-events.jsonl")).expect("events")
+    }
+
+    fn state(&self) -> serde_json::Value {
+        let bytes = fs::read(self.path().join(".dao/state.json")).expect("state.json");
+        serde_json::from_slice(&bytes).expect("parse state.json")
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn completed_run_is_persisted_and_replayable() {
+    let sandbox = Sandbox::new();
+
+    let run = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(run.status.code(), Some(0), "{}", stdout(&run));
+    assert!(stdout(&run).contains("workflow 1 completed"));
+    for file in ["workflow-events.jsonl", "snapshot.json", "state.json"] {
+        assert!(sandbox.path().join(".dao").join(file).exists(), "{file}");
+    }
+
+    let state = sandbox.state();
+    let steps = state["artifacts"]["plan"]["steps"]
+        .as_array()
+        .expect("plan steps")
+        .iter()
+        .map(|step| step["label"].as_str().expect("label").to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(steps, vec!["Read notes", "Append a line"]);
+    let diff = state["artifacts"]["diff"]["files"][0]["path"]
+        .as_str()
+        .expect("diff path");
+    assert_eq!(diff, "notes.txt");
+
+    let replay = sandbox.dao(&["replay", "--last", "--repo", "."], "");
+    assert_eq!(replay.status.code(), Some(0));
+    let replay = stdout(&replay);
+    assert!(replay.contains("run_id: 1"), "{replay}");
+    assert!(replay.contains("status: completed"), "{replay}");
+    assert!(replay.contains("pending_approval: none"), "{replay}");
+
+    let resume = sandbox.dao(&["resume", "--repo", "."], "");
+    assert_eq!(resume.status.code(), Some(0));
+    assert!(stdout(&resume).contains("nothing to resume"));
+}
+
+#[test]
+fn uncommitted_changes_get_an_approval_summary() {
+    let sandbox = Sandbox::new();
+
+    let run = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(run.status.code(), Some(0), "{}", stdout(&run));
+    assert!(
+        stdout(&run).contains("approval summary: "),
+        "{}",
+        stdout(&run)
+    );
+
+    let path = sandbox.path().join(".dao/approval-1.md");
+    let summary = fs::read_to_string(&path).expect("approval summary");
+    assert!(
+        summary.contains("| `notes.txt` | Modified | 1 | 0 |"),
+        "{summary}"
+    );
+    assert!(
+        summary.contains("| compute_diff | allowed | auto | succeeded |"),
+        "{summary}"
+    );
+    assert!(summary.contains("Not committed: awaiting human review."));
+
+    fs::remove_file(&path).expect("remove summary");
+    let rerun = sandbox.dao(&["approval-summary", "--repo", "."], "");
+    assert_eq!(rerun.status.code(), Some(0));
+    assert_eq!(fs::read_to_string(&path).expect("rewritten"), summary);
+
+    let wrong_run = sandbox.dao(&["approval-summary", "--repo", ".", "--run", "4"], "");
+    assert_eq!(wrong_run.status.code(), Some(1));
+}
+
+#[test]
+fn replay_selects_an_older_run_by_id() {
+    let sandbox = Sandbox::new();
+    for _ in 0..3 {
+        let run = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+        assert_eq!(run.status.code(), Some(0), "{}", stdout(&run));
+    }
+
+    let latest = stdout(&sandbox.dao(&["replay", "--repo", "."], ""));
+    assert!(latest.contains("run_id: 3"), "{latest}");
+    let older = sandbox.dao(&["replay", "--run", "2", "--repo", "."], "");
+    assert_eq!(older.status.code(), Some(0));
+    let older = stdout(&older);
+    assert!(older.contains("run_id: 2"), "{older}");
+    assert!(older.contains("status: completed"), "{older}");
+
+    let missing = sandbox.dao(&["replay", "--run", "7", "--repo", "."], "");
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("run 7 not found"));
+}
+
+#[test]
+fn template_flag_selects_the_workflow_and_is_replayed() {
+    let sandbox = Sandbox::new();
+
+    let unknown = sandbox.dao(&["run", "--repo", ".", "--template", "scan-only"], "");
+    assert_eq!(unknown.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&unknown.stderr);
+    assert!(
+        stderr.contains("expected one of: scan_plan_diff_verify, scan_plan_only"),
+        "{stderr}"
+    );
+
+    let run = sandbox.dao(
+        &[
+            "run",
+            "--repo",
+            ".",
+            "--no-cache",
+            "--template",
+            "scan-plan-only",
+        ],
+        "",
+    );
+    assert_eq!(run.status.code(), Some(0), "{}", stdout(&run));
+    assert!(sandbox
+        .events()
+        .contains(r#""template_id":"scan_plan_only""#));
+    let state = sandbox.state();
+    assert!(state["artifacts"]["plan"].is_object());
+    assert!(state["artifacts"]["diff"].is_null());
+
+    let replay = stdout(&sandbox.dao(&["replay", "--repo", "."], ""));
+    assert!(replay.contains("template: scan_plan_only"), "{replay}");
+    assert!(replay.contains("status: completed"), "{replay}");
+
+    let resume = sandbox.dao(
+        &[
+            "resume",
+            "--repo",
+            ".",
+            "--template",
+            "scan_plan_diff_verify",
+        ],
+        "",
+    );
+    assert_eq!(resume.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&resume.stderr)
+        .contains("run 1 uses template scan_plan_only, not scan_plan_diff_verify"));
+}
+
+#[test]
+fn test_template_runs_the_test_suite_as_its_last_step() {
+    let sandbox = Sandbox::new();
+
+    let run = sandbox.dao_with_env(
+        &[
+            "run",
+            "--repo",
+            ".",
+            "--no-cache",
+            "--template",
+            "scan_plan_diff_test",
+        ],
+        "",
+        &[("DAO_AUTO_APPROVE", "1")],
+    );
+    assert_eq!(run.status.code(), Some(3), "{}", stdout(&run));
+    assert!(stdout(&run).contains("workflow ended at test with status failed"));
+    let events = sandbox.events();
+    assert!(events.contains(r#""template_id":"scan_plan_diff_test""#));
+    assert!(events.contains(r#""tool_id":"run_tests","status":"failed""#));
+
+    let replay = stdout(&sandbox.dao(&["replay", "--repo", "."], ""));
+    assert!(
+        replay.contains(
+            "no test runner found (expected Cargo.toml or package.json at the repository root)"
+        ),
+        "{replay}"
+    );
+}
+
+#[test]
+fn test_step_needs_approval_even_for_a_small_diff() {
+    let sandbox = Sandbox::new();
+
+    let run = sandbox.dao(
+        &[
+            "run",
+            "--repo",
+            ".",
+            "--no-cache",
+            "--template",
+            "scan_plan_diff_test",
+        ],
+        "",
+    );
+    assert_eq!(run.status.code(), Some(3), "{}", stdout(&run));
+    assert!(
+        stdout(&run).contains("workflow blocked: approval denied at test"),
+        "{}",
+        stdout(&run)
+    );
+    let events = sandbox.events();
+    assert!(events.contains(r#""kind":"approval_requested""#));
+    assert!(!events.contains(r#""tool_id":"run_tests","status""#));
+}
+
+#[test]
+fn json_log_format_streams_persisted_events_on_stderr() {
+    let sandbox = Sandbox::new();
+
+    let run = sandbox.dao(
+        &["run", "--repo", ".", "--no-cache", "--log-format", "json"],
+        "",
+    );
+    assert_eq!(run.status.code(), Some(0));
+    assert_eq!(stdout(&run), "");
+
+    let stderr = String::from_utf8_lossy(&run.stderr);
+    // Tools may still write their own diagnostics to stderr.
+    let emitted = stderr
+        .lines()
+        .filter(|line| line.starts_with('{'))
+        .map(|line| {
+            assert!(line.starts_with(r#"{"seq":"#), "{line}");
+            assert!(line.contains(r#","ts_ms":"#), "{line}");
+            serde_json::from_str::<PersistedShellEvent>(line).expect("event line")
+        })
+        .collect::<Vec<_>>();
+    let persisted = sandbox
+        .events()
+        .lines()
+        .map(|line| serde_json::from_str::<PersistedShellEventRecord>(line).expect("record"))
+        .map(|record| record.event)
+        .collect::<Vec<_>>();
+    assert_eq!(emitted, persisted);
+    assert!(matches!(
+        emitted.last(),
+        Some(PersistedShellEvent::WorkflowStatusChanged {
+            status: PersistedWorkflowStatus::Completed,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn compaction_keeps_the_latest_run_replayable() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(run.status.code(), Some(0), "{}", stdout(&run));
+    let before = stdout(&sandbox.dao(&["replay", "--repo", "."], ""));
+    let runs_before = stdout(&sandbox.dao(&["runs", "--repo", "."], ""));
+    let last_seq = sandbox
+        .events()
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<PersistedShellEventRecord>(line)
+                .expect("record")
+                .seq
+        })
+        .max()
+        .expect("events");
+
+    let compact = sandbox.dao(&["compact", "--repo", "."], "");
+    assert_eq!(compact.status.code(), Some(0));
+    assert!(
+        stdout(&compact).starts_with("compacted "),
+        "{}",
+        stdout(&compact)
+    );
+    // Only the run's start survives, since it carries the policy tier.
+    let kept = sandbox
+        .events()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("record"))
+        .collect::<Vec<_>>();
+    assert_eq!(kept.len(), 1);
+    assert_eq!(kept[0]["kind"], "workflow_run_started");
+    assert_eq!(kept[0]["run_id"], 1);
+    let after = stdout(&sandbox.dao(&["replay", "--repo", "."], ""));
+    assert_eq!(
+        after
+            .lines()
+            .filter(|line| !line.starts_with("last_log_seq"))
+            .collect::<Vec<_>>(),
+        before
+            .lines()
+            .filter(|line| !line.starts_with("last_log_seq"))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        stdout(&sandbox.dao(&["runs", "--repo", "."], "")),
+        runs_before
+    );
+
+    let rerun = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(rerun.status.code(), Some(0), "{}", stdout(&rerun));
+    assert!(stdout(&rerun).contains("workflow 2 completed"));
+    let first_new_seq = sandbox
+        .events()
+        .lines()
+        .map(|line| serde_json::from_str::<PersistedShellEventRecord>(line).expect("record"))
+        .find(|record| record.event.run_id() == Some(2))
+        .expect("run 2 event")
+        .seq;
+    assert!(first_new_seq > last_seq, "seq restarted at {first_new_seq}");
+}
+
+#[test]
+fn export_round_trips_through_serde() {
+    let sandbox = Sandbox::new();
+
+    let run = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(run.status.code(), Some(0), "{}", stdout(&run));
+
+    let export = sandbox.dao(&["export", "--repo", ".", "--run", "1"], "");
+    assert_eq!(export.status.code(), Some(0));
+    let parsed: WorkflowRunExport = serde_json::from_slice(&export.stdout).expect("parse export");
+    assert_eq!(parsed.run.run_id, 1);
+    assert_eq!(parsed.run.status, PersistedWorkflowStatus::Completed);
+    assert_eq!(parsed.artifacts, WorkflowArtifactFlags::for_step(4));
+    assert!(parsed.last_log_seq > 0);
+    let reserialized = serde_json::to_string_pretty(&parsed).expect("serialize");
+    assert_eq!(reserialized, stdout(&export).trim_end());
+
+    let latest = sandbox.dao(&["export", "--repo", "."], "");
+    assert_eq!(latest.stdout, export.stdout);
+
+    let yaml = sandbox.dao(&["export", "--repo", ".", "--format", "yaml"], "");
+    assert_eq!(yaml.status.code(), Some(0));
+    assert!(stdout(&yaml).contains("run_id: 1"), "{}", stdout(&yaml));
+
+    let missing = sandbox.dao(&["export", "--repo", ".", "--run", "9"], "");
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("run not found"));
+}
+
+#[test]
+fn cat_prints_the_latest_artifact_and_rejects_older_runs() {
+    let sandbox = Sandbox::new();
+    for _ in 0..2 {
+        let run = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+        assert_eq!(run.status.code(), Some(0), "{}", stdout(&run));
+    }
+    let cat = |run: &str| {
+        sandbox.dao(
+            &["cat", "--repo", ".", "--artifact", "plan", "--run", run],
+            "",
+        )
+    };
+
+    let latest = cat("2");
+    assert_eq!(latest.status.code(), Some(0));
+    assert!(
+        stdout(&latest).contains("Read notes"),
+        "{}",
+        stdout(&latest)
+    );
+
+    let older = cat("1");
+    assert_eq!(older.status.code(), Some(1));
+    assert!(
+        String::from_utf8_lossy(&older.stderr).contains(
+            "plan artifact for run 1 is no longer saved; only the latest (run 2) is kept"
+        ),
+        "{}",
+        String::from_utf8_lossy(&older.stderr)
+    );
+
+    let missing = cat("9");
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("run not found: 9"));
+}
+
+#[test]
+fn watch_keeps_going_after_a_failed_iteration() {
+    let sandbox = Sandbox::new();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dao"))
+        .args(["run", "--watch", "--repo", ".", "--no-cache"])
+        .args(["--policy", "allow.yaml"])
+        .current_dir(sandbox.path())
+        .env("HOME", sandbox.home.path())
+        .env("XDG_CONFIG_HOME", sandbox.home.path())
+        .env_remove("DAO_MODEL")
+        .env_remove("DAO_PROVIDER")
+        .env_remove("DAO_AUTO_APPROVE")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn dao");
+    let (tx, rx) = mpsc::channel();
+    let stdout = child.stdout.take().expect("stdout");
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let _ = tx.send(line);
+        }
+    });
+    let wait_for = |needle: &str| {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+            match rx.recv_timeout(left) {
+                Ok(line) if line.contains(needle) => return true,
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        false
+    };
+
+    // The policy file does not exist yet, so the first run fails.
+    let first_wait = wait_for("watching");
+    fs::write(
+        sandbox.path().join("allow.yaml"),
+        "id: \"allow\"\nversion: \"1\"\napplies_to: {}\ndefaults:\n  approval: {}\nmode: \"allow_by_default\"\nrules: []\n",
+    )
+    .expect("write policy");
+    let completed = first_wait && wait_for("workflow 1 completed");
+    let _ = child.kill();
+    let output = child.wait_with_output().expect("wait dao");
+
+    assert!(first_wait, "watch stopped after the failed run");
+    assert!(completed, "watch did not re-run after the change");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("error: "),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn simulate_reports_each_step_gate_without_running() {
+    let sandbox = Sandbox::new();
+    let run = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(run.status.code(), Some(0), "{}", stdout(&run));
+    let events = sandbox.events();
+
+    let policy = |name: &str, body: &str| {
+        let yaml = format!(
+            "id: \"{name}\"\nversion: \"1\"\napplies_to: {{}}\ndefaults:\n  approval: {{}}\n{body}"
+        );
+        fs::write(sandbox.path().join(format!("{name}.yaml")), yaml).expect("write policy");
+    };
+    policy(
+        "allow",
+        "mode: \"allow_by_default\"\nrules:\n  - id: \"notes\"\n    paths: [\"notes.txt\"]\n    then:\n      action: \"allow\"\n      message: \"Notes are fine.\"\n",
+    );
+    policy(
+        "block",
+        "content_rules:\n  - id: \"no-second\"\n    pattern: \"second\"\n    then:\n      action: \"block\"\n      message: \"No second lines.\"\nrules: []\n",
+    );
+
+    let simulate = |policy: &str| {
+        let output = sandbox.dao(&["simulate", "--policy", policy, "--repo", "."], "");
+        assert_eq!(output.status.code(), Some(0), "{}", stdout(&output));
+        stdout(&output)
+    };
+    let allowed = simulate("allow.yaml");
+    assert!(allowed.contains("policy: allow\n"), "{allowed}");
+    assert!(allowed.contains("diff: run 1 "), "{allowed}");
+    // Steps before `compute_diff` run without a diff, so only the last two match.
+    assert!(
+        allowed.contains("scan (scan_repo): allowed [(default)]"),
+        "{allowed}"
+    );
+    assert!(
+        allowed.contains("diff (compute_diff): allowed [notes] Notes are fine."),
+        "{allowed}"
+    );
+    assert_eq!(allowed.matches(": allowed [notes]").count(), 2, "{allowed}");
+
+    let blocked = simulate("block.yaml");
+    assert!(
+        blocked.contains("diff (compute_diff): blocked [no-second] No second lines."),
+        "{blocked}"
+    );
+
+    let review = simulate("review.yaml");
+    assert!(
+        review.contains(
+            "verify (verify): approval_required [always-review] Every step needs a reviewer."
+        ),
+        "{review}"
+    );
+
+    policy(
+        "intent",
+        "rules:\n  - id: \"login-fix\"\n    when: 'commit_message == \"fix the login bug\"'\n    then:\n      action: \"block\"\n      message: \"Login fixes go through review.\"\n",
+    );
+    let intent = sandbox.dao(
+        &[
+            "simulate",
+            "--policy",
+            "intent.yaml",
+            "--repo",
+            ".",
+            "fix",
+            "the",
+            "login",
+            "bug",
+        ],
+        "",
+    );
+    assert_eq!(intent.status.code(), Some(0), "{}", stdout(&intent));
+    assert!(
+        stdout(&intent).contains("scan (scan_repo): blocked [login-fix]"),
+        "{}",
+        stdout(&intent)
+    );
+
+    assert_eq!(sandbox.events(), events);
+    let missing = sandbox.dao(&["simulate", "--repo", "."], "");
+    assert_eq!(missing.status.code(), Some(1));
+}
+
+#[test]
+fn denied_approval_blocks_the_run() {
+    let sandbox = Sandbox::new();
+
+    let run = sandbox.dao(
+        &[
+            "run",
+            "--repo",
+            ".",
+            "--policy",
+            "review.yaml",
+            "--no-cache",
+        ],
+        "n\n",
+    );
+    assert_eq!(run.status.code(), Some(3), "{}", stdout(&run));
+    assert!(stdout(&run).contains("workflow blocked: approval denied at scan"));
+
+    let events = sandbox.events();
+    assert!(events.contains(r#""kind":"approval_requested""#));
+    assert!(events.contains(r#""decision":"denied""#));
+    assert!(!events.contains(r#""kind":"tool_invocation_issued""#));
+
+    let replay = stdout(&sandbox.dao(&["replay", "--last", "--repo", "."], ""));
+    assert!(replay.contains("status: blocked"), "{replay}");
+
+    let resume = sandbox.dao(&["resume", "--repo", "."], "");
+    assert_eq!(resume.status.code(), Some(0));
+    assert!(stdout(&resume).contains("nothing to resume"));
+}
+
+#[test]
+fn runs_lists_completed_and_blocked_runs_newest_first() {
+    let sandbox = Sandbox::new();
+    let empty = sandbox.dao(&["runs", "--repo", "."], "");
+    assert_eq!(empty.status.code(), Some(0));
+    assert!(stdout(&empty).contains("no workflow runs found"));
+
+    let completed = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(completed.status.code(), Some(0), "{}", stdout(&completed));
+    let blocked = sandbox.dao(
+        &[
+            "run",
+            "--repo",
+            ".",
+            "--policy",
+            "review.yaml",
+            "--no-cache",
+        ],
+        "",
+    );
+    assert_eq!(blocked.status.code(), Some(3), "{}", stdout(&blocked));
+
+    let runs = stdout(&sandbox.dao(&["runs", "--repo", "."], ""));
+    let rows = runs
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        vec![
+            vec!["RUN", "TEMPLATE", "STATUS", "STEPS"],
+            vec!["2", "scan_plan_diff_verify", "blocked", "0"],
+            vec!["1", "scan_plan_diff_verify", "completed", "4"],
+        ],
+        "{runs}"
+    );
+
+    let limited = stdout(&sandbox.dao(&["runs", "--repo", ".", "--limit", "1"], ""));
+    assert_eq!(limited.lines().count(), 2, "{limited}");
+    assert!(limited.contains("blocked"), "{limited}");
+
+    let invalid = sandbox.dao(&["runs", "--repo", ".", "--limit", "0"], "");
+    assert_eq!(invalid.status.code(), Some(1));
+}
+
+#[test]
+fn prune_drops_one_run_and_keeps_the_others() {
+    let sandbox = Sandbox::new();
+    let completed = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(completed.status.code(), Some(0), "{}", stdout(&completed));
+    let blocked = sandbox.dao(
+        &[
+            "run",
+            "--repo",
+            ".",
+            "--policy",
+            "review.yaml",
+            "--no-cache",
+        ],
+        "",
+    );
+    assert_eq!(blocked.status.code(), Some(3), "{}", stdout(&blocked));
+    let events = sandbox.events();
+
+    // Without a terminal to confirm on, only --yes deletes anything.
+    let unconfirmed = sandbox.dao(&["prune", "--repo", ".", "--run", "2"], "");
+    assert_eq!(unconfirmed.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&unconfirmed.stderr).contains("pass --yes"));
+    assert_eq!(sandbox.events(), events);
+    let ambiguous = sandbox.dao(
+        &["prune", "--repo", ".", "--run", "2", "--all", "--yes"],
+        "",
+    );
+    assert_eq!(ambiguous.status.code(), Some(1));
+
+    let pruned = sandbox.dao(&["prune", "--repo", ".", "--run", "2", "--yes"], "");
+    assert_eq!(pruned.status.code(), Some(0), "{}", stdout(&pruned));
+    assert!(stdout(&pruned).contains("(runs: 2)"), "{}", stdout(&pruned));
+    let kept = events
+        .lines()
+        .filter(|line| {
+            let record = serde_json::from_str::<serde_json::Value>(line).expect("record");
+            record["run_id"] != 2
+        })
+        .map(|line| format!("{line}\n"))
+        .collect::<String>();
+    assert_eq!(sandbox.events(), kept);
+
+    let runs = stdout(&sandbox.dao(&["runs", "--repo", "."], ""));
+    assert_eq!(runs.lines().count(), 2, "{runs}");
+    let replay = stdout(&sandbox.dao(&["replay", "--last", "--repo", "."], ""));
+    assert!(replay.contains("run_id: 1"), "{replay}");
+    assert!(replay.contains("status: completed"), "{replay}");
+
+    let cleared = sandbox.dao(&["prune", "--repo", ".", "--all", "--yes"], "");
+    assert_eq!(cleared.status.code(), Some(0), "{}", stdout(&cleared));
+    assert_eq!(sandbox.events(), "");
+    let runs = stdout(&sandbox.dao(&["runs", "--repo", "."], ""));
+    assert!(runs.contains("no workflow runs found"), "{runs}");
+
+    // Ids of pruned runs are not handed out again.
+    let next = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(next.status.code(), Some(0), "{}", stdout(&next));
+    let replay = stdout(&sandbox.dao(&["replay", "--last", "--repo", "."], ""));
+    assert!(replay.contains("run_id: 3"), "{replay}");
+}
+
+#[test]
+fn resume_picks_up_a_pending_approval() {
+    let sandbox = Sandbox::new();
+    sandbox.interrupt_at_scan_approval();
+
+    let replay = stdout(&sandbox.dao(&["replay", "--last", "--repo", "."], ""));
+    assert!(replay.contains("status: awaiting_approval"), "{replay}");
+    assert!(
+        replay.contains("pending_approval: request_id=req-1-1 tool_id=scan_repo invocation_id=1"),
+        "{replay}"
+    );
+
+    let piped = sandbox.dao(&["resume", "--repo", ".", "--no-cache"], "y\n");
+    assert_eq!(piped.status.code(), Some(3), "{}", stdout(&piped));
+    assert!(stdout(&piped).contains("scan_repo: denied (stdin is not a terminal"));
+
+    let sandbox = Sandbox::new();
+    sandbox.interrupt_at_scan_approval();
+    let resume = sandbox.dao_with_env(
+        &["resume", "--repo", ".", "--no-cache"],
+        "",
+        &[("DAO_AUTO_APPROVE", "1")],
+    );
+    assert_eq!(resume.status.code(), Some(0), "{}", stdout(&resume));
+    assert!(stdout(&resume).contains("scan_repo: approved (DAO_AUTO_APPROVE=1)"));
+    assert!(stdout(&resume).contains("workflow 1 completed"));
+
+    let events = sandbox.events();
+    assert!(events.contains(r#""decision":"approved""#));
+    assert!(events.contains(r#""kind":"workflow_resumed""#));
+    assert!(events.contains(r#""tool_id":"compute_diff""#));
+
+    let replay = stdout(&sandbox.dao(&["replay", "--last", "--repo", "."], ""));
+    assert!(replay.contains("status: completed"), "{replay}");
+}
+
+#[test]
+fn resume_flags_answer_the_approval_prompt() {
+    let sandbox = Sandbox::new();
+    sandbox.interrupt_at_scan_approval();
+    let both = sandbox.dao(&["resume", "--repo", ".", "--approve", "--deny"], "");
+    assert_eq!(both.status.code(), Some(1));
+    assert!(sandbox.approval_decisions().is_empty());
+
+    let deny = sandbox.dao(&["resume", "--repo", ".", "--deny"], "y\n");
+    assert_eq!(deny.status.code(), Some(3), "{}", stdout(&deny));
+    assert!(stdout(&deny).contains("scan_repo: denied (--deny)"));
+    assert_eq!(sandbox.approval_decisions(), vec!["denied"]);
+
+    let sandbox = Sandbox::new();
+    sandbox.interrupt_at_scan_approval();
+    let approve = sandbox.dao(&["resume", "--repo", ".", "--no-cache", "--approve"], "");
+    assert_eq!(approve.status.code(), Some(0), "{}", stdout(&approve));
+    assert!(stdout(&approve).contains("workflow 1 completed"));
+    assert_eq!(sandbox.approval_decisions(), vec!["approved"]);
+}
+
+#[test]
+fn retries_rerun_a_failed_tool_with_backoff() {
+    let sandbox = Sandbox::new();
+    let flaky = |idempotent: bool| {
+        format!(
+            r#"
+[[tools]]
+name = "flaky"
+command = "test -f .dao/flaky.mark || {{ touch .dao/flaky.mark; echo upstream down; exit 1; }}"
+output = "diff"
+idempotent = {idempotent}
+"#
+        )
+    };
+
+    let invalid = sandbox.dao(&["run", "--repo", ".", "--retries", "many"], "");
+    assert_eq!(invalid.status.code(), Some(1));
+
+    // Only a tool marked idempotent is run again.
+    sandbox.user_config(&flaky(false));
+    let run = sandbox.dao_with_env(
+        &["run", "--repo", ".", "--no-cache", "--retries", "2"],
+        "",
+        &[("DAO_AUTO_APPROVE", "1")],
+    );
+    assert_eq!(run.status.code(), Some(3), "{}", stdout(&run));
+    assert!(!stdout(&run).contains("attempt 1 of 3"), "{}", stdout(&run));
+    fs::remove_file(sandbox.path().join(".dao/flaky.mark")).expect("remove mark");
+
+    sandbox.user_config(&flaky(true));
+    let run = sandbox.dao_with_env(
+        &["run", "--repo", ".", "--no-cache", "--retries", "2"],
+        "",
+        &[("DAO_AUTO_APPROVE", "1")],
+    );
+    assert_eq!(run.status.code(), Some(0), "{}", stdout(&run));
+    assert!(
+        stdout(&run).contains("flaky attempt 1 of 3 failed: flaky exited with"),
+        "{}",
+        stdout(&run)
+    );
+    assert!(!stdout(&run).contains("attempt 2 of 3"));
+
+    let events = sandbox.events();
+    assert!(events.contains(r#""tool_id":"flaky","status":"succeeded""#));
+    assert!(events.contains("attempt 1 of 3 failed"));
+}
+
+#[test]
+fn external_tools_run_as_workflow_steps() {
+    let sandbox = Sandbox::new();
+    let tool = r#"
+[[tools]]
+name = "lint"
+command = "test -d {cwd} && echo linted"
+risk = "ReadOnly"
+output = "verify"
+"#;
+    fs::write(sandbox.path().join(".dao/config.toml"), tool).expect("write config");
+    let ignored = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(ignored.status.code(), Some(0), "{}", stdout(&ignored));
+    assert!(String::from_utf8_lossy(&ignored.stderr).contains("warning: ignoring"));
+    assert!(!sandbox.events().contains(r#""tool_id":"lint""#));
+    fs::remove_file(sandbox.path().join(".dao/config.toml")).expect("remove config");
+
+    // From the user config the tool runs, but is gated as Execution despite
+    // declaring ReadOnly.
+    sandbox.user_config(tool);
+    let denied = sandbox.dao(&["run", "--repo", ".", "--no-cache"], "");
+    assert_eq!(denied.status.code(), Some(3), "{}", stdout(&denied));
+    assert!(stdout(&denied).contains("approval required for lint"));
+
+    let run = sandbox.dao_with_env(
+        &["run", "--repo", ".", "--no-cache"],
+        "",
+        &[("DAO_AUTO_APPROVE", "1")],
+    );
+    assert_eq!(run.status.code(), Some(0), "{}", stdout(&run));
+
+    let events = sandbox.events();
+    assert!(events.contains(
+        r#""kind":"tool_invocation_issued","run_id":3,"invocation_id":5,"tool_id":"lint""#
+    ));
+    assert!(events.contains(r#""tool_id":"lint","status":"succeeded""#));
+
+    let replay = stdout(&sandbox.dao(&["replay", "--last", "--repo", "."], ""));
+    assert!(
+        replay.contains("tool_output: lint invocation_id=5\n  ran lint:"),
+        "{replay}"
+    );
+    assert!(replay.contains("\n  linted\n"), "{replay}");
+
+    let checks = sandbox.state()["artifacts"]["verify"]["checks"]
+        .as_array()
+        .expect("verify checks")
+        .iter()
+        .map(|check| check["name"].as_str().expect("name").to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(checks, vec!["git diff --check", "lint"]);
+}
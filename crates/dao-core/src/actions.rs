@@ -6,6 +6,7 @@ use super::state::ThreadId;
 
 use std::path::PathBuf;
 
+use super::chat_context::ChatSummary;
 use super::policy_engine::ReviewPolicy;
 use super::state::ApplyStatus;
 use super::state::ApprovalAction;
@@ -29,11 +30,13 @@ use super::state::RiskLevel;
 use super::state::SafetyMode;
 use super::state::ScanStatus;
 use super::state::ShellTab;
+use super::state::StepStatus;
 use super::state::SystemArtifact;
 use super::state::UiTheme;
 use super::state::UsageSnapshot;
 use super::state::VerifyArtifact;
 use super::state::VerifyStatus;
+use super::tool_registry::ToolId;
 
 #[derive(Debug, Clone)]
 pub enum ShellAction {
@@ -52,6 +55,8 @@ pub enum UserAction {
     CycleKeymapPreset,
     SetTheme(UiTheme),
     CycleTheme,
+    SetPolicyTier(PolicyTier),
+    CyclePolicyTier,
     ToggleJourneyPanel,
     ToggleOverviewPanel,
     ToggleActionBar,
@@ -76,21 +81,47 @@ pub enum UserAction {
     },
     SetLogLevelFilter(Option<LogLevel>),
     SetLogSearch(String),
+    SetLogsTextSearch(String),
     ScrollLogs(i16),
+    ScrollDiffHorizontal(i16),
     SetLogScroll(u16),
     SetLogStickToBottom(bool),
+    JumpLogRunBoundary {
+        forward: bool,
+    },
+    ToggleLogBookmark(u64),
+    /// Reads more unchanged lines around hunk `hunk` of `file` from the working tree.
+    ExpandHunkContext {
+        file: String,
+        hunk: usize,
+    },
+    JumpLogBookmark {
+        forward: bool,
+    },
     ClearArtifact {
         which: ClearWhich,
         reason: ClearReason,
     },
     ChatInput(char),
+    /// Starts a new line in the chat input instead of submitting it.
+    ChatInputNewline,
     ChatBackspace,
+    /// Deletes the word before the end of the input, with the whitespace
+    /// after it.
+    ChatDeleteWord,
+    /// Clears the line being typed; on an empty line, joins it to the one
+    /// above.
+    ChatClearLine,
     ChatSubmit,
     SetChatFocus(bool),
+    /// Stops the reply being streamed; what arrived so far is kept.
+    CancelChat,
     ResetSession,
     ConfirmReset,
     CancelReset,
     ShowHelp,
+    ShowApprovalPreview,
+    ScrollApprovalPreview(i16),
     ChatHistoryUp,
     ChatHistoryDown,
     ReviewChanges,
@@ -101,16 +132,38 @@ pub enum UserAction {
     ModelListMoveDown,
     ModelListSubmit,
     CopyDiffToClipboard,
+    /// Copies the selected plan step as `id [status] label`.
+    CopySelectedPlanStep,
     SetPlanStickToRunning(bool),
     PlanStepUp,
     PlanStepDown,
     TogglePlanStepExpansion,
+    ToggleDiffFileCollapse {
+        path: String,
+    },
+    /// Marks the selected step done, or back to pending; refused while any of
+    /// its dependencies is not done.
+    TogglePlanStepDone,
+    /// Sets a step's status by hand. Like `TogglePlanStepDone`, marking it
+    /// done is refused while any of its dependencies is not done.
+    SetPlanStepStatus {
+        id: String,
+        status: StepStatus,
+    },
     PlanStepPageUp,
     PlanStepPageDown,
     FileBrowserUp,
     FileBrowserDown,
     FileBrowserEnter,
     FileBrowserBack,
+    /// Loads `file_browser.preview` for the selected entry.
+    LoadFilePreview,
+    /// Shows `git diff` of the selected file on the Diff tab.
+    DiffSelectedFile,
+    /// Approves the pending request; the host stamps the decision time.
+    ApprovePending {
+        timestamp_ms: u64,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -122,6 +175,18 @@ pub enum RuntimeFlag {
     Verifying,
 }
 
+impl RuntimeFlag {
+    /// Flag raised while `tool_id` runs.
+    pub fn for_tool(tool_id: ToolId) -> Self {
+        match tool_id {
+            ToolId::ScanRepo => Self::Scanning,
+            ToolId::GeneratePlan => Self::Planning,
+            ToolId::ComputeDiff => Self::Diffing,
+            ToolId::Verify | ToolId::RunTests => Self::Verifying,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ClearWhich {
     System,
@@ -180,7 +245,8 @@ pub enum RuntimeAction {
 
     SetJourneyErrorState(Option<JourneyError>),
     SetPolicyTier(PolicyTier),
-    SetReviewPolicy(ReviewPolicy),
+    SetRiskOverride(Option<ApprovalRiskClass>),
+    SetReviewPolicy(Box<ReviewPolicy>),
     AssessPolicyGate {
         run_id: u64,
         action: ApprovalAction,
@@ -189,16 +255,27 @@ pub enum RuntimeAction {
     },
     RequestApproval(ApprovalRequestRecord),
     ResolveApproval(ApprovalDecisionRecord),
+    ReopenApproval {
+        request_id: String,
+    },
     ClearApprovalState(ClearReason),
 
     AppendStructuredLog(LogEntry),
     ClearLogs(ClearReason),
+    ClearChat(ClearReason),
+    SetChatSummary(Option<ChatSummary>),
 
     // Compatibility actions while app/runtime adapter migrates.
     SetOverview(String),
     SetSystem(String),
     SetPlan(String),
     SetDiff(String),
+    /// Shows `git diff` of one browsed file on the Diff tab without touching
+    /// the run's diff.
+    SetBrowseDiff {
+        path: String,
+        text: String,
+    },
     SetExplain(String),
     AppendLog(String),
     SetThinking(bool),
@@ -217,6 +294,8 @@ pub enum PaletteCommand {
     ToggleAutoIntentFollow,
     OpenPermissions,
     OpenApprovals,
+    ApprovePending,
+    RerunVerify,
     OpenSkills,
     StartNewSession,
     Quit,
@@ -228,7 +307,7 @@ pub struct PaletteItem {
     pub command: PaletteCommand,
 }
 
-pub const PALETTE_ITEMS: [PaletteItem; 20] = [
+pub const PALETTE_ITEMS: [PaletteItem; 24] = [
     PaletteItem {
         label: "Continue in chat",
         command: PaletteCommand::ContinueInChat,
@@ -249,6 +328,10 @@ pub const PALETTE_ITEMS: [PaletteItem; 20] = [
         label: "Keymap: Windows",
         command: PaletteCommand::SetKeymapPreset(KeymapPreset::Windows),
     },
+    PaletteItem {
+        label: "Keymap: Vim",
+        command: PaletteCommand::SetKeymapPreset(KeymapPreset::Vim),
+    },
     PaletteItem {
         label: "Theme: Classic",
         command: PaletteCommand::SetTheme(UiTheme::Classic),
@@ -269,6 +352,10 @@ pub const PALETTE_ITEMS: [PaletteItem; 20] = [
         label: "Theme: Forest Zen",
         command: PaletteCommand::SetTheme(UiTheme::ForestZen),
     },
+    PaletteItem {
+        label: "Theme: Midnight Slate",
+        command: PaletteCommand::SetTheme(UiTheme::MidnightSlate),
+    },
     PaletteItem {
         label: "Switch theme",
         command: PaletteCommand::CycleTheme,
@@ -297,6 +384,14 @@ pub const PALETTE_ITEMS: [PaletteItem; 20] = [
         label: "Open approvals",
         command: PaletteCommand::OpenApprovals,
     },
+    PaletteItem {
+        label: "Approve pending request",
+        command: PaletteCommand::ApprovePending,
+    },
+    PaletteItem {
+        label: "Re-run verification",
+        command: PaletteCommand::RerunVerify,
+    },
     PaletteItem {
         label: "Open skills",
         command: PaletteCommand::OpenSkills,
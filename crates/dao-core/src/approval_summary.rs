@@ -0,0 +1,395 @@
+use std::fmt::Write;
+
+use crate::persistence::PersistedShellEvent;
+use crate::persistence::PersistedShellEventRecord;
+use crate::state::DiffLineKind;
+use crate::state::ShellState;
+
+pub fn approval_summary_file_name(run_id: u64) -> String {
+    format!("approval-{run_id}.md")
+}
+
+/// One workflow step as seen through the event log.
+#[derive(Debug, Default)]
+struct GateRow {
+    tool_id: String,
+    risk: Option<String>,
+    request_id: Option<String>,
+    decision: Option<String>,
+    result: Option<String>,
+}
+
+/// Markdown report a reviewer reads before approving the changes of
+/// `run_id`: files changed, diff risk, policy notes and the gate decisions
+/// recorded in the event log.
+pub fn approval_summary_markdown(
+    state: &ShellState,
+    run_id: u64,
+    records: &[PersistedShellEventRecord],
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Approval summary: run {run_id}");
+    let _ = writeln!(out);
+
+    let diff = state
+        .artifacts
+        .diff
+        .as_ref()
+        .filter(|diff| diff.run_id == run_id);
+    let _ = writeln!(out, "## Files changed");
+    let _ = writeln!(out);
+    match diff.filter(|diff| !diff.files.is_empty()) {
+        Some(diff) => {
+            let _ = writeln!(out, "| File | Status | Added | Removed |");
+            let _ = writeln!(out, "| --- | --- | ---: | ---: |");
+            for file in &diff.files {
+                let (added, removed) = file.hunks.iter().flat_map(|hunk| &hunk.lines).fold(
+                    (0usize, 0usize),
+                    |(added, removed), line| match line.kind {
+                        DiffLineKind::Add => (added + 1, removed),
+                        DiffLineKind::Remove => (added, removed + 1),
+                        DiffLineKind::Context => (added, removed),
+                    },
+                );
+                let _ = writeln!(
+                    out,
+                    "| `{}` | {:?} | {added} | {removed} |",
+                    file.path, file.status
+                );
+            }
+        }
+        None => {
+            let _ = writeln!(out, "No changes recorded for this run.");
+        }
+    }
+    let _ = writeln!(out);
+
+    let _ = writeln!(out, "## Risk");
+    let _ = writeln!(out);
+    match diff {
+        Some(diff) => {
+            let assessment = diff.assess_risk(&state.config.risk);
+            let _ = writeln!(
+                out,
+                "**{}** (score {:.1})",
+                assessment.risk.label(),
+                assessment.score
+            );
+            for factor in &assessment.factors {
+                let _ = writeln!(out, "- {factor}");
+            }
+        }
+        None => {
+            let _ = writeln!(out, "No diff to assess.");
+        }
+    }
+    let _ = writeln!(out);
+
+    let _ = writeln!(out, "## Policy");
+    let _ = writeln!(out);
+    let approval = &state.approval;
+    let _ = writeln!(out, "- Tier: {}", approval.policy_tier.label());
+    match &approval.active_policy {
+        Some(policy) => {
+            let _ = writeln!(out, "- Review policy: {} v{}", policy.id, policy.version);
+        }
+        None => {
+            let _ = writeln!(out, "- Review policy: none (tier defaults)");
+        }
+    }
+    if let Some(risk) = approval.risk_override {
+        let _ = writeln!(out, "- Risk override: {}", risk.label());
+    }
+    if let Some(gate) = approval
+        .last_gate
+        .as_ref()
+        .filter(|gate| gate.run_id == run_id)
+    {
+        let rule = gate
+            .matched_rule
+            .as_deref()
+            .map(|rule| format!(" (rule {rule})"))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "- Last gate: {}{rule}: {}",
+            gate.requirement.label(),
+            gate.reason
+        );
+    }
+    let _ = writeln!(out);
+
+    let mut rows: Vec<(u64, GateRow)> = Vec::new();
+    let mut notes = Vec::new();
+    let mut commit = None;
+    for record in records {
+        match &record.event {
+            PersistedShellEvent::ApprovalRequested {
+                request_id,
+                run_id: event_run,
+                invocation_id,
+                tool_id,
+                risk,
+                ..
+            } if *event_run == run_id => {
+                let row = gate_row(&mut rows, *invocation_id, tool_id);
+                row.risk = Some(risk.clone());
+                row.request_id = Some(request_id.clone());
+            }
+            PersistedShellEvent::ApprovalResolved {
+                request_id,
+                run_id: event_run,
+                decision,
+                ..
+            } if *event_run == run_id => {
+                if let Some((_, row)) = rows
+                    .iter_mut()
+                    .find(|(_, row)| row.request_id.as_ref() == Some(request_id))
+                {
+                    row.decision = Some(decision.clone());
+                }
+            }
+            PersistedShellEvent::ApprovalReopened {
+                request_id,
+                run_id: event_run,
+                ..
+            } if *event_run == run_id => {
+                if let Some((_, row)) = rows
+                    .iter_mut()
+                    .find(|(_, row)| row.request_id.as_ref() == Some(request_id))
+                {
+                    row.decision = None;
+                }
+                notes.push(format!("Reopened: request {request_id}"));
+            }
+            PersistedShellEvent::ToolInvocationIssued {
+                run_id: event_run,
+                invocation_id,
+                tool_id,
+            } if *event_run == run_id => {
+                gate_row(&mut rows, *invocation_id, tool_id);
+            }
+            PersistedShellEvent::ToolResultRecorded {
+                run_id: event_run,
+                invocation_id,
+                tool_id,
+                status,
+                ..
+            } if *event_run == run_id => {
+                gate_row(&mut rows, *invocation_id, tool_id).result = Some(status.clone());
+            }
+            PersistedShellEvent::WorkflowStatusChanged {
+                run_id: event_run,
+                status,
+                reason: Some(reason),
+                ..
+            } if *event_run == run_id => {
+                notes.push(format!("{status:?}: {reason}"));
+            }
+            PersistedShellEvent::CommitRecorded {
+                run_id: event_run,
+                hash,
+            } if *event_run == run_id => {
+                commit = Some(hash.clone());
+            }
+            _ => {}
+        }
+    }
+
+    let _ = writeln!(out, "## Gate decisions");
+    let _ = writeln!(out);
+    if rows.is_empty() {
+        let _ = writeln!(out, "No gate decisions recorded for this run.");
+    } else {
+        let _ = writeln!(out, "| Step | Tool | Gate | Decision | Result |");
+        let _ = writeln!(out, "| ---: | --- | --- | --- | --- |");
+        for (invocation_id, row) in &rows {
+            let gate = match &row.risk {
+                Some(risk) => format!("approval ({risk})"),
+                None => "allowed".to_string(),
+            };
+            let decision = match (&row.request_id, &row.decision) {
+                (Some(_), Some(decision)) => decision.as_str(),
+                (Some(_), None) => "pending",
+                (None, _) => "auto",
+            };
+            let _ = writeln!(
+                out,
+                "| {invocation_id} | {} | {gate} | {decision} | {} |",
+                row.tool_id,
+                row.result.as_deref().unwrap_or("-")
+            );
+        }
+    }
+    for note in &notes {
+        let _ = writeln!(out, "\n> {note}");
+    }
+    let _ = writeln!(out);
+    match commit {
+        Some(hash) => {
+            let _ = writeln!(out, "Committed as `{hash}`.");
+        }
+        None => {
+            let _ = writeln!(out, "Not committed: awaiting human review.");
+        }
+    }
+    out
+}
+
+fn gate_row<'a>(
+    rows: &'a mut Vec<(u64, GateRow)>,
+    invocation_id: u64,
+    tool_id: &str,
+) -> &'a mut GateRow {
+    let idx = match rows.iter().position(|(id, _)| *id == invocation_id) {
+        Some(idx) => idx,
+        None => {
+            rows.push((
+                invocation_id,
+                GateRow {
+                    tool_id: tool_id.to_string(),
+                    ..GateRow::default()
+                },
+            ));
+            rows.len() - 1
+        }
+    };
+    &mut rows[idx].1
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::state::DiffArtifact;
+    use crate::state::DiffFile;
+    use crate::state::DiffFileStatus;
+    use crate::state::DiffHunk;
+    use crate::state::DiffLine;
+    use crate::state::Personality;
+    use crate::state::ARTIFACT_SCHEMA_V1;
+
+    fn record(seq: u64, event: PersistedShellEvent) -> PersistedShellEventRecord {
+        PersistedShellEventRecord {
+            seq,
+            ts_ms: seq as i64,
+            event,
+        }
+    }
+
+    #[test]
+    fn summary_lists_files_risk_and_gate_decisions_for_the_run() {
+        let mut state = ShellState::new(
+            "project".to_string(),
+            Personality::Pragmatic,
+            Config::default(),
+        );
+        state.artifacts.diff = Some(DiffArtifact {
+            schema_version: ARTIFACT_SCHEMA_V1,
+            run_id: 2,
+            artifact_id: 1,
+            files: vec![DiffFile {
+                path: "src/lib.rs".to_string(),
+                old_path: None,
+                status: DiffFileStatus::Modified,
+                binary: false,
+                hunks: vec![DiffHunk {
+                    header: "@@ -1 +1,2 @@".to_string(),
+                    lines: vec![
+                        DiffLine {
+                            kind: DiffLineKind::Context,
+                            text: " a".to_string(),
+                        },
+                        DiffLine {
+                            kind: DiffLineKind::Add,
+                            text: "+b".to_string(),
+                        },
+                    ],
+                }],
+            }],
+            summary: String::new(),
+            error: None,
+        });
+        let records = vec![
+            record(
+                1,
+                PersistedShellEvent::ToolInvocationIssued {
+                    run_id: 1,
+                    invocation_id: 1,
+                    tool_id: "scan_repo".to_string(),
+                },
+            ),
+            record(
+                2,
+                PersistedShellEvent::ApprovalRequested {
+                    request_id: "req-2-1".to_string(),
+                    run_id: 2,
+                    invocation_id: 1,
+                    tool_id: "scan_repo".to_string(),
+                    risk: "read_only".to_string(),
+                    preview: String::new(),
+                    created_at_ms: 0,
+                },
+            ),
+            record(
+                3,
+                PersistedShellEvent::ApprovalResolved {
+                    request_id: "req-2-1".to_string(),
+                    run_id: 2,
+                    decision: "approved".to_string(),
+                    timestamp_ms: 0,
+                },
+            ),
+            record(
+                4,
+                PersistedShellEvent::ToolInvocationIssued {
+                    run_id: 2,
+                    invocation_id: 1,
+                    tool_id: "scan_repo".to_string(),
+                },
+            ),
+            record(
+                5,
+                PersistedShellEvent::ToolResultRecorded {
+                    run_id: 2,
+                    invocation_id: 1,
+                    tool_id: "scan_repo".to_string(),
+                    status: "succeeded".to_string(),
+                    cache_hit: false,
+                    logs: Vec::new(),
+                },
+            ),
+            record(
+                6,
+                PersistedShellEvent::ToolResultRecorded {
+                    run_id: 2,
+                    invocation_id: 3,
+                    tool_id: "compute_diff".to_string(),
+                    status: "succeeded".to_string(),
+                    cache_hit: true,
+                    logs: Vec::new(),
+                },
+            ),
+        ];
+
+        let summary = approval_summary_markdown(&state, 2, &records);
+        assert!(summary.starts_with("# Approval summary: run 2\n"));
+        assert!(summary.contains("| `src/lib.rs` | Modified | 1 | 0 |"));
+        assert!(summary.contains("- Review policy: none (tier defaults)"));
+        let gates = summary
+            .lines()
+            .filter(|line| line.starts_with("| 1 ") || line.starts_with("| 3 "))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            gates,
+            vec![
+                "| 1 | scan_repo | approval (read_only) | approved | succeeded |",
+                "| 3 | compute_diff | allowed | auto | succeeded |",
+            ]
+        );
+        assert!(summary.ends_with("Not committed: awaiting human review.\n"));
+        assert_eq!(approval_summary_file_name(2), "approval-2.md");
+    }
+}
@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+
+use crate::state::ChatRole;
+use crate::state::ChatTranscript;
+use crate::state::ShellState;
+
+/// How much of the conversation is sent alongside each chat message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum ChatContextStrategy {
+    /// Only the current diff.
+    DiffOnly,
+    /// The last `turns` exchanges, verbatim.
+    Recent { turns: usize },
+    /// A running summary of older exchanges plus the last `turns` verbatim.
+    Summarize { turns: usize },
+}
+
+pub const DEFAULT_RECENT_TURNS: usize = 6;
+
+impl Default for ChatContextStrategy {
+    fn default() -> Self {
+        Self::Recent {
+            turns: DEFAULT_RECENT_TURNS,
+        }
+    }
+}
+
+impl ChatContextStrategy {
+    pub fn label(self) -> String {
+        match self {
+            Self::DiffOnly => "diff".to_string(),
+            Self::Recent { turns } => format!("recent {turns}"),
+            Self::Summarize { turns } => format!("summarize {turns}"),
+        }
+    }
+
+    pub fn recent_turns(self) -> usize {
+        match self {
+            Self::DiffOnly => 0,
+            Self::Recent { turns } | Self::Summarize { turns } => turns,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatTurn {
+    /// Transcript seq of the question, which eviction does not change.
+    pub seq: u64,
+    pub user: String,
+    pub assistant: Option<String>,
+}
+
+/// Summary of the turns that have aged out of the recent window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatSummary {
+    pub text: String,
+    /// Seq of the last turn folded into `text`.
+    #[serde(default)]
+    pub covered_through: u64,
+}
+
+/// Longest summary kept; the oldest lines are dropped past it.
+pub const CHAT_SUMMARY_MAX_CHARS: usize = 2_000;
+
+/// User/assistant exchanges recorded in the chat transcript, oldest first.
+pub fn chat_turns(chat: &ChatTranscript) -> Vec<ChatTurn> {
+    let mut turns: Vec<ChatTurn> = Vec::new();
+    for entry in chat.iter() {
+        match entry.role {
+            ChatRole::User => turns.push(ChatTurn {
+                seq: entry.seq,
+                user: entry.text.clone(),
+                assistant: None,
+            }),
+            ChatRole::Assistant => {
+                if let Some(turn) = turns.last_mut() {
+                    match &mut turn.assistant {
+                        Some(existing) => {
+                            existing.push('\n');
+                            existing.push_str(&entry.text);
+                        }
+                        None => turn.assistant = Some(entry.text.clone()),
+                    }
+                }
+            }
+            ChatRole::Meta => {}
+        }
+    }
+    turns
+}
+
+pub trait ChatSummarizer {
+    /// Folds `turns` into the `previous` summary and returns the new one.
+    fn summarize(&self, previous: Option<&str>, turns: &[ChatTurn]) -> String;
+}
+
+/// Keeps the first line of each question and answer. Needs no model call, so
+/// it is the default until a model-backed summarizer is plugged in.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtractiveSummarizer;
+
+impl ChatSummarizer for ExtractiveSummarizer {
+    fn summarize(&self, previous: Option<&str>, turns: &[ChatTurn]) -> String {
+        let mut lines = previous
+            .map(|summary| summary.lines().map(str::to_string).collect::<Vec<_>>())
+            .unwrap_or_default();
+        for turn in turns {
+            let question = turn.user.lines().next().unwrap_or_default();
+            let answer = turn
+                .assistant
+                .as_deref()
+                .and_then(|reply| reply.lines().find(|line| !line.trim().is_empty()))
+                .unwrap_or("(no reply)");
+            lines.push(format!("- asked: {question} | answered: {answer}"));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Folds turns that have left the recent window into the running summary.
+/// Returns `None` when the strategy does not summarize or nothing new aged out.
+pub fn next_chat_summary(
+    state: &ShellState,
+    summarizer: &dyn ChatSummarizer,
+) -> Option<ChatSummary> {
+    let ChatContextStrategy::Summarize { turns: keep } = state.config.chat.context else {
+        return None;
+    };
+    let turns = chat_turns(&state.artifacts.chat);
+    let aged_out = &turns[..turns.len().saturating_sub(keep)];
+    let previous = state.interaction.chat_summary.as_ref();
+    let covered_through = previous.map_or(0, |summary| summary.covered_through);
+    // Turns evicted from the transcript before they were folded are lost;
+    // the rest are matched by seq, so eviction never shifts them.
+    let start = aged_out.partition_point(|turn| turn.seq <= covered_through);
+    let last = aged_out.last()?;
+    if start == aged_out.len() {
+        return None;
+    }
+    let text = summarizer.summarize(
+        previous.map(|summary| summary.text.as_str()),
+        &aged_out[start..],
+    );
+    Some(ChatSummary {
+        text: keep_tail(text, CHAT_SUMMARY_MAX_CHARS),
+        covered_through: last.seq,
+    })
+}
+
+/// The last `max_chars` bytes of `text`, starting on a whole line when one
+/// fits.
+fn keep_tail(text: String, max_chars: usize) -> String {
+    if text.len() <= max_chars {
+        return text;
+    }
+    let mut start = text.len() - max_chars;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    let start = text[start..]
+        .find('\n')
+        .map_or(start, |newline| start + newline + 1);
+    text[start..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::actions::RuntimeAction;
+    use crate::actions::ShellAction;
+    use crate::config::Config;
+    use crate::reducer::reduce;
+    use crate::state::Personality;
+
+    #[test]
+    fn summary_folds_only_turns_outside_the_recent_window() {
+        let mut config = Config::default();
+        config.chat.context = ChatContextStrategy::Summarize { turns: 1 };
+        let mut state = ShellState::new("project".to_string(), Personality::Pragmatic, config);
+        for (question, answer) in [("first?", "one"), ("second?", "two")] {
+            reduce(
+                &mut state,
+                ShellAction::Runtime(RuntimeAction::AppendLog(format!("> {question}"))),
+            );
+            reduce(
+                &mut state,
+                ShellAction::Runtime(RuntimeAction::AppendLog(format!("[assistant] {answer}"))),
+            );
+        }
+
+        let summary = next_chat_summary(&state, &ExtractiveSummarizer).expect("summary");
+        assert_eq!(summary.text, "- asked: first? | answered: one");
+        assert_eq!(summary.covered_through, 1);
+
+        state.interaction.chat_summary = Some(summary);
+        assert_eq!(next_chat_summary(&state, &ExtractiveSummarizer), None);
+
+        reduce(
+            &mut state,
+            ShellAction::Runtime(RuntimeAction::AppendLog("> third?".to_string())),
+        );
+        let summary = next_chat_summary(&state, &ExtractiveSummarizer).expect("summary");
+        assert_eq!(
+            summary.text,
+            "- asked: first? | answered: one\n- asked: second? | answered: two"
+        );
+        assert_eq!(summary.covered_through, 3);
+    }
+
+    fn exchange(state: &mut ShellState, question: &str, answer: &str) {
+        reduce(
+            state,
+            ShellAction::Runtime(RuntimeAction::AppendLog(format!("> {question}"))),
+        );
+        reduce(
+            state,
+            ShellAction::Runtime(RuntimeAction::AppendLog(format!("[assistant] {answer}"))),
+        );
+    }
+
+    #[test]
+    fn summary_keeps_up_when_old_turns_are_evicted() {
+        let mut config = Config::default();
+        config.chat.context = ChatContextStrategy::Summarize { turns: 1 };
+        let mut state = ShellState::new("project".to_string(), Personality::Pragmatic, config);
+        state.artifacts.chat = ChatTranscript::new(4);
+        exchange(&mut state, "first?", "one");
+        exchange(&mut state, "second?", "two");
+        state.interaction.chat_summary = next_chat_summary(&state, &ExtractiveSummarizer);
+
+        // The first exchange leaves the transcript; the second one ages out.
+        exchange(&mut state, "third?", "three");
+        let summary = next_chat_summary(&state, &ExtractiveSummarizer).expect("summary");
+        assert_eq!(
+            summary.text,
+            "- asked: first? | answered: one\n- asked: second? | answered: two"
+        );
+        assert_eq!(summary.covered_through, 3);
+    }
+
+    #[test]
+    fn summary_drops_its_oldest_lines_past_the_cap() {
+        let mut config = Config::default();
+        config.chat.context = ChatContextStrategy::Summarize { turns: 0 };
+        let mut state = ShellState::new("project".to_string(), Personality::Pragmatic, config);
+        for i in 0..200 {
+            exchange(&mut state, &format!("question {i}?"), "an answer");
+            if let Some(summary) = next_chat_summary(&state, &ExtractiveSummarizer) {
+                state.interaction.chat_summary = Some(summary);
+            }
+        }
+
+        let summary = state.interaction.chat_summary.expect("summary");
+        assert!(summary.text.len() <= CHAT_SUMMARY_MAX_CHARS);
+        assert!(summary.text.starts_with("- asked: question "));
+        assert!(summary
+            .text
+            .ends_with("- asked: question 199? | answered: an answer"));
+    }
+}
@@ -1,31 +1,276 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::actions::RuntimeAction;
+use crate::chat_context::ChatContextStrategy;
+use crate::state::policy_requirement_for_risk;
+use crate::state::ApprovalGateRequirement;
+use crate::state::ApprovalRiskClass;
+use crate::state::ExplanationDepth;
+use crate::state::JourneyState;
+use crate::state::LogLevel;
+use crate::state::PersonaOutputFormat;
+use crate::state::Personality;
+use crate::state::PolicyTier;
+use crate::state::ShellTab;
+use crate::state::DEFAULT_LOG_ENTRIES;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct Config {
     pub model: ModelConfig,
+    pub risk: RiskConfig,
+    pub auto_open: AutoOpenConfig,
+    pub chat: ChatConfig,
+    pub context: ContextConfig,
+    pub logging: LoggingConfig,
+    pub scan: ScanConfig,
+    pub diff: DiffConfig,
+    pub ui: UiConfig,
+    pub policy: PolicyConfig,
+    pub telemetry: TelemetryConfig,
+    pub persona: PersonaConfig,
+    /// External commands run as extra workflow steps, configured as `[[tools]]`.
+    pub tools: Vec<ExternalToolConfig>,
+}
+
+/// A command registered as a workflow step. It runs after the built-in steps, in
+/// config order, and goes through the same policy gate as they do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExternalToolConfig {
+    pub name: String,
+    /// Shell command; `{cwd}` and `{intent}` are substituted before running.
+    pub command: String,
+    /// Declared risk; see [`Self::gate_risk`].
+    #[serde(default = "default_external_tool_risk")]
+    pub risk: ApprovalRiskClass,
+    pub output: ExternalToolOutputKind,
+    /// Whether running the command twice is safe, so `--retries` may re-run
+    /// it after a failure.
+    #[serde(default)]
+    pub idempotent: bool,
+}
+
+impl ExternalToolConfig {
+    /// Risk the step is gated with. An arbitrary command is never rated below
+    /// `Execution`, so `risk` can only raise it.
+    pub fn gate_risk(&self) -> ApprovalRiskClass {
+        self.risk.max(ApprovalRiskClass::Execution)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExternalToolOutputKind {
+    Diff,
+    Verify,
+}
+
+fn default_external_tool_risk() -> ApprovalRiskClass {
+    ApprovalRiskClass::Execution
+}
+
+/// Globs narrowing which repository paths the scan step considers.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ScanConfig {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct DiffConfig {
+    /// Context lines added above and below a hunk per expansion in the Diff tab.
+    pub expand_lines: usize,
 }
 
-impl Default for Config {
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self { expand_lines: 10 }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct UiConfig {
+    /// TOML file of hex colors used by the `custom` theme. A relative path is
+    /// resolved against the repository.
+    pub custom_theme_path: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Append every UI telemetry sample to `.dao/telemetry.csv` on exit.
+    pub record: bool,
+}
+
+/// Preferred personality and persona policy overrides, configured as `[persona]`.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct PersonaConfig {
+    /// Personality used when `--personality` is not given.
+    pub personality: Option<Personality>,
+    pub tier_ceiling: Option<PolicyTier>,
+    pub explanation_depth: Option<ExplanationDepth>,
+    pub output_format: Option<PersonaOutputFormat>,
+}
+
+impl PersonaConfig {
+    /// Override actions for the fields that are set, to apply after `ShellState::new`.
+    pub fn override_actions(&self) -> Vec<RuntimeAction> {
+        let mut actions = Vec::new();
+        if let Some(tier) = self.tier_ceiling {
+            actions.push(RuntimeAction::SetPersonaTierCeilingOverride(Some(tier)));
+        }
+        if let Some(depth) = self.explanation_depth {
+            actions.push(RuntimeAction::SetPersonaExplanationDepthOverride(Some(
+                depth,
+            )));
+        }
+        if let Some(format) = self.output_format {
+            actions.push(RuntimeAction::SetPersonaOutputFormatOverride(Some(format)));
+        }
+        actions
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct PolicyConfig {
+    pub tiers: TierRequirements,
+}
+
+/// Gate requirement per policy tier and risk class, used when no review policy
+/// is active.
+///
+/// Configured as `[policy.tiers.Balanced]` tables such as `Execution = "RequireApproval"`.
+/// Cells left out keep the built-in mapping from `policy_requirement_for_risk`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct TierRequirements(
+    pub BTreeMap<PolicyTier, BTreeMap<ApprovalRiskClass, ApprovalGateRequirement>>,
+);
+
+impl TierRequirements {
+    pub fn requirement(
+        &self,
+        tier: PolicyTier,
+        risk: ApprovalRiskClass,
+    ) -> ApprovalGateRequirement {
+        self.0
+            .get(&tier)
+            .and_then(|risks| risks.get(&risk))
+            .copied()
+            .unwrap_or_else(|| policy_requirement_for_risk(tier, risk))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Log entries below this level are shown in the session but left out of
+    /// `state.json`.
+    pub min_persist_level: LogLevel,
+    /// The log buffer keeps at most this many entries, dropping the oldest.
+    pub max_entries: usize,
+}
+
+impl Default for LoggingConfig {
     fn default() -> Self {
         Self {
-            model: ModelConfig::default(),
+            min_persist_level: LogLevel::Trace,
+            max_entries: DEFAULT_LOG_ENTRIES,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ContextConfig {
+    /// Upper bound on the context sent with a chat message; history may use
+    /// half of it and the diff is truncated at the rest.
+    pub max_chars: usize,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self { max_chars: 32_000 }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ChatConfig {
+    pub context: ChatContextStrategy,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct ModelConfig {
     pub default_model: Option<String>,
     pub default_provider: Option<String>,
 }
 
-impl Default for ModelConfig {
+/// Tuning for `DiffArtifact::assess_risk`.
+///
+/// The weighted score is `lines_changed * line_weight + files_changed * file_weight +
+/// sensitive_files * sensitive_path_weight`. A score at or above a threshold escalates the
+/// base classification; the default weights are zero, so only the base rules apply.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    pub deleted_files_destructive: bool,
+    pub line_weight: f64,
+    pub file_weight: f64,
+    pub sensitive_path_weight: f64,
+    pub sensitive_paths: Vec<String>,
+    pub execution_threshold: f64,
+    pub destructive_threshold: f64,
+}
+
+impl Default for RiskConfig {
     fn default() -> Self {
         Self {
-            default_model: None,
-            default_provider: None,
+            deleted_files_destructive: true,
+            line_weight: 0.0,
+            file_weight: 0.0,
+            sensitive_path_weight: 0.0,
+            sensitive_paths: Vec::new(),
+            execution_threshold: 100.0,
+            destructive_threshold: 200.0,
         }
     }
 }
+
+/// Tab the UI switches to when the journey reaches a state, while auto-follow is on.
+///
+/// Configured as a `[auto_open]` table such as `ReviewReady = "Diff"`. A configured table
+/// replaces the defaults entirely, so states left out of it never switch tabs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AutoOpenConfig(pub BTreeMap<JourneyState, ShellTab>);
+
+impl AutoOpenConfig {
+    pub fn tab_for(&self, state: JourneyState) -> Option<ShellTab> {
+        self.0.get(&state).copied()
+    }
+}
+
+impl Default for AutoOpenConfig {
+    fn default() -> Self {
+        Self(BTreeMap::from([
+            (JourneyState::Idle, ShellTab::Chat),
+            (JourneyState::Scanning, ShellTab::System),
+            (JourneyState::Planning, ShellTab::Plan),
+            (JourneyState::Diffing, ShellTab::Diff),
+            (JourneyState::ReviewReady, ShellTab::Diff),
+            (JourneyState::AwaitingApproval, ShellTab::Logs),
+            (JourneyState::Verifying, ShellTab::Logs),
+            (JourneyState::Failed, ShellTab::Logs),
+            (JourneyState::Completed, ShellTab::Explain),
+        ]))
+    }
+}
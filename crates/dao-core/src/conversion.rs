@@ -0,0 +1,771 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::state::ArtifactError;
+use crate::state::DiffArtifact;
+use crate::state::DiffFile;
+use crate::state::DiffFileStatus;
+use crate::state::DiffHunk;
+use crate::state::DiffLine;
+use crate::state::DiffLineKind;
+use crate::state::ErrorKind;
+use crate::state::PlanArtifact;
+use crate::state::PlanStep;
+use crate::state::SystemArtifact;
+use crate::state::VerifyArtifact;
+use crate::state::VerifyCheck;
+use crate::state::VerifyCheckStatus;
+use crate::state::VerifyOverall;
+use crate::state::ARTIFACT_SCHEMA_V1;
+use crate::tool_registry::ToolId;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ToolExecutionPayload {
+    System {
+        summary: String,
+        detected_stack: Vec<String>,
+        entrypoints: Vec<String>,
+        risk_flags: Vec<String>,
+    },
+    Plan {
+        steps: Vec<String>,
+    },
+    Diff {
+        unified_diff: String,
+    },
+    Verify {
+        checks: Vec<String>,
+        passing: bool,
+    },
+    Commit {
+        hash: String,
+        message: String,
+    },
+}
+
+/// Artifact produced by one workflow step, before run and artifact ids are assigned.
+#[derive(Debug, Clone)]
+pub enum StepResult {
+    System(SystemArtifact),
+    Plan(PlanArtifact),
+    Diff(DiffArtifact),
+    Verify(VerifyArtifact),
+    Commit(SystemArtifact),
+}
+
+pub fn payload_to_step_result(tool_id: ToolId, payload: ToolExecutionPayload) -> StepResult {
+    match (tool_id, payload) {
+        (
+            ToolId::ScanRepo,
+            ToolExecutionPayload::System {
+                summary,
+                detected_stack,
+                entrypoints,
+                risk_flags,
+            },
+        ) => StepResult::System(SystemArtifact {
+            schema_version: ARTIFACT_SCHEMA_V1,
+            run_id: 0,
+            artifact_id: 0,
+            repo_root: String::new(),
+            detected_stack,
+            entrypoints,
+            risk_flags,
+            summary,
+            error: None,
+        }),
+        (ToolId::GeneratePlan, ToolExecutionPayload::Plan { steps }) => {
+            StepResult::Plan(PlanArtifact {
+                schema_version: ARTIFACT_SCHEMA_V1,
+                run_id: 0,
+                artifact_id: 0,
+                title: "Workflow plan".to_string(),
+                steps: steps
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, label)| PlanStep::from_line(idx.saturating_add(1), &label))
+                    .collect(),
+                assumptions: Vec::new(),
+                error: None,
+            })
+        }
+        (ToolId::ComputeDiff, ToolExecutionPayload::Diff { unified_diff }) => {
+            let files = diff_files_from_unified(&unified_diff);
+            StepResult::Diff(DiffArtifact {
+                schema_version: ARTIFACT_SCHEMA_V1,
+                run_id: 0,
+                artifact_id: 0,
+                files,
+                summary: "Diff preview".to_string(),
+                error: None,
+            })
+        }
+        (ToolId::Verify | ToolId::RunTests, ToolExecutionPayload::Verify { checks, passing }) => {
+            StepResult::Verify(VerifyArtifact {
+                schema_version: ARTIFACT_SCHEMA_V1,
+                run_id: 0,
+                artifact_id: 0,
+                checks: checks
+                    .into_iter()
+                    .map(|check| VerifyCheck {
+                        name: check,
+                        status: if passing {
+                            VerifyCheckStatus::Pass
+                        } else {
+                            VerifyCheckStatus::Fail
+                        },
+                        details: None,
+                    })
+                    .collect(),
+                overall: if passing {
+                    VerifyOverall::Passing
+                } else {
+                    VerifyOverall::Failing
+                },
+                error: if passing {
+                    None
+                } else {
+                    Some(ArtifactError {
+                        kind: ErrorKind::Runtime,
+                        message: "verification failed".into(),
+                    })
+                },
+            })
+        }
+        (_, ToolExecutionPayload::Commit { hash, message }) => StepResult::Commit(SystemArtifact {
+            schema_version: ARTIFACT_SCHEMA_V1,
+            run_id: 0,
+            artifact_id: 0,
+            repo_root: String::new(),
+            detected_stack: Vec::new(),
+            entrypoints: Vec::new(),
+            risk_flags: Vec::new(),
+            summary: format!("Committed {}: {}", hash, message),
+            error: None,
+        }),
+        (_, _) => StepResult::Plan(PlanArtifact {
+            schema_version: ARTIFACT_SCHEMA_V1,
+            run_id: 0,
+            artifact_id: 0,
+            title: "Workflow plan".to_string(),
+            steps: Vec::new(),
+            assumptions: Vec::new(),
+            error: Some(ArtifactError {
+                kind: ErrorKind::Unknown,
+                message: "payload mismatch".into(),
+            }),
+        }),
+    }
+}
+
+/// Splits a unified diff into files and hunks.
+///
+/// Git extended headers (`new file mode`, `deleted file mode`, `rename from`/`rename to`)
+/// and `/dev/null` sides set each file's status. Text with no file headers becomes a
+/// single `<patch>` file so it can still be shown.
+pub fn diff_files_from_unified(text: &str) -> Vec<DiffFile> {
+    let mut files = Vec::new();
+    let mut current_file: Option<DiffFile> = None;
+    let mut current_hunk: Option<DiffHunk> = None;
+    // Between `diff --git` and the file's first hunk, where extended headers appear.
+    let mut in_git_header = false;
+    // The `---` side of a file header, waiting for its `+++` line.
+    let mut old_path: Option<String> = None;
+    // Old and new lines still owed to the open hunk, from its `@@ -a,b +c,d @@` header.
+    let mut remaining: Option<(usize, usize)> = None;
+
+    let finish_hunk = |file: &mut Option<DiffFile>, hunk: &mut Option<DiffHunk>| {
+        if let Some(hunk_value) = hunk.take() {
+            if let Some(file_value) = file.as_mut() {
+                file_value.hunks.push(hunk_value);
+            }
+        }
+    };
+
+    let finish_file =
+        |files: &mut Vec<DiffFile>, file: &mut Option<DiffFile>, hunk: &mut Option<DiffHunk>| {
+            finish_hunk(file, hunk);
+            if let Some(file_value) = file.take() {
+                files.push(file_value);
+            }
+        };
+
+    let mut lines = text.lines().peekable();
+    while let Some(line) = lines.next() {
+        // A file header right before a hunk header ends a hunk whose counts
+        // promised more lines than it has.
+        let mut ahead = lines.clone();
+        let starts_file = (line.starts_with("+++ ")
+            && ahead.peek().is_some_and(|next| next.starts_with("@@")))
+            || (line.starts_with("--- ")
+                && ahead.next().is_some_and(|next| next.starts_with("+++ "))
+                && ahead.next().is_some_and(|next| next.starts_with("@@")));
+        if starts_file {
+            remaining = None;
+        }
+        if let Some((old, new)) = remaining.as_mut().filter(|(old, new)| *old + *new > 0) {
+            let kind = match line.chars().next() {
+                Some('+') => {
+                    *new = new.saturating_sub(1);
+                    Some(DiffLineKind::Add)
+                }
+                Some('-') => {
+                    *old = old.saturating_sub(1);
+                    Some(DiffLineKind::Remove)
+                }
+                Some(' ') | None => {
+                    *old = old.saturating_sub(1);
+                    *new = new.saturating_sub(1);
+                    (!line.is_empty()).then_some(DiffLineKind::Context)
+                }
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                if let Some(hunk) = current_hunk.as_mut() {
+                    hunk.lines.push(DiffLine {
+                        kind,
+                        text: line.to_string(),
+                    });
+                }
+                continue;
+            }
+            if line.is_empty() {
+                continue;
+            }
+        }
+
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            finish_file(&mut files, &mut current_file, &mut current_hunk);
+            let path = paths
+                .rsplit_once(" b/")
+                .map_or(paths, |(_, new_path)| new_path);
+            current_file = Some(DiffFile {
+                path: path.to_string(),
+                old_path: None,
+                status: DiffFileStatus::Modified,
+                binary: false,
+                hunks: Vec::new(),
+            });
+            in_git_header = true;
+            old_path = None;
+            continue;
+        }
+
+        if in_git_header {
+            if let Some(file) = current_file.as_mut() {
+                if line.starts_with("new file mode") {
+                    file.status = DiffFileStatus::Added;
+                    continue;
+                }
+                if line.starts_with("deleted file mode") {
+                    file.status = DiffFileStatus::Deleted;
+                    continue;
+                }
+                if let Some(path) = line.strip_prefix("rename from ") {
+                    file.status = DiffFileStatus::Renamed;
+                    file.old_path = Some(path.to_string());
+                    continue;
+                }
+                if let Some(path) = line.strip_prefix("rename to ") {
+                    file.status = DiffFileStatus::Renamed;
+                    file.path = path.to_string();
+                    continue;
+                }
+                if GIT_EXTENDED_HEADERS
+                    .iter()
+                    .any(|header| line.starts_with(header))
+                {
+                    continue;
+                }
+            }
+        }
+
+        if let Some(paths) = line
+            .strip_prefix("Binary files ")
+            .and_then(|rest| rest.strip_suffix(" differ"))
+        {
+            let (old, new) = paths.split_once(" and ").unwrap_or((paths, paths));
+            let (old, new) = (header_path(old, "a/"), header_path(new, "b/"));
+            if !in_git_header {
+                finish_file(&mut files, &mut current_file, &mut current_hunk);
+            }
+            let file = current_file.get_or_insert_with(|| DiffFile {
+                path: if new == DEV_NULL {
+                    old.clone()
+                } else {
+                    new.clone()
+                },
+                status: if old == DEV_NULL {
+                    DiffFileStatus::Added
+                } else if new == DEV_NULL {
+                    DiffFileStatus::Deleted
+                } else {
+                    DiffFileStatus::Modified
+                },
+                old_path: None,
+                hunks: Vec::new(),
+                binary: false,
+            });
+            file.binary = true;
+            in_git_header = false;
+            continue;
+        }
+
+        if let Some(path) = line.strip_prefix("--- ") {
+            let is_header = in_git_header
+                || current_hunk.is_none()
+                || lines.peek().is_some_and(|next| next.starts_with("+++ "));
+            if is_header {
+                old_path = Some(header_path(path, "a/"));
+                continue;
+            }
+        }
+
+        if let Some(path) = line.strip_prefix("+++ ") {
+            let is_header = old_path.is_some()
+                || current_hunk.is_none()
+                || lines.peek().is_some_and(|next| next.starts_with("@@"));
+            if is_header {
+                let old = old_path.take();
+                let new = header_path(path, "b/");
+                if !in_git_header {
+                    finish_file(&mut files, &mut current_file, &mut current_hunk);
+                }
+                let file = current_file.get_or_insert_with(|| DiffFile {
+                    path: new.clone(),
+                    old_path: None,
+                    status: DiffFileStatus::Modified,
+                    binary: false,
+                    hunks: Vec::new(),
+                });
+                if old.as_deref() == Some(DEV_NULL) {
+                    file.status = DiffFileStatus::Added;
+                } else if new == DEV_NULL {
+                    file.status = DiffFileStatus::Deleted;
+                    if let Some(old) = old {
+                        file.path = old;
+                    }
+                }
+                continue;
+            }
+        }
+
+        if let Some(header) = line.strip_prefix("@@") {
+            finish_hunk(&mut current_file, &mut current_hunk);
+            in_git_header = false;
+            remaining = hunk_line_counts(header);
+            current_hunk = Some(DiffHunk {
+                header: format!("@@{header}"),
+                lines: Vec::new(),
+            });
+            continue;
+        }
+
+        let kind = if line.starts_with('+') {
+            Some(DiffLineKind::Add)
+        } else if line.starts_with('-') {
+            Some(DiffLineKind::Remove)
+        } else if !line.is_empty() {
+            Some(DiffLineKind::Context)
+        } else {
+            None
+        };
+
+        if let Some(kind) = kind {
+            if current_hunk.is_none() {
+                current_hunk = Some(DiffHunk {
+                    header: "@@".to_string(),
+                    lines: Vec::new(),
+                });
+            }
+            if let Some(hunk) = current_hunk.as_mut() {
+                hunk.lines.push(DiffLine {
+                    kind,
+                    text: line.to_string(),
+                });
+            }
+        }
+    }
+
+    finish_file(&mut files, &mut current_file, &mut current_hunk);
+
+    if files.is_empty() {
+        files.push(DiffFile {
+            path: "<patch>".to_string(),
+            old_path: None,
+            status: DiffFileStatus::Modified,
+            binary: false,
+            hunks: vec![DiffHunk {
+                header: "@@".to_string(),
+                lines: text
+                    .lines()
+                    .map(|line| DiffLine {
+                        kind: if line.starts_with('+') {
+                            DiffLineKind::Add
+                        } else if line.starts_with('-') {
+                            DiffLineKind::Remove
+                        } else {
+                            DiffLineKind::Context
+                        },
+                        text: line.to_string(),
+                    })
+                    .collect(),
+            }],
+        });
+    }
+
+    files
+}
+
+const DEV_NULL: &str = "/dev/null";
+
+/// Git header lines that carry no content and no status change.
+const GIT_EXTENDED_HEADERS: &[&str] = &[
+    "index ",
+    "old mode ",
+    "new mode ",
+    "similarity index ",
+    "dissimilarity index ",
+    "copy from ",
+    "copy to ",
+];
+
+/// Old and new line counts from the part of a hunk header after `@@`, such as
+/// ` -1,2 +1,3 @@`. A count left out means one line.
+fn hunk_line_counts(header: &str) -> Option<(usize, usize)> {
+    let mut ranges = header.split_whitespace();
+    let old = ranges.next()?.strip_prefix('-')?;
+    let new = ranges.next()?.strip_prefix('+')?;
+    let count = |range: &str| match range.split_once(',') {
+        Some((_, count)) => count.parse().ok(),
+        None => range.parse::<usize>().ok().map(|_| 1),
+    };
+    Some((count(old)?, count(new)?))
+}
+
+/// Path from a `---`/`+++` header, without the `a/`/`b/` prefix or a trailing
+/// tab-separated timestamp.
+fn header_path(raw: &str, prefix: &str) -> String {
+    let path = raw.split('\t').next().unwrap_or(raw);
+    path.strip_prefix(prefix).unwrap_or(path).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn line_kinds(file: &DiffFile) -> Vec<Vec<DiffLineKind>> {
+        file.hunks
+            .iter()
+            .map(|hunk| hunk.lines.iter().map(|line| line.kind).collect())
+            .collect()
+    }
+
+    #[test]
+    fn unified_diff_splits_files_and_hunks() {
+        let text = "\
+diff --git a/a.rs b/a.rs
+--- a/a.rs
++++ b/a.rs
+@@ -1,2 +1,2 @@ fn a()
+ keep
+-old
++new
+@@ -10 +10 @@
++tail
++++ b.rs
+@@ -1 +1 @@
+-x
++y
+";
+        let files = diff_files_from_unified(text);
+
+        let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["a.rs", "b.rs"]);
+        assert!(files
+            .iter()
+            .all(|file| file.status == DiffFileStatus::Modified));
+        assert_eq!(files[0].hunks[0].header, "@@ -1,2 +1,2 @@ fn a()");
+        assert_eq!(
+            line_kinds(&files[0]),
+            vec![
+                vec![
+                    DiffLineKind::Context,
+                    DiffLineKind::Remove,
+                    DiffLineKind::Add,
+                ],
+                vec![DiffLineKind::Add],
+            ]
+        );
+        assert_eq!(
+            line_kinds(&files[1]),
+            vec![vec![DiffLineKind::Remove, DiffLineKind::Add]]
+        );
+    }
+
+    #[test]
+    fn file_status_matrix() {
+        let cases: &[(&str, &str, &str, DiffFileStatus)] = &[
+            (
+                "git modified",
+                "diff --git a/src/lib.rs b/src/lib.rs\nindex 1..2 100644\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-a\n+b\n",
+                "src/lib.rs",
+                DiffFileStatus::Modified,
+            ),
+            (
+                "git added",
+                "diff --git a/new.rs b/new.rs\nnew file mode 100644\nindex 0..1\n--- /dev/null\n+++ b/new.rs\n@@ -0,0 +1 @@\n+fn f() {}\n",
+                "new.rs",
+                DiffFileStatus::Added,
+            ),
+            (
+                "git deleted",
+                "diff --git a/old.rs b/old.rs\ndeleted file mode 100644\nindex 1..0\n--- a/old.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-fn f() {}\n",
+                "old.rs",
+                DiffFileStatus::Deleted,
+            ),
+            (
+                "git pure rename",
+                "diff --git a/a.rs b/b.rs\nsimilarity index 100%\nrename from a.rs\nrename to b.rs\n",
+                "b.rs",
+                DiffFileStatus::Renamed,
+            ),
+            (
+                "git rename with edits",
+                "diff --git a/a.rs b/b.rs\nsimilarity index 90%\nrename from a.rs\nrename to b.rs\nindex 1..2 100644\n--- a/a.rs\n+++ b/b.rs\n@@ -1 +1 @@\n-x\n+y\n",
+                "b.rs",
+                DiffFileStatus::Renamed,
+            ),
+            (
+                "plain added",
+                "--- /dev/null\n+++ b/fresh.txt\n@@ -0,0 +1 @@\n+hello\n",
+                "fresh.txt",
+                DiffFileStatus::Added,
+            ),
+            (
+                "plain deleted",
+                "--- a/gone.txt\n+++ /dev/null\n@@ -1 +0,0 @@\n-bye\n",
+                "gone.txt",
+                DiffFileStatus::Deleted,
+            ),
+            (
+                "plain modified with timestamps",
+                "--- notes.txt\t2024-01-01 00:00:00\n+++ notes.txt\t2024-01-02 00:00:00\n@@ -1 +1 @@\n-a\n+b\n",
+                "notes.txt",
+                DiffFileStatus::Modified,
+            ),
+        ];
+
+        for (name, text, path, status) in cases {
+            let files = diff_files_from_unified(text);
+            assert_eq!(files.len(), 1, "{name}");
+            assert_eq!(files[0].path, *path, "{name}");
+            assert_eq!(files[0].status, *status, "{name}");
+        }
+    }
+
+    #[test]
+    fn header_lines_do_not_leak_into_hunks() {
+        let text = "\
+diff --git a/a.rs b/a.rs
+--- a/a.rs
++++ b/a.rs
+@@ -1,2 +1,2 @@
+--- old comment
++++ new comment
+diff --git a/new.rs b/new.rs
+new file mode 100644
+--- /dev/null
++++ b/new.rs
+@@ -0,0 +1 @@
++fn f() {}
+--- /dev/null
++++ b/untracked.txt
+@@
++<untracked file>
+";
+        let files = diff_files_from_unified(text);
+
+        let summary: Vec<(&str, DiffFileStatus)> = files
+            .iter()
+            .map(|file| (file.path.as_str(), file.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("a.rs", DiffFileStatus::Modified),
+                ("new.rs", DiffFileStatus::Added),
+                ("untracked.txt", DiffFileStatus::Added),
+            ]
+        );
+        assert_eq!(
+            line_kinds(&files[0]),
+            vec![vec![DiffLineKind::Remove, DiffLineKind::Add]]
+        );
+        assert_eq!(line_kinds(&files[1]), vec![vec![DiffLineKind::Add]]);
+    }
+
+    #[test]
+    fn lines_before_a_hunk_header_open_an_unnamed_hunk() {
+        let files = diff_files_from_unified("+++ b/notes.txt\n+first\n\n+second\n");
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].hunks.len(), 1);
+        assert_eq!(files[0].hunks[0].header, "@@");
+        assert_eq!(
+            line_kinds(&files[0]),
+            vec![vec![DiffLineKind::Add, DiffLineKind::Add]]
+        );
+    }
+
+    #[test]
+    fn binary_marker_becomes_a_file_without_hunks() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1..2 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1 +1 @@
+-old
++new
+diff --git a/logo.png b/logo.png
+index 3..4 100644
+Binary files a/logo.png and b/logo.png differ
+Binary files /dev/null and b/icon.png differ
+";
+        let files = diff_files_from_unified(diff);
+
+        let summary: Vec<_> = files
+            .iter()
+            .map(|file| {
+                (
+                    file.path.as_str(),
+                    file.status,
+                    file.binary,
+                    file.hunks.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("src/lib.rs", DiffFileStatus::Modified, false, 1),
+                ("logo.png", DiffFileStatus::Modified, true, 0),
+                ("icon.png", DiffFileStatus::Added, true, 0),
+            ]
+        );
+        let counts: Vec<_> = files
+            .iter()
+            .map(|file| (file.added_lines(), file.removed_lines()))
+            .collect();
+        assert_eq!(counts, vec![(1, 1), (0, 0), (0, 0)]);
+    }
+
+    #[test]
+    fn text_without_file_headers_falls_back_to_a_patch_file() {
+        let files = diff_files_from_unified("+added\n-removed\nplain\n");
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "<patch>");
+        assert_eq!(files[0].hunks[0].header, "@@");
+        assert_eq!(
+            line_kinds(&files[0]),
+            vec![vec![
+                DiffLineKind::Add,
+                DiffLineKind::Remove,
+                DiffLineKind::Context,
+            ]]
+        );
+    }
+
+    #[test]
+    fn plan_payload_numbers_steps_from_one() {
+        let StepResult::Plan(plan) = payload_to_step_result(
+            ToolId::GeneratePlan,
+            ToolExecutionPayload::Plan {
+                steps: vec!["Review context".to_string(), "Draft changes".to_string()],
+            },
+        ) else {
+            panic!("expected a plan");
+        };
+        assert_eq!(plan.title, "Workflow plan");
+        let labels: Vec<(String, String)> = plan
+            .steps
+            .iter()
+            .map(|step| (step.id.clone(), step.label.clone()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("step-1".to_string(), "Review context".to_string()),
+                ("step-2".to_string(), "Draft changes".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_payload_parses_files_from_the_unified_diff() {
+        let StepResult::Diff(diff) = payload_to_step_result(
+            ToolId::ComputeDiff,
+            ToolExecutionPayload::Diff {
+                unified_diff: "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-old\n+new\n"
+                    .to_string(),
+            },
+        ) else {
+            panic!("expected a diff");
+        };
+        assert_eq!(diff.files.len(), 1);
+        assert_eq!(diff.files[0].path, "src/lib.rs");
+        assert_eq!(diff.files[0].hunks[0].lines.len(), 2);
+    }
+
+    #[test]
+    fn failing_verify_payload_marks_checks_and_error() {
+        let StepResult::Verify(verify) = payload_to_step_result(
+            ToolId::Verify,
+            ToolExecutionPayload::Verify {
+                checks: vec!["cargo test".to_string()],
+                passing: false,
+            },
+        ) else {
+            panic!("expected a verify artifact");
+        };
+        assert_eq!(verify.overall, VerifyOverall::Failing);
+        assert_eq!(verify.checks[0].status, VerifyCheckStatus::Fail);
+        assert_eq!(
+            verify.error.map(|error| error.message),
+            Some("verification failed".to_string())
+        );
+    }
+
+    #[test]
+    fn commit_payload_becomes_a_system_summary() {
+        let StepResult::Commit(system) = payload_to_step_result(
+            ToolId::Verify,
+            ToolExecutionPayload::Commit {
+                hash: "a1b2c3d".to_string(),
+                message: "Add feature".to_string(),
+            },
+        ) else {
+            panic!("expected a commit");
+        };
+        assert_eq!(system.summary, "Committed a1b2c3d: Add feature");
+    }
+
+    #[test]
+    fn mismatched_payload_yields_an_empty_plan_with_an_error() {
+        let StepResult::Plan(plan) = payload_to_step_result(
+            ToolId::ScanRepo,
+            ToolExecutionPayload::Plan { steps: Vec::new() },
+        ) else {
+            panic!("expected a plan");
+        };
+        assert!(plan.steps.is_empty());
+        assert_eq!(
+            plan.error.map(|error| error.message),
+            Some("payload mismatch".to_string())
+        );
+    }
+}
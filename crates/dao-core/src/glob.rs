@@ -0,0 +1,76 @@
+/// Matches a whole path against a glob. `*` and `?` stay within one path
+/// component, `**/` spans any number of directories and `[...]` (with `!` or
+/// `^` to negate) matches one character from a set or range.
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches_chars(&pattern, &path)
+}
+
+fn matches_chars(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => {
+            let rest = rest.strip_prefix(&['/']).unwrap_or(rest);
+            if rest.is_empty() {
+                return true;
+            }
+            (0..=path.len()).any(|skip| {
+                (skip == 0 || path[skip - 1] == '/') && matches_chars(rest, &path[skip..])
+            })
+        }
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&skip| skip == 0 || path[skip - 1] != '/')
+            .any(|skip| matches_chars(rest, &path[skip..])),
+        ['?', rest @ ..] => {
+            matches!(path, [first, tail @ ..] if *first != '/' && matches_chars(rest, tail))
+        }
+        ['[', class @ ..] => match class.iter().skip(1).position(|c| *c == ']') {
+            Some(end) => {
+                let (set, rest) = (&class[..end + 1], &class[end + 2..]);
+                matches!(path, [first, tail @ ..]
+                    if *first != '/' && class_matches(set, *first) && matches_chars(rest, tail))
+            }
+            None => matches!(path, ['[', tail @ ..] if matches_chars(class, tail)),
+        },
+        [literal, rest @ ..] => {
+            matches!(path, [first, tail @ ..] if first == literal && matches_chars(rest, tail))
+        }
+    }
+}
+
+fn class_matches(set: &[char], c: char) -> bool {
+    let (negated, set) = match set {
+        ['!' | '^', rest @ ..] => (true, rest),
+        _ => (false, set),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            found |= (set[i]..=set[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= set[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_matches;
+
+    #[test]
+    fn glob_classes_ranges_and_negation() {
+        assert!(glob_matches("v[0-9].txt", "v7.txt"));
+        assert!(!glob_matches("v[!0-9].txt", "v7.txt"));
+        assert!(glob_matches("v[!0-9].txt", "vx.txt"));
+        assert!(glob_matches("a?c", "abc"));
+        assert!(!glob_matches("a?c", "a/c"));
+        assert!(glob_matches("**/*.lock", "Cargo.lock"));
+        assert!(glob_matches("docs/**", "docs/a/b.md"));
+        assert!(glob_matches("[x", "[x"));
+    }
+}
@@ -1,5 +1,9 @@
 pub mod actions;
+pub mod approval_summary;
+pub mod chat_context;
 pub mod config;
+pub mod conversion;
+pub mod glob;
 pub mod persistence;
 pub mod policy_engine;
 pub mod policy_simulation;
@@ -9,6 +13,7 @@ pub mod tool_registry;
 pub mod workflow;
 
 pub use actions::*;
+pub use conversion::*;
 pub use policy_engine::*;
 pub use reducer::*;
 pub use state::*;
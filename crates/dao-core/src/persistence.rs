@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::BufRead;
@@ -5,10 +7,18 @@ use std::io::BufReader;
 use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::PoisonError;
 
+use fs2::FileExt;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::state::SchemaVersion;
+use crate::state::ShellState;
+use crate::state::SHELL_STATE_SCHEMA;
+use crate::state::UNVERSIONED_SHELL_STATE_SCHEMA;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PersistedExecutionMode {
@@ -16,7 +26,7 @@ pub enum PersistedExecutionMode {
     Runtime,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PersistedWorkflowStatus {
     Running,
@@ -52,6 +62,12 @@ pub enum PersistedShellEvent {
         invocation_id: u64,
         tool_id: String,
         status: String,
+        #[serde(default)]
+        cache_hit: bool,
+        /// Output the tool printed, capped by [`cap_tool_logs`]; empty for
+        /// events recorded before it was captured.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        logs: Vec<String>,
     },
     ApprovalRequested {
         request_id: String,
@@ -60,11 +76,27 @@ pub enum PersistedShellEvent {
         tool_id: String,
         risk: String,
         preview: String,
+        /// Wall-clock time the request was raised in ms; 0 for events
+        /// recorded before it was captured.
+        #[serde(default)]
+        created_at_ms: u64,
     },
     ApprovalResolved {
         request_id: String,
         run_id: u64,
         decision: String,
+        /// Wall-clock time of the decision in ms; 0 for events recorded
+        /// before it was captured.
+        #[serde(default)]
+        timestamp_ms: u64,
+    },
+    /// A denied request was reopened from `dao ui`; the run awaits its
+    /// approval again.
+    ApprovalReopened {
+        request_id: String,
+        run_id: u64,
+        #[serde(default)]
+        timestamp_ms: u64,
     },
     WorkflowResumed {
         run_id: u64,
@@ -78,6 +110,28 @@ pub enum PersistedShellEvent {
         policy: PersistedPersonaPolicy,
         source: String,
     },
+    CommitRecorded {
+        run_id: u64,
+        hash: String,
+    },
+}
+
+impl PersistedShellEvent {
+    /// The workflow run the event belongs to, if any.
+    pub fn run_id(&self) -> Option<u64> {
+        match self {
+            Self::WorkflowRunStarted { run_id, .. }
+            | Self::WorkflowStatusChanged { run_id, .. }
+            | Self::ToolInvocationIssued { run_id, .. }
+            | Self::ToolResultRecorded { run_id, .. }
+            | Self::ApprovalRequested { run_id, .. }
+            | Self::ApprovalResolved { run_id, .. }
+            | Self::ApprovalReopened { run_id, .. }
+            | Self::WorkflowResumed { run_id }
+            | Self::CommitRecorded { run_id, .. } => Some(*run_id),
+            Self::PolicyChanged { .. } | Self::PersonaPolicyChanged { .. } => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -95,11 +149,40 @@ pub struct PersistedShellEventRecord {
     pub event: PersistedShellEvent,
 }
 
+/// Runs removed by [`ShellEventStore::prune`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PruneTarget {
+    Run(u64),
+    /// Every record, including those of no run.
+    All,
+    /// Runs with an id below this one.
+    Before(u64),
+}
+
+impl PruneTarget {
+    /// Whether a record of `run_id` (`None` for events of no run) is pruned.
+    pub fn selects(self, run_id: Option<u64>) -> bool {
+        match self {
+            Self::Run(id) => run_id == Some(id),
+            Self::All => true,
+            Self::Before(id) => run_id.is_some_and(|run_id| run_id < id),
+        }
+    }
+}
+
+/// Append-only JSONL event log. The store can be shared across threads:
+/// appends are serialized, and records land in the file in strictly
+/// increasing `seq` order with no gaps or duplicates. Appends and rewrites
+/// also hold an exclusive lock on a sibling `.lock` file, so a rewrite in
+/// one process never drops an append made by another.
 #[derive(Debug)]
 pub struct ShellEventStore {
     path: PathBuf,
     snapshot_path: PathBuf,
-    next_seq: u64,
+    lock_path: PathBuf,
+    // Held for the whole append so seq assignment and the write are one step.
+    next_seq: Mutex<u64>,
+    read_only: bool,
 }
 
 impl ShellEventStore {
@@ -108,26 +191,65 @@ impl ShellEventStore {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        let existing = load_records(path.as_path())?;
-        let next_seq = existing
+        let mut store = Self::at(path);
+        let existing = load_records(store.path.as_path())?;
+        // A compacted log may have dropped every record up to the snapshot, so
+        // the snapshot seq is a floor too.
+        let snapshot_seq = store
+            .load_snapshot()
+            .ok()
+            .flatten()
+            .map_or(0, |snapshot| snapshot.seq);
+        let last_seq = existing
             .iter()
             .map(|record| record.seq)
             .max()
-            .map_or(1, |seq| seq.saturating_add(1));
+            .unwrap_or(0)
+            .max(snapshot_seq);
+        *store
+            .next_seq
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner) = last_seq.saturating_add(1);
+        Ok(store)
+    }
+
+    /// Opens the store for reading. Nothing is created, so a missing log
+    /// reads as empty, and the log is not scanned; appends, rewrites and
+    /// snapshot saves fail.
+    pub fn open_read_only(path: impl AsRef<Path>) -> Self {
+        Self {
+            read_only: true,
+            ..Self::at(path.as_ref().to_path_buf())
+        }
+    }
+
+    fn at(path: PathBuf) -> Self {
         let snapshot_path = path
             .parent()
             .unwrap_or_else(|| Path::new("."))
             .join("workflow-snapshot.json");
-        Ok(Self {
+        let lock_path = path.with_extension("jsonl.lock");
+        Self {
             path,
             snapshot_path,
-            next_seq,
-        })
+            lock_path,
+            next_seq: Mutex::new(1),
+            read_only: false,
+        }
+    }
+
+    pub fn append(&self, event: PersistedShellEvent) -> std::io::Result<u64> {
+        self.append_record(event).map(|record| record.seq)
     }
 
-    pub fn append(&mut self, event: PersistedShellEvent) -> std::io::Result<u64> {
-        let seq = self.next_seq;
-        self.next_seq = self.next_seq.saturating_add(1);
+    /// Like [`Self::append`], returning the record as written.
+    pub fn append_record(
+        &self,
+        event: PersistedShellEvent,
+    ) -> std::io::Result<PersistedShellEventRecord> {
+        let mut next_seq = self.next_seq.lock().unwrap_or_else(PoisonError::into_inner);
+        let _file_lock = self.lock_file()?;
+        let seq = *next_seq;
         let record = PersistedShellEventRecord {
             seq,
             ts_ms: chrono::Utc::now().timestamp_millis(),
@@ -136,28 +258,186 @@ impl ShellEventStore {
         let line = serde_json::to_string(&record)
             .map_err(|err| std::io::Error::other(format!("serialize: {err}")))?;
         append_line(self.path.as_path(), line.as_str())?;
-        Ok(seq)
+        // Only consume the seq once the record is on disk, so a failed write
+        // never leaves a gap.
+        *next_seq = seq.saturating_add(1);
+        Ok(record)
+    }
+
+    /// Drops every record with `seq <= up_to_seq` and returns how many were
+    /// dropped. Records after the saved snapshot are always kept, so
+    /// replaying from the snapshot plus `load_since(snapshot.seq)` gives the
+    /// same run as before. The snapshot holds neither a run's policy tier nor
+    /// its commits, so the snapshot run's `WorkflowRunStarted` and every
+    /// `CommitRecorded` are kept too. The file is rewritten through a temp
+    /// file and a rename, so a crash leaves either the old or the new log.
+    pub fn compact(&self, up_to_seq: u64) -> std::io::Result<usize> {
+        let _appends = self.next_seq.lock().unwrap_or_else(PoisonError::into_inner);
+        let _file_lock = self.lock_file()?;
+        let snapshot = self.load_snapshot()?;
+        let snapshot_seq = snapshot.as_ref().map_or(0, |snapshot| snapshot.seq);
+        let snapshot_run_id = snapshot
+            .and_then(|snapshot| snapshot.workflow)
+            .map(|run| run.run_id);
+        let up_to_seq = up_to_seq.min(snapshot_seq);
+        let records = load_records(self.path.as_path())?;
+        let before = records.len();
+        let kept = records
+            .into_iter()
+            .filter(|record| {
+                record.seq > up_to_seq
+                    || match &record.event {
+                        PersistedShellEvent::WorkflowRunStarted { run_id, .. } => {
+                            Some(*run_id) == snapshot_run_id
+                        }
+                        PersistedShellEvent::CommitRecorded { .. } => true,
+                        _ => false,
+                    }
+            })
+            .collect::<Vec<_>>();
+        self.rewrite(&kept)?;
+        Ok(before - kept.len())
+    }
+
+    /// Drops the records of the runs `target` selects and returns how many
+    /// were dropped. The snapshot is kept, rebuilt from the remaining records
+    /// up to its seq if its run was pruned, or emptied by `PruneTarget::All`.
+    /// Either way it keeps the highest run id and seq seen, so neither is
+    /// reused. The log is rewritten the same way as by [`Self::compact`].
+    pub fn prune(&self, target: PruneTarget) -> std::io::Result<usize> {
+        let _appends = self.next_seq.lock().unwrap_or_else(PoisonError::into_inner);
+        let _file_lock = self.lock_file()?;
+        let records = load_records(self.path.as_path())?;
+        let snapshot = self.load_snapshot()?;
+        let max_run_id = records
+            .iter()
+            .filter_map(|record| record.event.run_id())
+            .chain(
+                snapshot
+                    .as_ref()
+                    .map(PersistedShellSnapshot::highest_run_id),
+            )
+            .max()
+            .unwrap_or(0);
+        let last_seq = records
+            .iter()
+            .map(|record| record.seq)
+            .chain(snapshot.as_ref().map(|snapshot| snapshot.seq))
+            .max()
+            .unwrap_or(0);
+        let before = records.len();
+        let kept = records
+            .into_iter()
+            .filter(|record| !target.selects(record.event.run_id()))
+            .collect::<Vec<_>>();
+        self.rewrite(&kept)?;
+
+        let snapshot = match snapshot {
+            _ if target == PruneTarget::All => PersistedShellSnapshot {
+                version: 1,
+                seq: last_seq,
+                workflow: None,
+                max_run_id,
+            },
+            Some(snapshot) => {
+                let pruned = snapshot
+                    .workflow
+                    .as_ref()
+                    .is_some_and(|run| target.selects(Some(run.run_id)));
+                let workflow = if pruned {
+                    let covered = kept
+                        .iter()
+                        .filter(|record| record.seq <= snapshot.seq)
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    replay_latest_workflow(&covered)
+                } else {
+                    snapshot.workflow
+                };
+                PersistedShellSnapshot {
+                    workflow,
+                    max_run_id,
+                    ..snapshot
+                }
+            }
+            // Seq 0 replays the whole log, as if there were no snapshot.
+            None => PersistedShellSnapshot {
+                version: 1,
+                seq: 0,
+                workflow: None,
+                max_run_id,
+            },
+        };
+        self.save_snapshot(&snapshot)?;
+        Ok(before - kept.len())
+    }
+
+    /// Takes the cross-process lock; it is released when the file is dropped.
+    /// Every write goes through it, so it also refuses a read-only store.
+    fn lock_file(&self) -> std::io::Result<File> {
+        self.ensure_writable()?;
+        let mut opts = OpenOptions::new();
+        opts.create(true).write(true).truncate(false);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        let file = opts.open(&self.lock_path)?;
+        file.lock_exclusive()?;
+        Ok(file)
+    }
+
+    fn ensure_writable(&self) -> std::io::Result<()> {
+        if self.read_only {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!("{} was opened read-only", self.path.display()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replaces the log with `records` through a temp file and a rename.
+    fn rewrite(&self, records: &[PersistedShellEventRecord]) -> std::io::Result<()> {
+        let mut buf = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut buf, record)
+                .map_err(|err| std::io::Error::other(format!("serialize: {err}")))?;
+            buf.push(b'\n');
+        }
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        let mut opts = OpenOptions::new();
+        opts.create(true).write(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        let mut file = opts.open(&tmp_path)?;
+        file.write_all(&buf)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, &self.path)
     }
 
     pub fn load(&self) -> std::io::Result<Vec<PersistedShellEventRecord>> {
         load_records(self.path.as_path())
     }
 
+    /// Loads the records after `seq_exclusive`; earlier lines are only read
+    /// far enough to get their seq.
     pub fn load_since(
         &self,
         seq_exclusive: u64,
     ) -> std::io::Result<Vec<PersistedShellEventRecord>> {
-        let records = self.load()?;
-        Ok(records
-            .into_iter()
-            .filter(|record| record.seq > seq_exclusive)
-            .collect())
+        load_records_after(self.path.as_path(), seq_exclusive)
     }
 
     pub fn save_snapshot(&self, snapshot: &PersistedShellSnapshot) -> std::io::Result<()> {
+        self.ensure_writable()?;
         let encoded = serde_json::to_vec(snapshot)
             .map_err(|err| std::io::Error::other(format!("serialize snapshot: {err}")))?;
-        std::fs::write(&self.snapshot_path, encoded)
+        write_atomic(&self.snapshot_path, &encoded)
     }
 
     pub fn load_snapshot(&self) -> std::io::Result<Option<PersistedShellSnapshot>> {
@@ -183,6 +463,70 @@ pub struct ReplayedWorkflowRun {
     pub pending_invocation_id: Option<u64>,
     pub next_invocation_id: u64,
     pub blocked_reason: Option<String>,
+    /// When the pending approval was requested, in ms since the epoch.
+    #[serde(default)]
+    pub pending_requested_ms: Option<u64>,
+    /// When the run's latest approval was decided, in ms since the epoch.
+    #[serde(default)]
+    pub last_approval_ms: Option<u64>,
+    /// Time from request to decision for the latest approval, when both
+    /// timestamps were recorded.
+    #[serde(default)]
+    pub last_approval_latency_ms: Option<u64>,
+    /// Output of the run's tools, in the order their results were recorded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_outputs: Vec<ReplayedToolOutput>,
+    /// The request whose denial blocked the run, kept so it can be reopened.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub denied_approval: Option<DeniedApproval>,
+}
+
+/// A denied approval request, as needed to put it back in front of the run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeniedApproval {
+    pub request_id: String,
+    pub tool_id: String,
+    pub invocation_id: u64,
+}
+
+/// Output recorded for one tool invocation of a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayedToolOutput {
+    pub invocation_id: u64,
+    pub tool_id: String,
+    pub logs: Vec<String>,
+}
+
+/// Bytes of tool output kept per `ToolResultRecorded` event.
+pub const TOOL_LOG_RECORD_CAP_BYTES: usize = 16 * 1024;
+
+/// Keeps the leading lines of `logs` that fit in
+/// [`TOOL_LOG_RECORD_CAP_BYTES`]. A line that crosses the cap is cut at a char
+/// boundary, and a marker line says how many lines were dropped or cut.
+pub fn cap_tool_logs(logs: &[String]) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut budget = TOOL_LOG_RECORD_CAP_BYTES;
+    for (index, line) in logs.iter().enumerate() {
+        if line.len() <= budget {
+            budget -= line.len();
+            kept.push(line.clone());
+            continue;
+        }
+        let mut end = budget;
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end > 0 {
+            kept.push(line[..end].to_string());
+        }
+        kept.push(format!(
+            "[output truncated: {} of {} lines not fully recorded]",
+            logs.len() - index,
+            logs.len()
+        ));
+        break;
+    }
+    kept
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -190,6 +534,63 @@ pub struct PersistedShellSnapshot {
     pub version: u8,
     pub seq: u64,
     pub workflow: Option<ReplayedWorkflowRun>,
+    /// Highest run id the log has seen. Pruning never lowers it, so a new run
+    /// never reuses the id of a pruned one.
+    #[serde(default)]
+    pub max_run_id: u64,
+}
+
+impl PersistedShellSnapshot {
+    /// `max_run_id`, or the snapshot run's id for snapshots saved before it.
+    pub fn highest_run_id(&self) -> u64 {
+        self.workflow
+            .as_ref()
+            .map_or(0, |run| run.run_id)
+            .max(self.max_run_id)
+    }
+}
+
+/// Which artifacts a run has produced, derived from its step index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkflowArtifactFlags {
+    pub system: bool,
+    pub plan: bool,
+    pub diff: bool,
+    pub verify: bool,
+}
+
+impl WorkflowArtifactFlags {
+    pub fn for_step(step_index: usize) -> Self {
+        Self {
+            system: step_index >= 1,
+            plan: step_index >= 2,
+            diff: step_index >= 3,
+            verify: step_index >= 4,
+        }
+    }
+}
+
+/// Single-document dump of one workflow run, written by `dao export`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkflowRunExport {
+    pub version: u8,
+    pub run: ReplayedWorkflowRun,
+    pub artifacts: WorkflowArtifactFlags,
+    pub last_log_seq: u64,
+}
+
+/// Replays only the events of `run_id`. Returns `None` when the log has no
+/// `WorkflowRunStarted` for that run.
+pub fn replay_workflow_run(
+    run_id: u64,
+    records: &[PersistedShellEventRecord],
+) -> Option<ReplayedWorkflowRun> {
+    let run_records = records
+        .iter()
+        .filter(|record| record.event.run_id() == Some(run_id))
+        .cloned()
+        .collect::<Vec<_>>();
+    replay_latest_workflow(&run_records)
 }
 
 pub fn replay_latest_workflow(
@@ -225,6 +626,11 @@ pub fn replay_workflow_from(
                     pending_invocation_id: None,
                     next_invocation_id: 1,
                     blocked_reason: None,
+                    pending_requested_ms: None,
+                    last_approval_ms: None,
+                    last_approval_latency_ms: None,
+                    tool_outputs: Vec::new(),
+                    denied_approval: None,
                 });
             }
             PersistedShellEvent::WorkflowStatusChanged {
@@ -242,15 +648,32 @@ pub fn replay_workflow_from(
                             run.pending_request_id = None;
                             run.pending_tool_id = None;
                             run.pending_invocation_id = None;
+                            run.pending_requested_ms = None;
                         }
                     }
                 }
             }
-            PersistedShellEvent::ToolResultRecorded { run_id, status, .. } => {
+            PersistedShellEvent::ToolResultRecorded {
+                run_id,
+                invocation_id,
+                tool_id,
+                status,
+                logs,
+                ..
+            } => {
                 if let Some(run) = latest.as_mut() {
-                    if run.run_id == run_id && status == "succeeded" {
-                        run.step_index = run.step_index.saturating_add(1);
-                        run.next_invocation_id = run.next_invocation_id.saturating_add(1);
+                    if run.run_id == run_id {
+                        if status == "succeeded" {
+                            run.step_index = run.step_index.saturating_add(1);
+                            run.next_invocation_id = run.next_invocation_id.saturating_add(1);
+                        }
+                        if !logs.is_empty() {
+                            run.tool_outputs.push(ReplayedToolOutput {
+                                invocation_id,
+                                tool_id,
+                                logs,
+                            });
+                        }
                     }
                 }
             }
@@ -259,15 +682,18 @@ pub fn replay_workflow_from(
                 run_id,
                 invocation_id,
                 tool_id,
+                created_at_ms,
                 ..
             } => {
                 if let Some(run) = latest.as_mut() {
                     if run.run_id == run_id {
                         run.status = PersistedWorkflowStatus::AwaitingApproval;
                         run.blocked_reason = None;
+                        run.denied_approval = None;
                         run.pending_request_id = Some(request_id);
                         run.pending_tool_id = Some(tool_id);
                         run.pending_invocation_id = Some(invocation_id);
+                        run.pending_requested_ms = (created_at_ms > 0).then_some(created_at_ms);
                         run.next_invocation_id = invocation_id.saturating_add(1);
                     }
                 }
@@ -276,6 +702,7 @@ pub fn replay_workflow_from(
                 request_id,
                 run_id,
                 decision,
+                timestamp_ms,
             } => {
                 if let Some(run) = latest.as_mut() {
                     if run.run_id == run_id
@@ -285,11 +712,49 @@ pub fn replay_workflow_from(
                             run.status = PersistedWorkflowStatus::Running;
                         } else {
                             run.status = PersistedWorkflowStatus::Blocked;
+                            run.denied_approval = run
+                                .pending_tool_id
+                                .clone()
+                                .zip(run.pending_invocation_id)
+                                .map(|(tool_id, invocation_id)| DeniedApproval {
+                                    request_id: request_id.clone(),
+                                    tool_id,
+                                    invocation_id,
+                                });
                         }
                         run.blocked_reason = None;
                         run.pending_request_id = None;
                         run.pending_tool_id = None;
                         run.pending_invocation_id = None;
+                        run.last_approval_ms = (timestamp_ms > 0).then_some(timestamp_ms);
+                        run.last_approval_latency_ms = run
+                            .pending_requested_ms
+                            .take()
+                            .zip(run.last_approval_ms)
+                            .map(|(requested, decided)| decided.saturating_sub(requested));
+                    }
+                }
+            }
+            PersistedShellEvent::ApprovalReopened {
+                request_id,
+                run_id,
+                timestamp_ms,
+            } => {
+                let reopened = latest.as_mut().filter(|run| {
+                    run.run_id == run_id
+                        && run
+                            .denied_approval
+                            .as_ref()
+                            .is_some_and(|denied| denied.request_id == request_id)
+                });
+                if let Some(run) = reopened {
+                    if let Some(denied) = run.denied_approval.take() {
+                        run.status = PersistedWorkflowStatus::AwaitingApproval;
+                        run.blocked_reason = None;
+                        run.pending_request_id = Some(denied.request_id);
+                        run.pending_tool_id = Some(denied.tool_id);
+                        run.pending_invocation_id = Some(denied.invocation_id);
+                        run.pending_requested_ms = (timestamp_ms > 0).then_some(timestamp_ms);
                     }
                 }
             }
@@ -298,22 +763,216 @@ pub fn replay_workflow_from(
                     if run.run_id == run_id {
                         run.status = PersistedWorkflowStatus::Running;
                         run.blocked_reason = None;
+                        run.denied_approval = None;
                         run.pending_request_id = None;
                         run.pending_tool_id = None;
                         run.pending_invocation_id = None;
+                        run.pending_requested_ms = None;
                     }
                 }
             }
             PersistedShellEvent::ToolInvocationIssued { .. }
             | PersistedShellEvent::PolicyChanged { .. }
-            | PersistedShellEvent::PersonaPolicyChanged { .. } => {}
+            | PersistedShellEvent::PersonaPolicyChanged { .. }
+            | PersistedShellEvent::CommitRecorded { .. } => {}
         }
     }
 
     latest
 }
 
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WorkflowStats {
+    pub runs: usize,
+    /// Runs by their latest status.
+    pub final_status: BTreeMap<PersistedWorkflowStatus, usize>,
+    /// Mean step index reached by completed runs.
+    pub avg_steps_to_completion: Option<f64>,
+    pub approvals_granted: usize,
+    pub approvals_denied: usize,
+    /// Blocked reasons, most common first.
+    pub blocked_reasons: Vec<(String, usize)>,
+}
+
+/// Tallies every run in the event log in a single pass.
+pub fn workflow_stats(records: &[PersistedShellEventRecord]) -> WorkflowStats {
+    let mut sorted: Vec<&PersistedShellEventRecord> = records.iter().collect();
+    sorted.sort_by_key(|record| record.seq);
+
+    let mut status_by_run: HashMap<u64, PersistedWorkflowStatus> = HashMap::new();
+    let mut completed_steps: HashMap<u64, usize> = HashMap::new();
+    let mut blocked: HashMap<String, usize> = HashMap::new();
+    let mut stats = WorkflowStats::default();
+
+    for record in sorted {
+        match &record.event {
+            PersistedShellEvent::WorkflowRunStarted { run_id, .. }
+            | PersistedShellEvent::WorkflowResumed { run_id } => {
+                status_by_run.insert(*run_id, PersistedWorkflowStatus::Running);
+            }
+            PersistedShellEvent::WorkflowStatusChanged {
+                run_id,
+                status,
+                step_index,
+                reason,
+            } => {
+                status_by_run.insert(*run_id, *status);
+                match status {
+                    PersistedWorkflowStatus::Completed => {
+                        completed_steps.insert(*run_id, *step_index);
+                    }
+                    PersistedWorkflowStatus::Blocked => {
+                        let reason = reason.as_deref().unwrap_or("<no reason>");
+                        *blocked.entry(reason.to_string()).or_default() += 1;
+                    }
+                    _ => {}
+                }
+            }
+            PersistedShellEvent::ApprovalRequested { run_id, .. }
+            | PersistedShellEvent::ApprovalReopened { run_id, .. } => {
+                status_by_run.insert(*run_id, PersistedWorkflowStatus::AwaitingApproval);
+            }
+            PersistedShellEvent::ApprovalResolved { decision, .. } => {
+                if decision == "approved" {
+                    stats.approvals_granted += 1;
+                } else {
+                    stats.approvals_denied += 1;
+                }
+            }
+            PersistedShellEvent::ToolInvocationIssued { .. }
+            | PersistedShellEvent::ToolResultRecorded { .. }
+            | PersistedShellEvent::PolicyChanged { .. }
+            | PersistedShellEvent::PersonaPolicyChanged { .. }
+            | PersistedShellEvent::CommitRecorded { .. } => {}
+        }
+    }
+
+    stats.runs = status_by_run.len();
+    for status in status_by_run.values() {
+        *stats.final_status.entry(*status).or_default() += 1;
+    }
+    // Only runs whose latest status is still Completed count towards the average.
+    let steps: Vec<usize> = completed_steps
+        .iter()
+        .filter(|(run_id, _)| {
+            status_by_run.get(run_id) == Some(&PersistedWorkflowStatus::Completed)
+        })
+        .map(|(_, steps)| *steps)
+        .collect();
+    if !steps.is_empty() {
+        stats.avg_steps_to_completion =
+            Some(steps.iter().sum::<usize>() as f64 / steps.len() as f64);
+    }
+    stats.blocked_reasons = blocked.into_iter().collect();
+    stats
+        .blocked_reasons
+        .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    stats
+}
+
+/// Every run in the event log, newest first, each replayed from its own
+/// events. Runs whose `WorkflowRunStarted` was compacted away are left out.
+pub fn workflow_runs(records: &[PersistedShellEventRecord]) -> Vec<ReplayedWorkflowRun> {
+    let mut started = Vec::new();
+    let mut by_run: HashMap<u64, Vec<PersistedShellEventRecord>> = HashMap::new();
+    for record in records {
+        let Some(run_id) = record.event.run_id() else {
+            continue;
+        };
+        if matches!(record.event, PersistedShellEvent::WorkflowRunStarted { .. }) {
+            started.push((record.seq, run_id));
+        }
+        by_run.entry(run_id).or_default().push(record.clone());
+    }
+    started.sort_by(|a, b| b.cmp(a));
+    started
+        .into_iter()
+        .filter_map(|(_, run_id)| replay_workflow_from(None, by_run.get(&run_id)?))
+        .collect()
+}
+
+/// Run id and hash of the most recent commit made by a workflow run.
+pub fn last_recorded_commit(records: &[PersistedShellEventRecord]) -> Option<(u64, String)> {
+    records
+        .iter()
+        .filter_map(|record| match &record.event {
+            PersistedShellEvent::CommitRecorded { run_id, hash } => {
+                Some((record.seq, *run_id, hash.clone()))
+            }
+            _ => None,
+        })
+        .max_by_key(|(seq, _, _)| *seq)
+        .map(|(_, run_id, hash)| (run_id, hash))
+}
+
+/// Chat inputs kept in `.dao/chat-history.json` across UI sessions.
+pub const CHAT_HISTORY_CAP: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PersistedChatHistory {
+    pub version: u8,
+    pub entries: Vec<String>,
+}
+
+/// Combines the history on disk with the in-memory one, keeping the newest
+/// `cap` entries. `current` usually already holds a copy of `saved` (through
+/// `state.json` or an earlier load), so the overlap is only kept once.
+pub fn merge_chat_history(saved: &[String], current: &[String], cap: usize) -> Vec<String> {
+    let merged = if saved.is_empty() || current.windows(saved.len()).any(|w| w == saved) {
+        current.to_vec()
+    } else if saved.ends_with(current) {
+        saved.to_vec()
+    } else {
+        let overlap = (0..=saved.len().min(current.len()))
+            .rev()
+            .find(|&k| saved[saved.len() - k..] == current[..k])
+            .unwrap_or(0);
+        saved.iter().chain(&current[overlap..]).cloned().collect()
+    };
+    let skip = merged.len().saturating_sub(cap);
+    merged.into_iter().skip(skip).collect()
+}
+
+pub fn load_chat_history(path: &Path) -> std::io::Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let bytes = std::fs::read(path)?;
+    let history = serde_json::from_slice::<PersistedChatHistory>(&bytes).map_err(|err| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("parse chat history: {err}"),
+        )
+    })?;
+    Ok(history.entries)
+}
+
+/// Writes `entries` merged with whatever is already on disk, so a second UI
+/// session does not clobber the first one's history.
+pub fn save_chat_history(path: &Path, entries: &[String]) -> std::io::Result<()> {
+    let saved = load_chat_history(path).unwrap_or_default();
+    let history = PersistedChatHistory {
+        version: 1,
+        entries: merge_chat_history(&saved, entries, CHAT_HISTORY_CAP),
+    };
+    let bytes = serde_json::to_vec_pretty(&history)
+        .map_err(|err| std::io::Error::other(format!("serialize chat history: {err}")))?;
+    std::fs::write(path, bytes)
+}
+
 fn load_records(path: &Path) -> std::io::Result<Vec<PersistedShellEventRecord>> {
+    load_records_after(path, 0)
+}
+
+#[derive(Deserialize)]
+struct RecordSeq {
+    seq: u64,
+}
+
+fn load_records_after(
+    path: &Path,
+    seq_exclusive: u64,
+) -> std::io::Result<Vec<PersistedShellEventRecord>> {
     if !path.exists() {
         return Ok(Vec::new());
     }
@@ -325,6 +984,15 @@ fn load_records(path: &Path) -> std::io::Result<Vec<PersistedShellEventRecord>>
         if line.trim().is_empty() {
             continue;
         }
+        // Skip the head of the log without decoding its events.
+        if seq_exclusive > 0
+            && !matches!(
+                serde_json::from_str::<RecordSeq>(&line),
+                Ok(record) if record.seq > seq_exclusive
+            )
+        {
+            continue;
+        }
         if let Ok(record) = serde_json::from_str::<PersistedShellEventRecord>(&line) {
             records.push(record);
         }
@@ -341,24 +1009,159 @@ fn append_line(path: &Path, line: &str) -> std::io::Result<()> {
         opts.mode(0o600);
     }
     let mut file = opts.open(path)?;
-    file.write_all(line.as_bytes())?;
-    file.write_all(b"\n")?;
+    let mut buf = Vec::with_capacity(line.len() + 1);
+    buf.extend_from_slice(line.as_bytes());
+    buf.push(b'\n');
+    file.write_all(&buf)?;
     file.flush()?;
     Ok(())
 }
 
+/// Replaces `path` with `bytes` by writing a sibling temp file and renaming it
+/// into place, so a crash mid-write leaves the previous contents intact.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let temp = atomic_temp_path(path);
+    if let Err(err) = std::fs::write(&temp, bytes) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(err);
+    }
+    std::fs::rename(&temp, path)
+}
+
+/// Temp file `write_atomic` stages `path` in; the process id keeps concurrent
+/// writers (the UI and a workflow run) from sharing one.
+fn atomic_temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.tmp", std::process::id()));
+    path.with_file_name(name)
+}
+
+/// What reading `.dao/state.json` found.
+#[derive(Debug)]
+pub enum LoadedShellState {
+    Missing,
+    Loaded(Box<ShellState>),
+    /// The file did not parse or has another schema; `read_shell_state` leaves
+    /// it in place.
+    Unusable {
+        reason: String,
+    },
+    /// As `Unusable`, but `load_shell_state` moved it to `backup` so the caller
+    /// can start from a fresh state.
+    Discarded {
+        backup: PathBuf,
+        reason: String,
+    },
+}
+
+/// Parses a saved `ShellState`, rejecting other `SHELL_STATE_SCHEMA` versions
+/// before they can deserialize into the wrong fields, and migrates artifacts.
+/// A file without a version predates versioning and is read as
+/// `UNVERSIONED_SHELL_STATE_SCHEMA`; the next save writes the current version.
+pub fn decode_shell_state(bytes: &[u8]) -> Result<ShellState, String> {
+    #[derive(Deserialize)]
+    struct Versioned {
+        schema_version: Option<SchemaVersion>,
+    }
+
+    let versioned: Versioned = serde_json::from_slice(bytes).map_err(|err| err.to_string())?;
+    let version = versioned
+        .schema_version
+        .unwrap_or(UNVERSIONED_SHELL_STATE_SCHEMA);
+    if version != SHELL_STATE_SCHEMA {
+        return Err(format!(
+            "state schema v{} does not match v{}",
+            version.0, SHELL_STATE_SCHEMA.0
+        ));
+    }
+    let mut state: ShellState = serde_json::from_slice(bytes).map_err(|err| err.to_string())?;
+    state.schema_version = SHELL_STATE_SCHEMA;
+    state.artifacts.migrate_schema()?;
+    let min_level = state.config.logging.min_persist_level;
+    state.artifacts.logs.set_min_level(Some(min_level));
+    Ok(state)
+}
+
+/// Reads the state file at `path` without changing it, for commands that
+/// only look at the saved state.
+pub fn read_shell_state(path: &Path) -> std::io::Result<LoadedShellState> {
+    if !path.exists() {
+        return Ok(LoadedShellState::Missing);
+    }
+    let bytes = std::fs::read(path)?;
+    Ok(match decode_shell_state(&bytes) {
+        Ok(state) => LoadedShellState::Loaded(Box::new(state)),
+        Err(reason) => LoadedShellState::Unusable { reason },
+    })
+}
+
+/// Reads the state file at `path` for a caller that is about to save over it,
+/// first moving one that `decode_shell_state` rejects to `<path>.bak`, or
+/// `<path>.bak.N` when earlier backups exist.
+pub fn load_shell_state(path: &Path) -> std::io::Result<LoadedShellState> {
+    match read_shell_state(path)? {
+        LoadedShellState::Unusable { reason } => {
+            let backup = move_aside(path)?;
+            Ok(LoadedShellState::Discarded { backup, reason })
+        }
+        loaded => Ok(loaded),
+    }
+}
+
+/// Renames `path` to `<path>.bak`, or `<path>.bak.N` when earlier backups
+/// exist, and returns the new path.
+pub fn move_aside(path: &Path) -> std::io::Result<PathBuf> {
+    let backup = (0..)
+        .map(|n| {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(".bak");
+            if n > 0 {
+                backup.push(format!(".{n}"));
+            }
+            PathBuf::from(backup)
+        })
+        .find(|backup| !backup.exists())
+        .expect("unbounded backup names");
+    std::fs::rename(path, &backup)?;
+    Ok(backup)
+}
+
 #[cfg(test)]
 mod tests {
+    use fs2::FileExt;
     use tempfile::tempdir;
 
+    use super::atomic_temp_path;
+    use super::cap_tool_logs;
+    use super::last_recorded_commit;
+    use super::load_chat_history;
+    use super::load_shell_state;
+    use super::merge_chat_history;
+    use super::move_aside;
+    use super::read_shell_state;
     use super::replay_latest_workflow;
     use super::replay_workflow_from;
+    use super::replay_workflow_run;
+    use super::save_chat_history;
+    use super::workflow_runs;
+    use super::workflow_stats;
+    use super::write_atomic;
+    use super::LoadedShellState;
     use super::PersistedExecutionMode;
     use super::PersistedPersonaPolicy;
     use super::PersistedShellEvent;
+    use super::PersistedShellEventRecord;
     use super::PersistedShellSnapshot;
     use super::PersistedWorkflowStatus;
+    use super::PruneTarget;
+    use super::ReplayedToolOutput;
     use super::ShellEventStore;
+    use super::CHAT_HISTORY_CAP;
+    use super::TOOL_LOG_RECORD_CAP_BYTES;
+    use crate::config::Config;
+    use crate::state::Personality;
+    use crate::state::ShellState;
+    use crate::state::SHELL_STATE_SCHEMA;
     use pretty_assertions::assert_eq;
 
     fn policy() -> PersistedPersonaPolicy {
@@ -373,7 +1176,7 @@ mod tests {
     fn append_records_are_monotonic() {
         let dir = tempdir().expect("tmpdir");
         let path = dir.path().join("events.jsonl");
-        let mut store = ShellEventStore::open(path).expect("open");
+        let store = ShellEventStore::open(path).expect("open");
         let seq1 = store
             .append(PersistedShellEvent::WorkflowRunStarted {
                 run_id: 1,
@@ -424,6 +1227,7 @@ mod tests {
                     tool_id: "compute_diff".to_string(),
                     risk: "patch-only".to_string(),
                     preview: "workflow-tool compute_diff".to_string(),
+                    created_at_ms: 0,
                 },
             },
             super::PersistedShellEventRecord {
@@ -433,6 +1237,7 @@ mod tests {
                     request_id: "req-1".to_string(),
                     run_id: 7,
                     decision: "approved".to_string(),
+                    timestamp_ms: 0,
                 },
             },
         ];
@@ -443,6 +1248,200 @@ mod tests {
         assert!(run.pending_request_id.is_none());
     }
 
+    #[test]
+    fn a_reopened_denial_puts_the_run_back_on_its_request() {
+        let event = |seq, event| super::PersistedShellEventRecord {
+            seq,
+            ts_ms: 0,
+            event,
+        };
+        let mut records = vec![
+            event(
+                1,
+                PersistedShellEvent::WorkflowRunStarted {
+                    run_id: 7,
+                    template_id: "scan_plan_diff_verify".to_string(),
+                    execution_mode: PersistedExecutionMode::Runtime,
+                    policy_tier: "balanced".to_string(),
+                    persona_policy: policy(),
+                },
+            ),
+            event(
+                2,
+                PersistedShellEvent::ApprovalRequested {
+                    request_id: "req-1".to_string(),
+                    run_id: 7,
+                    invocation_id: 3,
+                    tool_id: "compute_diff".to_string(),
+                    risk: "patch-only".to_string(),
+                    preview: "workflow tool compute_diff".to_string(),
+                    created_at_ms: 0,
+                },
+            ),
+            event(
+                3,
+                PersistedShellEvent::ApprovalResolved {
+                    request_id: "req-1".to_string(),
+                    run_id: 7,
+                    decision: "denied".to_string(),
+                    timestamp_ms: 0,
+                },
+            ),
+            event(
+                4,
+                PersistedShellEvent::WorkflowStatusChanged {
+                    run_id: 7,
+                    status: PersistedWorkflowStatus::Blocked,
+                    step_index: 2,
+                    reason: Some("approval denied".to_string()),
+                },
+            ),
+        ];
+        let blocked = replay_latest_workflow(&records).expect("replay");
+        assert_eq!(blocked.status, PersistedWorkflowStatus::Blocked);
+
+        records.push(event(
+            5,
+            PersistedShellEvent::ApprovalReopened {
+                request_id: "req-2".to_string(),
+                run_id: 7,
+                timestamp_ms: 0,
+            },
+        ));
+        let unknown = replay_latest_workflow(&records).expect("replay");
+        assert_eq!(unknown.status, PersistedWorkflowStatus::Blocked);
+
+        records.push(event(
+            6,
+            PersistedShellEvent::ApprovalReopened {
+                request_id: "req-1".to_string(),
+                run_id: 7,
+                timestamp_ms: 1_760_000_000_000,
+            },
+        ));
+        // The snapshot taken while blocked must carry enough to reopen.
+        let reopened = replay_workflow_from(Some(blocked), &records[4..]).expect("replay");
+        assert_eq!(reopened.status, PersistedWorkflowStatus::AwaitingApproval);
+        assert_eq!(reopened.blocked_reason, None);
+        assert_eq!(reopened.pending_request_id.as_deref(), Some("req-1"));
+        assert_eq!(reopened.pending_tool_id.as_deref(), Some("compute_diff"));
+        assert_eq!(reopened.pending_invocation_id, Some(3));
+        assert_eq!(reopened.pending_requested_ms, Some(1_760_000_000_000));
+        assert_eq!(reopened.denied_approval, None);
+        assert_eq!(
+            workflow_stats(&records)
+                .final_status
+                .get(&PersistedWorkflowStatus::AwaitingApproval),
+            Some(&1)
+        );
+    }
+
+    #[test]
+    fn resolved_approval_round_trips_its_timestamp_into_replay() {
+        let dir = tempdir().expect("tmpdir");
+        let store = ShellEventStore::open(dir.path().join("events.jsonl")).expect("open");
+        let events = [
+            PersistedShellEvent::WorkflowRunStarted {
+                run_id: 4,
+                template_id: "scan_plan_diff_verify".to_string(),
+                execution_mode: PersistedExecutionMode::Runtime,
+                policy_tier: "balanced".to_string(),
+                persona_policy: policy(),
+            },
+            PersistedShellEvent::ApprovalRequested {
+                request_id: "req-4".to_string(),
+                run_id: 4,
+                invocation_id: 2,
+                tool_id: "compute_diff".to_string(),
+                risk: "patch-only".to_string(),
+                preview: "workflow tool compute_diff".to_string(),
+                created_at_ms: 0,
+            },
+            PersistedShellEvent::ApprovalResolved {
+                request_id: "req-4".to_string(),
+                run_id: 4,
+                decision: "approved".to_string(),
+                timestamp_ms: 1_760_000_000_123,
+            },
+        ];
+        for event in events {
+            store.append(event).expect("append");
+        }
+
+        let records = store.load().expect("load");
+        assert!(matches!(
+            records[2].event,
+            PersistedShellEvent::ApprovalResolved {
+                timestamp_ms: 1_760_000_000_123,
+                ..
+            }
+        ));
+        let run = replay_latest_workflow(&records).expect("replay");
+        assert_eq!(run.last_approval_ms, Some(1_760_000_000_123));
+
+        let legacy: PersistedShellEvent = serde_json::from_str(
+            r#"{"kind":"approval_resolved","request_id":"r","run_id":1,"decision":"denied"}"#,
+        )
+        .expect("legacy event");
+        assert!(matches!(
+            legacy,
+            PersistedShellEvent::ApprovalResolved {
+                timestamp_ms: 0,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn request_time_flows_into_the_decision_latency() {
+        let event = |seq, event| super::PersistedShellEventRecord {
+            seq,
+            ts_ms: 0,
+            event,
+        };
+        let mut records = vec![
+            event(
+                1,
+                PersistedShellEvent::WorkflowRunStarted {
+                    run_id: 5,
+                    template_id: "scan_plan_diff_verify".to_string(),
+                    execution_mode: PersistedExecutionMode::Runtime,
+                    policy_tier: "balanced".to_string(),
+                    persona_policy: policy(),
+                },
+            ),
+            event(
+                2,
+                PersistedShellEvent::ApprovalRequested {
+                    request_id: "req-5".to_string(),
+                    run_id: 5,
+                    invocation_id: 2,
+                    tool_id: "compute_diff".to_string(),
+                    risk: "patch-only".to_string(),
+                    preview: "workflow tool compute_diff".to_string(),
+                    created_at_ms: 10_000,
+                },
+            ),
+        ];
+        let pending = replay_latest_workflow(&records).expect("replay");
+        assert_eq!(pending.pending_requested_ms, Some(10_000));
+        assert_eq!(pending.last_approval_latency_ms, None);
+
+        records.push(event(
+            3,
+            PersistedShellEvent::ApprovalResolved {
+                request_id: "req-5".to_string(),
+                run_id: 5,
+                decision: "approved".to_string(),
+                timestamp_ms: 12_500,
+            },
+        ));
+        let run = replay_latest_workflow(&records).expect("replay");
+        assert_eq!(run.pending_requested_ms, None);
+        assert_eq!(run.last_approval_ms, Some(12_500));
+        assert_eq!(run.last_approval_latency_ms, Some(2_500));
+    }
+
     #[test]
     fn replay_tracks_succeeded_results_into_step_index() {
         let records = vec![
@@ -465,6 +1464,8 @@ mod tests {
                     invocation_id: 1,
                     tool_id: "scan_repo".to_string(),
                     status: "succeeded".to_string(),
+                    cache_hit: false,
+                    logs: Vec::new(),
                 },
             },
             super::PersistedShellEventRecord {
@@ -475,6 +1476,8 @@ mod tests {
                     invocation_id: 2,
                     tool_id: "generate_plan".to_string(),
                     status: "succeeded".to_string(),
+                    cache_hit: false,
+                    logs: Vec::new(),
                 },
             },
         ];
@@ -483,11 +1486,531 @@ mod tests {
         assert_eq!(run.step_index, 2);
     }
 
+    #[test]
+    fn workflow_runs_lists_every_run_newest_first() {
+        let dir = tempdir().expect("tmpdir");
+        let store = ShellEventStore::open(dir.path().join("events.jsonl")).expect("open");
+        let runs = [
+            (
+                1,
+                "scan_plan_diff_verify",
+                PersistedWorkflowStatus::Completed,
+                4,
+            ),
+            (2, "scan_plan_only", PersistedWorkflowStatus::Completed, 2),
+            (
+                3,
+                "scan_plan_diff_verify",
+                PersistedWorkflowStatus::Blocked,
+                2,
+            ),
+            (
+                4,
+                "scan_plan_diff_test",
+                PersistedWorkflowStatus::Completed,
+                4,
+            ),
+        ];
+        for (run_id, template_id, status, step_index) in runs {
+            store
+                .append(PersistedShellEvent::WorkflowRunStarted {
+                    run_id,
+                    template_id: template_id.to_string(),
+                    execution_mode: PersistedExecutionMode::Runtime,
+                    policy_tier: "balanced".to_string(),
+                    persona_policy: policy(),
+                })
+                .expect("append");
+            store
+                .append(PersistedShellEvent::PolicyChanged {
+                    tier: "balanced".to_string(),
+                    source: "cli".to_string(),
+                })
+                .expect("append");
+            store
+                .append(PersistedShellEvent::WorkflowStatusChanged {
+                    run_id,
+                    status,
+                    step_index,
+                    reason: (status == PersistedWorkflowStatus::Blocked)
+                        .then(|| "approval denied".to_string()),
+                })
+                .expect("append");
+        }
+
+        let listed = workflow_runs(&store.load().expect("load"))
+            .into_iter()
+            .map(|run| (run.run_id, run.template_id, run.status, run.step_index))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            listed,
+            runs.iter()
+                .rev()
+                .map(|(run_id, template_id, status, step_index)| {
+                    (*run_id, template_id.to_string(), *status, *step_index)
+                })
+                .collect::<Vec<_>>()
+        );
+        assert!(workflow_runs(&[]).is_empty());
+    }
+
+    #[test]
+    fn replay_workflow_run_isolates_interleaved_runs() {
+        let mut records = Vec::new();
+        let mut push = |event| {
+            records.push(PersistedShellEventRecord {
+                seq: records.len() as u64 + 1,
+                ts_ms: 0,
+                event,
+            })
+        };
+        for run_id in 1..=3 {
+            push(PersistedShellEvent::WorkflowRunStarted {
+                run_id,
+                template_id: "scan_plan_diff_verify".to_string(),
+                execution_mode: PersistedExecutionMode::Runtime,
+                policy_tier: "balanced".to_string(),
+                persona_policy: policy(),
+            });
+        }
+        for run_id in [2, 1, 2, 3] {
+            push(PersistedShellEvent::ToolResultRecorded {
+                run_id,
+                invocation_id: 1,
+                tool_id: "scan_repo".to_string(),
+                status: "succeeded".to_string(),
+                cache_hit: false,
+                logs: Vec::new(),
+            });
+        }
+        push(PersistedShellEvent::PolicyChanged {
+            tier: "strict".to_string(),
+            source: "ui".to_string(),
+        });
+        push(PersistedShellEvent::WorkflowStatusChanged {
+            run_id: 1,
+            status: PersistedWorkflowStatus::Blocked,
+            step_index: 1,
+            reason: Some("denied".to_string()),
+        });
+
+        let first = replay_workflow_run(1, &records).expect("run 1");
+        assert_eq!(first.status, PersistedWorkflowStatus::Blocked);
+        assert_eq!(first.blocked_reason.as_deref(), Some("denied"));
+        let second = replay_workflow_run(2, &records).expect("run 2");
+        assert_eq!(
+            (second.status, second.step_index),
+            (PersistedWorkflowStatus::Running, 2)
+        );
+        let third = replay_workflow_run(3, &records).expect("run 3");
+        assert_eq!(third.step_index, 1);
+        assert_eq!(replay_workflow_run(4, &records), None);
+        assert_eq!(
+            replay_latest_workflow(&records).map(|run| run.run_id),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn merge_chat_history_dedupes_overlap_and_keeps_newest() {
+        let entries = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let saved = entries(&["a", "b", "c"]);
+
+        assert_eq!(
+            merge_chat_history(&saved, &entries(&["b", "c", "d"]), 10),
+            entries(&["a", "b", "c", "d"])
+        );
+        assert_eq!(
+            merge_chat_history(&saved, &entries(&["x", "a", "b", "c", "d"]), 10),
+            entries(&["x", "a", "b", "c", "d"])
+        );
+        assert_eq!(merge_chat_history(&saved, &entries(&["c"]), 10), saved);
+        assert_eq!(
+            merge_chat_history(&saved, &entries(&["d", "e"]), 4),
+            entries(&["b", "c", "d", "e"])
+        );
+        assert_eq!(merge_chat_history(&[], &saved, 2), entries(&["b", "c"]));
+    }
+
+    #[test]
+    fn chat_history_file_is_capped_and_merged_on_save() {
+        let dir = tempdir().expect("tmpdir");
+        let path = dir.path().join("chat-history.json");
+        assert_eq!(
+            load_chat_history(&path).expect("missing file"),
+            Vec::<String>::new()
+        );
+
+        let first = (0..CHAT_HISTORY_CAP + 20)
+            .map(|i| format!("msg {i}"))
+            .collect::<Vec<_>>();
+        save_chat_history(&path, &first).expect("save");
+        let loaded = load_chat_history(&path).expect("load");
+        assert_eq!(loaded.len(), CHAT_HISTORY_CAP);
+        assert_eq!(loaded.first().map(String::as_str), Some("msg 20"));
+
+        save_chat_history(&path, &["other session".to_string()]).expect("save");
+        let loaded = load_chat_history(&path).expect("load");
+        assert_eq!(loaded.len(), CHAT_HISTORY_CAP);
+        assert_eq!(loaded.last().map(String::as_str), Some("other session"));
+        assert_eq!(
+            loaded[loaded.len() - 2],
+            format!("msg {}", CHAT_HISTORY_CAP + 19)
+        );
+    }
+
+    #[test]
+    fn corrupt_chat_history_reads_as_invalid_data_and_can_be_moved_aside() {
+        let dir = tempdir().expect("tmpdir");
+        let path = dir.path().join("chat-history.json");
+        std::fs::write(&path, "{\"entries\": [").expect("write");
+
+        let err = load_chat_history(&path).expect_err("corrupt");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let backup = move_aside(&path).expect("move aside");
+        assert_eq!(backup, dir.path().join("chat-history.json.bak"));
+        assert_eq!(
+            load_chat_history(&path).expect("missing file"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn replay_rematerializes_recorded_tool_logs() {
+        let dir = tempdir().expect("tmpdir");
+        let store = ShellEventStore::open(dir.path().join("events.jsonl")).expect("open");
+        store
+            .append(PersistedShellEvent::WorkflowRunStarted {
+                run_id: 1,
+                template_id: "scan_plan_diff_verify".to_string(),
+                execution_mode: PersistedExecutionMode::Runtime,
+                policy_tier: "balanced".to_string(),
+                persona_policy: policy(),
+            })
+            .expect("append");
+        for (invocation_id, tool_id, status, logs) in [
+            (1, "scan_repo", "succeeded", vec!["scanned 12 files"]),
+            (2, "generate_plan", "succeeded", vec![]),
+            (
+                3,
+                "compute_diff",
+                "failed",
+                vec!["stdout: ok", "stderr: boom"],
+            ),
+        ] {
+            store
+                .append(PersistedShellEvent::ToolResultRecorded {
+                    run_id: 1,
+                    invocation_id,
+                    tool_id: tool_id.to_string(),
+                    status: status.to_string(),
+                    cache_hit: false,
+                    logs: logs.into_iter().map(str::to_string).collect(),
+                })
+                .expect("append");
+        }
+
+        let run = replay_latest_workflow(&store.load().expect("load")).expect("run");
+        assert_eq!(
+            run.tool_outputs,
+            vec![
+                ReplayedToolOutput {
+                    invocation_id: 1,
+                    tool_id: "scan_repo".to_string(),
+                    logs: vec!["scanned 12 files".to_string()],
+                },
+                ReplayedToolOutput {
+                    invocation_id: 3,
+                    tool_id: "compute_diff".to_string(),
+                    logs: vec!["stdout: ok".to_string(), "stderr: boom".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn recorded_tool_logs_are_capped() {
+        let short = vec!["a".to_string(), "b".to_string()];
+        assert_eq!(cap_tool_logs(&short), short);
+
+        let line = "é".repeat(TOOL_LOG_RECORD_CAP_BYTES / 4);
+        let logs = vec![line.clone(), line.clone(), line.clone(), "tail".to_string()];
+        let capped = cap_tool_logs(&logs);
+        assert_eq!(capped.len(), 3);
+        assert_eq!(capped[..2], [line.clone(), line]);
+        assert_eq!(
+            capped[2],
+            "[output truncated: 2 of 4 lines not fully recorded]"
+        );
+        let stored = capped[..2].iter().map(String::len).sum::<usize>();
+        assert!(stored <= TOOL_LOG_RECORD_CAP_BYTES);
+
+        let odd = "é".repeat(TOOL_LOG_RECORD_CAP_BYTES);
+        let capped = cap_tool_logs(&[odd]);
+        assert_eq!(capped[0].len(), TOOL_LOG_RECORD_CAP_BYTES);
+        assert_eq!(
+            capped[1],
+            "[output truncated: 1 of 1 lines not fully recorded]"
+        );
+    }
+
+    #[test]
+    fn compaction_keeps_the_replayed_run_and_seq_continuity() {
+        let dir = tempdir().expect("tmpdir");
+        let path = dir.path().join("events.jsonl");
+        let store = ShellEventStore::open(&path).expect("open");
+        for run_id in [1, 2] {
+            store
+                .append(PersistedShellEvent::WorkflowRunStarted {
+                    run_id,
+                    template_id: "scan_plan_diff_verify".to_string(),
+                    execution_mode: PersistedExecutionMode::Simulated,
+                    policy_tier: "balanced".to_string(),
+                    persona_policy: policy(),
+                })
+                .expect("append");
+            store
+                .append(PersistedShellEvent::ToolResultRecorded {
+                    run_id,
+                    invocation_id: 1,
+                    tool_id: "scan_repo".to_string(),
+                    status: "succeeded".to_string(),
+                    cache_hit: false,
+                    logs: Vec::new(),
+                })
+                .expect("append");
+            if run_id == 1 {
+                store
+                    .append(PersistedShellEvent::CommitRecorded {
+                        run_id,
+                        hash: "abc123".to_string(),
+                    })
+                    .expect("append");
+            }
+        }
+        let snapshot_seq = store
+            .append(PersistedShellEvent::ApprovalRequested {
+                request_id: "req-2-2".to_string(),
+                run_id: 2,
+                invocation_id: 2,
+                tool_id: "generate_plan".to_string(),
+                risk: "read_only".to_string(),
+                preview: String::new(),
+                created_at_ms: 0,
+            })
+            .expect("append");
+        store
+            .save_snapshot(&PersistedShellSnapshot {
+                version: 1,
+                seq: snapshot_seq,
+                workflow: replay_latest_workflow(&store.load().expect("load")),
+                max_run_id: 0,
+            })
+            .expect("snapshot");
+        store
+            .append(PersistedShellEvent::ApprovalResolved {
+                request_id: "req-2-2".to_string(),
+                run_id: 2,
+                decision: "approved".to_string(),
+                timestamp_ms: 0,
+            })
+            .expect("append");
+        let before = replay_latest_workflow(&store.load().expect("load")).expect("run");
+        let tail_before = store.load_since(snapshot_seq).expect("tail");
+        let mut expected = store
+            .load()
+            .expect("load")
+            .into_iter()
+            .filter(|record| {
+                matches!(
+                    record.event,
+                    PersistedShellEvent::WorkflowRunStarted { run_id: 2, .. }
+                        | PersistedShellEvent::CommitRecorded { .. }
+                )
+            })
+            .collect::<Vec<_>>();
+        expected.extend(tail_before.iter().cloned());
+
+        // Run 2's start and run 1's commit outlive the records around them.
+        assert_eq!(store.compact(snapshot_seq).expect("compact"), 4);
+        assert_eq!(store.load().expect("load"), expected);
+        assert_eq!(
+            last_recorded_commit(&store.load().expect("load")),
+            Some((1, "abc123".to_string()))
+        );
+        assert_eq!(store.load_since(snapshot_seq).expect("tail"), tail_before);
+        let snapshot = store.load_snapshot().expect("load").expect("snapshot");
+        let after = replay_workflow_from(snapshot.workflow, &tail_before).expect("run");
+        assert_eq!(after, before);
+
+        // Never past the snapshot, so nothing left to drop.
+        assert_eq!(store.compact(u64::MAX).expect("compact again"), 0);
+        let reopened = ShellEventStore::open(&path).expect("reopen");
+        let seq = reopened
+            .append(PersistedShellEvent::WorkflowResumed { run_id: 2 })
+            .expect("append");
+        assert_eq!(seq, snapshot_seq + 2);
+    }
+
+    #[test]
+    fn read_only_store_creates_nothing_and_refuses_writes() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join(".dao/events.jsonl");
+        let missing = ShellEventStore::open_read_only(&path);
+        assert!(missing.load().expect("load").is_empty());
+        assert!(missing.load_snapshot().expect("snapshot").is_none());
+        assert!(!dir.path().join(".dao").exists());
+
+        let store = ShellEventStore::open(&path).expect("open");
+        for run_id in 1..=3 {
+            store
+                .append(PersistedShellEvent::WorkflowResumed { run_id })
+                .expect("append");
+        }
+        let reader = ShellEventStore::open_read_only(&path);
+        let tail = reader.load_since(2).expect("tail");
+        assert_eq!(
+            tail.iter().map(|record| record.seq).collect::<Vec<_>>(),
+            vec![3]
+        );
+        let err = reader
+            .append(PersistedShellEvent::WorkflowResumed { run_id: 4 })
+            .expect_err("read-only");
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        assert_eq!(store.load().expect("load").len(), 3);
+    }
+
+    #[test]
+    fn appends_and_prunes_wait_for_another_process_holding_the_log_lock() {
+        let dir = tempdir().expect("tmpdir");
+        let path = dir.path().join("events.jsonl");
+        let store = ShellEventStore::open(&path).expect("open");
+        let held = std::fs::File::create(dir.path().join("events.jsonl.lock")).expect("lock file");
+        held.lock_exclusive().expect("lock");
+
+        std::thread::scope(|scope| {
+            let append =
+                scope.spawn(|| store.append(PersistedShellEvent::WorkflowResumed { run_id: 1 }));
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            assert!(!append.is_finished());
+            assert!(store.load().expect("load").is_empty());
+            drop(held);
+            assert_eq!(append.join().expect("join").expect("append"), 1);
+        });
+
+        let held = std::fs::File::create(dir.path().join("events.jsonl.lock")).expect("lock file");
+        held.lock_exclusive().expect("lock");
+        std::thread::scope(|scope| {
+            let prune = scope.spawn(|| store.prune(PruneTarget::All));
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            assert!(!prune.is_finished());
+            assert_eq!(store.load().expect("load").len(), 1);
+            drop(held);
+            assert_eq!(prune.join().expect("join").expect("prune"), 1);
+        });
+    }
+
+    #[test]
+    fn pruning_a_run_removes_exactly_its_records() {
+        let dir = tempdir().expect("tmpdir");
+        let store = ShellEventStore::open(dir.path().join("events.jsonl")).expect("open");
+        for run_id in [1, 2, 3] {
+            store
+                .append(PersistedShellEvent::WorkflowRunStarted {
+                    run_id,
+                    template_id: "scan_plan_diff_verify".to_string(),
+                    execution_mode: PersistedExecutionMode::Simulated,
+                    policy_tier: "balanced".to_string(),
+                    persona_policy: policy(),
+                })
+                .expect("append");
+            store
+                .append(PersistedShellEvent::PolicyChanged {
+                    tier: "balanced".to_string(),
+                    source: "cli".to_string(),
+                })
+                .expect("append");
+            store
+                .append(PersistedShellEvent::WorkflowStatusChanged {
+                    run_id,
+                    status: PersistedWorkflowStatus::Completed,
+                    step_index: 4,
+                    reason: None,
+                })
+                .expect("append");
+        }
+        let records = store.load().expect("load");
+        let last_seq = records.last().expect("records").seq;
+        store
+            .save_snapshot(&PersistedShellSnapshot {
+                version: 1,
+                seq: last_seq,
+                workflow: replay_latest_workflow(&records),
+                max_run_id: 0,
+            })
+            .expect("snapshot");
+        let without = |target: PruneTarget, records: &[PersistedShellEventRecord]| {
+            records
+                .iter()
+                .filter(|record| !target.selects(record.event.run_id()))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let snapshot_run = |store: &ShellEventStore| {
+            store
+                .load_snapshot()
+                .expect("load")
+                .expect("snapshot")
+                .workflow
+                .map(|run| run.run_id)
+        };
+
+        // Run 2 is neither the snapshot's run nor sharing events with others.
+        assert_eq!(store.prune(PruneTarget::Run(2)).expect("prune"), 2);
+        let remaining = without(PruneTarget::Run(2), &records);
+        assert_eq!(store.load().expect("load"), remaining);
+        assert_eq!(remaining.len(), records.len() - 2);
+        assert_eq!(snapshot_run(&store), Some(3));
+
+        // Pruning the snapshot's run rebuilds it from what is left.
+        assert_eq!(store.prune(PruneTarget::Run(3)).expect("prune"), 2);
+        let remaining = without(PruneTarget::Run(3), &remaining);
+        assert_eq!(store.load().expect("load"), remaining);
+        assert_eq!(snapshot_run(&store), Some(1));
+        assert_eq!(
+            store.load_snapshot().expect("load").expect("snapshot").seq,
+            last_seq
+        );
+
+        assert_eq!(store.prune(PruneTarget::Before(1)).expect("prune"), 0);
+        assert_eq!(store.prune(PruneTarget::Before(2)).expect("prune"), 2);
+        assert_eq!(snapshot_run(&store), None);
+        assert!(store
+            .load()
+            .expect("load")
+            .iter()
+            .all(|record| record.event.run_id().is_none()));
+
+        assert_eq!(store.prune(PruneTarget::All).expect("prune"), 3);
+        assert!(store.load().expect("load").is_empty());
+        // The emptied snapshot still remembers the highest run id and seq.
+        assert_eq!(
+            store.load_snapshot().expect("load"),
+            Some(PersistedShellSnapshot {
+                version: 1,
+                seq: last_seq,
+                workflow: None,
+                max_run_id: 3,
+            })
+        );
+    }
+
     #[test]
     fn snapshot_round_trip_and_bounded_replay() {
         let dir = tempdir().expect("tmpdir");
         let path = dir.path().join("events.jsonl");
-        let mut store = ShellEventStore::open(path).expect("open");
+        let store = ShellEventStore::open(path).expect("open");
 
         let seq1 = store
             .append(PersistedShellEvent::WorkflowRunStarted {
@@ -504,6 +2027,8 @@ mod tests {
                 invocation_id: 1,
                 tool_id: "scan_repo".to_string(),
                 status: "succeeded".to_string(),
+                cache_hit: false,
+                logs: Vec::new(),
             })
             .expect("append");
         let before_snapshot = replay_latest_workflow(&store.load().expect("load")).expect("run");
@@ -512,6 +2037,7 @@ mod tests {
                 version: 1,
                 seq: seq2,
                 workflow: Some(before_snapshot),
+                max_run_id: 0,
             })
             .expect("save snapshot");
         let _seq3 = store
@@ -520,6 +2046,8 @@ mod tests {
                 invocation_id: 2,
                 tool_id: "generate_plan".to_string(),
                 status: "succeeded".to_string(),
+                cache_hit: false,
+                logs: Vec::new(),
             })
             .expect("append");
         assert_eq!(seq1, 1);
@@ -532,4 +2060,308 @@ mod tests {
         let replayed = replay_workflow_from(snapshot.workflow, &tail).expect("replayed");
         assert_eq!(replayed.step_index, 2);
     }
+
+    #[test]
+    fn last_recorded_commit_picks_highest_seq() {
+        let records = vec![
+            PersistedShellEventRecord {
+                seq: 5,
+                ts_ms: 0,
+                event: PersistedShellEvent::CommitRecorded {
+                    run_id: 3,
+                    hash: "bbbbbbb".to_string(),
+                },
+            },
+            PersistedShellEventRecord {
+                seq: 2,
+                ts_ms: 0,
+                event: PersistedShellEvent::CommitRecorded {
+                    run_id: 1,
+                    hash: "aaaaaaa".to_string(),
+                },
+            },
+            PersistedShellEventRecord {
+                seq: 6,
+                ts_ms: 0,
+                event: PersistedShellEvent::WorkflowResumed { run_id: 3 },
+            },
+        ];
+        assert_eq!(
+            last_recorded_commit(&records),
+            Some((3, "bbbbbbb".to_string()))
+        );
+        assert_eq!(last_recorded_commit(&records[2..]), None);
+    }
+
+    #[test]
+    fn concurrent_appends_keep_seq_monotonic_without_losing_records() {
+        const THREADS: u64 = 8;
+        const PER_THREAD: u64 = 50;
+
+        let dir = tempdir().expect("tmpdir");
+        let store = ShellEventStore::open(dir.path().join("events.jsonl")).expect("open");
+        std::thread::scope(|scope| {
+            for thread in 0..THREADS {
+                let store = &store;
+                scope.spawn(move || {
+                    let mut last = 0;
+                    for i in 0..PER_THREAD {
+                        let seq = store
+                            .append(PersistedShellEvent::WorkflowResumed {
+                                run_id: thread * PER_THREAD + i,
+                            })
+                            .expect("append");
+                        assert!(seq > last);
+                        last = seq;
+                    }
+                });
+            }
+        });
+
+        let loaded = store.load().expect("load");
+        let seqs: Vec<u64> = loaded.iter().map(|record| record.seq).collect();
+        assert_eq!(seqs, (1..=THREADS * PER_THREAD).collect::<Vec<_>>());
+        let mut run_ids: Vec<u64> = loaded
+            .iter()
+            .map(|record| match record.event {
+                PersistedShellEvent::WorkflowResumed { run_id } => run_id,
+                _ => panic!("unexpected event"),
+            })
+            .collect();
+        run_ids.sort_unstable();
+        assert_eq!(run_ids, (0..THREADS * PER_THREAD).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn workflow_stats_tallies_final_status_approvals_and_blocked_reasons() {
+        let events = vec![
+            PersistedShellEvent::WorkflowRunStarted {
+                run_id: 1,
+                template_id: "scan_plan_diff_verify".to_string(),
+                execution_mode: PersistedExecutionMode::Runtime,
+                policy_tier: "balanced".to_string(),
+                persona_policy: policy(),
+            },
+            PersistedShellEvent::ApprovalRequested {
+                request_id: "req-1".to_string(),
+                run_id: 1,
+                invocation_id: 3,
+                tool_id: "compute_diff".to_string(),
+                risk: "patch_only".to_string(),
+                preview: String::new(),
+                created_at_ms: 0,
+            },
+            PersistedShellEvent::ApprovalResolved {
+                request_id: "req-1".to_string(),
+                run_id: 1,
+                decision: "approved".to_string(),
+                timestamp_ms: 0,
+            },
+            PersistedShellEvent::WorkflowStatusChanged {
+                run_id: 1,
+                status: PersistedWorkflowStatus::Completed,
+                step_index: 4,
+                reason: None,
+            },
+            PersistedShellEvent::WorkflowRunStarted {
+                run_id: 2,
+                template_id: "scan_plan_diff_verify".to_string(),
+                execution_mode: PersistedExecutionMode::Runtime,
+                policy_tier: "strict".to_string(),
+                persona_policy: policy(),
+            },
+            PersistedShellEvent::WorkflowStatusChanged {
+                run_id: 2,
+                status: PersistedWorkflowStatus::Blocked,
+                step_index: 2,
+                reason: Some("policy denied".to_string()),
+            },
+            PersistedShellEvent::WorkflowRunStarted {
+                run_id: 3,
+                template_id: "scan_plan_diff_verify".to_string(),
+                execution_mode: PersistedExecutionMode::Runtime,
+                policy_tier: "strict".to_string(),
+                persona_policy: policy(),
+            },
+            PersistedShellEvent::ApprovalResolved {
+                request_id: "req-3".to_string(),
+                run_id: 3,
+                decision: "denied".to_string(),
+                timestamp_ms: 0,
+            },
+            PersistedShellEvent::WorkflowStatusChanged {
+                run_id: 3,
+                status: PersistedWorkflowStatus::Blocked,
+                step_index: 2,
+                reason: Some("policy denied".to_string()),
+            },
+        ];
+        let records: Vec<PersistedShellEventRecord> = events
+            .into_iter()
+            .enumerate()
+            .map(|(idx, event)| PersistedShellEventRecord {
+                seq: idx as u64 + 1,
+                ts_ms: 0,
+                event,
+            })
+            .collect();
+
+        let stats = workflow_stats(&records);
+        assert_eq!(stats.runs, 3);
+        assert_eq!(
+            stats.final_status.into_iter().collect::<Vec<_>>(),
+            vec![
+                (PersistedWorkflowStatus::Blocked, 2),
+                (PersistedWorkflowStatus::Completed, 1),
+            ]
+        );
+        assert_eq!(stats.avg_steps_to_completion, Some(4.0));
+        assert_eq!((stats.approvals_granted, stats.approvals_denied), (1, 1));
+        assert_eq!(
+            stats.blocked_reasons,
+            vec![("policy denied".to_string(), 2)]
+        );
+    }
+
+    fn saved_state_json() -> serde_json::Value {
+        let state = ShellState::new(
+            "project".to_string(),
+            Personality::Pragmatic,
+            Config::default(),
+        );
+        serde_json::to_value(&state).expect("serialize state")
+    }
+
+    #[test]
+    fn shell_state_round_trips_through_the_state_file() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, saved_state_json().to_string()).expect("write state");
+
+        let LoadedShellState::Loaded(state) = load_shell_state(&path).expect("load") else {
+            panic!("expected the saved state to load");
+        };
+        assert_eq!(state.header.project_name, "project");
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn truncated_state_file_is_backed_up_and_discarded() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("state.json");
+        let json = saved_state_json().to_string();
+        std::fs::write(&path, &json[..json.len() / 2]).expect("write state");
+
+        let LoadedShellState::Discarded { backup, .. } = load_shell_state(&path).expect("load")
+        else {
+            panic!("expected the truncated state to be discarded");
+        };
+        assert_eq!(backup, dir.path().join("state.json.bak"));
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read_to_string(&backup).expect("read backup"),
+            json[..json.len() / 2]
+        );
+        assert!(matches!(
+            load_shell_state(&path).expect("reload"),
+            LoadedShellState::Missing
+        ));
+    }
+
+    #[test]
+    fn state_file_from_another_schema_is_refused() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("state.json");
+        let mut json = saved_state_json();
+        json["schema_version"] = serde_json::json!(2);
+        std::fs::write(&path, json.to_string()).expect("write state");
+
+        let LoadedShellState::Discarded { reason, .. } = load_shell_state(&path).expect("load")
+        else {
+            panic!("expected the mismatched state to be discarded");
+        };
+        assert_eq!(reason, "state schema v2 does not match v1");
+    }
+
+    #[test]
+    fn state_file_without_a_schema_version_loads_as_v1() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("state.json");
+        let mut json = saved_state_json();
+        json.as_object_mut()
+            .expect("state object")
+            .remove("schema_version");
+        std::fs::write(&path, json.to_string()).expect("write state");
+
+        let LoadedShellState::Loaded(state) = load_shell_state(&path).expect("load") else {
+            panic!("expected the unversioned state to load");
+        };
+        assert_eq!(state.schema_version, SHELL_STATE_SCHEMA);
+        assert_eq!(state.header.project_name, "project");
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn only_a_writer_moves_an_unusable_state_file_aside() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, "{").expect("write state");
+
+        assert!(matches!(
+            read_shell_state(&path).expect("read"),
+            LoadedShellState::Unusable { .. }
+        ));
+        assert!(path.exists());
+
+        let mut backups = Vec::new();
+        for contents in ["{", "[", "nope"] {
+            std::fs::write(&path, contents).expect("write state");
+            let LoadedShellState::Discarded { backup, .. } = load_shell_state(&path).expect("load")
+            else {
+                panic!("expected {contents:?} to be discarded");
+            };
+            assert_eq!(
+                std::fs::read_to_string(&backup).expect("read backup"),
+                contents
+            );
+            backups.push(backup);
+        }
+        assert_eq!(
+            backups,
+            vec![
+                dir.path().join("state.json.bak"),
+                dir.path().join("state.json.bak.1"),
+                dir.path().join("state.json.bak.2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn atomic_write_replaces_the_file_and_leaves_no_temp_file() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("state.json");
+        std::fs::write(&path, "old").expect("write state");
+
+        write_atomic(&path, b"new").expect("atomic write");
+
+        assert_eq!(std::fs::read_to_string(&path).expect("read"), "new");
+        let names: Vec<_> = std::fs::read_dir(dir.path())
+            .expect("read dir")
+            .map(|entry| entry.expect("entry").file_name())
+            .collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("state.json")]);
+    }
+
+    #[test]
+    fn failed_atomic_write_keeps_the_previous_contents() {
+        let dir = tempdir().expect("tempdir");
+        let path = dir.path().join("snapshot.json");
+        std::fs::write(&path, "complete").expect("write snapshot");
+        // A directory in the temp file's place makes the staging write fail
+        // the way an interrupted one would, before anything reaches `path`.
+        std::fs::create_dir(atomic_temp_path(&path)).expect("block temp path");
+
+        assert!(write_atomic(&path, b"partial").is_err());
+        assert_eq!(std::fs::read_to_string(&path).expect("read"), "complete");
+    }
 }
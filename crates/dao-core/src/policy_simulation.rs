@@ -1,4 +1,4 @@
-use crate::state::policy_requirement_for_risk;
+use crate::config::TierRequirements;
 use crate::state::ApprovalGateRequirement;
 use crate::state::PolicyTier;
 use crate::tool_registry::tier_satisfies;
@@ -22,7 +22,13 @@ pub struct PolicySimulationReport {
     pub blocked_count: usize,
 }
 
-pub fn simulate_tool(policy_tier: PolicyTier, tool_id: ToolId) -> ToolPolicyOutcome {
+/// Predicts the gate for `tool_id` the way a run assesses it, including the
+/// `[policy.tiers]` overrides in `tiers`.
+pub fn simulate_tool(
+    policy_tier: PolicyTier,
+    tool_id: ToolId,
+    tiers: &TierRequirements,
+) -> ToolPolicyOutcome {
     let spec = ToolRegistry::get(tool_id);
     if !tier_satisfies(policy_tier, spec.min_tier) {
         return ToolPolicyOutcome {
@@ -33,7 +39,7 @@ pub fn simulate_tool(policy_tier: PolicyTier, tool_id: ToolId) -> ToolPolicyOutc
         };
     }
 
-    let requirement = policy_requirement_for_risk(policy_tier, spec.risk_class);
+    let requirement = tiers.requirement(policy_tier, spec.risk_class);
     let blocked = matches!(requirement, ApprovalGateRequirement::Deny);
     let reason = match requirement {
         ApprovalGateRequirement::Allow => "allowed by risk policy",
@@ -50,10 +56,14 @@ pub fn simulate_tool(policy_tier: PolicyTier, tool_id: ToolId) -> ToolPolicyOutc
 }
 
 #[allow(dead_code)]
-pub fn simulate_tools(policy_tier: PolicyTier, tool_ids: &[ToolId]) -> PolicySimulationReport {
+pub fn simulate_tools(
+    policy_tier: PolicyTier,
+    tool_ids: &[ToolId],
+    tiers: &TierRequirements,
+) -> PolicySimulationReport {
     let outcomes: Vec<ToolPolicyOutcome> = tool_ids
         .iter()
-        .map(|tool_id| simulate_tool(policy_tier, *tool_id))
+        .map(|tool_id| simulate_tool(policy_tier, *tool_id, tiers))
         .collect();
 
     let allow_count = outcomes
@@ -84,25 +94,50 @@ mod tests {
     use pretty_assertions::assert_eq;
 
     use super::*;
+    use crate::state::policy_requirement_for_risk;
+    use crate::state::ApprovalRiskClass;
     use crate::workflow::workflow_template;
     use crate::workflow::WorkflowTemplateId;
 
     #[test]
     fn strict_policy_blocks_tools_below_minimum_tier() {
-        let outcome = simulate_tool(PolicyTier::Strict, ToolId::ComputeDiff);
+        let outcome = simulate_tool(
+            PolicyTier::Strict,
+            ToolId::ComputeDiff,
+            &TierRequirements::default(),
+        );
         assert_eq!(outcome.requirement, ApprovalGateRequirement::Deny);
         assert!(outcome.blocked);
     }
 
     #[test]
     fn risk_mapping_is_consistent_with_classifier() {
-        let outcome = simulate_tool(PolicyTier::Balanced, ToolId::Verify);
+        let outcome = simulate_tool(
+            PolicyTier::Balanced,
+            ToolId::Verify,
+            &TierRequirements::default(),
+        );
         assert_eq!(
             outcome.requirement,
             policy_requirement_for_risk(PolicyTier::Balanced, ToolRegistry::risk(ToolId::Verify))
         );
     }
 
+    #[test]
+    fn tier_overrides_change_the_simulated_gate() {
+        let tiers = TierRequirements(
+            [(
+                PolicyTier::Balanced,
+                [(ApprovalRiskClass::Execution, ApprovalGateRequirement::Deny)].into(),
+            )]
+            .into(),
+        );
+        let outcome = simulate_tool(PolicyTier::Balanced, ToolId::Verify, &tiers);
+        assert_eq!(outcome.requirement, ApprovalGateRequirement::Deny);
+        assert!(outcome.blocked);
+        assert_eq!(outcome.reason, "blocked by risk policy");
+    }
+
     #[test]
     fn simulation_is_deterministic_for_same_inputs() {
         let tool_ids = [
@@ -111,8 +146,16 @@ mod tests {
             ToolId::ComputeDiff,
             ToolId::Verify,
         ];
-        let first = simulate_tools(PolicyTier::Balanced, &tool_ids);
-        let second = simulate_tools(PolicyTier::Balanced, &tool_ids);
+        let first = simulate_tools(
+            PolicyTier::Balanced,
+            &tool_ids,
+            &TierRequirements::default(),
+        );
+        let second = simulate_tools(
+            PolicyTier::Balanced,
+            &tool_ids,
+            &TierRequirements::default(),
+        );
         assert_eq!(first, second);
     }
 
@@ -120,7 +163,11 @@ mod tests {
     fn workflow_simulation_report_counts_are_stable() {
         let template = workflow_template(WorkflowTemplateId::ScanPlanDiffVerify);
         let tool_ids: Vec<ToolId> = template.steps.iter().map(|step| step.tool_id).collect();
-        let report = simulate_tools(PolicyTier::Balanced, &tool_ids);
+        let report = simulate_tools(
+            PolicyTier::Balanced,
+            &tool_ids,
+            &TierRequirements::default(),
+        );
 
         assert_eq!(report.allow_count, 3);
         assert_eq!(report.approval_count, 1);
@@ -131,7 +178,7 @@ mod tests {
     fn strict_workflow_simulation_reports_blocked_steps() {
         let template = workflow_template(WorkflowTemplateId::ScanPlanDiffVerify);
         let tool_ids: Vec<ToolId> = template.steps.iter().map(|step| step.tool_id).collect();
-        let report = simulate_tools(PolicyTier::Strict, &tool_ids);
+        let report = simulate_tools(PolicyTier::Strict, &tool_ids, &TierRequirements::default());
 
         assert_eq!(report.allow_count, 2);
         assert_eq!(report.approval_count, 0);
@@ -19,6 +19,47 @@ pub enum DaoEffect {
     StartProviderAuth {
         provider: String,
     },
+    /// Selection state worth keeping across sessions changed.
+    PersistState,
+    WriteApprovalSummary {
+        run_id: u64,
+    },
+    /// Append `ApprovalReopened` to the workflow event log so the blocked run
+    /// awaits the request again.
+    RecordApprovalReopened {
+        request_id: String,
+        run_id: u64,
+    },
+    /// The user asked to approve the pending request. The host answers with
+    /// `UserAction::ApprovePending` carrying the current time.
+    ApprovePending,
+    /// Run `git diff` on one file and show it on the Diff tab.
+    DiffFile {
+        path: PathBuf,
+    },
+    /// Run one workflow tool in the background and apply its artifact.
+    RunTool(GatedToolRun),
+    /// Stop the running chat stream and keep its partial reply.
+    CancelChat,
+}
+
+/// A tool run that passed the policy gate in `request_tool_run`. Its fields
+/// are private, so code outside the reducer cannot emit `DaoEffect::RunTool`
+/// for a tool the gate has not seen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GatedToolRun {
+    tool_id: ToolId,
+    run_id: u64,
+}
+
+impl GatedToolRun {
+    pub fn tool_id(self) -> ToolId {
+        self.tool_id
+    }
+
+    pub fn run_id(self) -> u64 {
+        self.run_id
+    }
 }
 
 use super::actions::filtered_palette_indices;
@@ -29,6 +70,10 @@ use super::actions::RuntimeFlag;
 use super::actions::ShellAction;
 use super::actions::UserAction;
 use super::actions::PALETTE_ITEMS;
+use super::chat_context::chat_turns;
+use super::chat_context::ChatContextStrategy;
+use super::chat_context::DEFAULT_RECENT_TURNS;
+use super::conversion::diff_files_from_unified;
 use super::policy_engine::DecisionOutcome;
 use super::policy_engine::PolicyDecision;
 use super::policy_engine::Signals;
@@ -36,16 +81,21 @@ use super::state::apply_persona_policy_overrides;
 use super::state::artifact_is_newer;
 use super::state::derive_journey;
 use super::state::persona_policy_for;
-use super::state::policy_requirement_for_risk;
+use super::state::ApprovalAction;
+use super::state::ApprovalDecisionKind;
+use super::state::ApprovalDecisionRecord;
 use super::state::ApprovalGateRequirement;
+use super::state::ApprovalRequestRecord;
 use super::state::ApprovalRiskClass;
+use super::state::ArtifactError;
+use super::state::ChatRole;
 use super::state::ClearReason;
 use super::state::DiffArtifact;
-use super::state::DiffFile;
 use super::state::DiffFileStatus;
 use super::state::DiffHunk;
-use super::state::DiffLine;
 use super::state::DiffLineKind;
+use super::state::FilePreview;
+use super::state::HunkExpansion;
 use super::state::JourneyError;
 use super::state::JourneyState;
 use super::state::LogEntry;
@@ -53,14 +103,23 @@ use super::state::LogLevel;
 use super::state::LogSource;
 use super::state::PendingApproval;
 use super::state::PersonaPolicyOverrides;
+use super::state::Personality;
 use super::state::PlanArtifact;
 use super::state::PlanStep;
 use super::state::PolicyGateState;
+use super::state::PolicyTier;
 use super::state::ShellOverlay;
 use super::state::ShellState;
+use super::state::ShellTab;
 use super::state::StepStatus;
 use super::state::SystemArtifact;
+use super::state::UiTheme;
+use super::state::VerifyArtifact;
 use super::state::ARTIFACT_SCHEMA_V1;
+use super::tool_registry::tier_satisfies;
+use super::tool_registry::ToolId;
+use super::tool_registry::ToolRegistry;
+use std::path::PathBuf;
 
 pub const AVAILABLE_MODELS: &[&str] = &[
     "gpt-5",
@@ -81,13 +140,18 @@ pub const AVAILABLE_MODELS: &[&str] = &[
 ];
 
 pub fn reduce(state: &mut ShellState, action: ShellAction) -> Vec<DaoEffect> {
-    match action {
+    let effects = match action {
         ShellAction::User(user) => reduce_user(state, user),
         ShellAction::Runtime(runtime) => {
             reduce_runtime(state, runtime);
             Vec::new()
         }
-    }
+    };
+    state
+        .artifacts
+        .logs
+        .retain_bookmarks(&mut state.selection.log_bookmarks);
+    effects
 }
 
 fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
@@ -141,7 +205,20 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
             vec![DaoEffect::RequestFrame]
         }
         UserAction::CycleTheme => {
-            state.customization.theme = state.customization.theme.next();
+            state.customization.theme = state.cycled_theme(true);
+            vec![DaoEffect::RequestFrame]
+        }
+        UserAction::SetPolicyTier(tier) => {
+            reduce_runtime(state, RuntimeAction::SetPolicyTier(tier));
+            vec![DaoEffect::RequestFrame]
+        }
+        UserAction::CyclePolicyTier => {
+            let tier = state.approval.policy_tier.next();
+            reduce_runtime(state, RuntimeAction::SetPolicyTier(tier));
+            reduce_runtime(
+                state,
+                RuntimeAction::AppendLog(format!("[meta] Policy tier set to {}", tier.label())),
+            );
             vec![DaoEffect::RequestFrame]
         }
         UserAction::ToggleJourneyPanel => {
@@ -174,6 +251,9 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
         }
         UserAction::SelectTab(tab) => {
             state.routing.tab = tab;
+            if tab != ShellTab::Diff && state.file_browser.diff.take().is_some() {
+                reset_diff_view(state);
+            }
             vec![DaoEffect::RequestFrame]
         }
         UserAction::NextJourneyStep | UserAction::PrevJourneyStep => Vec::new(),
@@ -269,11 +349,21 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
             state.selection.log_search = search;
             vec![DaoEffect::RequestFrame]
         }
+        UserAction::SetLogsTextSearch(search) => {
+            state.selection.logs_text_search = search;
+            state.selection.log_scroll = 0;
+            vec![DaoEffect::RequestFrame]
+        }
         UserAction::ScrollLogs(delta) => {
             state.selection.log_stick_to_bottom = false;
             state.selection.log_scroll = state.selection.log_scroll.saturating_add_signed(delta);
             vec![DaoEffect::RequestFrame]
         }
+        UserAction::ScrollDiffHorizontal(delta) => {
+            state.selection.diff_h_scroll =
+                state.selection.diff_h_scroll.saturating_add_signed(delta);
+            vec![DaoEffect::RequestFrame]
+        }
         UserAction::SetLogScroll(scroll) => {
             state.selection.log_scroll = scroll;
             vec![DaoEffect::RequestFrame]
@@ -282,6 +372,61 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
             state.selection.log_stick_to_bottom = stick;
             vec![DaoEffect::RequestFrame]
         }
+        UserAction::JumpLogRunBoundary { forward } => {
+            let boundaries = state
+                .artifacts
+                .logs
+                .run_boundaries(&state.selection.log_filter());
+            let current = usize::from(state.selection.log_scroll);
+            let target = if forward {
+                boundaries.into_iter().find(|idx| *idx > current)
+            } else {
+                boundaries.into_iter().rev().find(|idx| *idx < current)
+            };
+            let Some(target) = target else {
+                return Vec::new();
+            };
+            state.selection.log_stick_to_bottom = false;
+            state.selection.log_scroll = u16::try_from(target).unwrap_or(u16::MAX);
+            vec![DaoEffect::RequestFrame]
+        }
+        UserAction::ToggleLogBookmark(id) => {
+            let bookmarks = &mut state.selection.log_bookmarks;
+            match bookmarks.binary_search(&id) {
+                Ok(idx) => {
+                    bookmarks.remove(idx);
+                }
+                Err(idx) => bookmarks.insert(idx, id),
+            }
+            vec![DaoEffect::PersistState, DaoEffect::RequestFrame]
+        }
+        UserAction::ExpandHunkContext { file, hunk } => {
+            if let Err(err) = expand_hunk_context(state, &file, hunk) {
+                reduce_runtime(
+                    state,
+                    RuntimeAction::AppendLog(format!("[meta] Cannot expand {file}: {err}")),
+                );
+            }
+            vec![DaoEffect::RequestFrame]
+        }
+        UserAction::JumpLogBookmark { forward } => {
+            let positions = state.artifacts.logs.bookmark_positions(
+                &state.selection.log_filter(),
+                &state.selection.log_bookmarks,
+            );
+            let current = usize::from(state.selection.log_scroll);
+            let target = if forward {
+                positions.into_iter().find(|idx| *idx > current)
+            } else {
+                positions.into_iter().rev().find(|idx| *idx < current)
+            };
+            let Some(target) = target else {
+                return Vec::new();
+            };
+            state.selection.log_stick_to_bottom = false;
+            state.selection.log_scroll = u16::try_from(target).unwrap_or(u16::MAX);
+            vec![DaoEffect::RequestFrame]
+        }
         UserAction::ClearArtifact { which, reason } => {
             reduce_runtime(
                 state,
@@ -299,10 +444,32 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
             state.interaction.chat_input.push(c);
             vec![DaoEffect::RequestFrame]
         }
+        UserAction::ChatInputNewline => {
+            state.interaction.chat_input.push('\n');
+            vec![DaoEffect::RequestFrame]
+        }
         UserAction::ChatBackspace => {
             state.interaction.chat_input.pop();
             vec![DaoEffect::RequestFrame]
         }
+        UserAction::ChatDeleteWord => {
+            let input = &mut state.interaction.chat_input;
+            let kept = input
+                .trim_end()
+                .trim_end_matches(|c: char| !c.is_whitespace())
+                .len();
+            input.truncate(kept);
+            vec![DaoEffect::RequestFrame]
+        }
+        UserAction::ChatClearLine => {
+            let input = &mut state.interaction.chat_input;
+            match input.rfind('\n') {
+                Some(idx) if idx + 1 < input.len() => input.truncate(idx + 1),
+                Some(idx) => input.truncate(idx),
+                None => input.clear(),
+            }
+            vec![DaoEffect::RequestFrame]
+        }
         UserAction::ChatSubmit => {
             let input = std::mem::take(&mut state.interaction.chat_input);
             let trimmed_input = input.trim();
@@ -369,13 +536,18 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
                             reduce_runtime(
                                 state,
                                 RuntimeAction::AppendLog(format!(
-                                    "[meta] Status | tab:{} | journey:{} | mode:{} | provider:{} | model:{} | risk:{}",
+                                    "[meta] Status | tab:{} | journey:{} | mode:{} | tier:{} | provider:{} | model:{} | risk:{} | risk-override:{}",
                                     state.routing.tab.label(),
                                     state.journey_status.state.label(),
                                     state.header.safety_mode.label(),
+                                    state.approval.policy_tier.label(),
                                     state.sm.model_provider.as_deref().unwrap_or("ollama"),
                                     state.sm.model_slug.as_deref().unwrap_or("default"),
-                                    state.header.risk.label()
+                                    state.header.risk.label(),
+                                    state
+                                        .approval
+                                        .risk_override
+                                        .map_or("none", |risk| risk.label())
                                 )),
                             );
                         }
@@ -413,6 +585,43 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
                                 );
                             }
                         }
+                        "/logsearch" => {
+                            if argument_tail.is_empty() {
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Usage: /logsearch <text|clear> | current: {}",
+                                        if state.selection.logs_text_search.is_empty() {
+                                            "(none)".to_string()
+                                        } else {
+                                            state.selection.logs_text_search.clone()
+                                        }
+                                    )),
+                                );
+                            } else if argument_tail.eq_ignore_ascii_case("clear")
+                                || argument_tail.eq_ignore_ascii_case("off")
+                            {
+                                reduce_user(state, UserAction::SetLogsTextSearch(String::new()));
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(
+                                        "[meta] Logs search filter cleared".to_string(),
+                                    ),
+                                );
+                            } else {
+                                reduce_user(
+                                    state,
+                                    UserAction::SetLogsTextSearch(argument_tail.to_string()),
+                                );
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Logs search filter set to '{}'",
+                                        argument_tail
+                                    )),
+                                );
+                            }
+                        }
                         "/streammeta" => {
                             let arg = argument_tail.to_ascii_lowercase();
                             match arg.as_str() {
@@ -512,13 +721,16 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
                             if argument_tail.is_empty() {
                                 reduce_runtime(
                                     state,
-                                    RuntimeAction::AppendLog(
-                                        "[meta] Usage: /theme <classic|cyberpunk|neon-noir|solar-flare|forest-zen|next|prev>"
-                                            .to_string(),
-                                    ),
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Usage: /theme <{}|next|prev>",
+                                        UiTheme::all()
+                                            .map(UiTheme::label)
+                                            .collect::<Vec<_>>()
+                                            .join("|")
+                                    )),
                                 );
                             } else if argument_tail.eq_ignore_ascii_case("next") {
-                                state.customization.theme = state.customization.theme.next();
+                                state.customization.theme = state.cycled_theme(true);
                                 reduce_runtime(
                                     state,
                                     RuntimeAction::AppendLog(format!(
@@ -527,7 +739,7 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
                                     )),
                                 );
                             } else if argument_tail.eq_ignore_ascii_case("prev") {
-                                state.customization.theme = state.customization.theme.prev();
+                                state.customization.theme = state.cycled_theme(false);
                                 reduce_runtime(
                                     state,
                                     RuntimeAction::AppendLog(format!(
@@ -535,7 +747,17 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
                                         state.customization.theme.label()
                                     )),
                                 );
-                            } else if let Some(theme) = parse_theme(argument_tail) {
+                            } else if UiTheme::from_name(argument_tail) == Some(UiTheme::Custom)
+                                && state.config.ui.custom_theme_path.is_none()
+                            {
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(
+                                        "[meta] Set [ui] custom_theme_path in config.toml to use the custom theme"
+                                            .to_string(),
+                                    ),
+                                );
+                            } else if let Some(theme) = UiTheme::from_name(argument_tail) {
                                 state.customization.theme = theme;
                                 reduce_runtime(
                                     state,
@@ -554,6 +776,152 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
                                 );
                             }
                         }
+                        "/tier" => {
+                            if argument_tail.is_empty() {
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Usage: /tier <strict|balanced|permissive|next> | current: {}",
+                                        state.approval.policy_tier.label()
+                                    )),
+                                );
+                            } else if let Some(tier) =
+                                parse_policy_tier(argument_tail).or_else(|| {
+                                    argument_tail
+                                        .eq_ignore_ascii_case("next")
+                                        .then(|| state.approval.policy_tier.next())
+                                })
+                            {
+                                reduce_runtime(state, RuntimeAction::SetPolicyTier(tier));
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Policy tier set to {}",
+                                        tier.label()
+                                    )),
+                                );
+                            } else {
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Unknown policy tier '{}'",
+                                        argument_tail
+                                    )),
+                                );
+                            }
+                        }
+                        "/personality" => {
+                            if argument_tail.is_empty() {
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Usage: /personality <friendly|pragmatic> | current: {}",
+                                        state.sm.personality.label()
+                                    )),
+                                );
+                            } else if let Some(personality) = Personality::parse(argument_tail) {
+                                reduce_runtime(state, RuntimeAction::SetPersonality(personality));
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Personality set to {}",
+                                        personality.label()
+                                    )),
+                                );
+                            } else {
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Unknown personality '{}'",
+                                        argument_tail
+                                    )),
+                                );
+                            }
+                        }
+                        "/context" => {
+                            let mut parts = argument_tail.split_whitespace();
+                            let kind = parts.next().map(str::to_ascii_lowercase);
+                            let turns = match parts.next().map(str::parse::<usize>) {
+                                Some(Ok(turns)) => Some(turns),
+                                Some(Err(_)) => None,
+                                None => Some(match state.config.chat.context.recent_turns() {
+                                    0 => DEFAULT_RECENT_TURNS,
+                                    turns => turns,
+                                }),
+                            };
+                            let strategy = match (kind.as_deref(), turns) {
+                                (Some("diff" | "diff_only" | "diff-only"), _) => {
+                                    Some(ChatContextStrategy::DiffOnly)
+                                }
+                                (Some("recent"), Some(turns)) => {
+                                    Some(ChatContextStrategy::Recent { turns })
+                                }
+                                (Some("summarize"), Some(turns)) => {
+                                    Some(ChatContextStrategy::Summarize { turns })
+                                }
+                                _ => None,
+                            };
+                            if let Some(strategy) = strategy {
+                                state.config.chat.context = strategy;
+                                state.interaction.chat_summary = None;
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Chat context set to {}",
+                                        strategy.label()
+                                    )),
+                                );
+                            } else {
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Usage: /context <diff|recent [N]|summarize [N]> | current: {}",
+                                        state.config.chat.context.label()
+                                    )),
+                                );
+                            }
+                        }
+                        "/risk" => {
+                            if argument_tail.is_empty() {
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Usage: /risk <readonly|edit|refactor|execution|destructive|clear> | override: {}",
+                                        state
+                                            .approval
+                                            .risk_override
+                                            .map_or("none", |risk| risk.label())
+                                    )),
+                                );
+                            } else if argument_tail.eq_ignore_ascii_case("clear")
+                                || argument_tail.eq_ignore_ascii_case("off")
+                            {
+                                reduce_runtime(state, RuntimeAction::SetRiskOverride(None));
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(
+                                        "[meta] Risk override cleared".to_string(),
+                                    ),
+                                );
+                            } else if let Some(risk) = parse_risk_class(argument_tail) {
+                                reduce_runtime(state, RuntimeAction::SetRiskOverride(Some(risk)));
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Risk override set to {} (use /risk clear to remove)",
+                                        risk.label()
+                                    )),
+                                );
+                            } else {
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(format!(
+                                        "[meta] Unknown risk class '{}'",
+                                        argument_tail
+                                    )),
+                                );
+                            }
+                        }
                         "/panel" => {
                             if argument_tail.is_empty() {
                                 reduce_runtime(
@@ -672,6 +1040,77 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
                                 ),
                             );
                         }
+                        "/verify" => {
+                            let mut effects =
+                                command_to_effects(state, PaletteCommand::RerunVerify);
+                            effects.push(DaoEffect::RequestFrame);
+                            return effects;
+                        }
+                        "/approve" => {
+                            let mut effects =
+                                command_to_effects(state, PaletteCommand::ApprovePending);
+                            effects.push(DaoEffect::RequestFrame);
+                            return effects;
+                        }
+                        "/reopen" => {
+                            let request_id = argument.map(str::to_string).or_else(|| {
+                                state
+                                    .approval
+                                    .last_decision
+                                    .as_ref()
+                                    .filter(|decision| {
+                                        decision.decision == ApprovalDecisionKind::Denied
+                                    })
+                                    .map(|decision| decision.request_id.clone())
+                            });
+                            let Some(request_id) = request_id else {
+                                reduce_runtime(
+                                    state,
+                                    RuntimeAction::AppendLog(
+                                        "[meta] No denied approval to reopen".to_string(),
+                                    ),
+                                );
+                                return vec![DaoEffect::RequestFrame];
+                            };
+                            reduce_runtime(
+                                state,
+                                RuntimeAction::ReopenApproval {
+                                    request_id: request_id.clone(),
+                                },
+                            );
+                            let reopened = state
+                                .approval
+                                .pending
+                                .as_ref()
+                                .map(|pending| &pending.request)
+                                .filter(|request| request.request_id == request_id);
+                            if let Some(request) = reopened {
+                                return vec![
+                                    DaoEffect::RecordApprovalReopened {
+                                        request_id,
+                                        run_id: request.run_id,
+                                    },
+                                    DaoEffect::PersistState,
+                                    DaoEffect::RequestFrame,
+                                ];
+                            }
+                        }
+                        "/approvalsummary" => {
+                            if let Some(run_id) =
+                                state.artifacts.diff.as_ref().map(|diff| diff.run_id)
+                            {
+                                return vec![
+                                    DaoEffect::WriteApprovalSummary { run_id },
+                                    DaoEffect::RequestFrame,
+                                ];
+                            }
+                            reduce_runtime(
+                                state,
+                                RuntimeAction::AppendLog(
+                                    "[meta] No diff available to summarize".to_string(),
+                                ),
+                            );
+                        }
                         "/copychat" => {
                             if let Some(text) = full_chat_text(state) {
                                 reduce_runtime(
@@ -720,12 +1159,16 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
                                 state,
                                 RuntimeAction::ClearLogs(ClearReason::UserRequest),
                             );
+                            reduce_runtime(
+                                state,
+                                RuntimeAction::ClearChat(ClearReason::UserRequest),
+                            );
                         }
                         "/h" | "/help" => {
                             reduce_runtime(
                                 state,
                                 RuntimeAction::AppendLog(
-                                    "[meta] Commands: /models, /model <name>, /provider <name>, /tab <name>, /theme <name|next|prev>, /panel <journey|context|actions>, /search <text|clear>, /streammeta <on|off|toggle|status>, /auth [codex], /login [codex], /telemetry, /status, /copylast, /copydiff, /copychat, /copylogs, /focus, /clear, /help"
+                                    "[meta] Commands: /models, /model <name>, /provider <name>, /tab <name>, /theme <name|next|prev>, /tier <strict|balanced|permissive>, /personality <friendly|pragmatic>, /context <diff|recent [N]|summarize [N]>, /risk <class|clear>, /panel <journey|context|actions>, /search <text|clear>, /logsearch <text|clear>, /streammeta <on|off|toggle|status>, /auth [codex], /login [codex], /telemetry, /status, /copylast, /copydiff, /copychat, /copylogs, /approvalsummary, /verify, /approve, /reopen [request-id], /focus, /clear, /help"
                                         .to_string(),
                                 ),
                             );
@@ -760,6 +1203,13 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
                 vec![DaoEffect::RequestFrame]
             }
         }
+        UserAction::CancelChat => {
+            if state.interaction.is_thinking {
+                vec![DaoEffect::CancelChat]
+            } else {
+                Vec::new()
+            }
+        }
         UserAction::SetChatFocus(focus) => {
             state.interaction.focus_in_chat = focus;
             vec![DaoEffect::RequestFrame]
@@ -788,6 +1238,7 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
                 RuntimeAction::ClearVerifyArtifact(ClearReason::UserRequest),
             );
             reduce_runtime(state, RuntimeAction::ClearLogs(ClearReason::UserRequest));
+            reduce_runtime(state, RuntimeAction::ClearChat(ClearReason::UserRequest));
             reduce_runtime(
                 state,
                 RuntimeAction::ClearApprovalState(ClearReason::UserRequest),
@@ -803,6 +1254,19 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
             state.interaction.overlay = ShellOverlay::Help;
             vec![DaoEffect::RequestFrame]
         }
+        UserAction::ShowApprovalPreview => {
+            if state.approval.pending.is_none() {
+                return Vec::new();
+            }
+            state.interaction.overlay = ShellOverlay::ApprovalPreview { scroll: 0 };
+            vec![DaoEffect::RequestFrame]
+        }
+        UserAction::ScrollApprovalPreview(delta) => {
+            if let ShellOverlay::ApprovalPreview { scroll } = &mut state.interaction.overlay {
+                *scroll = scroll.saturating_add_signed(delta);
+            }
+            vec![DaoEffect::RequestFrame]
+        }
         UserAction::ChatHistoryUp => {
             if let Some(idx) = state.interaction.chat_history_index {
                 if idx > 0 {
@@ -830,6 +1294,7 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
             }
             vec![DaoEffect::RequestFrame]
         }
+        UserAction::ApprovePending { timestamp_ms } => approve_pending(state, timestamp_ms),
         UserAction::ReviewChanges => {
             state.routing.tab = super::state::ShellTab::Diff;
             let input = "Please review the changes in the current diff.".to_string();
@@ -886,7 +1351,7 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
             vec![DaoEffect::RequestFrame]
         }
         UserAction::CopyDiffToClipboard => {
-            if let Some(diff) = &state.artifacts.diff {
+            if let Some(diff) = state.shown_diff() {
                 let content = if let Some(selected) = &state.selection.selected_diff_file {
                     diff.files
                         .iter()
@@ -913,6 +1378,30 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
             }
             vec![DaoEffect::RequestFrame]
         }
+        UserAction::CopySelectedPlanStep => {
+            let text = state.selection.selected_plan_step.as_ref().and_then(|id| {
+                state
+                    .artifacts
+                    .plan
+                    .as_ref()?
+                    .steps
+                    .iter()
+                    .find(|step| step.id == *id)
+                    .map(|step| format!("{} [{}] {}", step.id, step.status.label(), step.label))
+            });
+            let Some(text) = text else {
+                reduce_runtime(
+                    state,
+                    RuntimeAction::AppendLog("[meta] No plan step selected to copy".to_string()),
+                );
+                return vec![DaoEffect::RequestFrame];
+            };
+            reduce_runtime(
+                state,
+                RuntimeAction::AppendLog("[meta] Copied plan step to clipboard".to_string()),
+            );
+            vec![DaoEffect::CopyToClipboard(text), DaoEffect::RequestFrame]
+        }
         UserAction::SetPlanStickToRunning(active) => {
             state.selection.plan_stick_to_running = active;
             if active {
@@ -974,21 +1463,72 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
             }
             vec![DaoEffect::RequestFrame]
         }
-        UserAction::PlanStepPageUp => {
-            if let Some(plan) = &state.artifacts.plan {
-                state.selection.plan_stick_to_running = false;
-                let current_idx = state
-                    .selection
-                    .selected_plan_step
-                    .as_ref()
-                    .and_then(|id| plan.steps.iter().position(|s| s.id == *id));
-
-                if let Some(idx) = current_idx {
-                    let new_idx = idx.saturating_sub(10);
-                    state.selection.selected_plan_step = Some(plan.steps[new_idx].id.clone());
-                } else if !plan.steps.is_empty() {
-                    state.selection.selected_plan_step =
-                        Some(plan.steps.last().unwrap().id.clone());
+        UserAction::ToggleDiffFileCollapse { path } => {
+            let collapsed = &mut state.selection.collapsed_diff_files;
+            if let Some(pos) = collapsed.iter().position(|p| *p == path) {
+                collapsed.remove(pos);
+            } else {
+                collapsed.push(path);
+            }
+            vec![DaoEffect::RequestFrame]
+        }
+        UserAction::TogglePlanStepDone => {
+            let Some(selected) = state.selection.selected_plan_step.clone() else {
+                return vec![DaoEffect::RequestFrame];
+            };
+            let Some(plan) = state.artifacts.plan.as_mut() else {
+                return vec![DaoEffect::RequestFrame];
+            };
+            let unmet = plan.unmet_dependencies(&selected);
+            let Some(step) = plan.steps.iter_mut().find(|step| step.id == selected) else {
+                return vec![DaoEffect::RequestFrame];
+            };
+            let message = if step.status == StepStatus::Done {
+                step.status = StepStatus::Pending;
+                format!("[meta] {selected} marked pending")
+            } else if unmet.is_empty() {
+                step.status = StepStatus::Done;
+                format!("[meta] {selected} marked done")
+            } else {
+                format!("[meta] {selected} waits on {}", unmet.join(", "))
+            };
+            reduce_runtime(state, RuntimeAction::AppendLog(message));
+            vec![DaoEffect::RequestFrame]
+        }
+        UserAction::SetPlanStepStatus { id, status } => {
+            let Some(plan) = state.artifacts.plan.as_mut() else {
+                return vec![DaoEffect::RequestFrame];
+            };
+            let unmet = plan.unmet_dependencies(&id);
+            let Some(step) = plan.steps.iter_mut().find(|step| step.id == id) else {
+                return vec![DaoEffect::RequestFrame];
+            };
+            let message = if status == StepStatus::Done && !unmet.is_empty() {
+                format!("[meta] {id} waits on {}", unmet.join(", "))
+            } else {
+                step.status = status;
+                format!("[meta] {id} marked {}", status.label())
+            };
+            reconcile_selected_plan_step(state);
+            recompute_journey(state);
+            reduce_runtime(state, RuntimeAction::AppendLog(message));
+            vec![DaoEffect::RequestFrame]
+        }
+        UserAction::PlanStepPageUp => {
+            if let Some(plan) = &state.artifacts.plan {
+                state.selection.plan_stick_to_running = false;
+                let current_idx = state
+                    .selection
+                    .selected_plan_step
+                    .as_ref()
+                    .and_then(|id| plan.steps.iter().position(|s| s.id == *id));
+
+                if let Some(idx) = current_idx {
+                    let new_idx = idx.saturating_sub(10);
+                    state.selection.selected_plan_step = Some(plan.steps[new_idx].id.clone());
+                } else if !plan.steps.is_empty() {
+                    state.selection.selected_plan_step =
+                        Some(plan.steps.last().unwrap().id.clone());
                 }
             }
             vec![DaoEffect::RequestFrame]
@@ -1018,25 +1558,44 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
             vec![DaoEffect::RequestFrame]
         }
         UserAction::FileBrowserDown => {
-            if state.file_browser.selected < state.file_browser.entries.len() - 1 {
+            if state.file_browser.selected + 1 < state.file_browser.entries.len() {
                 state.file_browser.selected += 1;
             }
             vec![DaoEffect::RequestFrame]
         }
         UserAction::FileBrowserEnter => {
-            let selected_entry = state.file_browser.entries[state.file_browser.selected].clone();
-            let mut new_path = state.file_browser.current_path.clone();
-            new_path.push(selected_entry);
+            let Some(new_path) = state.file_browser.selected_path() else {
+                return vec![];
+            };
             if new_path.is_dir() {
                 state.file_browser.current_path = new_path;
                 state.file_browser.selected = 0;
+                state.file_browser.refresh_entries();
             }
             vec![DaoEffect::RequestFrame]
         }
         UserAction::FileBrowserBack => {
             if state.file_browser.current_path.pop() {
                 state.file_browser.selected = 0;
+                state.file_browser.refresh_entries();
+            }
+            vec![DaoEffect::RequestFrame]
+        }
+        UserAction::DiffSelectedFile => match state.file_browser.selected_path() {
+            Some(path) if path.is_file() => vec![DaoEffect::DiffFile { path }],
+            _ => {
+                reduce_runtime(
+                    state,
+                    RuntimeAction::AppendLog("[meta] Select a file to diff.".to_string()),
+                );
+                vec![DaoEffect::RequestFrame]
             }
+        },
+        UserAction::LoadFilePreview => {
+            state.file_browser.preview = state
+                .file_browser
+                .selected_path()
+                .and_then(|path| FilePreview::load(&path));
             vec![DaoEffect::RequestFrame]
         }
     }
@@ -1044,27 +1603,65 @@ fn reduce_user(state: &mut ShellState, action: UserAction) -> Vec<DaoEffect> {
 
 fn build_chat_context(state: &ShellState) -> Option<String> {
     let mut context = String::new();
-    const MAX_CONTEXT_CHARS: usize = 32_000;
+    let max_context_chars = state.config.context.max_chars;
+    // Conversation history may use at most half the budget; the diff gets the rest.
+    let max_history_chars = max_context_chars / 2;
+
+    let strategy = state.config.chat.context;
+    if let (ChatContextStrategy::Summarize { .. }, Some(summary)) =
+        (strategy, &state.interaction.chat_summary)
+    {
+        context.push_str("Earlier conversation (summary):\n");
+        context.push_str(&summary.text);
+        context.push_str("\n\n");
+    }
+
+    let mut turns = chat_turns(&state.artifacts.chat);
+    // The message being sent is already the last logged turn.
+    if turns.last().is_some_and(|turn| turn.assistant.is_none()) {
+        turns.pop();
+    }
+    let mut history = Vec::new();
+    let mut history_len = 0;
+    for turn in turns.iter().rev().take(strategy.recent_turns()) {
+        let entry = format!(
+            "User: {}\nAssistant: {}\n",
+            turn.user,
+            turn.assistant.as_deref().unwrap_or("(no reply)")
+        );
+        if context.len() + history_len + entry.len() > max_history_chars {
+            break;
+        }
+        history_len += entry.len();
+        history.push(entry);
+    }
+    if !history.is_empty() {
+        context.push_str("Recent conversation:\n");
+        for entry in history.iter().rev() {
+            context.push_str(entry);
+        }
+        context.push('\n');
+    }
 
     if let Some(diff) = &state.artifacts.diff {
         context.push_str("Current Diff:\n");
         'outer: for file in &diff.files {
             let file_header = format!("File: {} ({:?})\n", file.path, file.status);
-            if context.len() + file_header.len() > MAX_CONTEXT_CHARS {
+            if context.len() + file_header.len() > max_context_chars {
                 context.push_str("... (truncated)\n");
                 break 'outer;
             }
             context.push_str(&file_header);
 
             for hunk in &file.hunks {
-                if context.len() + hunk.header.len() + 1 > MAX_CONTEXT_CHARS {
+                if context.len() + hunk.header.len() + 1 > max_context_chars {
                     context.push_str("... (truncated)\n");
                     break 'outer;
                 }
                 context.push_str(&hunk.header);
                 context.push('\n');
                 for line in &hunk.lines {
-                    if context.len() + line.text.len() + 1 > MAX_CONTEXT_CHARS {
+                    if context.len() + line.text.len() + 1 > max_context_chars {
                         context.push_str("... (truncated)\n");
                         break 'outer;
                     }
@@ -1094,35 +1691,77 @@ fn parse_shell_tab(input: &str) -> Option<super::state::ShellTab> {
         "7" | "explain" => Some(super::state::ShellTab::Explain),
         "8" | "logs" => Some(super::state::ShellTab::Logs),
         "9" | "files" | "file" | "filebrowser" => Some(super::state::ShellTab::FileBrowser),
+        "10" | "approvals" | "approval" => Some(super::state::ShellTab::Approvals),
         _ => None,
     }
 }
 
-fn parse_theme(input: &str) -> Option<super::state::UiTheme> {
+fn parse_policy_tier(input: &str) -> Option<PolicyTier> {
     match input.trim().to_ascii_lowercase().as_str() {
-        "classic" => Some(super::state::UiTheme::Classic),
-        "cyberpunk" => Some(super::state::UiTheme::Cyberpunk),
-        "neon-noir" | "neonnoir" => Some(super::state::UiTheme::NeonNoir),
-        "solar-flare" | "solarflare" => Some(super::state::UiTheme::SolarFlare),
-        "forest-zen" | "forestzen" => Some(super::state::UiTheme::ForestZen),
+        "strict" => Some(PolicyTier::Strict),
+        "balanced" => Some(PolicyTier::Balanced),
+        "permissive" => Some(PolicyTier::Permissive),
+        _ => None,
+    }
+}
+
+fn parse_risk_class(input: &str) -> Option<ApprovalRiskClass> {
+    match input.trim().to_ascii_lowercase().as_str() {
+        "read" | "readonly" | "read-only" => Some(ApprovalRiskClass::ReadOnly),
+        "edit" | "patch" | "patchonly" | "patch-only" => Some(ApprovalRiskClass::PatchOnly),
+        "refactor" => Some(ApprovalRiskClass::Refactor),
+        "exec" | "execution" => Some(ApprovalRiskClass::Execution),
+        "destructive" => Some(ApprovalRiskClass::Destructive),
         _ => None,
     }
 }
 
 fn latest_assistant_text(state: &ShellState) -> Option<String> {
-    state.artifacts.logs.iter().rev().find_map(|entry| {
-        entry
-            .message
-            .strip_prefix("[assistant] ")
-            .map(|s| s.to_string())
-    })
+    state
+        .artifacts
+        .chat
+        .iter()
+        .rev()
+        .find(|entry| entry.role == ChatRole::Assistant)
+        .map(|entry| entry.text.clone())
 }
 
-fn full_diff_text(state: &ShellState) -> Option<String> {
-    let diff = state.artifacts.diff.as_ref()?;
+pub fn full_diff_text(state: &ShellState) -> Option<String> {
+    let out = diff_artifact_text(state.artifacts.diff.as_ref()?);
+    if out.trim().is_empty() {
+        None
+    } else {
+        Some(out)
+    }
+}
+
+/// Renders a diff artifact as unified diff text.
+pub fn diff_artifact_text(diff: &DiffArtifact) -> String {
     let mut out = String::new();
     for file in &diff.files {
-        out.push_str(&format!("--- {} ({:?})\n", file.path, file.status));
+        let old_path = match (file.status, &file.old_path) {
+            (DiffFileStatus::Added, _) => "/dev/null".to_string(),
+            (_, Some(old_path)) => format!("a/{old_path}"),
+            _ => format!("a/{}", file.path),
+        };
+        if let Some(old) = &file.old_path {
+            out.push_str(&format!(
+                "diff --git a/{old} b/{new}\nrename from {old}\nrename to {new}\n",
+                new = file.path
+            ));
+        }
+        let new_path = match file.status {
+            DiffFileStatus::Deleted => "/dev/null".to_string(),
+            _ => format!("b/{}", file.path),
+        };
+        if file.binary {
+            out.push_str(&format!("Binary files {old_path} and {new_path} differ\n"));
+            continue;
+        }
+        if file.old_path.is_some() && file.hunks.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("--- {old_path}\n+++ {new_path}\n"));
         for hunk in &file.hunks {
             out.push_str(&hunk.header);
             out.push('\n');
@@ -1131,24 +1770,78 @@ fn full_diff_text(state: &ShellState) -> Option<String> {
                 out.push('\n');
             }
         }
-        out.push('\n');
     }
-    if out.trim().is_empty() {
-        None
-    } else {
-        Some(out)
+    out
+}
+
+/// Renders a plan artifact as a numbered list of steps.
+pub fn plan_artifact_text(plan: &PlanArtifact) -> String {
+    let mut out = format!("{}\n", plan.title);
+    for (idx, step) in plan.steps.iter().enumerate() {
+        out.push_str(&format!(
+            "{}. [{:?}] {}\n",
+            idx + 1,
+            step.status,
+            step.label
+        ));
+    }
+    if !plan.assumptions.is_empty() {
+        out.push_str("Assumptions:\n");
+        for assumption in &plan.assumptions {
+            out.push_str(&format!("- {assumption}\n"));
+        }
+    }
+    push_artifact_error(&mut out, plan.error.as_ref());
+    out
+}
+
+/// Renders a verify artifact as a status/check table.
+pub fn verify_artifact_text(verify: &VerifyArtifact) -> String {
+    let mut out = format!("overall: {:?}\n", verify.overall);
+    out.push_str(&format!("{:<8} CHECK\n", "STATUS"));
+    for check in &verify.checks {
+        out.push_str(&format!(
+            "{:<8} {}\n",
+            format!("{:?}", check.status),
+            check.name
+        ));
+        if let Some(details) = check.details.as_deref() {
+            for line in details.lines() {
+                out.push_str(&format!("{:<8}   {line}\n", ""));
+            }
+        }
+    }
+    push_artifact_error(&mut out, verify.error.as_ref());
+    out
+}
+
+/// Renders a system artifact as its summary followed by detected facts.
+pub fn system_artifact_text(system: &SystemArtifact) -> String {
+    let mut out = format!("{}\n", system.summary);
+    for (label, values) in [
+        ("stack", &system.detected_stack),
+        ("entrypoints", &system.entrypoints),
+        ("risk flags", &system.risk_flags),
+    ] {
+        if !values.is_empty() {
+            out.push_str(&format!("{label}: {}\n", values.join(", ")));
+        }
+    }
+    push_artifact_error(&mut out, system.error.as_ref());
+    out
+}
+
+fn push_artifact_error(out: &mut String, error: Option<&ArtifactError>) {
+    if let Some(error) = error {
+        out.push_str(&format!("error ({:?}): {}\n", error.kind, error.message));
     }
 }
 
 fn full_chat_text(state: &ShellState) -> Option<String> {
     let mut out = String::new();
-    for entry in state
-        .artifacts
-        .logs
-        .iter()
-        .filter(|e| e.source == LogSource::Shell || e.source == LogSource::Runtime)
-    {
-        out.push_str(&entry.message);
+    for entry in state.artifacts.chat.iter() {
+        out.push_str(entry.role.prefix());
+        out.push_str(&entry.text);
         out.push('\n');
     }
     if out.trim().is_empty() {
@@ -1186,7 +1879,7 @@ fn command_to_effects(state: &mut ShellState, command: PaletteCommand) -> Vec<Da
             Vec::new()
         }
         PaletteCommand::CycleTheme => {
-            state.customization.theme = state.customization.theme.next();
+            state.customization.theme = state.cycled_theme(true);
             Vec::new()
         }
         PaletteCommand::ToggleJourneyPanel => {
@@ -1211,6 +1904,8 @@ fn command_to_effects(state: &mut ShellState, command: PaletteCommand) -> Vec<Da
         PaletteCommand::OpenApprovals => {
             vec![DaoEffect::EmitHostEvent(DaoHostEvent::OpenApprovalsPopup)]
         }
+        PaletteCommand::ApprovePending => vec![DaoEffect::ApprovePending],
+        PaletteCommand::RerunVerify => request_tool_run(state, ToolId::Verify),
         PaletteCommand::OpenSkills => {
             vec![DaoEffect::EmitHostEvent(DaoHostEvent::OpenSkillsList)]
         }
@@ -1221,25 +1916,153 @@ fn command_to_effects(state: &mut ShellState, command: PaletteCommand) -> Vec<Da
     }
 }
 
-fn tab_for_journey(state: JourneyState) -> super::state::ShellTab {
-    match state {
-        JourneyState::Idle => super::state::ShellTab::Chat,
-        JourneyState::Scanning => super::state::ShellTab::System,
-        JourneyState::Planning => super::state::ShellTab::Plan,
-        JourneyState::Diffing | JourneyState::ReviewReady => super::state::ShellTab::Diff,
-        JourneyState::AwaitingApproval | JourneyState::Verifying | JourneyState::Failed => {
-            super::state::ShellTab::Logs
+/// Resolves the pending request as approved at `timestamp_ms`, then starts
+/// the tool that was waiting on it.
+fn approve_pending(state: &mut ShellState, timestamp_ms: u64) -> Vec<DaoEffect> {
+    let Some(request) = state
+        .approval
+        .pending
+        .as_ref()
+        .map(|pending| pending.request.clone())
+    else {
+        reduce_runtime(
+            state,
+            RuntimeAction::AppendLog("[meta] No pending approval to approve".to_string()),
+        );
+        return Vec::new();
+    };
+    reduce_runtime(
+        state,
+        RuntimeAction::ResolveApproval(ApprovalDecisionRecord {
+            request_id: request.request_id.clone(),
+            run_id: request.run_id,
+            action: request.action,
+            decision: ApprovalDecisionKind::Approved,
+            timestamp_ms,
+        }),
+    );
+    reduce_runtime(
+        state,
+        RuntimeAction::AppendLog(format!(
+            "[meta] Approved request {} for run {}",
+            request.request_id, request.run_id
+        )),
+    );
+    let mut effects = match state.approval.queued_tool.take() {
+        Some((request_id, tool_id)) if request_id == request.request_id => {
+            start_tool_run(state, tool_id, request.run_id)
+        }
+        _ => Vec::new(),
+    };
+    effects.push(DaoEffect::PersistState);
+    effects
+}
+
+/// Runs `tool_id` from the UI the way a workflow step would: refused while
+/// another run is in progress, then through the policy gate, waiting for
+/// `/approve` when the gate requires it.
+fn request_tool_run(state: &mut ShellState, tool_id: ToolId) -> Vec<DaoEffect> {
+    let tool = tool_id.as_str();
+    if state.run_in_progress() {
+        reduce_runtime(
+            state,
+            RuntimeAction::AppendLog(format!(
+                "[meta] A run is in progress; {tool} was not started"
+            )),
+        );
+        return Vec::new();
+    }
+    let run_id = state.current_run_id().max(1);
+    let spec = ToolRegistry::get(tool_id);
+    if !tier_satisfies(state.approval.policy_tier, spec.min_tier) {
+        reduce_runtime(
+            state,
+            RuntimeAction::AppendLog(format!(
+                "[meta] {tool} needs the {} policy tier or above",
+                spec.min_tier.label()
+            )),
+        );
+        return Vec::new();
+    }
+    assess_policy_gate(
+        state,
+        run_id,
+        ApprovalAction::Execute,
+        spec.risk_class,
+        format!("re-run {tool}"),
+    );
+    let Some(gate) = state.approval.last_gate.clone() else {
+        return Vec::new();
+    };
+    match gate.requirement {
+        ApprovalGateRequirement::Allow => start_tool_run(state, tool_id, run_id),
+        ApprovalGateRequirement::Deny => {
+            reduce_runtime(
+                state,
+                RuntimeAction::AppendLog(format!("[meta] {tool} blocked: {}", gate.reason)),
+            );
+            Vec::new()
+        }
+        ApprovalGateRequirement::RequireApproval => {
+            let request_id = format!("tool-{run_id}-{}", state.approval.next_request_seq);
+            reduce_runtime(
+                state,
+                RuntimeAction::RequestApproval(ApprovalRequestRecord {
+                    request_id: request_id.clone(),
+                    run_id,
+                    action: ApprovalAction::Execute,
+                    risk: gate.risk,
+                    reason: gate.reason.clone(),
+                    preview: format!("re-run {tool}"),
+                    created_at_ms: None,
+                }),
+            );
+            // Keep the assessed gate, which may come from a review policy.
+            state.approval.last_gate = Some(gate);
+            if !state
+                .approval
+                .pending
+                .as_ref()
+                .is_some_and(|pending| pending.request.request_id == request_id)
+            {
+                return Vec::new();
+            }
+            state.approval.queued_tool = Some((request_id, tool_id));
+            reduce_runtime(
+                state,
+                RuntimeAction::AppendLog(format!(
+                    "[meta] {tool} requires approval; /approve to run it"
+                )),
+            );
+            vec![DaoEffect::PersistState]
         }
-        JourneyState::Completed => super::state::ShellTab::Explain,
     }
 }
 
+/// Marks `tool_id` as running and hands it to the UI loop, which clears the
+/// flag when the result arrives.
+fn start_tool_run(state: &mut ShellState, tool_id: ToolId, run_id: u64) -> Vec<DaoEffect> {
+    reduce_runtime(
+        state,
+        RuntimeAction::SetRuntimeFlag {
+            flag: RuntimeFlag::for_tool(tool_id),
+            active: true,
+            run_id,
+        },
+    );
+    reduce_runtime(
+        state,
+        RuntimeAction::AppendLog(format!("[meta] Running {}", tool_id.as_str())),
+    );
+    vec![DaoEffect::RunTool(GatedToolRun { tool_id, run_id })]
+}
+
 fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
     let mut dirty = false;
 
     match action {
         RuntimeAction::SetProjectName(name) => {
-            state.header.project_name = name.into();
+            state.header.project_name = name;
         }
         RuntimeAction::SetThreadId(thread_id) => {
             state.thread_id = thread_id;
@@ -1368,7 +2191,8 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
             dirty = true;
             state.journey_status.error = None;
         }
-        RuntimeAction::SetSystemArtifact(artifact) => {
+        RuntimeAction::SetSystemArtifact(mut artifact) => {
+            artifact.normalize();
             let current = state
                 .artifacts
                 .system
@@ -1376,11 +2200,6 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
                 .map(|a| (a.run_id, a.artifact_id));
             if artifact_is_newer(artifact.run_id, artifact.artifact_id, current) {
                 state.artifacts.system = Some(artifact);
-                if matches!(state.routing.tab, super::state::ShellTab::Overview)
-                    && state.customization.auto_follow_intent
-                {
-                    state.routing.tab = super::state::ShellTab::System;
-                }
                 dirty = true;
             }
         }
@@ -1393,13 +2212,6 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
             if artifact_is_newer(artifact.run_id, artifact.artifact_id, current) {
                 state.artifacts.plan = Some(artifact);
                 reconcile_selected_plan_step(state);
-                if matches!(
-                    state.routing.tab,
-                    super::state::ShellTab::Overview | super::state::ShellTab::System
-                ) && state.customization.auto_follow_intent
-                {
-                    state.routing.tab = super::state::ShellTab::Plan;
-                }
                 dirty = true;
             }
         }
@@ -1411,8 +2223,8 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
                 .map(|a| (a.run_id, a.artifact_id));
             if artifact_is_newer(artifact.run_id, artifact.artifact_id, current) {
                 state.artifacts.diff = Some(artifact);
-                reconcile_selected_diff_file(state);
-                maybe_follow_tab(state, super::state::ShellTab::Diff);
+                state.file_browser.diff = None;
+                reset_diff_view(state);
                 dirty = true;
             }
         }
@@ -1439,6 +2251,8 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
         RuntimeAction::ClearDiffArtifact(_) => {
             state.artifacts.diff = None;
             state.selection.selected_diff_file = None;
+            state.selection.hunk_expansions.clear();
+            state.selection.collapsed_diff_files.clear();
             dirty = true;
         }
         RuntimeAction::ClearVerifyArtifact(_) => {
@@ -1470,9 +2284,14 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
         }
         RuntimeAction::SetPolicyTier(tier) => {
             state.approval.policy_tier = tier;
+            reassess_last_gate(state);
+        }
+        RuntimeAction::SetRiskOverride(risk) => {
+            state.approval.risk_override = risk;
+            reassess_last_gate(state);
         }
         RuntimeAction::SetReviewPolicy(policy) => {
-            state.approval.active_policy = Some(policy);
+            state.approval.active_policy = Some(*policy);
         }
         RuntimeAction::AssessPolicyGate {
             run_id,
@@ -1480,131 +2299,7 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
             risk,
             reason,
         } => {
-            if let Some(policy) = &state.approval.active_policy {
-                let signals = Signals {
-                    diff_files_changed: state
-                        .artifacts
-                        .diff
-                        .as_ref()
-                        .map(|d| d.files.len())
-                        .unwrap_or(0),
-                    diff_lines_added: state
-                        .artifacts
-                        .diff
-                        .as_ref()
-                        .map(|d| {
-                            d.files
-                                .iter()
-                                .flat_map(|f| f.hunks.iter())
-                                .flat_map(|h| h.lines.iter())
-                                .filter(|l| l.kind == DiffLineKind::Add)
-                                .count()
-                        })
-                        .unwrap_or(0),
-                    diff_lines_deleted: state
-                        .artifacts
-                        .diff
-                        .as_ref()
-                        .map(|d| {
-                            d.files
-                                .iter()
-                                .flat_map(|f| f.hunks.iter())
-                                .flat_map(|h| h.lines.iter())
-                                .filter(|l| l.kind == DiffLineKind::Remove)
-                                .count()
-                        })
-                        .unwrap_or(0),
-                    risk_class: risk.label().to_string(),
-                    diff_file_names: state
-                        .artifacts
-                        .diff
-                        .as_ref()
-                        .map(|d| {
-                            d.files
-                                .iter()
-                                .map(|f| f.path.clone())
-                                .collect::<Vec<_>>()
-                                .join("\n")
-                        })
-                        .unwrap_or_default(),
-                    commit_message: reason.clone(),
-                    diff_added_content: state
-                        .artifacts
-                        .diff
-                        .as_ref()
-                        .map(|d| {
-                            d.files
-                                .iter()
-                                .flat_map(|f| f.hunks.iter())
-                                .flat_map(|h| h.lines.iter())
-                                .filter_map(|l| {
-                                    if l.kind == DiffLineKind::Add {
-                                        Some(l.text.clone())
-                                    } else {
-                                        None
-                                    }
-                                })
-                                .collect::<Vec<_>>()
-                                .join("\n")
-                        })
-                        .unwrap_or_default(),
-                    new_file_contents: state
-                        .artifacts
-                        .diff
-                        .as_ref()
-                        .map(|d| {
-                            d.files
-                                .iter()
-                                .filter(|f| f.status == DiffFileStatus::Added)
-                                .map(|f| {
-                                    f.hunks
-                                        .iter()
-                                        .flat_map(|h| h.lines.iter())
-                                        .map(|l| l.text.clone())
-                                        .collect::<Vec<_>>()
-                                        .join("\n")
-                                })
-                                .collect()
-                        })
-                        .unwrap_or_default(),
-                    new_file_paths: state
-                        .artifacts
-                        .diff
-                        .as_ref()
-                        .map(|d| {
-                            d.files
-                                .iter()
-                                .filter(|f| f.status == DiffFileStatus::Added)
-                                .map(|f| f.path.clone())
-                                .collect()
-                        })
-                        .unwrap_or_default(),
-                };
-
-                let decision: PolicyDecision = policy.evaluate(&signals);
-                let requirement = match decision.decision {
-                    DecisionOutcome::Allowed => ApprovalGateRequirement::Allow,
-                    DecisionOutcome::Blocked => ApprovalGateRequirement::Deny,
-                    DecisionOutcome::ApprovalRequired => ApprovalGateRequirement::RequireApproval,
-                };
-
-                state.approval.last_gate = Some(PolicyGateState {
-                    run_id,
-                    action,
-                    risk,
-                    requirement,
-                    reason: decision.message,
-                });
-            } else {
-                let requirement = policy_requirement_for_risk(state.approval.policy_tier, risk);
-                state.approval.last_gate = Some(PolicyGateState {
-                    run_id,
-                    action,
-                    risk,
-                    requirement,
-                    reason,
-                });
-            }
+            assess_policy_gate(state, run_id, action, risk, reason);
         }
         RuntimeAction::RequestApproval(mut request) => {
             let run_id = request.run_id.max(1);
@@ -1626,14 +2321,27 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
             if run_id >= latest_approval_run_id {
                 dirty = true;
                 request.run_id = run_id;
-                let requirement =
-                    policy_requirement_for_risk(state.approval.policy_tier, request.risk);
+                let requirement = state
+                    .config
+                    .policy
+                    .tiers
+                    .requirement(state.approval.policy_tier, request.risk);
+                // A request raised from a gate keeps that gate's unadjusted risk.
+                let base_risk = state
+                    .approval
+                    .last_gate
+                    .as_ref()
+                    .filter(|gate| gate.run_id == run_id && gate.action == request.action)
+                    .and_then(|gate| gate.base_risk)
+                    .unwrap_or(request.risk);
                 state.approval.last_gate = Some(PolicyGateState {
                     run_id,
                     action: request.action,
                     risk: request.risk,
+                    base_risk: Some(base_risk),
                     requirement,
                     reason: request.reason.clone(),
+                    matched_rule: None,
                 });
                 let sequence = state.approval.next_request_seq;
                 state.approval.next_request_seq = state.approval.next_request_seq.saturating_add(1);
@@ -1642,6 +2350,7 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
                 state.runtime_flags.awaiting_approval.run_id = run_id;
                 state.artifacts.logs.append(LogEntry {
                     seq: 0,
+                    id: 0,
                     level: LogLevel::Warn,
                     ts_ms: None,
                     source: LogSource::Shell,
@@ -1657,31 +2366,32 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
                     && pending.request.run_id == decision.run_id
                 {
                     dirty = true;
-                    state.approval.pending = None;
+                    state.approval.last_resolved = state.approval.pending.take();
+                    state.approval.history.push(decision.clone());
                     state.approval.last_decision = Some(decision.clone());
+                    close_approval_preview(state);
                     if state.runtime_flags.awaiting_approval.run_id == decision.run_id {
                         state.runtime_flags.awaiting_approval.active = false;
                     }
+                    let decided_gate = state.approval.last_gate.as_ref().filter(|gate| {
+                        gate.run_id == decision.run_id && gate.action == decision.action
+                    });
                     state.approval.last_gate = Some(PolicyGateState {
                         run_id: decision.run_id,
                         action: decision.action,
-                        risk: state
-                            .approval
-                            .last_gate
-                            .as_ref()
-                            .filter(|gate| {
-                                gate.run_id == decision.run_id && gate.action == decision.action
-                            })
-                            .map_or(ApprovalRiskClass::Execution, |gate| gate.risk),
+                        risk: decided_gate.map_or(ApprovalRiskClass::Execution, |gate| gate.risk),
+                        base_risk: decided_gate.and_then(|gate| gate.base_risk),
                         requirement: ApprovalGateRequirement::Allow,
                         reason: format!(
                             "request {} {}",
                             decision.request_id,
                             decision.decision.label()
                         ),
+                        matched_rule: None,
                     });
                     state.artifacts.logs.append(LogEntry {
                         seq: 0,
+                        id: 0,
                         level: LogLevel::Info,
                         ts_ms: Some(decision.timestamp_ms),
                         source: LogSource::Shell,
@@ -1696,12 +2406,77 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
                 }
             }
         }
+        RuntimeAction::ReopenApproval { request_id } => {
+            let reopenable = state.approval.pending.is_none()
+                && state
+                    .approval
+                    .last_decision
+                    .as_ref()
+                    .is_some_and(|decision| {
+                        decision.request_id == request_id
+                            && decision.decision == ApprovalDecisionKind::Denied
+                    })
+                && state
+                    .approval
+                    .last_resolved
+                    .as_ref()
+                    .is_some_and(|resolved| resolved.request.request_id == request_id);
+            if !reopenable {
+                reduce_runtime(
+                    state,
+                    RuntimeAction::AppendLog(format!(
+                        "[meta] Cannot reopen approval {request_id}: it is not the latest denied request."
+                    )),
+                );
+                return;
+            }
+            let Some(pending) = state.approval.last_resolved.take() else {
+                return;
+            };
+            dirty = true;
+            let run_id = pending.request.run_id;
+            let action = pending.request.action;
+            let base_risk = state
+                .approval
+                .last_gate
+                .as_ref()
+                .filter(|gate| gate.run_id == run_id && gate.action == action)
+                .and_then(|gate| gate.base_risk)
+                .unwrap_or(pending.request.risk);
+            let reason = pending.request.reason.clone();
+            state.approval.last_decision = None;
+            state.approval.pending = Some(pending);
+            assess_policy_gate(state, run_id, action, base_risk, reason);
+            state.runtime_flags.awaiting_approval.active = true;
+            state.runtime_flags.awaiting_approval.run_id = run_id;
+            state.artifacts.logs.append(LogEntry {
+                seq: 0,
+                id: 0,
+                level: LogLevel::Warn,
+                ts_ms: None,
+                source: LogSource::Shell,
+                context: Some("approval".to_string()),
+                message: format!("approval request {request_id} reopened for run {run_id}"),
+                run_id,
+            });
+            reduce_runtime(
+                state,
+                RuntimeAction::AppendLog(format!(
+                    "[meta] Reopened denied approval {request_id}; it is pending again."
+                )),
+            );
+        }
         RuntimeAction::ClearApprovalState(_) => {
             dirty = true;
             state.approval.pending = None;
             state.approval.last_decision = None;
             state.approval.last_gate = None;
+            state.approval.risk_override = None;
+            state.approval.history.clear();
+            state.approval.last_resolved = None;
+            state.approval.queued_tool = None;
             state.runtime_flags.awaiting_approval.active = false;
+            close_approval_preview(state);
         }
         RuntimeAction::AppendStructuredLog(entry) => {
             state.artifacts.logs.append(entry);
@@ -1709,9 +2484,17 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
         RuntimeAction::ClearLogs(_) => {
             state.artifacts.logs.clear();
         }
+        RuntimeAction::ClearChat(_) => {
+            state.artifacts.chat.clear();
+            state.interaction.chat_summary = None;
+        }
+        RuntimeAction::SetChatSummary(summary) => {
+            state.interaction.chat_summary = summary;
+        }
         RuntimeAction::SetOverview(value) => {
             state.artifacts.logs.append(LogEntry {
                 seq: 0,
+                id: 0,
                 level: LogLevel::Debug,
                 ts_ms: None,
                 source: LogSource::Shell,
@@ -1746,15 +2529,10 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
                 .lines()
                 .enumerate()
                 .filter_map(|(idx, line)| {
-                    let trimmed = line.trim();
-                    if trimmed.is_empty() {
+                    if line.trim().is_empty() {
                         None
                     } else {
-                        Some(PlanStep {
-                            id: format!("step-{}", idx + 1),
-                            label: trimmed.to_string(),
-                            status: StepStatus::Pending,
-                        })
+                        Some(PlanStep::from_line(idx + 1, line))
                     }
                 })
                 .collect();
@@ -1772,7 +2550,7 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
         RuntimeAction::SetDiff(value) => {
             let run_id = state.current_run_id().max(1);
             let artifact_id = next_diff_artifact_id(state);
-            let files = legacy_diff_files_from_text(value.as_str());
+            let files = diff_files_from_unified(value.as_str());
             let artifact = DiffArtifact {
                 schema_version: ARTIFACT_SCHEMA_V1,
                 run_id,
@@ -1783,9 +2561,21 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
             };
             reduce_runtime(state, RuntimeAction::SetDiffArtifact(artifact));
         }
+        RuntimeAction::SetBrowseDiff { path, text } => {
+            state.file_browser.diff = Some(DiffArtifact {
+                schema_version: ARTIFACT_SCHEMA_V1,
+                run_id: 0,
+                artifact_id: 0,
+                files: diff_files_from_unified(&text),
+                summary: format!("git diff of {path}"),
+                error: None,
+            });
+            reset_diff_view(state);
+        }
         RuntimeAction::SetExplain(value) => {
             state.artifacts.logs.append(LogEntry {
                 seq: 0,
+                id: 0,
                 level: LogLevel::Info,
                 ts_ms: None,
                 source: LogSource::Shell,
@@ -1795,8 +2585,14 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
             });
         }
         RuntimeAction::AppendLog(value) => {
+            if let Some((role, text)) = ChatRole::parse_prefixed(&value) {
+                let run_id = state.current_run_id();
+                state.artifacts.chat.append(role, text.to_string(), run_id);
+                return;
+            }
             state.artifacts.logs.append(LogEntry {
                 seq: 0,
+                id: 0,
                 level: LogLevel::Info,
                 ts_ms: None,
                 source: LogSource::Runtime,
@@ -1816,6 +2612,170 @@ fn reduce_runtime(state: &mut ShellState, action: RuntimeAction) {
     }
 }
 
+fn assess_policy_gate(
+    state: &mut ShellState,
+    run_id: u64,
+    action: ApprovalAction,
+    risk: ApprovalRiskClass,
+    reason: String,
+) {
+    let base_risk = risk;
+    let mut patterns = state.config.risk.sensitive_paths.clone();
+    if let Some(policy) = &state.approval.active_policy {
+        patterns.extend(policy.sensitive_paths.iter().cloned());
+    }
+    let sensitive_files = state
+        .artifacts
+        .diff
+        .as_ref()
+        .map(|d| d.sensitive_files(&patterns))
+        .unwrap_or_default();
+    // The note only goes on the gate's reason; policy rules see `reason` as
+    // the commit message unchanged.
+    let mut risk = risk;
+    let mut note = None;
+    if !sensitive_files.is_empty() {
+        risk = risk.max(ApprovalRiskClass::Execution);
+        note = Some(format!("sensitive paths: {}", sensitive_files.join(", ")));
+    }
+    let with_note = |message: String| match &note {
+        Some(note) if !message.contains(note.as_str()) => format!("{message} ({note})"),
+        _ => message,
+    };
+    let risk = state.approval.risk_override.unwrap_or(risk);
+    if let Some(policy) = &state.approval.active_policy {
+        let signals = Signals {
+            diff_files_changed: state
+                .artifacts
+                .diff
+                .as_ref()
+                .map_or(0, |d| d.files_changed()),
+            diff_lines_added: state.artifacts.diff.as_ref().map_or(0, |d| d.added_lines()),
+            diff_lines_deleted: state
+                .artifacts
+                .diff
+                .as_ref()
+                .map_or(0, |d| d.removed_lines()),
+            risk_class: risk.label().to_string(),
+            diff_file_names: state
+                .artifacts
+                .diff
+                .as_ref()
+                .map(|d| {
+                    d.files
+                        .iter()
+                        .flat_map(|f| f.paths())
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .unwrap_or_default(),
+            commit_message: reason.clone(),
+            diff_added_content: state
+                .artifacts
+                .diff
+                .as_ref()
+                .map(|d| {
+                    d.files
+                        .iter()
+                        .flat_map(|f| f.hunks.iter())
+                        .flat_map(|h| h.lines.iter())
+                        .filter_map(|l| {
+                            if l.kind == DiffLineKind::Add {
+                                Some(l.text.clone())
+                            } else {
+                                None
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .unwrap_or_default(),
+            new_file_contents: state
+                .artifacts
+                .diff
+                .as_ref()
+                .map(|d| {
+                    d.files
+                        .iter()
+                        .filter(|f| f.status == DiffFileStatus::Added)
+                        .map(|f| {
+                            f.hunks
+                                .iter()
+                                .flat_map(|h| h.lines.iter())
+                                .map(|l| l.text.clone())
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            new_file_paths: state
+                .artifacts
+                .diff
+                .as_ref()
+                .map(|d| {
+                    d.files
+                        .iter()
+                        .filter(|f| f.status == DiffFileStatus::Added)
+                        .map(|f| f.path.clone())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            sensitive_files: sensitive_files.join("\n"),
+        };
+
+        let decision: PolicyDecision = policy.evaluate(&signals);
+        let requirement = match decision.decision {
+            DecisionOutcome::Allowed => ApprovalGateRequirement::Allow,
+            DecisionOutcome::Blocked => ApprovalGateRequirement::Deny,
+            DecisionOutcome::ApprovalRequired => ApprovalGateRequirement::RequireApproval,
+        };
+
+        state.approval.last_gate = Some(PolicyGateState {
+            run_id,
+            action,
+            risk,
+            base_risk: Some(base_risk),
+            requirement,
+            matched_rule: decision.matched_rule_id,
+            reason: with_note(decision.message),
+        });
+    } else {
+        let requirement = state
+            .config
+            .policy
+            .tiers
+            .requirement(state.approval.policy_tier, risk);
+        state.approval.last_gate = Some(PolicyGateState {
+            run_id,
+            action,
+            risk,
+            base_risk: Some(base_risk),
+            requirement,
+            reason: with_note(reason),
+            matched_rule: None,
+        });
+    }
+}
+
+fn reassess_last_gate(state: &mut ShellState) {
+    if state.approval.active_policy.is_some() {
+        return;
+    }
+    let Some(gate) = state.approval.last_gate.clone() else {
+        return;
+    };
+    let resolved = state
+        .approval
+        .last_decision
+        .as_ref()
+        .is_some_and(|d| d.run_id == gate.run_id && d.action == gate.action);
+    if !resolved {
+        let risk = gate.base_risk.unwrap_or(gate.risk);
+        assess_policy_gate(state, gate.run_id, gate.action, risk, gate.reason);
+    }
+}
+
 fn recompute_journey(state: &mut ShellState) {
     let projection = derive_journey(
         &state.artifacts,
@@ -1828,7 +2788,18 @@ fn recompute_journey(state: &mut ShellState) {
     state.journey_status.active_run_id = projection.active_run_id;
     state.routing.journey = projection.step;
     if state.customization.auto_follow_intent {
-        state.routing.tab = tab_for_journey(projection.state);
+        if let Some(tab) = state.config.auto_open.tab_for(projection.state) {
+            state.routing.tab = tab;
+        }
+    }
+}
+
+fn close_approval_preview(state: &mut ShellState) {
+    if matches!(
+        state.interaction.overlay,
+        ShellOverlay::ApprovalPreview { .. }
+    ) {
+        state.interaction.overlay = ShellOverlay::None;
     }
 }
 
@@ -1869,8 +2840,79 @@ fn next_diff_artifact_id(state: &ShellState) -> u64 {
         .map_or(1, |a| a.artifact_id.saturating_add(1))
 }
 
+fn expand_hunk_context(state: &mut ShellState, file: &str, hunk: usize) -> Result<(), String> {
+    let diff_file = state
+        .shown_diff()
+        .and_then(|diff| diff.files.iter().find(|candidate| candidate.path == file))
+        .ok_or("not in the current diff")?;
+    if diff_file.status == DiffFileStatus::Deleted {
+        return Err("file was deleted".to_string());
+    }
+    let ranges = diff_file
+        .hunks
+        .iter()
+        .map(DiffHunk::new_range)
+        .collect::<Option<Vec<_>>>()
+        .ok_or("unrecognized hunk header")?;
+    let (first, count) = *ranges.get(hunk).ok_or("no such hunk")?;
+    let cwd = state.cwd.as_ref().ok_or("no working directory")?;
+    let text = std::fs::read_to_string(cwd.join(file)).map_err(|err| err.to_string())?;
+    let lines = text.lines().collect::<Vec<_>>();
+
+    // Never read into the neighbouring hunks.
+    let lower = hunk
+        .checked_sub(1)
+        .map_or(0, |prev| ranges[prev].0 + ranges[prev].1);
+    let upper = ranges
+        .get(hunk + 1)
+        .map_or(lines.len(), |next| next.0)
+        .min(lines.len());
+    let end = (first + count).min(upper);
+
+    let step = state.config.diff.expand_lines;
+    let expansions = &mut state.selection.hunk_expansions;
+    let idx = match expansions
+        .iter()
+        .position(|expansion| expansion.file == file && expansion.hunk == hunk)
+    {
+        Some(idx) => idx,
+        None => {
+            expansions.push(HunkExpansion {
+                file: file.to_string(),
+                hunk,
+                before: Vec::new(),
+                after: Vec::new(),
+            });
+            expansions.len() - 1
+        }
+    };
+    let expansion = &mut expansions[idx];
+    let before_start = first
+        .saturating_sub(expansion.before.len() + step)
+        .max(lower)
+        .min(first)
+        .min(lines.len());
+    let after_end = (end + expansion.after.len() + step).min(upper).max(end);
+    expansion.before = lines[before_start..first.min(lines.len())]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    expansion.after = lines[end..after_end]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+    Ok(())
+}
+
+/// Drops hunk expansions and stale file selections after the shown diff changes.
+fn reset_diff_view(state: &mut ShellState) {
+    state.selection.hunk_expansions.clear();
+    reconcile_selected_diff_file(state);
+    reconcile_collapsed_diff_files(state);
+}
+
 fn reconcile_selected_diff_file(state: &mut ShellState) {
-    let Some(diff) = state.artifacts.diff.as_ref() else {
+    let Some(diff) = state.shown_diff() else {
         state.selection.selected_diff_file = None;
         return;
     };
@@ -1893,6 +2935,18 @@ fn reconcile_selected_diff_file(state: &mut ShellState) {
     state.selection.selected_diff_file = diff.files.first().map(|file| file.path.clone());
 }
 
+fn reconcile_collapsed_diff_files(state: &mut ShellState) {
+    let Some(diff) = state.shown_diff() else {
+        state.selection.collapsed_diff_files.clear();
+        return;
+    };
+    let shown: Vec<String> = diff.files.iter().map(|file| file.path.clone()).collect();
+    state
+        .selection
+        .collapsed_diff_files
+        .retain(|path| shown.contains(path));
+}
+
 fn reconcile_selected_plan_step(state: &mut ShellState) {
     let Some(plan) = state.artifacts.plan.as_ref() else {
         state.selection.selected_plan_step = None;
@@ -1937,110 +2991,5 @@ fn reconcile_selected_plan_step(state: &mut ShellState) {
     state.selection.selected_plan_step = plan.steps.first().map(|step| step.id.clone());
 }
 
-fn legacy_diff_files_from_text(text: &str) -> Vec<DiffFile> {
-    let mut files = Vec::new();
-    let mut current_file: Option<DiffFile> = None;
-    let mut current_hunk: Option<DiffHunk> = None;
-
-    let finish_hunk = |file: &mut Option<DiffFile>, hunk: &mut Option<DiffHunk>| {
-        if let Some(hunk_value) = hunk.take() {
-            if let Some(file_value) = file.as_mut() {
-                file_value.hunks.push(hunk_value);
-            }
-        }
-    };
-
-    let finish_file =
-        |files: &mut Vec<DiffFile>, file: &mut Option<DiffFile>, hunk: &mut Option<DiffHunk>| {
-            finish_hunk(file, hunk);
-            if let Some(file_value) = file.take() {
-                files.push(file_value);
-            }
-        };
-
-    for line in text.lines() {
-        if let Some(path) = line.strip_prefix("+++ b/") {
-            finish_file(&mut files, &mut current_file, &mut current_hunk);
-            current_file = Some(DiffFile {
-                path: path.to_string(),
-                status: DiffFileStatus::Modified,
-                hunks: Vec::new(),
-            });
-            continue;
-        }
-
-        if let Some(path) = line.strip_prefix("+++ ") {
-            finish_file(&mut files, &mut current_file, &mut current_hunk);
-            current_file = Some(DiffFile {
-                path: path.to_string(),
-                status: DiffFileStatus::Modified,
-                hunks: Vec::new(),
-            });
-            continue;
-        }
-
-        if let Some(header) = line.strip_prefix("@@") {
-            finish_hunk(&mut current_file, &mut current_hunk);
-            current_hunk = Some(DiffHunk {
-                header: format!("@@{}", header),
-                lines: Vec::new(),
-            });
-            continue;
-        }
-
-        let kind = if line.starts_with('+') {
-            Some(DiffLineKind::Add)
-        } else if line.starts_with('-') {
-            Some(DiffLineKind::Remove)
-        } else if !line.is_empty() {
-            Some(DiffLineKind::Context)
-        } else {
-            None
-        };
-
-        if let Some(kind) = kind {
-            if current_hunk.is_none() {
-                current_hunk = Some(DiffHunk {
-                    header: "@@".to_string(),
-                    lines: Vec::new(),
-                });
-            }
-            if let Some(hunk) = current_hunk.as_mut() {
-                hunk.lines.push(DiffLine {
-                    kind,
-                    text: line.to_string(),
-                });
-            }
-        }
-    }
-
-    finish_file(&mut files, &mut current_file, &mut current_hunk);
-
-    if files.is_empty() {
-        files.push(DiffFile {
-            path: "<patch>".to_string(),
-            status: DiffFileStatus::Modified,
-            hunks: vec![DiffHunk {
-                header: "@@".to_string(),
-                lines: text
-                    .lines()
-                    .map(|line| DiffLine {
-                        kind: if line.starts_with('+') {
-                            DiffLineKind::Add
-                        } else if line.starts_with('-') {
-                            DiffLineKind::Remove
-                        } else {
-                            DiffLineKind::Context
-                        },
-                        text: line.to_string(),
-                    })
-                    .collect(),
-            }],
-        });
-    }
-
-    files
-}
-
 #[cfg(test)]
 mod tests;
@@ -223,3 +223,22 @@ fn older_cross_artifact_arrival_is_stored_but_not_active() {
     assert_eq!(state.journey_status.active_run_id, 12);
     assert_eq!(state.journey_status.state, JourneyState::ReviewReady);
 }
+
+#[test]
+fn system_artifact_lists_are_sorted_and_deduped() {
+    let mut state = state();
+    let mut artifact = system_artifact(1, 1, "scan");
+    artifact.detected_stack = vec!["rust".into(), "node".into(), "rust".into()];
+    artifact.entrypoints = vec![
+        "src/main.rs".into(),
+        "index.js".into(),
+        "src/main.rs".into(),
+    ];
+    artifact.risk_flags = vec!["dirty_worktree".into(), "dirty_worktree".into()];
+    run_runtime(&mut state, RuntimeAction::SetSystemArtifact(artifact));
+
+    let system = state.artifacts.system.as_ref().expect("system");
+    assert_eq!(system.detected_stack, vec!["node", "rust"]);
+    assert_eq!(system.entrypoints, vec!["index.js", "src/main.rs"]);
+    assert_eq!(system.risk_flags, vec!["dirty_worktree"]);
+}
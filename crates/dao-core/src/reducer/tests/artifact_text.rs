@@ -0,0 +1,203 @@
+use super::*;
+use crate::state::DiffHunk;
+use crate::state::DiffLine;
+use crate::state::DiffLineKind;
+use crate::state::VerifyCheck;
+use crate::state::VerifyCheckStatus;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn diff_text_uses_unified_headers_for_added_and_deleted_files() {
+    let mut modified = diff_file("src/lib.rs", DiffFileStatus::Modified);
+    modified.hunks.push(DiffHunk {
+        header: "@@ -1 +1 @@".to_string(),
+        lines: vec![
+            DiffLine {
+                kind: DiffLineKind::Remove,
+                text: "-old".to_string(),
+            },
+            DiffLine {
+                kind: DiffLineKind::Add,
+                text: "+new".to_string(),
+            },
+        ],
+    });
+    let diff = diff_artifact(
+        1,
+        1,
+        vec![
+            modified,
+            diff_file("new.txt", DiffFileStatus::Added),
+            diff_file("gone.txt", DiffFileStatus::Deleted),
+        ],
+    );
+
+    assert_eq!(
+        diff_artifact_text(&diff),
+        "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1 @@\n-old\n+new\n\
+         --- /dev/null\n+++ b/new.txt\n\
+         --- a/gone.txt\n+++ /dev/null\n"
+    );
+}
+
+#[test]
+fn diff_text_keeps_renames_through_a_reparse() {
+    let mut edited = diff_file("src/new.rs", DiffFileStatus::Renamed);
+    edited.old_path = Some("src/old.rs".to_string());
+    edited.hunks.push(DiffHunk {
+        header: "@@ -1 +1 @@".to_string(),
+        lines: vec![
+            DiffLine {
+                kind: DiffLineKind::Remove,
+                text: "-old".to_string(),
+            },
+            DiffLine {
+                kind: DiffLineKind::Add,
+                text: "+new".to_string(),
+            },
+        ],
+    });
+    let mut moved = diff_file("b.txt", DiffFileStatus::Renamed);
+    moved.old_path = Some("a.txt".to_string());
+    let text = diff_artifact_text(&diff_artifact(1, 1, vec![edited, moved]));
+
+    assert_eq!(
+        text,
+        "diff --git a/src/old.rs b/src/new.rs\nrename from src/old.rs\nrename to src/new.rs\n\
+         --- a/src/old.rs\n+++ b/src/new.rs\n@@ -1 +1 @@\n-old\n+new\n\
+         diff --git a/a.txt b/b.txt\nrename from a.txt\nrename to b.txt\n"
+    );
+    let reparsed: Vec<(String, Option<String>, DiffFileStatus)> =
+        crate::conversion::diff_files_from_unified(&text)
+            .into_iter()
+            .map(|file| (file.path, file.old_path, file.status))
+            .collect();
+    assert_eq!(
+        reparsed,
+        vec![
+            (
+                "src/new.rs".to_string(),
+                Some("src/old.rs".to_string()),
+                DiffFileStatus::Renamed
+            ),
+            (
+                "b.txt".to_string(),
+                Some("a.txt".to_string()),
+                DiffFileStatus::Renamed
+            ),
+        ]
+    );
+}
+
+#[test]
+fn plan_text_is_a_numbered_list() {
+    let mut plan = plan_artifact(
+        2,
+        1,
+        vec![
+            plan_step("scan", StepStatus::Done),
+            plan_step("edit", StepStatus::Running),
+        ],
+    );
+    plan.assumptions.push("tests exist".to_string());
+
+    assert_eq!(
+        plan_artifact_text(&plan),
+        "Plan\n1. [Done] scan\n2. [Running] edit\nAssumptions:\n- tests exist\n"
+    );
+}
+
+#[test]
+fn verify_text_is_a_check_table_with_error() {
+    let mut verify = verify_artifact(3, 1, VerifyOverall::Failing);
+    verify.checks.push(VerifyCheck {
+        name: "cargo test".to_string(),
+        status: VerifyCheckStatus::Fail,
+        details: Some("1 failed".to_string()),
+    });
+    verify.error = Some(ArtifactError {
+        kind: ErrorKind::Runtime,
+        message: "verification failed".to_string(),
+    });
+
+    assert_eq!(
+        verify_artifact_text(&verify),
+        "overall: Failing\nSTATUS   CHECK\nFail     cargo test\n           1 failed\n\
+         error (Runtime): verification failed\n"
+    );
+}
+
+#[test]
+fn browse_diff_is_shown_without_replacing_the_run_diff() {
+    let mut state = state();
+    let run_diff = diff_artifact(
+        4,
+        1,
+        vec![diff_file("src/lib.rs", DiffFileStatus::Modified)],
+    );
+    run_runtime(&mut state, RuntimeAction::SetDiffArtifact(run_diff));
+    let text = "diff --git a/README.md b/README.md\n\
+                index 1c2b3a4..5d6e7f8 100644\n\
+                --- a/README.md\n\
+                +++ b/README.md\n\
+                @@ -1 +1,2 @@\n \
+                fixture\n\
+                +changed\n";
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetBrowseDiff {
+            path: "README.md".to_string(),
+            text: text.to_string(),
+        },
+    );
+
+    let run_diff = state.artifacts.diff.as_ref().expect("run diff");
+    assert_eq!(
+        (run_diff.run_id, run_diff.files[0].path.as_str()),
+        (4, "src/lib.rs")
+    );
+    let diff = state.shown_diff().expect("browse diff should be shown");
+    assert_eq!(diff.summary, "git diff of README.md");
+    assert_eq!(diff.files.len(), 1);
+    let file = &diff.files[0];
+    assert_eq!(file.path, "README.md");
+    assert_eq!(file.hunks.len(), 1);
+    assert_eq!(file.hunks[0].header, "@@ -1 +1,2 @@");
+    let lines: Vec<(DiffLineKind, &str)> = file.hunks[0]
+        .lines
+        .iter()
+        .map(|line| (line.kind, line.text.as_str()))
+        .collect();
+    assert_eq!(
+        lines,
+        vec![
+            (DiffLineKind::Context, " fixture"),
+            (DiffLineKind::Add, "+changed"),
+        ]
+    );
+}
+
+#[test]
+fn leaving_the_diff_tab_closes_the_browse_diff() {
+    let mut state = state();
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetBrowseDiff {
+            path: "README.md".to_string(),
+            text: "--- a/README.md\n+++ b/README.md\n@@ -1 +1 @@\n-a\n+b\n".to_string(),
+        },
+    );
+    reduce(
+        &mut state,
+        ShellAction::User(UserAction::SelectTab(ShellTab::Diff)),
+    );
+    assert!(state.file_browser.diff.is_some());
+
+    reduce(
+        &mut state,
+        ShellAction::User(UserAction::SelectTab(ShellTab::Logs)),
+    );
+    assert!(state.file_browser.diff.is_none());
+    assert!(state.shown_diff().is_none());
+    assert_eq!(state.selection.selected_diff_file, None);
+}
@@ -0,0 +1,143 @@
+use super::*;
+use crate::chat_context::ChatContextStrategy;
+use crate::chat_context::ChatSummary;
+use pretty_assertions::assert_eq;
+
+fn log_exchange(state: &mut ShellState, question: &str, answer: &str) {
+    run_runtime(state, RuntimeAction::AppendLog(format!("> {question}")));
+    run_runtime(
+        state,
+        RuntimeAction::AppendLog(format!("[assistant] {answer}")),
+    );
+}
+
+fn submit(state: &mut ShellState, input: &str) -> Option<String> {
+    state.interaction.chat_input = input.to_string();
+    reduce(state, ShellAction::User(UserAction::ChatSubmit))
+        .into_iter()
+        .find_map(|effect| match effect {
+            DaoEffect::SubmitChat { context, .. } => Some(context),
+            _ => None,
+        })
+        .expect("chat submitted")
+}
+
+#[test]
+fn recent_strategy_sends_last_turns_without_current_message() {
+    let mut state = state();
+    state.config.chat.context = ChatContextStrategy::Recent { turns: 2 };
+    log_exchange(&mut state, "first?", "one");
+    log_exchange(&mut state, "second?", "two");
+    log_exchange(&mut state, "third?", "three");
+
+    let context = submit(&mut state, "fourth?").expect("context");
+    assert_eq!(
+        context,
+        "Recent conversation:\nUser: second?\nAssistant: two\nUser: third?\nAssistant: three\n\n"
+    );
+}
+
+#[test]
+fn diff_only_strategy_omits_history() {
+    let mut state = state();
+    state.config.chat.context = ChatContextStrategy::DiffOnly;
+    log_exchange(&mut state, "first?", "one");
+
+    assert_eq!(submit(&mut state, "second?"), None);
+}
+
+#[test]
+fn summarize_strategy_prepends_running_summary() {
+    let mut state = state();
+    state.config.chat.context = ChatContextStrategy::Summarize { turns: 1 };
+    log_exchange(&mut state, "first?", "one");
+    log_exchange(&mut state, "second?", "two");
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetChatSummary(Some(ChatSummary {
+            text: "- asked: first? | answered: one".to_string(),
+            covered_through: 1,
+        })),
+    );
+
+    let context = submit(&mut state, "third?").expect("context");
+    assert_eq!(
+        context,
+        "Earlier conversation (summary):\n- asked: first? | answered: one\n\n\
+         Recent conversation:\nUser: second?\nAssistant: two\n\n"
+    );
+}
+
+#[test]
+fn context_command_selects_strategy_and_resets_summary() {
+    let mut state = state();
+    state.interaction.chat_summary = Some(ChatSummary {
+        text: "old".to_string(),
+        covered_through: 5,
+    });
+
+    state.interaction.chat_input = "/context summarize 4".to_string();
+    let _ = reduce(&mut state, ShellAction::User(UserAction::ChatSubmit));
+    assert_eq!(
+        state.config.chat.context,
+        ChatContextStrategy::Summarize { turns: 4 }
+    );
+    assert_eq!(state.interaction.chat_summary, None);
+
+    state.interaction.chat_input = "/context recent".to_string();
+    let _ = reduce(&mut state, ShellAction::User(UserAction::ChatSubmit));
+    assert_eq!(
+        state.config.chat.context,
+        ChatContextStrategy::Recent { turns: 4 }
+    );
+
+    state.interaction.chat_input = "/context recent lots".to_string();
+    let _ = reduce(&mut state, ShellAction::User(UserAction::ChatSubmit));
+    assert_eq!(
+        state.config.chat.context,
+        ChatContextStrategy::Recent { turns: 4 }
+    );
+    assert!(state
+        .artifacts
+        .chat
+        .iter()
+        .any(|e| e.role == ChatRole::Meta && e.text.starts_with("Usage: /context")));
+}
+
+#[test]
+fn diff_context_truncates_at_the_configured_cap() {
+    let mut state = state();
+    state.config.chat.context = ChatContextStrategy::DiffOnly;
+    // Headers take 60 characters and each added line 8, so two lines fit exactly.
+    state.config.context.max_chars = 76;
+    let mut file = diff_file("src/lib.rs", DiffFileStatus::Modified);
+    file.hunks = vec![DiffHunk {
+        header: "@@ -1,20 +1,20 @@".to_string(),
+        lines: (0..20)
+            .map(|n| DiffLine {
+                kind: DiffLineKind::Add,
+                text: format!("+line {n}"),
+            })
+            .collect(),
+    }];
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetDiffArtifact(diff_artifact(1, 1, vec![file])),
+    );
+
+    let context = submit(&mut state, "review?").expect("context");
+    assert_eq!(
+        context,
+        "Current Diff:\nFile: src/lib.rs (Modified)\n@@ -1,20 +1,20 @@\n\
+         +line 0\n+line 1\n... (truncated)\n\n"
+    );
+
+    state.config.context.max_chars = 75;
+    let context = submit(&mut state, "review?").expect("context");
+    assert!(context.ends_with("+line 0\n... (truncated)\n\n"));
+
+    state.config.context.max_chars = 10_000;
+    let context = submit(&mut state, "review?").expect("context");
+    assert!(context.contains("+line 19\n"));
+    assert!(!context.contains("(truncated)"));
+}
@@ -0,0 +1,75 @@
+use super::*;
+use pretty_assertions::assert_eq;
+
+fn type_text(state: &mut ShellState, text: &str) {
+    for c in text.chars() {
+        reduce(state, ShellAction::User(UserAction::ChatInput(c)));
+    }
+}
+
+#[test]
+fn newline_is_inserted_without_submitting() {
+    let mut state = state();
+    type_text(&mut state, "first");
+    let effects = reduce(&mut state, ShellAction::User(UserAction::ChatInputNewline));
+    type_text(&mut state, "second");
+
+    assert!(!effects
+        .iter()
+        .any(|effect| matches!(effect, DaoEffect::SubmitChat { .. })));
+    assert_eq!(state.interaction.chat_input, "first\nsecond");
+}
+
+#[test]
+fn multi_line_messages_submit_whole_and_come_back_from_history() {
+    let mut state = state();
+    type_text(&mut state, "line one");
+    reduce(&mut state, ShellAction::User(UserAction::ChatInputNewline));
+    type_text(&mut state, "line two");
+
+    let message = reduce(&mut state, ShellAction::User(UserAction::ChatSubmit))
+        .into_iter()
+        .find_map(|effect| match effect {
+            DaoEffect::SubmitChat { message, .. } => Some(message),
+            _ => None,
+        })
+        .expect("chat submitted");
+    assert_eq!(message, "line one\nline two");
+    assert!(state.interaction.chat_input.is_empty());
+
+    reduce(&mut state, ShellAction::User(UserAction::ChatHistoryUp));
+    assert_eq!(state.interaction.chat_input, "line one\nline two");
+}
+
+#[test]
+fn delete_word_skips_trailing_spaces_then_removes_one_word() {
+    let mut state = state();
+    state.interaction.chat_input = "fix the  parser   ".to_string();
+    reduce(&mut state, ShellAction::User(UserAction::ChatDeleteWord));
+    assert_eq!(state.interaction.chat_input, "fix the  ");
+    reduce(&mut state, ShellAction::User(UserAction::ChatDeleteWord));
+    assert_eq!(state.interaction.chat_input, "fix ");
+    reduce(&mut state, ShellAction::User(UserAction::ChatDeleteWord));
+    assert_eq!(state.interaction.chat_input, "");
+}
+
+#[test]
+fn delete_word_and_clear_line_leave_empty_input_alone() {
+    let mut state = state();
+    reduce(&mut state, ShellAction::User(UserAction::ChatDeleteWord));
+    assert_eq!(state.interaction.chat_input, "");
+    reduce(&mut state, ShellAction::User(UserAction::ChatClearLine));
+    assert_eq!(state.interaction.chat_input, "");
+}
+
+#[test]
+fn clear_line_clears_only_the_line_being_typed() {
+    let mut state = state();
+    state.interaction.chat_input = "first line\nsecond".to_string();
+    reduce(&mut state, ShellAction::User(UserAction::ChatClearLine));
+    assert_eq!(state.interaction.chat_input, "first line\n");
+    reduce(&mut state, ShellAction::User(UserAction::ChatClearLine));
+    assert_eq!(state.interaction.chat_input, "first line");
+    reduce(&mut state, ShellAction::User(UserAction::ChatClearLine));
+    assert_eq!(state.interaction.chat_input, "");
+}
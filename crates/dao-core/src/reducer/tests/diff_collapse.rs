@@ -0,0 +1,101 @@
+use super::*;
+use pretty_assertions::assert_eq;
+
+fn toggle(state: &mut ShellState, path: &str) -> Vec<DaoEffect> {
+    reduce(
+        state,
+        ShellAction::User(UserAction::ToggleDiffFileCollapse {
+            path: path.to_string(),
+        }),
+    )
+}
+
+#[test]
+fn diff_file_collapse_toggles_on_and_off() {
+    let mut state = state();
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetDiffArtifact(diff_artifact(
+            1,
+            1,
+            vec![
+                diff_file("a.rs", DiffFileStatus::Modified),
+                diff_file("b.rs", DiffFileStatus::Modified),
+            ],
+        )),
+    );
+
+    assert_eq!(toggle(&mut state, "b.rs"), vec![DaoEffect::RequestFrame]);
+    toggle(&mut state, "a.rs");
+    assert_eq!(
+        state.selection.collapsed_diff_files,
+        vec!["b.rs".to_string(), "a.rs".to_string()]
+    );
+
+    toggle(&mut state, "b.rs");
+    assert_eq!(
+        state.selection.collapsed_diff_files,
+        vec!["a.rs".to_string()]
+    );
+    toggle(&mut state, "a.rs");
+    assert!(state.selection.collapsed_diff_files.is_empty());
+}
+
+#[test]
+fn collapsed_diff_files_follow_the_current_diff() {
+    let mut state = state();
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetDiffArtifact(diff_artifact(
+            1,
+            1,
+            vec![
+                diff_file("a.rs", DiffFileStatus::Modified),
+                diff_file("b.rs", DiffFileStatus::Modified),
+            ],
+        )),
+    );
+    toggle(&mut state, "a.rs");
+    toggle(&mut state, "b.rs");
+
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetDiffArtifact(diff_artifact(
+            1,
+            2,
+            vec![diff_file("b.rs", DiffFileStatus::Modified)],
+        )),
+    );
+    assert_eq!(
+        state.selection.collapsed_diff_files,
+        vec!["b.rs".to_string()]
+    );
+
+    run_runtime(
+        &mut state,
+        RuntimeAction::ClearDiffArtifact(ClearReason::UserRequest),
+    );
+    assert!(state.selection.collapsed_diff_files.is_empty());
+}
+
+#[test]
+fn diff_horizontal_scroll_clamps_at_the_left_edge() {
+    let mut state = state();
+    let scroll = |state: &mut ShellState, delta| {
+        reduce(
+            state,
+            ShellAction::User(UserAction::ScrollDiffHorizontal(delta)),
+        )
+    };
+
+    assert_eq!(scroll(&mut state, -8), vec![DaoEffect::RequestFrame]);
+    assert_eq!(state.selection.diff_h_scroll, 0);
+
+    scroll(&mut state, 8);
+    scroll(&mut state, 8);
+    assert_eq!(state.selection.diff_h_scroll, 16);
+    scroll(&mut state, -10);
+    assert_eq!(state.selection.diff_h_scroll, 6);
+    scroll(&mut state, -10);
+    assert_eq!(state.selection.diff_h_scroll, 0);
+}
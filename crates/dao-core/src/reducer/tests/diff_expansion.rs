@@ -0,0 +1,69 @@
+use super::*;
+use pretty_assertions::assert_eq;
+
+fn hunk(header: &str) -> DiffHunk {
+    DiffHunk {
+        header: header.to_string(),
+        lines: vec![DiffLine {
+            kind: DiffLineKind::Add,
+            text: "+changed".to_string(),
+        }],
+    }
+}
+
+fn expand(state: &mut ShellState, hunk: usize) {
+    reduce(
+        state,
+        ShellAction::User(UserAction::ExpandHunkContext {
+            file: "lib.rs".to_string(),
+            hunk,
+        }),
+    );
+}
+
+#[test]
+fn hunk_context_expands_from_the_working_tree_up_to_neighbouring_hunks() {
+    let repo = tempfile::tempdir().expect("tempdir");
+    let text = (1..=20).map(|n| format!("line {n}\n")).collect::<String>();
+    std::fs::write(repo.path().join("lib.rs"), text).expect("write file");
+
+    let mut state = state();
+    state.cwd = Some(repo.path().to_path_buf());
+    state.config.diff.expand_lines = 2;
+    let mut file = diff_file("lib.rs", DiffFileStatus::Modified);
+    file.hunks = vec![hunk("@@ -5 +5 @@"), hunk("@@ -9,1 +9,1 @@ fn main")];
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetDiffArtifact(diff_artifact(1, 1, vec![file])),
+    );
+
+    expand(&mut state, 1);
+    let expansion = &state.selection.hunk_expansions[0];
+    assert_eq!(expansion.before, vec!["line 7", "line 8"]);
+    assert_eq!(expansion.after, vec!["line 10", "line 11"]);
+
+    expand(&mut state, 1);
+    expand(&mut state, 1);
+    let expansion = &state.selection.hunk_expansions[0];
+    assert_eq!(expansion.before, vec!["line 6", "line 7", "line 8"]);
+    assert_eq!(expansion.after.len(), 6);
+    assert_eq!(expansion.after.last().map(String::as_str), Some("line 15"));
+
+    expand(&mut state, 0);
+    let first = &state.selection.hunk_expansions[1];
+    assert_eq!(first.before, vec!["line 3", "line 4"]);
+    assert_eq!(first.after, vec!["line 6", "line 7"]);
+
+    expand(&mut state, 4);
+    assert!(state
+        .artifacts
+        .chat
+        .iter()
+        .any(|entry| entry.text == "Cannot expand lib.rs: no such hunk"));
+
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetDiffArtifact(diff_artifact(1, 2, Vec::new())),
+    );
+    assert!(state.selection.hunk_expansions.is_empty());
+}
@@ -1,5 +1,3 @@
-use std::sync::Arc;
-
 use super::*;
 use pretty_assertions::assert_eq;
 
@@ -69,7 +67,7 @@ fn journey_projection_matches_after_runtime_mutations() {
         &mut state,
         RuntimeAction::SetJourneyErrorState(Some(JourneyError::new(
             ErrorKind::Runtime,
-            Arc::<str>::from("boom"),
+            "boom".to_string(),
             1,
         ))),
     );
@@ -106,6 +104,7 @@ fn append_log_does_not_change_journey_projection() {
         &mut state,
         RuntimeAction::AppendStructuredLog(LogEntry {
             seq: 0,
+            id: 0,
             level: LogLevel::Info,
             ts_ms: Some(1),
             source: LogSource::Runtime,
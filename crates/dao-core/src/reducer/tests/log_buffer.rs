@@ -67,3 +67,396 @@ fn changing_log_filter_does_not_mutate_log_ordering() {
         .collect();
     assert_eq!(before, after);
 }
+
+#[test]
+fn jump_log_run_boundary_walks_first_and_last_entries_of_each_run() {
+    let mut state = state();
+    for run_id in [1, 1, 1, 2, 3, 3] {
+        run_runtime(
+            &mut state,
+            RuntimeAction::AppendStructuredLog(LogEntry {
+                seq: 0,
+                id: 0,
+                level: LogLevel::Info,
+                ts_ms: None,
+                source: LogSource::Runtime,
+                context: None,
+                message: format!("run {run_id}"),
+                run_id,
+            }),
+        );
+    }
+    assert_eq!(
+        state.artifacts.logs.run_boundaries(&LogFilter::default()),
+        vec![0, 2, 3, 4, 5]
+    );
+
+    let mut visited = Vec::new();
+    loop {
+        let effects = reduce(
+            &mut state,
+            ShellAction::User(UserAction::JumpLogRunBoundary { forward: true }),
+        );
+        if effects.is_empty() {
+            break;
+        }
+        visited.push(state.selection.log_scroll);
+    }
+    assert_eq!(visited, vec![2, 3, 4, 5]);
+    assert!(!state.selection.log_stick_to_bottom);
+
+    reduce(
+        &mut state,
+        ShellAction::User(UserAction::JumpLogRunBoundary { forward: false }),
+    );
+    assert_eq!(state.selection.log_scroll, 4);
+}
+
+#[test]
+fn log_bookmarks_toggle_and_jump_between_marked_entries() {
+    let mut state = state();
+    for idx in 0..6 {
+        run_runtime(
+            &mut state,
+            RuntimeAction::AppendStructuredLog(LogEntry {
+                seq: 0,
+                id: 0,
+                level: if idx == 1 {
+                    LogLevel::Debug
+                } else {
+                    LogLevel::Info
+                },
+                ts_ms: None,
+                source: LogSource::Runtime,
+                context: None,
+                message: format!("line {idx}"),
+                run_id: 1,
+            }),
+        );
+    }
+    let ids = state
+        .artifacts
+        .logs
+        .iter()
+        .map(|entry| entry.id)
+        .collect::<Vec<_>>();
+
+    for idx in [4, 1, 3, 4] {
+        let effects = reduce(
+            &mut state,
+            ShellAction::User(UserAction::ToggleLogBookmark(ids[idx])),
+        );
+        assert!(effects.contains(&DaoEffect::PersistState));
+    }
+    assert_eq!(state.selection.log_bookmarks, vec![ids[1], ids[3]]);
+
+    reduce(
+        &mut state,
+        ShellAction::User(UserAction::ToggleLogBookmark(ids[5])),
+    );
+    state.selection.log_level_filter = Some(LogLevel::Info);
+    assert_eq!(
+        state.artifacts.logs.bookmark_positions(
+            &state.selection.log_filter(),
+            &state.selection.log_bookmarks
+        ),
+        vec![2, 4]
+    );
+
+    let mut visited = Vec::new();
+    while !reduce(
+        &mut state,
+        ShellAction::User(UserAction::JumpLogBookmark { forward: true }),
+    )
+    .is_empty()
+    {
+        visited.push(state.selection.log_scroll);
+    }
+    assert_eq!(visited, vec![2, 4]);
+    assert!(!state.selection.log_stick_to_bottom);
+
+    reduce(
+        &mut state,
+        ShellAction::User(UserAction::JumpLogBookmark { forward: false }),
+    );
+    assert_eq!(state.selection.log_scroll, 2);
+
+    let restored: ShellState =
+        serde_json::from_slice(&serde_json::to_vec(&state).expect("serialize")).expect("parse");
+    assert_eq!(
+        restored.selection.log_bookmarks,
+        state.selection.log_bookmarks
+    );
+}
+
+#[test]
+fn chat_lines_go_to_the_transcript_and_survive_log_clearing() {
+    let mut state = state();
+    for message in [
+        "> hello",
+        "[assistant] hi there",
+        "[meta] Tab: Diff",
+        "scan finished",
+    ] {
+        run_runtime(&mut state, RuntimeAction::AppendLog(message.to_string()));
+    }
+
+    let chat = state
+        .artifacts
+        .chat
+        .iter()
+        .map(|entry| (entry.role, entry.text.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        chat,
+        vec![
+            (ChatRole::User, "hello"),
+            (ChatRole::Assistant, "hi there"),
+            (ChatRole::Meta, "Tab: Diff"),
+        ]
+    );
+    let logs = state
+        .artifacts
+        .logs
+        .iter()
+        .map(|entry| entry.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(logs, vec!["scan finished"]);
+
+    run_runtime(
+        &mut state,
+        RuntimeAction::ClearLogs(ClearReason::UserRequest),
+    );
+    assert!(state.artifacts.logs.is_empty());
+    assert_eq!(state.artifacts.chat.iter().count(), 3);
+
+    run_runtime(
+        &mut state,
+        RuntimeAction::ClearChat(ClearReason::UserRequest),
+    );
+    assert!(state.artifacts.chat.is_empty());
+}
+
+#[test]
+fn state_saved_with_chat_in_the_log_buffer_is_migrated_on_load() {
+    let mut legacy = state();
+    for message in ["> hello", "scan finished", "[assistant] hi there"] {
+        legacy.artifacts.logs.append(LogEntry {
+            seq: 0,
+            id: 0,
+            level: LogLevel::Info,
+            ts_ms: None,
+            source: LogSource::Runtime,
+            context: None,
+            message: message.to_string(),
+            run_id: 2,
+        });
+    }
+    let mut saved = serde_json::to_value(&legacy).expect("serialize");
+    saved["artifacts"]
+        .as_object_mut()
+        .expect("artifacts")
+        .remove("chat");
+
+    let mut loaded: ShellState = serde_json::from_value(saved).expect("parse legacy state");
+    loaded.artifacts.migrate_schema().expect("migrate");
+
+    let chat = loaded
+        .artifacts
+        .chat
+        .iter()
+        .map(|entry| (entry.role, entry.text.as_str(), entry.run_id))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        chat,
+        vec![
+            (ChatRole::User, "hello", 2),
+            (ChatRole::Assistant, "hi there", 2),
+        ]
+    );
+    let logs = loaded
+        .artifacts
+        .logs
+        .iter()
+        .map(|entry| entry.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(logs, vec!["scan finished"]);
+}
+
+#[test]
+fn logs_text_search_combines_with_the_level_filter() {
+    let mut state = state();
+    for (level, message) in [
+        (LogLevel::Debug, "scan: reading Cargo.toml"),
+        (LogLevel::Info, "Scan complete"),
+        (LogLevel::Warn, "plan step skipped"),
+        (LogLevel::Error, "SCAN failed on src/lib.rs"),
+    ] {
+        run_runtime(
+            &mut state,
+            RuntimeAction::AppendStructuredLog(LogEntry {
+                seq: 0,
+                id: 0,
+                level,
+                ts_ms: None,
+                source: LogSource::Runtime,
+                context: None,
+                message: message.to_string(),
+                run_id: 1,
+            }),
+        );
+    }
+    let visible = |state: &ShellState| {
+        let filter = state.selection.log_filter();
+        state
+            .artifacts
+            .logs
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .map(|entry| entry.message.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let effects = reduce(
+        &mut state,
+        ShellAction::User(UserAction::SetLogsTextSearch("scan".to_string())),
+    );
+    assert_eq!(effects, vec![DaoEffect::RequestFrame]);
+    assert_eq!(visible(&state).len(), 3);
+    assert_eq!(state.selection.log_search, "");
+
+    reduce(
+        &mut state,
+        ShellAction::User(UserAction::SetLogLevelFilter(Some(LogLevel::Info))),
+    );
+    assert_eq!(
+        visible(&state),
+        vec![
+            "Scan complete".to_string(),
+            "SCAN failed on src/lib.rs".to_string()
+        ]
+    );
+
+    reduce(
+        &mut state,
+        ShellAction::User(UserAction::SetLogsTextSearch(String::new())),
+    );
+    assert_eq!(visible(&state).len(), 3);
+}
+
+#[test]
+fn entries_below_min_persist_level_are_shown_but_not_persisted() {
+    let mut config = Config::default();
+    config.logging.min_persist_level = LogLevel::Info;
+    let mut state = ShellState::new("project".to_string(), Personality::Friendly, config);
+    for level in [
+        LogLevel::Trace,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Warn,
+    ] {
+        run_runtime(
+            &mut state,
+            RuntimeAction::AppendStructuredLog(LogEntry {
+                seq: 0,
+                id: 0,
+                level,
+                ts_ms: None,
+                source: LogSource::Runtime,
+                context: None,
+                message: format!("{level:?}"),
+                run_id: 1,
+            }),
+        );
+    }
+
+    assert_eq!(state.artifacts.logs.len(), 4);
+
+    let saved: ShellState = serde_json::from_slice(&serde_json::to_vec(&state).expect("serialize"))
+        .expect("deserialize");
+    let stored: Vec<(u64, LogLevel)> = saved
+        .artifacts
+        .logs
+        .iter()
+        .map(|entry| (entry.seq, entry.level))
+        .collect();
+    assert_eq!(stored, vec![(2, LogLevel::Info), (4, LogLevel::Warn)]);
+}
+
+#[test]
+fn max_entries_bounds_the_buffer_and_keeps_the_newest() {
+    let mut config = Config::default();
+    config.logging.max_entries = 4;
+    let mut state = ShellState::new("project".to_string(), Personality::Friendly, config);
+    for n in 1..=10 {
+        run_runtime(&mut state, RuntimeAction::AppendLog(format!("entry {n}")));
+    }
+
+    assert_eq!(state.artifacts.logs.len(), 4);
+    let kept: Vec<(u64, String)> = state
+        .artifacts
+        .logs
+        .iter()
+        .map(|entry| (entry.seq, entry.message.clone()))
+        .collect();
+    assert_eq!(
+        kept,
+        vec![
+            (7, "entry 7".to_string()),
+            (8, "entry 8".to_string()),
+            (9, "entry 9".to_string()),
+            (10, "entry 10".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn shrinking_max_entries_evicts_the_oldest_without_resetting_seq() {
+    let mut state = state();
+    for n in 1..=6 {
+        run_runtime(&mut state, RuntimeAction::AppendLog(format!("entry {n}")));
+    }
+    state.artifacts.logs.set_cap(2);
+    run_runtime(&mut state, RuntimeAction::AppendLog("entry 7".to_string()));
+
+    let seqs: Vec<u64> = state.artifacts.logs.iter().map(|entry| entry.seq).collect();
+    assert_eq!(seqs, vec![6, 7]);
+}
+
+#[test]
+fn log_bookmarks_are_dropped_with_their_entries_and_never_reused() {
+    let mut state = state();
+    state.artifacts.logs.set_cap(3);
+    for idx in 0..3 {
+        run_runtime(&mut state, RuntimeAction::AppendLog(format!("line {idx}")));
+    }
+    let ids = state
+        .artifacts
+        .logs
+        .iter()
+        .map(|entry| entry.id)
+        .collect::<Vec<_>>();
+    for id in [ids[0], ids[2]] {
+        reduce(
+            &mut state,
+            ShellAction::User(UserAction::ToggleLogBookmark(id)),
+        );
+    }
+
+    run_runtime(&mut state, RuntimeAction::AppendLog("line 3".to_string()));
+    assert_eq!(state.selection.log_bookmarks, vec![ids[2]]);
+
+    run_runtime(
+        &mut state,
+        RuntimeAction::ClearLogs(ClearReason::UserRequest),
+    );
+    assert!(state.selection.log_bookmarks.is_empty());
+
+    run_runtime(
+        &mut state,
+        RuntimeAction::AppendLog("after clear".to_string()),
+    );
+    let entry = state.artifacts.logs.iter().next().expect("entry");
+    assert_eq!(entry.seq, 1);
+    assert!(entry.id > ids[2]);
+}
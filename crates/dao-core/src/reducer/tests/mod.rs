@@ -1,8 +1,13 @@
+pub(super) use crate::config::AutoOpenConfig;
+use crate::config::Config;
 use crate::state::Personality;
 use pretty_assertions::assert_eq;
 
 pub(super) use super::derive_journey;
+pub(super) use super::diff_artifact_text;
+pub(super) use super::plan_artifact_text;
 pub(super) use super::reduce;
+pub(super) use super::verify_artifact_text;
 pub(super) use crate::actions::RuntimeAction;
 pub(super) use crate::actions::RuntimeFlag;
 pub(super) use crate::actions::ShellAction;
@@ -12,13 +17,18 @@ pub(super) use crate::state::policy_requirement_for_risk;
 pub(super) use crate::state::ApprovalAction;
 pub(super) use crate::state::ApprovalDecisionKind;
 pub(super) use crate::state::ApprovalDecisionRecord;
+pub(super) use crate::state::ApprovalGateRequirement;
 pub(super) use crate::state::ApprovalRequestRecord;
 pub(super) use crate::state::ApprovalRiskClass;
 pub(super) use crate::state::ArtifactError;
+pub(super) use crate::state::ChatRole;
 pub(super) use crate::state::ClearReason;
 pub(super) use crate::state::DiffArtifact;
 pub(super) use crate::state::DiffFile;
 pub(super) use crate::state::DiffFileStatus;
+pub(super) use crate::state::DiffHunk;
+pub(super) use crate::state::DiffLine;
+pub(super) use crate::state::DiffLineKind;
 pub(super) use crate::state::ErrorKind;
 pub(super) use crate::state::ExplanationDepth;
 pub(super) use crate::state::JourneyError;
@@ -27,6 +37,7 @@ pub(super) use crate::state::JourneyState;
 pub(super) use crate::state::JourneyStep;
 pub(super) use crate::state::LogBuffer;
 pub(super) use crate::state::LogEntry;
+pub(super) use crate::state::LogFilter;
 pub(super) use crate::state::LogLevel;
 pub(super) use crate::state::LogSource;
 pub(super) use crate::state::PersonaOutputFormat;
@@ -44,15 +55,26 @@ pub(super) use crate::state::ARTIFACT_SCHEMA_V1;
 
 mod approvals_policy;
 mod artifact_guards;
+mod artifact_text;
 mod auth_commands;
+mod chat_context;
+mod chat_input;
+mod diff_collapse;
+mod diff_expansion;
 mod invariants;
 mod log_buffer;
 mod persona_projection;
+mod plan_dependencies;
 mod projection_matrix;
 mod selection_reconcile;
+mod verify_rerun;
 
 fn state() -> ShellState {
-    ShellState::new("project".to_string(), Personality::Friendly)
+    ShellState::new(
+        "project".to_string(),
+        Personality::Friendly,
+        Config::default(),
+    )
 }
 
 fn system_artifact(run_id: u64, artifact_id: u64, summary: &str) -> SystemArtifact {
@@ -106,7 +128,9 @@ fn verify_artifact(run_id: u64, artifact_id: u64, overall: VerifyOverall) -> Ver
 fn diff_file(path: &str, status: DiffFileStatus) -> DiffFile {
     DiffFile {
         path: path.to_string(),
+        old_path: None,
         status,
+        binary: false,
         hunks: Vec::new(),
     }
 }
@@ -116,6 +140,7 @@ fn plan_step(id: &str, status: StepStatus) -> PlanStep {
         id: id.to_string(),
         label: id.to_string(),
         status,
+        depends_on: Vec::new(),
     }
 }
 
@@ -1,4 +1,5 @@
 use super::*;
+use crate::config::Config;
 use crate::state::Personality;
 use pretty_assertions::assert_eq;
 
@@ -8,28 +9,39 @@ fn run_user(state: &mut ShellState, action: UserAction) {
 
 #[test]
 fn pragmatic_persona_defaults_to_chat_first_tab_priority() {
-    let state = ShellState::new("project".to_string(), Personality::Pragmatic);
+    let state = ShellState::new(
+        "project".to_string(),
+        Personality::Pragmatic,
+        Config::default(),
+    );
     assert_eq!(state.routing.tab, ShellTab::Chat);
     assert_eq!(
         state.ordered_tabs(),
         &[
             ShellTab::Chat,
+            ShellTab::Telemetry,
             ShellTab::Diff,
             ShellTab::Logs,
             ShellTab::Plan,
             ShellTab::System,
+            ShellTab::FileBrowser,
             ShellTab::Explain,
             ShellTab::Overview,
+            ShellTab::Approvals,
         ]
     );
 }
 
 #[test]
 fn next_prev_tab_follow_persona_ordering() {
-    let mut state = ShellState::new("project".to_string(), Personality::Pragmatic);
+    let mut state = ShellState::new(
+        "project".to_string(),
+        Personality::Pragmatic,
+        Config::default(),
+    );
 
     run_user(&mut state, UserAction::NextTab);
-    assert_eq!(state.routing.tab, ShellTab::Diff);
+    assert_eq!(state.routing.tab, ShellTab::Telemetry);
 
     run_user(&mut state, UserAction::PrevTab);
     assert_eq!(state.routing.tab, ShellTab::Chat);
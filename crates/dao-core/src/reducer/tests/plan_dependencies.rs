@@ -0,0 +1,195 @@
+use super::*;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn plan_lines_parse_trailing_dependencies() {
+    let mut state = state();
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetPlan(
+            "Read config\nAdd parser\nWire parser (after 1, 2)\nShip it (after 3)\nNote (aside)"
+                .to_string(),
+        ),
+    );
+
+    let plan = state.artifacts.plan.as_ref().expect("plan");
+    let labels = plan
+        .steps
+        .iter()
+        .map(|step| step.label.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        labels,
+        vec![
+            "Read config",
+            "Add parser",
+            "Wire parser",
+            "Ship it",
+            "Note (aside)"
+        ]
+    );
+    assert_eq!(plan.steps[2].depends_on, vec!["step-1", "step-2"]);
+    assert_eq!(plan.steps[3].depends_on, vec!["step-3"]);
+    assert_eq!(plan.dependency_depths(), vec![0, 0, 1, 2, 0]);
+}
+
+#[test]
+fn step_cannot_be_marked_done_before_its_dependencies() {
+    let mut state = state();
+    let mut second = plan_step("step-2", StepStatus::Pending);
+    second.depends_on = vec!["step-1".to_string()];
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetPlanArtifact(plan_artifact(
+            1,
+            1,
+            vec![plan_step("step-1", StepStatus::Pending), second],
+        )),
+    );
+
+    state.selection.selected_plan_step = Some("step-2".to_string());
+    reduce(
+        &mut state,
+        ShellAction::User(UserAction::TogglePlanStepDone),
+    );
+    let plan = state.artifacts.plan.as_ref().expect("plan");
+    assert_eq!(plan.steps[1].status, StepStatus::Pending);
+    assert!(state
+        .artifacts
+        .chat
+        .iter()
+        .any(|entry| entry.text == "step-2 waits on step-1"));
+
+    state.selection.selected_plan_step = Some("step-1".to_string());
+    reduce(
+        &mut state,
+        ShellAction::User(UserAction::TogglePlanStepDone),
+    );
+    state.selection.selected_plan_step = Some("step-2".to_string());
+    reduce(
+        &mut state,
+        ShellAction::User(UserAction::TogglePlanStepDone),
+    );
+    let plan = state.artifacts.plan.as_ref().expect("plan");
+    assert_eq!(plan.steps[0].status, StepStatus::Done);
+    assert_eq!(plan.steps[1].status, StepStatus::Done);
+}
+
+#[test]
+fn dependency_cycles_do_not_loop() {
+    let mut first = plan_step("step-1", StepStatus::Pending);
+    first.depends_on = vec!["step-2".to_string()];
+    let mut second = plan_step("step-2", StepStatus::Pending);
+    second.depends_on = vec!["step-1".to_string()];
+    let plan = plan_artifact(1, 1, vec![first, second]);
+
+    assert_eq!(plan.dependency_depths(), vec![2, 1]);
+}
+
+fn set_status(state: &mut ShellState, id: &str, status: StepStatus) {
+    reduce(
+        state,
+        ShellAction::User(UserAction::SetPlanStepStatus {
+            id: id.to_string(),
+            status,
+        }),
+    );
+}
+
+fn status_of(state: &ShellState, index: usize) -> StepStatus {
+    state.artifacts.plan.as_ref().expect("plan").steps[index].status
+}
+
+#[test]
+fn plan_step_status_can_be_set_by_hand() {
+    let mut state = state();
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetPlanArtifact(plan_artifact(
+            1,
+            1,
+            vec![
+                plan_step("step-1", StepStatus::Pending),
+                plan_step("step-2", StepStatus::Running),
+            ],
+        )),
+    );
+    // As after moving the selection by hand.
+    state.selection.plan_stick_to_running = false;
+    state.selection.selected_plan_step = Some("step-1".to_string());
+    let journey = state.journey_status.state;
+
+    set_status(&mut state, "step-1", StepStatus::Done);
+    assert_eq!(status_of(&state, 0), StepStatus::Done);
+    set_status(&mut state, "step-1", StepStatus::Failed);
+    assert_eq!(status_of(&state, 0), StepStatus::Failed);
+    set_status(&mut state, "step-1", StepStatus::Pending);
+    assert_eq!(status_of(&state, 0), StepStatus::Pending);
+    set_status(&mut state, "step-2", StepStatus::Done);
+    assert_eq!(status_of(&state, 1), StepStatus::Done);
+
+    assert_eq!(
+        state.selection.selected_plan_step.as_deref(),
+        Some("step-1")
+    );
+    assert_eq!(state.journey_status.state, journey);
+    assert!(state
+        .artifacts
+        .chat
+        .iter()
+        .any(|entry| entry.text == "step-1 marked failed"));
+}
+
+#[test]
+fn plan_step_status_respects_dependencies_and_unknown_ids() {
+    let mut state = state();
+    let mut second = plan_step("step-2", StepStatus::Pending);
+    second.depends_on = vec!["step-1".to_string()];
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetPlanArtifact(plan_artifact(
+            1,
+            1,
+            vec![plan_step("step-1", StepStatus::Pending), second],
+        )),
+    );
+
+    set_status(&mut state, "step-2", StepStatus::Done);
+    assert_eq!(status_of(&state, 1), StepStatus::Pending);
+    // Failing a step does not wait on its dependencies.
+    set_status(&mut state, "step-2", StepStatus::Failed);
+    assert_eq!(status_of(&state, 1), StepStatus::Failed);
+
+    set_status(&mut state, "step-9", StepStatus::Done);
+    assert_eq!(status_of(&state, 0), StepStatus::Pending);
+}
+
+#[test]
+fn copying_the_selected_plan_step_carries_id_status_and_label() {
+    let mut state = state();
+    let mut step = plan_step("step-2", StepStatus::Failed);
+    step.label = "Wire parser".to_string();
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetPlanArtifact(plan_artifact(
+            1,
+            1,
+            vec![plan_step("step-1", StepStatus::Done), step],
+        )),
+    );
+    state.selection.selected_plan_step = Some("step-2".to_string());
+
+    let effects = reduce(
+        &mut state,
+        ShellAction::User(UserAction::CopySelectedPlanStep),
+    );
+    assert!(matches!(
+        effects.first(),
+        Some(DaoEffect::CopyToClipboard(text)) if text == "step-2 [failed] Wire parser"
+    ));
+    assert!(state
+        .artifacts
+        .chat
+        .iter()
+        .any(|entry| entry.text == "Copied plan step to clipboard"));
+}
@@ -1,5 +1,3 @@
-use std::sync::Arc;
-
 use super::*;
 use pretty_assertions::assert_eq;
 
@@ -102,7 +100,7 @@ fn artifact_error_does_not_force_failed_journey() {
     let mut verify = verify_artifact(5, 1, VerifyOverall::Failing);
     verify.error = Some(ArtifactError {
         kind: ErrorKind::Runtime,
-        message: Arc::<str>::from("check failed"),
+        message: "check failed".to_string(),
     });
 
     run_runtime(&mut state, RuntimeAction::SetVerifyArtifact(verify));
@@ -115,3 +113,76 @@ fn artifact_error_does_not_force_failed_journey() {
         .and_then(|artifact| artifact.error.as_ref())
         .is_some());
 }
+
+#[test]
+fn auto_follow_uses_default_tab_for_each_journey_state() {
+    let mut state = state();
+    state.customization.auto_follow_intent = true;
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetRuntimeFlag {
+            flag: RuntimeFlag::Planning,
+            active: true,
+            run_id: 2,
+        },
+    );
+    assert_eq!(state.routing.tab, ShellTab::Plan);
+
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetRuntimeFlag {
+            flag: RuntimeFlag::Planning,
+            active: false,
+            run_id: 2,
+        },
+    );
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetDiffArtifact(diff_artifact(
+            2,
+            1,
+            vec![diff_file("a.rs", DiffFileStatus::Modified)],
+        )),
+    );
+    assert_eq!(state.routing.tab, ShellTab::Diff);
+}
+
+#[test]
+fn auto_follow_only_switches_for_configured_journey_states() {
+    let mut state = state();
+    state.customization.auto_follow_intent = true;
+    state.config.auto_open = AutoOpenConfig(
+        [(JourneyState::ReviewReady, ShellTab::Diff)]
+            .into_iter()
+            .collect(),
+    );
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetRuntimeFlag {
+            flag: RuntimeFlag::Diffing,
+            active: true,
+            run_id: 4,
+        },
+    );
+    assert_eq!(state.journey_status.state, JourneyState::Diffing);
+    assert_eq!(state.routing.tab, ShellTab::Chat);
+
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetRuntimeFlag {
+            flag: RuntimeFlag::Diffing,
+            active: false,
+            run_id: 4,
+        },
+    );
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetDiffArtifact(diff_artifact(
+            4,
+            1,
+            vec![diff_file("a.rs", DiffFileStatus::Modified)],
+        )),
+    );
+    assert_eq!(state.journey_status.state, JourneyState::ReviewReady);
+    assert_eq!(state.routing.tab, ShellTab::Diff);
+}
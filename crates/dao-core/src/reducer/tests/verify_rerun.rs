@@ -0,0 +1,170 @@
+use super::*;
+use crate::reducer::request_tool_run;
+use crate::reducer::GatedToolRun;
+use crate::tool_registry::ToolId;
+use pretty_assertions::assert_eq;
+
+fn run_verify_effect() -> DaoEffect {
+    DaoEffect::RunTool(GatedToolRun {
+        tool_id: ToolId::Verify,
+        run_id: 1,
+    })
+}
+
+fn submit(state: &mut ShellState, command: &str) -> Vec<DaoEffect> {
+    state.interaction.chat_input = command.to_string();
+    reduce(state, ShellAction::User(UserAction::ChatSubmit))
+}
+
+#[test]
+fn rerun_verify_palette_command_runs_verify_when_the_gate_allows_it() {
+    let mut state = state();
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetPolicyTier(PolicyTier::Permissive),
+    );
+    state.interaction.overlay = ShellOverlay::ActionPalette {
+        selected: 0,
+        query: "re-run verification".to_string(),
+    };
+
+    let effects = reduce(&mut state, ShellAction::User(UserAction::OverlaySubmit));
+
+    assert!(effects.contains(&run_verify_effect()));
+    assert_eq!(state.interaction.overlay, ShellOverlay::None);
+    assert!(state.runtime_flags.verifying.active);
+}
+
+#[test]
+fn verify_slash_command_waits_for_approval_under_balanced() {
+    let mut state = state();
+
+    let effects = submit(&mut state, "/verify");
+
+    assert!(!effects.contains(&run_verify_effect()));
+    let pending = state.approval.pending.as_ref().expect("pending approval");
+    assert_eq!(pending.request.risk, ApprovalRiskClass::Execution);
+    assert_eq!(
+        state.approval.queued_tool,
+        Some((pending.request.request_id.clone(), ToolId::Verify))
+    );
+    assert!(!state.runtime_flags.verifying.active);
+
+    let effects = submit(&mut state, "/approve");
+    assert!(effects.contains(&DaoEffect::ApprovePending));
+
+    let effects = reduce(
+        &mut state,
+        ShellAction::User(UserAction::ApprovePending {
+            timestamp_ms: 1_000,
+        }),
+    );
+
+    assert!(effects.contains(&run_verify_effect()));
+    assert!(state.approval.pending.is_none());
+    assert_eq!(state.approval.queued_tool, None);
+    assert!(state.runtime_flags.verifying.active);
+    assert!(state
+        .artifacts
+        .chat
+        .iter()
+        .any(|e| e.text.contains("Running verify")));
+}
+
+#[test]
+fn verify_is_refused_below_its_minimum_tier() {
+    let mut state = state();
+    run_runtime(&mut state, RuntimeAction::SetPolicyTier(PolicyTier::Strict));
+
+    let effects = submit(&mut state, "/verify");
+
+    assert_eq!(effects, vec![DaoEffect::RequestFrame]);
+    assert!(state.approval.pending.is_none());
+    assert!(!state.runtime_flags.verifying.active);
+}
+
+#[test]
+fn verify_is_refused_while_a_run_is_in_progress() {
+    let mut state = state();
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetPolicyTier(PolicyTier::Permissive),
+    );
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetRuntimeFlag {
+            flag: RuntimeFlag::Scanning,
+            active: true,
+            run_id: 1,
+        },
+    );
+
+    assert_eq!(submit(&mut state, "/verify"), vec![DaoEffect::RequestFrame]);
+
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetRuntimeFlag {
+            flag: RuntimeFlag::Scanning,
+            active: false,
+            run_id: 1,
+        },
+    );
+    assert!(submit(&mut state, "/verify").contains(&run_verify_effect()));
+    // The first run is still going, so a second one is refused.
+    assert_eq!(submit(&mut state, "/verify"), vec![DaoEffect::RequestFrame]);
+    assert!(state.artifacts.chat.iter().any(|e| e
+        .text
+        .contains("A run is in progress; verify was not started")));
+}
+
+#[test]
+fn every_tool_run_goes_through_the_gate() {
+    let mut balanced = state();
+
+    // run_tests executes build scripts, so it is gated like verify.
+    assert_eq!(
+        request_tool_run(&mut balanced, ToolId::RunTests),
+        vec![DaoEffect::PersistState]
+    );
+    assert!(!balanced.runtime_flags.verifying.active);
+    assert_eq!(
+        balanced
+            .approval
+            .queued_tool
+            .as_ref()
+            .map(|(_, tool_id)| *tool_id),
+        Some(ToolId::RunTests)
+    );
+
+    let mut state = state();
+    run_runtime(
+        &mut state,
+        RuntimeAction::SetPolicyTier(PolicyTier::Permissive),
+    );
+    assert_eq!(
+        request_tool_run(&mut state, ToolId::ScanRepo),
+        vec![DaoEffect::RunTool(GatedToolRun {
+            tool_id: ToolId::ScanRepo,
+            run_id: 1,
+        })]
+    );
+    assert!(state.runtime_flags.scanning.active);
+}
+
+#[test]
+fn help_lists_the_approval_commands() {
+    let mut state = state();
+
+    submit(&mut state, "/help");
+
+    let help = state
+        .artifacts
+        .chat
+        .iter()
+        .find(|entry| entry.text.contains("Commands:"))
+        .map(|entry| entry.text.clone())
+        .expect("help line");
+    for command in ["/verify", "/approve", "/reopen"] {
+        assert!(help.contains(command), "{command} missing from {help}");
+    }
+}
@@ -1,6 +1,10 @@
 #![allow(dead_code)]
+use crate::chat_context::ChatSummary;
 use crate::config::Config;
+use crate::config::LoggingConfig;
+use crate::config::RiskConfig;
 use crate::policy_engine::ReviewPolicy;
+use crate::tool_registry::ToolId;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::iter::DoubleEndedIterator;
@@ -11,6 +15,13 @@ pub struct FileBrowserState {
     pub current_path: PathBuf,
     pub entries: Vec<String>,
     pub selected: usize,
+    /// Reloaded on selection, so not saved to `state.json`.
+    #[serde(skip)]
+    pub preview: Option<FilePreview>,
+    /// `git diff` of one file, shown on the Diff tab in place of the run's
+    /// diff until another tab is selected. Never saved, and not part of a run.
+    #[serde(skip)]
+    pub diff: Option<DiffArtifact>,
 }
 
 impl Default for FileBrowserState {
@@ -19,16 +30,124 @@ impl Default for FileBrowserState {
             current_path: PathBuf::from("."),
             entries: Vec::new(),
             selected: 0,
+            preview: None,
+            diff: None,
+        }
+    }
+}
+
+impl FileBrowserState {
+    /// Re-reads `current_path`: directories first (with a trailing `/`), then
+    /// files, each sorted by name. `.git` is skipped.
+    pub fn refresh_entries(&mut self) {
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+        if let Ok(read) = std::fs::read_dir(&self.current_path) {
+            for entry in read.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name == ".git" {
+                    continue;
+                }
+                if entry.path().is_dir() {
+                    dirs.push(format!("{name}/"));
+                } else {
+                    files.push(name);
+                }
+            }
+        }
+        dirs.sort();
+        files.sort();
+        dirs.extend(files);
+        self.entries = dirs;
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        self.preview = None;
+    }
+
+    pub fn selected_path(&self) -> Option<PathBuf> {
+        let name = self.entries.get(self.selected)?;
+        Some(self.current_path.join(name))
+    }
+}
+
+/// Files larger than this are not previewed.
+pub const FILE_PREVIEW_MAX_BYTES: u64 = 256 * 1024;
+pub const FILE_PREVIEW_MAX_LINES: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FilePreview {
+    pub path: String,
+    pub lines: Vec<String>,
+    pub binary: bool,
+    pub too_large: bool,
+    pub truncated: bool,
+}
+
+impl FilePreview {
+    /// Reads the first `FILE_PREVIEW_MAX_LINES` lines of a regular file.
+    /// Returns `None` for directories and unreadable paths.
+    pub fn load(path: &std::path::Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        if !meta.is_file() {
+            return None;
+        }
+        let mut preview = Self {
+            path: path.display().to_string(),
+            lines: Vec::new(),
+            binary: false,
+            too_large: false,
+            truncated: false,
+        };
+        if meta.len() > FILE_PREVIEW_MAX_BYTES {
+            preview.too_large = true;
+            return Some(preview);
+        }
+        let bytes = std::fs::read(path).ok()?;
+        if looks_binary(&bytes) {
+            preview.binary = true;
+            return Some(preview);
         }
+        let text = String::from_utf8_lossy(&bytes);
+        let mut lines = text.lines();
+        preview.lines = lines
+            .by_ref()
+            .take(FILE_PREVIEW_MAX_LINES)
+            .map(str::to_string)
+            .collect();
+        preview.truncated = lines.next().is_some();
+        Some(preview)
     }
 }
 
+/// Treats content as binary when a NUL byte appears in the first 8 KiB,
+/// the same heuristic git uses.
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8 * 1024).any(|b| *b == 0)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Personality {
     Friendly,
     Pragmatic,
 }
 
+impl Personality {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Friendly => "friendly",
+            Self::Pragmatic => "pragmatic",
+        }
+    }
+
+    /// Parses a `label`, ignoring case and surrounding whitespace.
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "friendly" => Some(Self::Friendly),
+            "pragmatic" => Some(Self::Pragmatic),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ReasoningEffort {
     Low,
@@ -43,6 +162,41 @@ pub struct ThreadId(pub String);
 pub struct SchemaVersion(pub u16);
 
 pub const ARTIFACT_SCHEMA_V1: SchemaVersion = SchemaVersion(1);
+pub const CURRENT_ARTIFACT_SCHEMA: SchemaVersion = ARTIFACT_SCHEMA_V1;
+/// Layout version of the `ShellState` saved to `.dao/state.json`. Bump it when
+/// an older file would otherwise deserialize into the wrong state.
+pub const SHELL_STATE_SCHEMA: SchemaVersion = SchemaVersion(1);
+/// Version assumed for state files saved before `schema_version` was written;
+/// they have the v1 layout.
+pub const UNVERSIONED_SHELL_STATE_SCHEMA: SchemaVersion = SchemaVersion(1);
+pub const DEFAULT_LOG_ENTRIES: usize = 5_000;
+
+/// `(from, to)` upgrades applied when loading older artifacts. Add an entry
+/// when `CURRENT_ARTIFACT_SCHEMA` is bumped (new fields take their serde
+/// defaults); versions with no path to the current one are rejected.
+const ARTIFACT_SCHEMA_MIGRATIONS: &[(SchemaVersion, SchemaVersion)] = &[];
+
+fn migrate_schema(kind: &str, version: &mut SchemaVersion) -> Result<(), String> {
+    while *version != CURRENT_ARTIFACT_SCHEMA {
+        if version.0 > CURRENT_ARTIFACT_SCHEMA.0 {
+            return Err(format!(
+                "{kind} artifact uses schema v{} but this dao only understands up to v{}; upgrade dao",
+                version.0, CURRENT_ARTIFACT_SCHEMA.0
+            ));
+        }
+        *version = ARTIFACT_SCHEMA_MIGRATIONS
+            .iter()
+            .find(|(from, _)| from == version)
+            .map(|(_, to)| *to)
+            .ok_or_else(|| {
+                format!(
+                    "{kind} artifact uses unrecognized schema v{} (expected v{})",
+                    version.0, CURRENT_ARTIFACT_SCHEMA.0
+                )
+            })?;
+    }
+    Ok(())
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ClearReason {
@@ -63,6 +217,7 @@ pub enum ShellTab {
     Explain,
     Logs,
     FileBrowser,
+    Approvals,
 }
 
 impl ShellTab {
@@ -76,13 +231,14 @@ impl ShellTab {
             Self::Diff => Self::Explain,
             Self::Explain => Self::Logs,
             Self::Logs => Self::FileBrowser,
-            Self::FileBrowser => Self::Chat,
+            Self::FileBrowser => Self::Approvals,
+            Self::Approvals => Self::Chat,
         }
     }
 
     pub fn prev(self) -> Self {
         match self {
-            Self::Chat => Self::FileBrowser,
+            Self::Chat => Self::Approvals,
             Self::Overview => Self::Chat,
             Self::Telemetry => Self::Overview,
             Self::System => Self::Telemetry,
@@ -91,6 +247,7 @@ impl ShellTab {
             Self::Explain => Self::Diff,
             Self::Logs => Self::Explain,
             Self::FileBrowser => Self::Logs,
+            Self::Approvals => Self::FileBrowser,
         }
     }
 
@@ -105,6 +262,7 @@ impl ShellTab {
             Self::Explain => "Explain",
             Self::Logs => "Logs",
             Self::FileBrowser => "File Browser",
+            Self::Approvals => "Approvals",
         }
     }
 }
@@ -121,6 +279,38 @@ pub enum JourneyStep {
 }
 
 impl JourneyStep {
+    /// Steps in the order the journey rail shows them, by `ordinal`.
+    pub fn all() -> &'static [JourneyStep] {
+        &[
+            Self::Idea,
+            Self::Understand,
+            Self::Plan,
+            Self::Preview,
+            Self::Approve,
+            Self::Verify,
+            Self::Learn,
+        ]
+    }
+
+    /// Position on the journey rail, from 0 for `Idea`.
+    pub fn ordinal(self) -> u8 {
+        match self {
+            Self::Idea => 0,
+            Self::Understand => 1,
+            Self::Plan => 2,
+            Self::Preview => 3,
+            Self::Approve => 4,
+            Self::Verify => 5,
+            Self::Learn => 6,
+        }
+    }
+
+    /// Share of the journey reached with this step as the current one.
+    pub fn progress_percent(self) -> u8 {
+        let total = Self::all().len() as u16;
+        ((u16::from(self.ordinal()) + 1) * 100 / total) as u8
+    }
+
     pub fn label(self) -> &'static str {
         match self {
             Self::Idea => "Idea",
@@ -132,9 +322,22 @@ impl JourneyStep {
             Self::Learn => "Learn",
         }
     }
+
+    /// One-word name the journey rail shows; `label` is the longer header form.
+    pub fn rail_label(self) -> &'static str {
+        match self {
+            Self::Idea => "Idea",
+            Self::Understand => "Understand",
+            Self::Plan => "Plan",
+            Self::Preview => "Preview",
+            Self::Approve => "Approve",
+            Self::Verify => "Verify",
+            Self::Learn => "Learn",
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum JourneyState {
     Idle,
     Scanning,
@@ -294,7 +497,7 @@ impl RiskLevel {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum PolicyTier {
     Strict,
     Balanced,
@@ -309,6 +512,14 @@ impl PolicyTier {
             Self::Permissive => "permissive",
         }
     }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Strict => Self::Balanced,
+            Self::Balanced => Self::Permissive,
+            Self::Permissive => Self::Strict,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -330,7 +541,7 @@ impl ApprovalAction {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum ApprovalRiskClass {
     ReadOnly,
     PatchOnly,
@@ -414,8 +625,15 @@ pub struct PolicyGateState {
     pub run_id: u64,
     pub action: ApprovalAction,
     pub risk: ApprovalRiskClass,
+    /// Risk the gate was assessed from, before sensitive paths or `/risk`
+    /// changed it; `None` for gates saved before it was kept.
+    #[serde(default)]
+    pub base_risk: Option<ApprovalRiskClass>,
     pub requirement: ApprovalGateRequirement,
     pub reason: String,
+    /// Review policy rule that decided the gate, if a policy is active and a rule matched.
+    #[serde(default)]
+    pub matched_rule: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -426,6 +644,18 @@ pub struct ApprovalState {
     pub last_decision: Option<ApprovalDecisionRecord>,
     pub last_gate: Option<PolicyGateState>,
     pub next_request_seq: u64,
+    #[serde(default)]
+    pub risk_override: Option<ApprovalRiskClass>,
+    /// Every decision resolved this session, oldest first.
+    #[serde(default)]
+    pub history: Vec<ApprovalDecisionRecord>,
+    /// The request behind `last_decision`, kept so a denial can be reopened.
+    #[serde(default)]
+    pub last_resolved: Option<PendingApproval>,
+    /// Tool run started from the UI that waits on the pending request with
+    /// this id, and starts once it is approved.
+    #[serde(default)]
+    pub queued_tool: Option<(String, ToolId)>,
 }
 
 impl Default for ApprovalState {
@@ -437,6 +667,10 @@ impl Default for ApprovalState {
             last_decision: None,
             last_gate: None,
             next_request_seq: 1,
+            risk_override: None,
+            history: Vec::new(),
+            last_resolved: None,
+            queued_tool: None,
         }
     }
 }
@@ -449,6 +683,7 @@ pub enum ShellOverlay {
     ConfirmReset,
     Help,
     ModelSelection { selected: usize },
+    ApprovalPreview { scroll: u16 },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -458,47 +693,58 @@ pub enum UiTheme {
     NeonNoir,
     SolarFlare,
     ForestZen,
-}
+    MidnightSlate,
+    /// Colors read from `[ui] custom_theme_path`.
+    Custom,
+}
+
+/// Every theme in cycle order, with its label and accent color name. Adding a
+/// theme means adding a row here and a style row in the UI.
+const UI_THEMES: &[(UiTheme, &str, &str)] = &[
+    (UiTheme::Classic, "classic", "cyan"),
+    (UiTheme::Cyberpunk, "cyberpunk", "magenta"),
+    (UiTheme::NeonNoir, "neon-noir", "light-blue"),
+    (UiTheme::SolarFlare, "solar-flare", "light-yellow"),
+    (UiTheme::ForestZen, "forest-zen", "light-green"),
+    (UiTheme::MidnightSlate, "midnight-slate", "slate-blue"),
+    (UiTheme::Custom, "custom", "custom"),
+];
 
 impl UiTheme {
+    pub fn all() -> impl Iterator<Item = Self> {
+        UI_THEMES.iter().map(|(theme, _, _)| *theme)
+    }
+
+    fn index(self) -> usize {
+        UI_THEMES
+            .iter()
+            .position(|(theme, _, _)| *theme == self)
+            .expect("every UiTheme has a row in UI_THEMES")
+    }
+
     pub fn label(self) -> &'static str {
-        match self {
-            Self::Classic => "classic",
-            Self::Cyberpunk => "cyberpunk",
-            Self::NeonNoir => "neon-noir",
-            Self::SolarFlare => "solar-flare",
-            Self::ForestZen => "forest-zen",
-        }
+        UI_THEMES[self.index()].1
+    }
+
+    /// Matches a label case-insensitively, with or without its dashes.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let wanted = name.trim().to_ascii_lowercase().replace(['-', '_'], "");
+        UI_THEMES
+            .iter()
+            .find(|(_, label, _)| label.replace('-', "") == wanted)
+            .map(|(theme, _, _)| *theme)
     }
 
     pub fn next(self) -> Self {
-        match self {
-            Self::Classic => Self::Cyberpunk,
-            Self::Cyberpunk => Self::NeonNoir,
-            Self::NeonNoir => Self::SolarFlare,
-            Self::SolarFlare => Self::ForestZen,
-            Self::ForestZen => Self::Classic,
-        }
+        UI_THEMES[(self.index() + 1) % UI_THEMES.len()].0
     }
 
     pub fn prev(self) -> Self {
-        match self {
-            Self::Classic => Self::ForestZen,
-            Self::Cyberpunk => Self::Classic,
-            Self::NeonNoir => Self::Cyberpunk,
-            Self::SolarFlare => Self::NeonNoir,
-            Self::ForestZen => Self::SolarFlare,
-        }
+        UI_THEMES[(self.index() + UI_THEMES.len() - 1) % UI_THEMES.len()].0
     }
 
     pub fn accent(self) -> &'static str {
-        match self {
-            Self::Classic => "cyan",
-            Self::Cyberpunk => "magenta",
-            Self::NeonNoir => "light-blue",
-            Self::SolarFlare => "light-yellow",
-            Self::ForestZen => "light-green",
-        }
+        UI_THEMES[self.index()].2
     }
 }
 
@@ -507,6 +753,8 @@ pub enum KeymapPreset {
     Standard,
     Mac,
     Windows,
+    /// `h/j/k/l` move between tabs and scroll, `gg`/`G` jump to top/bottom.
+    Vim,
 }
 
 impl KeymapPreset {
@@ -515,6 +763,7 @@ impl KeymapPreset {
             Self::Standard => "standard",
             Self::Mac => "mac",
             Self::Windows => "windows",
+            Self::Vim => "vim",
         }
     }
 
@@ -522,7 +771,8 @@ impl KeymapPreset {
         match self {
             Self::Standard => Self::Mac,
             Self::Mac => Self::Windows,
-            Self::Windows => Self::Standard,
+            Self::Windows => Self::Vim,
+            Self::Vim => Self::Standard,
         }
     }
 }
@@ -557,6 +807,8 @@ pub struct ShellInteraction {
     pub live_assistant_preview: String,
     #[serde(default)]
     pub stream_meta_enabled: bool,
+    #[serde(default)]
+    pub chat_summary: Option<ChatSummary>,
     #[serde(skip)]
     pub chat_history_index: Option<usize>,
 }
@@ -596,8 +848,13 @@ pub struct TelemetrySnapshot {
     pub gpu_mem_used_mb: Option<u64>,
     pub gpu_mem_total_mb: Option<u64>,
     pub gpu_status: Option<String>,
+    /// Chunks per second the last reply streamed at. Providers send several
+    /// tokens per chunk, so this is not a model token rate.
     pub tokens_per_second: Option<f32>,
+    /// Chunks the last reply streamed in.
     pub tokens_generated: Option<u64>,
+    #[serde(default)]
+    pub stream_bytes_per_second: Option<f32>,
     pub sample_ts_ms: Option<u64>,
 }
 
@@ -700,11 +957,57 @@ pub struct SystemArtifact {
     pub error: Option<ArtifactError>,
 }
 
+impl SystemArtifact {
+    /// Sorts and dedupes the detected lists so runs over the same tree compare equal.
+    pub fn normalize(&mut self) {
+        for list in [
+            &mut self.detected_stack,
+            &mut self.entrypoints,
+            &mut self.risk_flags,
+        ] {
+            list.sort();
+            list.dedup();
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlanStep {
     pub id: String,
     pub label: String,
     pub status: StepStatus,
+    /// Ids of steps that must be done before this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+}
+
+impl PlanStep {
+    /// Builds step `number` from a plan line. A trailing `(after 1, 3)` names
+    /// the steps it depends on and is removed from the label.
+    pub fn from_line(number: usize, line: &str) -> Self {
+        let line = line.trim();
+        let (label, depends_on) = match line
+            .strip_suffix(')')
+            .and_then(|rest| rest.rsplit_once("(after "))
+        {
+            Some((label, deps)) if !label.trim().is_empty() => {
+                let depends_on = deps
+                    .split(',')
+                    .map(|dep| dep.trim().trim_start_matches("step-"))
+                    .filter(|dep| !dep.is_empty() && dep.chars().all(|c| c.is_ascii_digit()))
+                    .map(|dep| format!("step-{dep}"))
+                    .collect::<Vec<_>>();
+                (label.trim_end(), depends_on)
+            }
+            _ => (line, Vec::new()),
+        };
+        Self {
+            id: format!("step-{number}"),
+            label: label.to_string(),
+            status: StepStatus::Pending,
+            depends_on,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -715,6 +1018,17 @@ pub enum StepStatus {
     Failed,
 }
 
+impl StepStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Done => "done",
+            Self::Failed => "failed",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlanArtifact {
     pub schema_version: SchemaVersion,
@@ -726,6 +1040,61 @@ pub struct PlanArtifact {
     pub error: Option<ArtifactError>,
 }
 
+impl PlanArtifact {
+    /// Dependencies of `step_id` that are not done yet. Unknown ids count as unmet.
+    pub fn unmet_dependencies(&self, step_id: &str) -> Vec<String> {
+        let Some(step) = self.steps.iter().find(|step| step.id == step_id) else {
+            return Vec::new();
+        };
+        step.depends_on
+            .iter()
+            .filter(|dep| {
+                !self
+                    .steps
+                    .iter()
+                    .any(|other| other.id == **dep && other.status == StepStatus::Done)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Depth of each step in the dependency graph, in plan order: steps with
+    /// no known dependencies sit at 0, others one below their deepest
+    /// dependency. Cycles are cut at the step that closes them.
+    pub fn dependency_depths(&self) -> Vec<usize> {
+        fn depth_of(
+            plan: &PlanArtifact,
+            idx: usize,
+            memo: &mut Vec<Option<usize>>,
+            visiting: &mut Vec<bool>,
+        ) -> usize {
+            if let Some(depth) = memo[idx] {
+                return depth;
+            }
+            if visiting[idx] {
+                return 0;
+            }
+            visiting[idx] = true;
+            let depth = plan.steps[idx]
+                .depends_on
+                .iter()
+                .filter_map(|dep| plan.steps.iter().position(|step| step.id == *dep))
+                .map(|dep| depth_of(plan, dep, memo, visiting) + 1)
+                .max()
+                .unwrap_or(0);
+            visiting[idx] = false;
+            memo[idx] = Some(depth);
+            depth
+        }
+
+        let mut memo = vec![None; self.steps.len()];
+        let mut visiting = vec![false; self.steps.len()];
+        (0..self.steps.len())
+            .map(|idx| depth_of(self, idx, &mut memo, &mut visiting))
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DiffFileStatus {
     Added,
@@ -753,11 +1122,74 @@ pub struct DiffHunk {
     pub lines: Vec<DiffLine>,
 }
 
+impl DiffHunk {
+    /// Zero-based index of the hunk's first line in the new file and its line
+    /// count, from a `@@ -a,b +c,d @@` header.
+    pub fn new_range(&self) -> Option<(usize, usize)> {
+        let new_side = self
+            .header
+            .split_whitespace()
+            .find_map(|part| part.strip_prefix('+'))?;
+        let (start, count) = match new_side.split_once(',') {
+            Some((start, count)) => (start.parse::<usize>().ok()?, count.parse().ok()?),
+            None => (new_side.parse::<usize>().ok()?, 1),
+        };
+        // An empty range names the line before it, so nothing is skipped.
+        let first = if count == 0 {
+            start
+        } else {
+            start.saturating_sub(1)
+        };
+        Some((first, count))
+    }
+}
+
+/// Extra context read from the working tree around one hunk of the diff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HunkExpansion {
+    pub file: String,
+    pub hunk: usize,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DiffFile {
     pub path: String,
+    /// Path before a rename; `None` for every other status.
+    #[serde(default)]
+    pub old_path: Option<String>,
     pub status: DiffFileStatus,
     pub hunks: Vec<DiffHunk>,
+    /// Git reported `Binary files ... differ`; such a file has no hunks.
+    #[serde(default)]
+    pub binary: bool,
+}
+
+impl DiffFile {
+    /// The file's path, then its path before a rename.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.path.as_str()).chain(self.old_path.as_deref())
+    }
+
+    pub fn added_lines(&self) -> usize {
+        self.count_lines(DiffLineKind::Add)
+    }
+
+    pub fn removed_lines(&self) -> usize {
+        self.count_lines(DiffLineKind::Remove)
+    }
+
+    fn count_lines(&self, kind: DiffLineKind) -> usize {
+        if self.binary {
+            return 0;
+        }
+        self.hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| line.kind == kind)
+            .count()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -770,36 +1202,129 @@ pub struct DiffArtifact {
     pub error: Option<ArtifactError>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskAssessment {
+    pub risk: ApprovalRiskClass,
+    pub score: f64,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub files_changed: usize,
+    pub deleted_files: Vec<String>,
+    pub sensitive_files: Vec<String>,
+    pub factors: Vec<String>,
+}
+
 impl DiffArtifact {
+    /// Line and file totals are computed on demand so older persisted
+    /// artifacts need no extra fields.
+    pub fn added_lines(&self) -> usize {
+        self.files.iter().map(DiffFile::added_lines).sum()
+    }
+
+    pub fn removed_lines(&self) -> usize {
+        self.files.iter().map(DiffFile::removed_lines).sum()
+    }
+
+    pub fn files_changed(&self) -> usize {
+        self.files.len()
+    }
+
     pub fn analyze_risk(&self) -> ApprovalRiskClass {
-        let mut added = 0;
-        let mut removed = 0;
-        let mut has_destructive_file_ops = false;
+        self.assess_risk(&RiskConfig::default()).risk
+    }
 
-        for file in &self.files {
-            if matches!(file.status, DiffFileStatus::Deleted) {
-                has_destructive_file_ops = true;
-            }
-            for hunk in &file.hunks {
-                for line in &hunk.lines {
-                    match line.kind {
-                        DiffLineKind::Add => added += 1,
-                        DiffLineKind::Remove => removed += 1,
-                        _ => {}
-                    }
-                }
+    /// Classifies the diff: deleted files are destructive (when enabled), more removed than
+    /// added lines is a refactor, anything else is patch-only. Touching a sensitive path
+    /// raises that to at least execution, and the weighted score from `RiskConfig` can
+    /// escalate further to execution or destructive.
+    pub fn assess_risk(&self, config: &RiskConfig) -> RiskAssessment {
+        let added = self.added_lines();
+        let removed = self.removed_lines();
+        let files_changed = self.files_changed();
+        let deleted_files: Vec<String> = self
+            .files
+            .iter()
+            .filter(|file| matches!(file.status, DiffFileStatus::Deleted))
+            .map(|file| file.path.clone())
+            .collect();
+        let sensitive_files = self.sensitive_files(&config.sensitive_paths);
+
+        let mut factors = vec![
+            format!("lines changed: +{added} -{removed}"),
+            format!("files changed: {files_changed}"),
+        ];
+        let mut risk = if config.deleted_files_destructive && !deleted_files.is_empty() {
+            factors.push(format!("deleted files: {}", deleted_files.join(", ")));
+            ApprovalRiskClass::Destructive
+        } else if removed > added {
+            factors.push("more lines removed than added".to_string());
+            ApprovalRiskClass::Refactor
+        } else {
+            ApprovalRiskClass::PatchOnly
+        };
+        if !sensitive_files.is_empty() {
+            factors.push(format!("sensitive paths: {}", sensitive_files.join(", ")));
+            if risk < ApprovalRiskClass::Execution {
+                factors.push("sensitive paths elevated risk to execution".to_string());
+                risk = ApprovalRiskClass::Execution;
             }
         }
 
-        if has_destructive_file_ops {
-            return ApprovalRiskClass::Destructive;
+        let score = (added + removed) as f64 * config.line_weight
+            + files_changed as f64 * config.file_weight
+            + sensitive_files.len() as f64 * config.sensitive_path_weight;
+        factors.push(format!(
+            "weighted score: {score:.1} (execution >= {:.1}, destructive >= {:.1})",
+            config.execution_threshold, config.destructive_threshold
+        ));
+        let escalated = if score >= config.destructive_threshold {
+            ApprovalRiskClass::Destructive
+        } else if score >= config.execution_threshold {
+            ApprovalRiskClass::Execution
+        } else {
+            risk
+        };
+        if escalated > risk {
+            factors.push(format!("score escalated risk to {}", escalated.label()));
+            risk = escalated;
         }
 
-        if removed > added {
-            return ApprovalRiskClass::Refactor;
+        RiskAssessment {
+            risk,
+            score,
+            lines_added: added,
+            lines_removed: removed,
+            files_changed,
+            deleted_files,
+            sensitive_files,
+            factors,
         }
+    }
+
+    pub fn sensitive_files(&self, patterns: &[String]) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|file| {
+                file.paths().any(|path| {
+                    patterns
+                        .iter()
+                        .any(|pattern| path_matches_sensitive(path, pattern))
+                })
+            })
+            .map(|file| file.path.clone())
+            .collect()
+    }
+}
 
-        ApprovalRiskClass::PatchOnly
+fn path_matches_sensitive(path: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return false;
+    }
+    if pattern.ends_with('/') {
+        path.starts_with(pattern) || path.contains(&format!("/{pattern}"))
+    } else {
+        path == pattern || path.ends_with(&format!("/{pattern}"))
     }
 }
 
@@ -838,10 +1363,15 @@ pub struct VerifyArtifact {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum LogLevel {
+    #[serde(alias = "trace")]
     Trace,
+    #[serde(alias = "debug")]
     Debug,
+    #[serde(alias = "info")]
     Info,
+    #[serde(alias = "warn")]
     Warn,
+    #[serde(alias = "error")]
     Error,
 }
 
@@ -855,6 +1385,9 @@ pub enum LogSource {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntry {
     pub seq: u64,
+    /// Set by `LogBuffer::append`; unlike `seq`, never reused after a clear.
+    #[serde(default)]
+    pub id: u64,
     pub level: LogLevel,
     pub ts_ms: Option<u64>,
     pub source: LogSource,
@@ -863,11 +1396,42 @@ pub struct LogEntry {
     pub run_id: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Entries below `min_level` are kept for display but left out when the
+/// buffer is serialized, so they never reach `state.json`.
+#[derive(Debug, Clone, Deserialize)]
 pub struct LogBuffer {
     cap: usize,
     next_seq: u64,
+    #[serde(default = "first_log_id")]
+    next_id: u64,
     buf: VecDeque<LogEntry>,
+    /// Comes from the config, which is applied again on load.
+    #[serde(skip)]
+    min_level: Option<LogLevel>,
+}
+
+impl Serialize for LogBuffer {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Persisted<'a> {
+            cap: usize,
+            next_seq: u64,
+            next_id: u64,
+            buf: Vec<&'a LogEntry>,
+        }
+
+        Persisted {
+            cap: self.cap,
+            next_seq: self.next_seq,
+            next_id: self.next_id,
+            buf: self.persisted().collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+fn first_log_id() -> u64 {
+    1
 }
 
 impl LogBuffer {
@@ -875,15 +1439,43 @@ impl LogBuffer {
         Self {
             cap,
             next_seq: 1,
+            next_id: first_log_id(),
             buf: VecDeque::with_capacity(cap),
+            min_level: None,
         }
     }
 
+    pub fn apply_config(&mut self, config: &LoggingConfig) {
+        self.set_cap(config.max_entries);
+        self.set_min_level(Some(config.min_persist_level));
+    }
+
+    /// Changes the capacity, evicting the oldest entries if it shrank.
+    pub fn set_cap(&mut self, cap: usize) {
+        self.cap = cap.max(1);
+        let excess = self.buf.len().saturating_sub(self.cap);
+        self.buf.drain(..excess);
+    }
+
+    /// Entries below `level` stay visible but are not persisted.
+    pub fn set_min_level(&mut self, level: Option<LogLevel>) {
+        self.min_level = level;
+    }
+
+    /// Entries at or above the persist level, the ones serialization keeps.
+    pub fn persisted(&self) -> impl Iterator<Item = &LogEntry> + '_ {
+        self.buf
+            .iter()
+            .filter(|entry| self.min_level.map_or(true, |min| entry.level >= min))
+    }
+
     pub fn append(&mut self, mut entry: LogEntry) {
         entry.seq = self.next_seq;
         self.next_seq += 1;
+        entry.id = self.next_id;
+        self.next_id += 1;
 
-        if self.buf.len() == self.cap {
+        while self.buf.len() >= self.cap.max(1) {
             self.buf.pop_front();
         }
         self.buf.push_back(entry);
@@ -901,6 +1493,162 @@ impl LogBuffer {
     pub fn is_empty(&self) -> bool {
         self.buf.is_empty()
     }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Line indexes of the first and last entry of each run, counted over the
+    /// entries `filter` keeps (the lines the Logs tab shows).
+    pub fn run_boundaries(&self, filter: &LogFilter) -> Vec<usize> {
+        let visible: Vec<u64> = self
+            .buf
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .map(|entry| entry.run_id)
+            .collect();
+        let mut boundaries = Vec::new();
+        for (idx, run_id) in visible.iter().enumerate() {
+            let starts = idx == 0 || visible[idx - 1] != *run_id;
+            let ends = visible.get(idx + 1) != Some(run_id);
+            if starts || ends {
+                boundaries.push(idx);
+            }
+        }
+        boundaries
+    }
+
+    /// Drops bookmarks whose entries have been evicted or cleared. Ids only
+    /// grow and eviction takes the oldest entries, so anything older than
+    /// the first entry is gone.
+    pub fn retain_bookmarks(&self, bookmarks: &mut Vec<u64>) {
+        let oldest = self.buf.front().map(|entry| entry.id);
+        bookmarks.retain(|id| oldest.is_some_and(|oldest| *id >= oldest));
+    }
+
+    /// Positions of bookmarked entries among those `filter` keeps.
+    pub fn bookmark_positions(&self, filter: &LogFilter, bookmarks: &[u64]) -> Vec<usize> {
+        self.buf
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .enumerate()
+            .filter(|(_, entry)| bookmarks.contains(&entry.id))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Keeps only the entries for which `keep` returns true.
+    pub fn retain(&mut self, keep: impl FnMut(&LogEntry) -> bool) {
+        self.buf.retain(keep);
+    }
+}
+
+/// The Logs tab filter: a minimum level plus a case-insensitive substring of
+/// the message. `text` is stored lowercased; empty matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogFilter {
+    pub min_level: Option<LogLevel>,
+    pub text: String,
+}
+
+impl LogFilter {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.min_level.map_or(true, |level| entry.level >= level)
+            && (self.text.is_empty() || entry.message.to_lowercase().contains(&self.text))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatRole {
+    User,
+    Assistant,
+    /// Shell feedback shown in the conversation, e.g. slash command replies.
+    Meta,
+}
+
+impl ChatRole {
+    /// Log-line prefix that `parse_prefixed` recognizes for this role.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Self::User => "> ",
+            Self::Assistant => "[assistant] ",
+            Self::Meta => "[meta] ",
+        }
+    }
+
+    /// Splits a chat-prefixed log line (`> `, `[assistant] `, `[meta] `) into
+    /// its role and text. Returns `None` for diagnostic lines.
+    pub fn parse_prefixed(message: &str) -> Option<(Self, &str)> {
+        if let Some(text) = message.strip_prefix("> ") {
+            Some((Self::User, text))
+        } else if let Some(text) = message.strip_prefix("[assistant] ") {
+            Some((Self::Assistant, text))
+        } else {
+            message
+                .strip_prefix("[meta] ")
+                .map(|text| (Self::Meta, text))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatEntry {
+    pub seq: u64,
+    pub role: ChatRole,
+    pub text: String,
+    pub run_id: u64,
+}
+
+/// Conversation shown in the Chat tab, kept apart from the diagnostic
+/// `LogBuffer` so each has its own capacity and clearing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatTranscript {
+    cap: usize,
+    next_seq: u64,
+    entries: VecDeque<ChatEntry>,
+}
+
+impl Default for ChatTranscript {
+    fn default() -> Self {
+        Self::new(1_000)
+    }
+}
+
+impl ChatTranscript {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            cap,
+            next_seq: 1,
+            entries: VecDeque::with_capacity(cap),
+        }
+    }
+
+    pub fn append(&mut self, role: ChatRole, text: String, run_id: u64) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        if self.entries.len() == self.cap {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(ChatEntry {
+            seq,
+            role,
+            text,
+            run_id,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.next_seq = 1;
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &ChatEntry> + '_ {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -911,6 +1659,8 @@ pub struct ShellArtifacts {
     pub diff: Option<DiffArtifact>,
     pub verify: Option<VerifyArtifact>,
     pub logs: LogBuffer,
+    #[serde(default)]
+    pub chat: ChatTranscript,
 }
 
 impl Default for ShellArtifacts {
@@ -921,11 +1671,49 @@ impl Default for ShellArtifacts {
             plan: None,
             diff: None,
             verify: None,
-            logs: LogBuffer::new(2_000),
+            logs: LogBuffer::new(DEFAULT_LOG_ENTRIES),
+            chat: ChatTranscript::default(),
         }
     }
 }
 
+impl ShellArtifacts {
+    /// Brings every loaded artifact up to `CURRENT_ARTIFACT_SCHEMA`, failing on
+    /// the first one whose version is unknown or newer than this build.
+    pub fn migrate_schema(&mut self) -> Result<(), String> {
+        migrate_schema("state", &mut self.schema_version)?;
+        if let Some(system) = self.system.as_mut() {
+            migrate_schema("system", &mut system.schema_version)?;
+        }
+        if let Some(plan) = self.plan.as_mut() {
+            migrate_schema("plan", &mut plan.schema_version)?;
+        }
+        if let Some(diff) = self.diff.as_mut() {
+            migrate_schema("diff", &mut diff.schema_version)?;
+        }
+        if let Some(verify) = self.verify.as_mut() {
+            migrate_schema("verify", &mut verify.schema_version)?;
+        }
+        self.migrate_chat_from_logs();
+        Ok(())
+    }
+
+    /// State saved before the chat transcript existed kept the conversation in
+    /// the log buffer; move those lines over.
+    fn migrate_chat_from_logs(&mut self) {
+        if !self.chat.is_empty() {
+            return;
+        }
+        for entry in self.logs.iter() {
+            if let Some((role, text)) = ChatRole::parse_prefixed(&entry.message) {
+                self.chat.append(role, text.to_string(), entry.run_id);
+            }
+        }
+        self.logs
+            .retain(|entry| ChatRole::parse_prefixed(&entry.message).is_none());
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct RuntimeFlagState {
     pub active: bool,
@@ -986,6 +1774,9 @@ pub struct ShellSelection {
     pub selected_plan_step: Option<String>,
     pub log_level_filter: Option<LogLevel>,
     pub log_search: String,
+    /// Substring filter for the Logs tab; `log_search` filters chat.
+    #[serde(default)]
+    pub logs_text_search: String,
     #[serde(default)]
     pub log_scroll: u16,
     #[serde(default = "default_true")]
@@ -994,6 +1785,17 @@ pub struct ShellSelection {
     pub plan_stick_to_running: bool,
     #[serde(default)]
     pub expanded_plan_steps: Vec<String>,
+    /// Diff files shown as a single summary line in the Diff tab.
+    #[serde(default)]
+    pub collapsed_diff_files: Vec<String>,
+    /// Columns the Diff tab is scrolled to the right.
+    #[serde(default)]
+    pub diff_h_scroll: u16,
+    /// Ids of bookmarked log entries, ascending.
+    #[serde(default)]
+    pub log_bookmarks: Vec<u64>,
+    #[serde(default)]
+    pub hunk_expansions: Vec<HunkExpansion>,
 }
 
 fn default_true() -> bool {
@@ -1002,6 +1804,8 @@ fn default_true() -> bool {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShellState {
+    #[serde(default = "unversioned_shell_state_schema")]
+    pub schema_version: SchemaVersion,
     pub header: ShellHeader,
     pub usage: UsageSnapshot,
     #[serde(default)]
@@ -1023,6 +1827,10 @@ pub struct ShellState {
     pub file_browser: FileBrowserState,
 }
 
+fn unversioned_shell_state_schema() -> SchemaVersion {
+    UNVERSIONED_SHELL_STATE_SCHEMA
+}
+
 const FRIENDLY_VISIBLE_TOOLS: &[&str] = &["scan_repo", "generate_plan", "verify"];
 const PRAGMATIC_VISIBLE_TOOLS: &[&str] = &["scan_repo", "generate_plan", "compute_diff", "verify"];
 const FRIENDLY_TAB_ORDER: &[ShellTab] = &[
@@ -1035,6 +1843,7 @@ const FRIENDLY_TAB_ORDER: &[ShellTab] = &[
     ShellTab::Logs,
     ShellTab::System,
     ShellTab::FileBrowser,
+    ShellTab::Approvals,
 ];
 const PRAGMATIC_TAB_ORDER: &[ShellTab] = &[
     ShellTab::Chat,
@@ -1046,6 +1855,7 @@ const PRAGMATIC_TAB_ORDER: &[ShellTab] = &[
     ShellTab::FileBrowser,
     ShellTab::Explain,
     ShellTab::Overview,
+    ShellTab::Approvals,
 ];
 
 pub fn persona_policy_for(personality: Personality) -> PersonaPolicy {
@@ -1073,6 +1883,15 @@ pub fn persona_policy_for(personality: Personality) -> PersonaPolicy {
     }
 }
 
+impl ShellSelection {
+    pub fn log_filter(&self) -> LogFilter {
+        LogFilter {
+            min_level: self.log_level_filter,
+            text: self.logs_text_search.trim().to_lowercase(),
+        }
+    }
+}
+
 impl Default for ShellSelection {
     fn default() -> Self {
         Self {
@@ -1080,10 +1899,15 @@ impl Default for ShellSelection {
             selected_plan_step: None,
             log_level_filter: None,
             log_search: String::new(),
+            logs_text_search: String::new(),
             log_scroll: 0,
             log_stick_to_bottom: true,
             plan_stick_to_running: true,
             expanded_plan_steps: Vec::new(),
+            collapsed_diff_files: Vec::new(),
+            diff_h_scroll: 0,
+            log_bookmarks: Vec::new(),
+            hunk_expansions: Vec::new(),
         }
     }
 }
@@ -1111,9 +1935,12 @@ impl ShellState {
     pub fn new(project_name: String, personality: Personality, config: Config) -> Self {
         let persona_policy_defaults = persona_policy_for(personality);
         let persona_policy_overrides = PersonaPolicyOverrides::default();
+        let mut artifacts = ShellArtifacts::default();
+        artifacts.logs.apply_config(&config.logging);
         Self {
+            schema_version: SHELL_STATE_SCHEMA,
             header: ShellHeader {
-                project_name: project_name.into(),
+                project_name,
                 safety_mode: SafetyMode::Safe,
                 scan: ScanStatus::Unknown,
                 apply: ApplyStatus::NotApplied,
@@ -1140,6 +1967,7 @@ impl ShellState {
                 chat_history: Vec::new(),
                 live_assistant_preview: String::new(),
                 stream_meta_enabled: false,
+                chat_summary: None,
                 chat_history_index: None,
             },
             customization: ShellCustomization {
@@ -1170,7 +1998,7 @@ impl ShellState {
                 model_provider: config.model.default_provider.clone(),
                 reasoning_effort: None,
             },
-            artifacts: ShellArtifacts::default(),
+            artifacts,
             runtime_flags: RuntimeFlags::default(),
             approval: ApprovalState::default(),
             selection: ShellSelection::default(),
@@ -1181,6 +2009,30 @@ impl ShellState {
         }
     }
 
+    /// Whether a workflow step or a UI tool run is underway or waiting on an
+    /// approval, so another tool run must not start.
+    pub fn run_in_progress(&self) -> bool {
+        let flags = &self.runtime_flags;
+        [
+            flags.scanning,
+            flags.planning,
+            flags.diffing,
+            flags.awaiting_approval,
+            flags.verifying,
+        ]
+        .iter()
+        .any(|flag| flag.active)
+            || self.approval.pending.is_some()
+    }
+
+    /// Diff the Diff tab shows: a file browser diff if one is open, else the run's.
+    pub fn shown_diff(&self) -> Option<&DiffArtifact> {
+        self.file_browser
+            .diff
+            .as_ref()
+            .or(self.artifacts.diff.as_ref())
+    }
+
     pub fn current_run_id(&self) -> u64 {
         let artifact_run_id = [
             self.artifacts.system.as_ref().map(|a| a.run_id),
@@ -1213,6 +2065,18 @@ impl ShellState {
             .max(self.journey_status.active_run_id)
     }
 
+    /// The theme after (or before) the current one, skipping `Custom` while
+    /// no custom theme file is configured.
+    pub fn cycled_theme(&self, forward: bool) -> UiTheme {
+        let step = |theme: UiTheme| if forward { theme.next() } else { theme.prev() };
+        let theme = step(self.customization.theme);
+        if theme == UiTheme::Custom && self.config.ui.custom_theme_path.is_none() {
+            step(theme)
+        } else {
+            theme
+        }
+    }
+
     pub fn ordered_tabs(&self) -> &[ShellTab] {
         &self.sm.persona_policy.tab_order
     }
@@ -1417,3 +2281,287 @@ pub fn policy_requirement_for_risk(
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn diff_with(files: Vec<DiffFile>) -> DiffArtifact {
+        DiffArtifact {
+            schema_version: ARTIFACT_SCHEMA_V1,
+            run_id: 1,
+            artifact_id: 1,
+            files,
+            summary: String::new(),
+            error: None,
+        }
+    }
+
+    fn file(path: &str, status: DiffFileStatus, added: usize, removed: usize) -> DiffFile {
+        let mut lines = Vec::new();
+        lines.extend((0..added).map(|i| DiffLine {
+            kind: DiffLineKind::Add,
+            text: format!("+added {i}"),
+        }));
+        lines.extend((0..removed).map(|i| DiffLine {
+            kind: DiffLineKind::Remove,
+            text: format!("-removed {i}"),
+        }));
+        DiffFile {
+            path: path.to_string(),
+            old_path: None,
+            status,
+            binary: false,
+            hunks: vec![DiffHunk {
+                header: "@@ -1 +1 @@".to_string(),
+                lines,
+            }],
+        }
+    }
+
+    #[test]
+    fn line_counts_skip_context_lines() {
+        let mut mixed = file("src/lib.rs", DiffFileStatus::Modified, 2, 1);
+        mixed.hunks.push(DiffHunk {
+            header: "@@ -10,4 +10,4 @@".to_string(),
+            lines: vec![
+                DiffLine {
+                    kind: DiffLineKind::Context,
+                    text: " unchanged".to_string(),
+                },
+                DiffLine {
+                    kind: DiffLineKind::Remove,
+                    text: "-old".to_string(),
+                },
+                DiffLine {
+                    kind: DiffLineKind::Add,
+                    text: "+new".to_string(),
+                },
+                DiffLine {
+                    kind: DiffLineKind::Context,
+                    text: " unchanged".to_string(),
+                },
+            ],
+        });
+        assert_eq!((mixed.added_lines(), mixed.removed_lines()), (3, 2));
+
+        let diff = diff_with(vec![
+            mixed,
+            file("src/old.rs", DiffFileStatus::Deleted, 0, 4),
+        ]);
+        assert_eq!(diff.added_lines(), 3);
+        assert_eq!(diff.removed_lines(), 6);
+        assert_eq!(diff.files_changed(), 2);
+        assert_eq!(diff_with(Vec::new()).added_lines(), 0);
+    }
+
+    #[test]
+    fn default_risk_config_keeps_base_classification() {
+        let patch = diff_with(vec![file("src/lib.rs", DiffFileStatus::Modified, 3, 1)]);
+        let refactor = diff_with(vec![file("src/lib.rs", DiffFileStatus::Modified, 1, 3)]);
+        let deleted = diff_with(vec![file("src/old.rs", DiffFileStatus::Deleted, 0, 3)]);
+
+        assert_eq!(patch.analyze_risk(), ApprovalRiskClass::PatchOnly);
+        assert_eq!(refactor.analyze_risk(), ApprovalRiskClass::Refactor);
+        assert_eq!(deleted.analyze_risk(), ApprovalRiskClass::Destructive);
+    }
+
+    #[test]
+    fn weighted_score_escalates_risk() {
+        let diff = diff_with(vec![
+            file("src/lib.rs", DiffFileStatus::Modified, 40, 0),
+            file("src/main.rs", DiffFileStatus::Modified, 1, 0),
+        ]);
+        let config = RiskConfig {
+            line_weight: 2.0,
+            file_weight: 10.0,
+            ..RiskConfig::default()
+        };
+
+        let assessment = diff.assess_risk(&config);
+        assert_eq!(assessment.score, 102.0);
+        assert_eq!(assessment.risk, ApprovalRiskClass::Execution);
+        assert!(assessment
+            .factors
+            .iter()
+            .any(|factor| factor == "score escalated risk to execution"));
+    }
+
+    #[test]
+    fn sensitive_paths_elevate_risk_to_execution() {
+        let diff = diff_with(vec![
+            file("src/lib.rs", DiffFileStatus::Modified, 2, 0),
+            file("deploy/Dockerfile", DiffFileStatus::Modified, 1, 0),
+        ]);
+        let config = RiskConfig {
+            sensitive_paths: vec!["Dockerfile".to_string(), ".github/".to_string()],
+            ..RiskConfig::default()
+        };
+
+        let assessment = diff.assess_risk(&config);
+        assert_eq!(assessment.risk, ApprovalRiskClass::Execution);
+        assert_eq!(
+            assessment.sensitive_files,
+            vec!["deploy/Dockerfile".to_string()]
+        );
+    }
+
+    #[test]
+    fn deleted_files_can_be_downgraded() {
+        let diff = diff_with(vec![file("src/old.rs", DiffFileStatus::Deleted, 0, 3)]);
+        let config = RiskConfig {
+            deleted_files_destructive: false,
+            ..RiskConfig::default()
+        };
+
+        assert_eq!(diff.assess_risk(&config).risk, ApprovalRiskClass::Refactor);
+    }
+
+    #[test]
+    fn sensitive_directory_patterns_match_nested_paths() {
+        assert!(path_matches_sensitive(
+            ".github/workflows/ci.yml",
+            ".github/"
+        ));
+        assert!(path_matches_sensitive(
+            "db/migrations/001.sql",
+            "migrations/"
+        ));
+        assert!(!path_matches_sensitive("src/migrations.rs", "migrations/"));
+        assert!(!path_matches_sensitive("Dockerfile.bak", "Dockerfile"));
+    }
+
+    #[test]
+    fn migrate_schema_accepts_current_artifacts() {
+        let mut artifacts = ShellArtifacts {
+            diff: Some(diff_with(Vec::new())),
+            ..ShellArtifacts::default()
+        };
+        assert_eq!(artifacts.migrate_schema(), Ok(()));
+        assert_eq!(artifacts.schema_version, CURRENT_ARTIFACT_SCHEMA);
+    }
+
+    #[test]
+    fn migrate_schema_rejects_newer_and_unknown_versions() {
+        let mut newer = ShellArtifacts {
+            diff: Some(DiffArtifact {
+                schema_version: SchemaVersion(CURRENT_ARTIFACT_SCHEMA.0 + 1),
+                ..diff_with(Vec::new())
+            }),
+            ..ShellArtifacts::default()
+        };
+        let err = newer
+            .migrate_schema()
+            .expect_err("newer schema should fail");
+        assert!(err.starts_with("diff artifact uses schema v2"), "{err}");
+
+        let mut unknown = ShellArtifacts {
+            schema_version: SchemaVersion(0),
+            ..ShellArtifacts::default()
+        };
+        assert_eq!(
+            unknown.migrate_schema(),
+            Err("state artifact uses unrecognized schema v0 (expected v1)".to_string())
+        );
+    }
+
+    #[test]
+    fn looks_binary_checks_for_nul_bytes() {
+        assert!(!looks_binary(b"fn main() {}\n"));
+        assert!(!looks_binary(b""));
+        assert!(looks_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+
+        let mut late_nul = vec![b'a'; 9 * 1024];
+        late_nul.push(0);
+        assert!(!looks_binary(&late_nul));
+    }
+
+    #[test]
+    fn file_preview_marks_binary_files_and_truncates_long_ones() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let bin = dir.path().join("image.bin");
+        std::fs::write(&bin, [0x7f, b'E', b'L', b'F', 0, 1]).expect("write bin");
+        let preview = FilePreview::load(&bin).expect("bin preview");
+        assert!(preview.binary);
+        assert!(preview.lines.is_empty());
+
+        let long = dir.path().join("long.txt");
+        let text: String = (1..=FILE_PREVIEW_MAX_LINES + 5)
+            .map(|n| format!("line {n}\n"))
+            .collect();
+        std::fs::write(&long, text).expect("write long");
+        let preview = FilePreview::load(&long).expect("text preview");
+        assert!(!preview.binary);
+        assert!(preview.truncated);
+        assert_eq!(preview.lines.len(), FILE_PREVIEW_MAX_LINES);
+        assert_eq!(preview.lines[0], "line 1");
+
+        assert_eq!(FilePreview::load(dir.path()), None);
+    }
+
+    #[test]
+    fn ui_themes_cycle_through_every_theme_and_parse_their_labels() {
+        let themes: Vec<UiTheme> = UiTheme::all().collect();
+        let mut cycled = vec![UiTheme::Classic];
+        while cycled.len() <= themes.len() {
+            cycled.push(cycled.last().expect("non-empty").next());
+        }
+        assert_eq!(cycled.last(), Some(&UiTheme::Classic));
+        assert_eq!(&cycled[..themes.len()], themes.as_slice());
+
+        for theme in themes {
+            assert_eq!(theme.next().prev(), theme);
+            assert_eq!(UiTheme::from_name(theme.label()), Some(theme));
+        }
+        assert_eq!(
+            UiTheme::from_name("MidnightSlate"),
+            Some(UiTheme::MidnightSlate)
+        );
+        assert_eq!(UiTheme::from_name("sepia"), None);
+    }
+
+    #[test]
+    fn journey_steps_have_rail_ordinals_and_progress() {
+        let ordinals: Vec<_> = JourneyStep::all()
+            .iter()
+            .map(|step| (step.ordinal(), step.progress_percent()))
+            .collect();
+        assert_eq!(
+            ordinals,
+            vec![
+                (0, 14),
+                (1, 28),
+                (2, 42),
+                (3, 57),
+                (4, 71),
+                (5, 85),
+                (6, 100)
+            ]
+        );
+    }
+
+    #[test]
+    fn journey_step_ordinals_are_contiguous_in_label_order() {
+        for (index, step) in JourneyStep::all().iter().enumerate() {
+            assert_eq!(usize::from(step.ordinal()), index);
+        }
+        let labels: Vec<_> = JourneyStep::all()
+            .iter()
+            .map(|step| step.rail_label())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "Idea",
+                "Understand",
+                "Plan",
+                "Preview",
+                "Approve",
+                "Verify",
+                "Learn",
+            ]
+        );
+    }
+}
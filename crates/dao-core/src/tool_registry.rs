@@ -1,12 +1,17 @@
+use serde::Deserialize;
+use serde::Serialize;
+
 use crate::state::ApprovalRiskClass;
 use crate::state::PolicyTier;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ToolId {
     ScanRepo,
     GeneratePlan,
     ComputeDiff,
     Verify,
+    RunTests,
 }
 
 impl ToolId {
@@ -16,6 +21,7 @@ impl ToolId {
             Self::GeneratePlan => "generate_plan",
             Self::ComputeDiff => "compute_diff",
             Self::Verify => "verify",
+            Self::RunTests => "run_tests",
         }
     }
 }
@@ -55,7 +61,7 @@ pub struct ToolSpec {
 
 pub struct ToolRegistry;
 
-const TOOL_SPECS: [ToolSpec; 4] = [
+const TOOL_SPECS: [ToolSpec; 5] = [
     ToolSpec {
         id: ToolId::ScanRepo,
         title: "Scan Repository",
@@ -100,6 +106,17 @@ const TOOL_SPECS: [ToolSpec; 4] = [
             emits: &[ArtifactKind::Verify, ArtifactKind::Logs],
         },
     },
+    ToolSpec {
+        id: ToolId::RunTests,
+        title: "Run Tests",
+        description: "Run the test suite of the Cargo.toml or package.json at the repository root and emit verify artifact data.",
+        risk_class: ApprovalRiskClass::Execution,
+        min_tier: PolicyTier::Balanced,
+        inputs: ToolInputSpec::None,
+        outputs: ToolOutputSpec {
+            emits: &[ArtifactKind::Verify, ArtifactKind::Logs],
+        },
+    },
 ];
 
 impl ToolRegistry {
@@ -114,6 +131,7 @@ impl ToolRegistry {
             ToolId::GeneratePlan => &TOOL_SPECS[1],
             ToolId::ComputeDiff => &TOOL_SPECS[2],
             ToolId::Verify => &TOOL_SPECS[3],
+            ToolId::RunTests => &TOOL_SPECS[4],
         }
     }
 
@@ -160,7 +178,13 @@ mod tests {
             .collect();
         assert_eq!(
             ids,
-            vec!["scan_repo", "generate_plan", "compute_diff", "verify"]
+            vec![
+                "scan_repo",
+                "generate_plan",
+                "compute_diff",
+                "verify",
+                "run_tests"
+            ]
         );
     }
 
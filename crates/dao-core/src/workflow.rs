@@ -3,6 +3,29 @@ use super::tool_registry::ToolId;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WorkflowTemplateId {
     ScanPlanDiffVerify,
+    ScanPlanOnly,
+    ScanPlanDiffTest,
+}
+
+impl WorkflowTemplateId {
+    pub const ALL: [Self; 3] = [
+        Self::ScanPlanDiffVerify,
+        Self::ScanPlanOnly,
+        Self::ScanPlanDiffTest,
+    ];
+
+    /// Name used on the command line and in `WorkflowRunStarted.template_id`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ScanPlanDiffVerify => "scan_plan_diff_verify",
+            Self::ScanPlanOnly => "scan_plan_only",
+            Self::ScanPlanDiffTest => "scan_plan_diff_test",
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|id| id.as_str() == raw)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -36,14 +59,58 @@ const SCAN_PLAN_DIFF_VERIFY_STEPS: [WorkflowStepSpec; 4] = [
     },
 ];
 
-const WORKFLOW_TEMPLATES: [WorkflowTemplate; 1] = [WorkflowTemplate {
-    id: WorkflowTemplateId::ScanPlanDiffVerify,
-    steps: &SCAN_PLAN_DIFF_VERIFY_STEPS,
-}];
+const SCAN_PLAN_ONLY_STEPS: [WorkflowStepSpec; 2] = [
+    WorkflowStepSpec {
+        step_id: "scan",
+        tool_id: ToolId::ScanRepo,
+    },
+    WorkflowStepSpec {
+        step_id: "plan",
+        tool_id: ToolId::GeneratePlan,
+    },
+];
+
+/// Like the default template, with the project's test suite in place of the
+/// whitespace check.
+const SCAN_PLAN_DIFF_TEST_STEPS: [WorkflowStepSpec; 4] = [
+    WorkflowStepSpec {
+        step_id: "scan",
+        tool_id: ToolId::ScanRepo,
+    },
+    WorkflowStepSpec {
+        step_id: "plan",
+        tool_id: ToolId::GeneratePlan,
+    },
+    WorkflowStepSpec {
+        step_id: "diff",
+        tool_id: ToolId::ComputeDiff,
+    },
+    WorkflowStepSpec {
+        step_id: "test",
+        tool_id: ToolId::RunTests,
+    },
+];
+
+const WORKFLOW_TEMPLATES: [WorkflowTemplate; 3] = [
+    WorkflowTemplate {
+        id: WorkflowTemplateId::ScanPlanDiffVerify,
+        steps: &SCAN_PLAN_DIFF_VERIFY_STEPS,
+    },
+    WorkflowTemplate {
+        id: WorkflowTemplateId::ScanPlanOnly,
+        steps: &SCAN_PLAN_ONLY_STEPS,
+    },
+    WorkflowTemplate {
+        id: WorkflowTemplateId::ScanPlanDiffTest,
+        steps: &SCAN_PLAN_DIFF_TEST_STEPS,
+    },
+];
 
 pub fn workflow_template(id: WorkflowTemplateId) -> &'static WorkflowTemplate {
     match id {
         WorkflowTemplateId::ScanPlanDiffVerify => &WORKFLOW_TEMPLATES[0],
+        WorkflowTemplateId::ScanPlanOnly => &WORKFLOW_TEMPLATES[1],
+        WorkflowTemplateId::ScanPlanDiffTest => &WORKFLOW_TEMPLATES[2],
     }
 }
 
@@ -59,4 +126,29 @@ mod tests {
         let steps: Vec<&'static str> = template.steps.iter().map(|step| step.step_id).collect();
         assert_eq!(steps, vec!["scan", "plan", "diff", "verify"]);
     }
+
+    #[test]
+    fn template_ids_round_trip_through_their_names() {
+        for id in WorkflowTemplateId::ALL {
+            assert_eq!(WorkflowTemplateId::parse(id.as_str()), Some(id));
+            assert_eq!(workflow_template(id).id, id);
+        }
+        assert_eq!(WorkflowTemplateId::parse("scan_only"), None);
+
+        let template = workflow_template(WorkflowTemplateId::ScanPlanOnly);
+        let steps: Vec<&'static str> = template.steps.iter().map(|step| step.step_id).collect();
+        assert_eq!(steps, vec!["scan", "plan"]);
+
+        let template = workflow_template(WorkflowTemplateId::ScanPlanDiffTest);
+        let tools: Vec<ToolId> = template.steps.iter().map(|step| step.tool_id).collect();
+        assert_eq!(
+            tools,
+            vec![
+                ToolId::ScanRepo,
+                ToolId::GeneratePlan,
+                ToolId::ComputeDiff,
+                ToolId::RunTests
+            ]
+        );
+    }
 }
@@ -1,19 +1,92 @@
+use crate::executor::output_unless_cancelled;
 use crate::executor::ToolExecutionPayload;
+use crate::test_runner::TestReport;
+use crate::test_runner::TestRunner;
+use serde::Serialize;
 use serde_json::Value;
-use std::io::{BufRead, BufReader, Read};
-use std::process::{Command, Stdio};
-use std::sync::mpsc;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+mod gemini;
 
 pub struct ShellAdapter;
 
 pub enum ChatEvent {
     Token(String),
     Meta(String),
+    /// The provider could not answer; `Done` still follows.
+    Failed(String),
     Done,
 }
 
+/// Assembles the tokens of a streamed reply into its final text.
+#[derive(Debug, Default)]
+pub struct ReplyAccumulator {
+    text: String,
+    chunks: usize,
+    failure: Option<String>,
+    done: bool,
+}
+
+/// A finished one-shot chat, as printed by `dao chat --json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChatReply {
+    pub text: String,
+    /// Pieces of output the provider streamed; not a model token count.
+    pub chunks: usize,
+    pub elapsed_ms: u64,
+    pub provider: String,
+    pub model: String,
+}
+
+impl ReplyAccumulator {
+    /// Records `event` and returns the token it carried, if any.
+    pub fn push<'a>(&mut self, event: &'a ChatEvent) -> Option<&'a str> {
+        match event {
+            ChatEvent::Token(token) if !token.is_empty() => {
+                self.text.push_str(token);
+                self.chunks += 1;
+                Some(token)
+            }
+            ChatEvent::Failed(reason) => {
+                self.failure = Some(reason.clone());
+                None
+            }
+            ChatEvent::Done => {
+                self.done = true;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Number of non-empty token events received.
+    pub fn chunks(&self) -> usize {
+        self.chunks
+    }
+
+    /// Why the provider failed, if it did.
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+
+    pub fn into_text(self) -> String {
+        self.text
+    }
+}
+
 fn build_chat_prompt(provider: &str, model: &str, message: &str, context: Option<&str>) -> String {
     let mut prompt = String::new();
     prompt.push_str(
@@ -41,7 +114,7 @@ Keep responses factual and concise.\n\n",
     prompt
 }
 
-fn resolve_provider(provider: Option<&str>) -> &str {
+fn resolve_provider(provider: Option<&str>) -> &'static str {
     match provider.unwrap_or("ollama").to_ascii_lowercase().as_str() {
         "codex" => "codex",
         "gemini" => "gemini",
@@ -49,6 +122,19 @@ fn resolve_provider(provider: Option<&str>) -> &str {
     }
 }
 
+/// The provider and model a chat is sent to: the chosen provider or ollama,
+/// and the chosen model or that provider's default.
+pub fn resolve_chat_target<'a>(
+    provider: Option<&str>,
+    model: Option<&'a str>,
+) -> (&'static str, &'a str) {
+    let provider = resolve_provider(provider);
+    (
+        provider,
+        model.unwrap_or(default_model_for_provider(provider)),
+    )
+}
+
 fn default_model_for_provider(provider: &str) -> &'static str {
     match provider {
         "codex" => "gpt-5",
@@ -57,15 +143,19 @@ fn default_model_for_provider(provider: &str) -> &'static str {
     }
 }
 
-fn stream_command_output<F>(mut cmd: Command, provider_label: &str, callback: &F)
-where
+fn stream_command_output<F>(
+    mut cmd: Command,
+    provider_label: &str,
+    cancel: &AtomicBool,
+    callback: &F,
+) where
     F: Fn(ChatEvent),
 {
     let spawn = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn();
     let mut child = match spawn {
         Ok(child) => child,
         Err(err) => {
-            callback(ChatEvent::Token(format!(
+            callback(ChatEvent::Failed(format!(
                 "Failed to start {} CLI: {}",
                 provider_label, err
             )));
@@ -86,6 +176,9 @@ where
     if let Some(mut stdout) = child.stdout.take() {
         let mut buf = [0_u8; 2048];
         loop {
+            if kill_if_cancelled(&mut child, cancel) {
+                break;
+            }
             match stdout.read(&mut buf) {
                 Ok(0) => break,
                 Ok(n) => {
@@ -100,6 +193,11 @@ where
         }
     }
 
+    if cancel.load(Ordering::Relaxed) {
+        let _ = child.wait();
+        callback(ChatEvent::Done);
+        return;
+    }
     let status = child.wait().ok();
     let stderr_text = stderr_handle
         .and_then(|h| h.join().ok())
@@ -113,7 +211,7 @@ where
         } else {
             format!("{} CLI error: {}", provider_label, stderr_text)
         };
-        callback(ChatEvent::Meta(msg));
+        callback(ChatEvent::Failed(msg));
     } else if !emitted {
         callback(ChatEvent::Token("[assistant] (empty response)".to_string()));
     }
@@ -121,6 +219,15 @@ where
     callback(ChatEvent::Done);
 }
 
+/// Kills `child` once `cancel` is set, telling the caller to stop reading.
+fn kill_if_cancelled(child: &mut Child, cancel: &AtomicBool) -> bool {
+    if !cancel.load(Ordering::Relaxed) {
+        return false;
+    }
+    let _ = child.kill();
+    true
+}
+
 fn strip_ansi_sequences(input: &str) -> String {
     let mut out = String::with_capacity(input.len());
     let mut chars = input.chars().peekable();
@@ -128,7 +235,7 @@ fn strip_ansi_sequences(input: &str) -> String {
         if c == '\u{1b}' {
             if chars.peek() == Some(&'[') {
                 let _ = chars.next();
-                while let Some(n) = chars.next() {
+                for n in chars.by_ref() {
                     if ('@'..='~').contains(&n) {
                         break;
                     }
@@ -196,7 +303,7 @@ where
     }
 }
 
-fn stream_gemini_json<F>(mut cmd: Command, callback: &F)
+fn stream_gemini_json<F>(mut cmd: Command, cancel: &AtomicBool, callback: &F)
 where
     F: Fn(ChatEvent),
 {
@@ -204,7 +311,7 @@ where
     let mut child = match spawn {
         Ok(child) => child,
         Err(err) => {
-            callback(ChatEvent::Token(format!(
+            callback(ChatEvent::Failed(format!(
                 "Failed to start Gemini CLI: {}",
                 err
             )));
@@ -226,6 +333,9 @@ where
     if let Some(stdout) = child.stdout.take() {
         let reader = BufReader::new(stdout);
         for line in reader.lines().map_while(Result::ok) {
+            if kill_if_cancelled(&mut child, cancel) {
+                break;
+            }
             let trimmed = line.trim();
             if !trimmed.starts_with('{') {
                 if !trimmed.is_empty() {
@@ -266,6 +376,11 @@ where
         }
     }
 
+    if cancel.load(Ordering::Relaxed) {
+        let _ = child.wait();
+        callback(ChatEvent::Done);
+        return;
+    }
     let status = child.wait().ok();
     let stderr_text = stderr_handle
         .and_then(|h| h.join().ok())
@@ -278,14 +393,14 @@ where
         } else {
             format!("Gemini CLI error: {}", stderr_text)
         };
-        callback(ChatEvent::Meta(msg));
+        callback(ChatEvent::Failed(msg));
     } else if !emitted {
         callback(ChatEvent::Token("[assistant] (empty response)".to_string()));
     }
     callback(ChatEvent::Done);
 }
 
-fn stream_codex_json<F>(mut cmd: Command, callback: &F)
+fn stream_codex_json<F>(mut cmd: Command, cancel: &AtomicBool, callback: &F)
 where
     F: Fn(ChatEvent),
 {
@@ -293,7 +408,7 @@ where
     let mut child = match spawn {
         Ok(child) => child,
         Err(err) => {
-            callback(ChatEvent::Token(format!(
+            callback(ChatEvent::Failed(format!(
                 "Failed to start Codex CLI: {}",
                 err
             )));
@@ -316,6 +431,9 @@ where
     if let Some(stdout) = child.stdout.take() {
         let reader = BufReader::new(stdout);
         for line in reader.lines().map_while(Result::ok) {
+            if kill_if_cancelled(&mut child, cancel) {
+                break;
+            }
             let trimmed = line.trim();
             if !trimmed.starts_with('{') {
                 if !trimmed.is_empty() {
@@ -382,6 +500,11 @@ where
         }
     }
 
+    if cancel.load(Ordering::Relaxed) {
+        let _ = child.wait();
+        callback(ChatEvent::Done);
+        return;
+    }
     let status = child.wait().ok();
     let stderr_text = stderr_handle
         .and_then(|h| h.join().ok())
@@ -394,7 +517,7 @@ where
         } else {
             format!("Codex CLI error: {}", stderr_text)
         };
-        callback(ChatEvent::Meta(msg));
+        callback(ChatEvent::Failed(msg));
     } else if !emitted {
         callback(ChatEvent::Token("[assistant] (empty response)".to_string()));
     }
@@ -470,7 +593,7 @@ impl ShellAdapter {
 
         eprintln!("> Generating plan with Ollama ({})...", model);
         if let Ok(mut child) = Command::new("ollama")
-            .args(&["run", model, &prompt])
+            .args(["run", model, &prompt])
             .stdout(Stdio::piped())
             .spawn()
         {
@@ -527,9 +650,28 @@ impl ShellAdapter {
         }
     }
 
+    /// Runs the test command of `runner` in `cwd` and returns its verify
+    /// checks along with the combined stdout and stderr. The command is
+    /// killed once `cancel` is set.
+    pub fn run_tests(
+        cwd: &std::path::Path,
+        runner: TestRunner,
+        cancel: Option<&AtomicBool>,
+    ) -> io::Result<(TestReport, String)> {
+        let output = output_unless_cancelled(
+            Command::new(runner.program())
+                .args(runner.args())
+                .current_dir(cwd)
+                .stdin(Stdio::null()),
+            cancel,
+        )?;
+        let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+        Ok((runner.report(&text, output.status.success()), text))
+    }
+
     pub fn chat(provider: Option<&str>, model: Option<&str>, message: &str) {
-        let provider = resolve_provider(provider);
-        let model = model.unwrap_or(default_model_for_provider(provider));
+        let (provider, model) = resolve_chat_target(provider, model);
         eprintln!("> Chatting with {} ({})...", provider, model);
 
         let prompt = if message.is_empty() {
@@ -579,26 +721,107 @@ impl ShellAdapter {
         let _ = child.wait();
     }
 
+    /// Writes a one-shot reply to `out` token by token, flushing each, and
+    /// returns the whole reply, or an error when the provider failed. Meta
+    /// lines go to stderr when `show_meta` is set.
+    pub fn chat_streamed(
+        provider: Option<&str>,
+        model: Option<&str>,
+        message: &str,
+        show_meta: bool,
+        out: &mut impl Write,
+    ) -> io::Result<String> {
+        let reply = Self::chat_to_completion(provider, model, message, |event| {
+            match event {
+                ChatEvent::Token(token) if !token.is_empty() => {
+                    out.write_all(token.as_bytes())?;
+                    out.flush()?;
+                }
+                ChatEvent::Meta(line) if show_meta && !line.trim().is_empty() => {
+                    eprintln!("{line}");
+                }
+                _ => {}
+            }
+            Ok(())
+        })?;
+        if !reply.text().is_empty() && !reply.text().ends_with('\n') {
+            writeln!(out)?;
+        }
+        if let Some(reason) = reply.failure() {
+            return Err(io::Error::other(reason.to_string()));
+        }
+        Ok(reply.into_text())
+    }
+
+    /// Runs a one-shot chat to the end without printing anything. Fails when
+    /// the provider did.
+    pub fn chat_reply(
+        provider: Option<&str>,
+        model: Option<&str>,
+        message: &str,
+    ) -> io::Result<ChatReply> {
+        let (provider_name, model_name) = resolve_chat_target(provider, model);
+        let started = Instant::now();
+        let reply = Self::chat_to_completion(provider, model, message, |_| Ok(()))?;
+        if let Some(reason) = reply.failure() {
+            return Err(io::Error::other(reason.to_string()));
+        }
+        Ok(ChatReply {
+            chunks: reply.chunks(),
+            text: reply.into_text(),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            provider: provider_name.to_string(),
+            model: model_name.to_string(),
+        })
+    }
+
+    fn chat_to_completion(
+        provider: Option<&str>,
+        model: Option<&str>,
+        message: &str,
+        mut on_event: impl FnMut(&ChatEvent) -> io::Result<()>,
+    ) -> io::Result<ReplyAccumulator> {
+        let (tx, rx) = mpsc::channel();
+        Self::chat_stream(
+            provider,
+            model,
+            message,
+            None,
+            Arc::new(AtomicBool::new(false)),
+            move |event| {
+                let _ = tx.send(event);
+            },
+        );
+        let mut reply = ReplyAccumulator::default();
+        for event in rx {
+            on_event(&event)?;
+            reply.push(&event);
+            if reply.is_done() {
+                break;
+            }
+        }
+        Ok(reply)
+    }
+
     pub fn chat_stream<F>(
         provider: Option<&str>,
         model: Option<&str>,
         message: &str,
         context: Option<&str>,
+        cancel: Arc<AtomicBool>,
         callback: F,
     ) where
         F: Fn(ChatEvent) + Send + 'static,
     {
-        let provider = resolve_provider(provider).to_string();
-        let model = model
-            .unwrap_or(default_model_for_provider(&provider))
-            .to_string();
-        let message = build_chat_prompt(&provider, &model, message, context);
+        let (provider, model) = resolve_chat_target(provider, model);
+        let message = build_chat_prompt(provider, model, message, context);
+        let model = model.to_string();
 
         thread::spawn(move || {
             if provider == "ollama" {
                 let mut cmd = Command::new("ollama");
                 cmd.args(["run", "--nowordwrap", &model, &message]);
-                stream_command_output(cmd, "Ollama", &callback);
+                stream_command_output(cmd, "Ollama", &cancel, &callback);
                 return;
             }
 
@@ -609,11 +832,22 @@ impl ShellAdapter {
                     cmd.arg("-m").arg(&model);
                 }
                 cmd.arg(&message);
-                stream_codex_json(cmd, &callback);
+                stream_codex_json(cmd, &cancel, &callback);
                 return;
             }
 
             if provider == "gemini" {
+                match gemini::api_key() {
+                    Some(key) => {
+                        gemini::stream_generate_content(&key, &model, &message, &cancel, &callback);
+                        return;
+                    }
+                    None => {
+                        if let Some(notice) = gemini::fallback_notice() {
+                            callback(ChatEvent::Meta(notice));
+                        }
+                    }
+                }
                 let mut cmd = Command::new("gemini");
                 cmd.arg("-p")
                     .arg(&message)
@@ -622,11 +856,11 @@ impl ShellAdapter {
                 if !model.is_empty() {
                     cmd.arg("-m").arg(&model);
                 }
-                stream_gemini_json(cmd, &callback);
+                stream_gemini_json(cmd, &cancel, &callback);
                 return;
             }
 
-            callback(ChatEvent::Token(format!(
+            callback(ChatEvent::Failed(format!(
                 "Unsupported provider: {}",
                 provider
             )));
@@ -634,3 +868,68 @@ impl ShellAdapter {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn reply_accumulator_joins_tokens_and_skips_meta() {
+        let mut reply = ReplyAccumulator::default();
+        let events = [
+            ChatEvent::Meta("codex event: turn.started".to_string()),
+            ChatEvent::Token("Hel".to_string()),
+            ChatEvent::Token(String::new()),
+            ChatEvent::Token("lo\n".to_string()),
+        ];
+        let echoed = events
+            .iter()
+            .filter_map(|event| reply.push(event))
+            .collect::<Vec<_>>();
+        assert_eq!(echoed, vec!["Hel", "lo\n"]);
+        assert!(!reply.is_done());
+
+        assert_eq!(reply.push(&ChatEvent::Done), None);
+        assert!(reply.is_done());
+        assert_eq!(reply.chunks(), 2);
+        assert_eq!(reply.failure(), None);
+        assert_eq!(reply.into_text(), "Hello\n");
+    }
+
+    #[test]
+    fn reply_accumulator_keeps_the_failure_apart_from_the_text() {
+        let mut reply = ReplyAccumulator::default();
+        for event in [
+            ChatEvent::Failed("Codex CLI error: not logged in".to_string()),
+            ChatEvent::Done,
+        ] {
+            assert_eq!(reply.push(&event), None);
+        }
+        assert!(reply.is_done());
+        assert_eq!(reply.failure(), Some("Codex CLI error: not logged in"));
+        assert_eq!(reply.into_text(), "");
+    }
+
+    #[test]
+    fn chat_reply_serializes_for_scripts() {
+        let (provider, model) = resolve_chat_target(Some("Codex"), None);
+        let reply = ChatReply {
+            text: "Hello".to_string(),
+            chunks: 2,
+            elapsed_ms: 340,
+            provider: provider.to_string(),
+            model: model.to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&reply).expect("reply should serialize"),
+            serde_json::json!({
+                "text": "Hello",
+                "chunks": 2,
+                "elapsed_ms": 340,
+                "provider": "codex",
+                "model": "gpt-5",
+            })
+        );
+    }
+}
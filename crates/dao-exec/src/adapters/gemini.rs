@@ -0,0 +1,275 @@
+use super::kill_if_cancelled;
+use super::ChatEvent;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+const API_KEY_ENV: &str = "GEMINI_API_KEY";
+const API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+
+/// Set once the missing-key notice has been shown, so a session reports the
+/// CLI fallback on its first chat only.
+static FALLBACK_NOTED: AtomicBool = AtomicBool::new(false);
+
+/// Notice that chat falls back to the gemini CLI, the first time it is asked for.
+pub(super) fn fallback_notice() -> Option<String> {
+    (!FALLBACK_NOTED.swap(true, Ordering::Relaxed))
+        .then(|| format!("{API_KEY_ENV} is not set; falling back to the gemini CLI"))
+}
+
+pub(super) fn api_key() -> Option<String> {
+    std::env::var(API_KEY_ENV)
+        .ok()
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+fn request_body(prompt: &str) -> String {
+    json!({ "contents": [{ "role": "user", "parts": [{ "text": prompt }] }] }).to_string()
+}
+
+/// Percent-encodes `segment` for use as one URL path segment.
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Quotes `value` as a curl config string.
+fn config_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// curl config for one streamed request. The key and the prompt go to curl on
+/// stdin, so neither shows up in argv nor runs into its length limit.
+fn curl_config(api_key: &str, model: &str, prompt: &str) -> String {
+    let url = format!(
+        "{API_BASE}/{}:streamGenerateContent?alt=sse",
+        encode_path_segment(model)
+    );
+    [
+        format!("url = {}", config_string(&url)),
+        format!(
+            "header = {}",
+            config_string(&format!("x-goog-api-key: {api_key}"))
+        ),
+        format!(
+            "header = {}",
+            config_string("Content-Type: application/json")
+        ),
+        format!("data-binary = {}", config_string(&request_body(prompt))),
+    ]
+    .iter()
+    .map(|line| format!("{line}\n"))
+    .collect()
+}
+
+/// Text parts of one streamed `GenerateContentResponse`.
+fn response_text(event: &Value) -> String {
+    let Some(candidates) = event.get("candidates").and_then(Value::as_array) else {
+        return String::new();
+    };
+    candidates
+        .iter()
+        .filter_map(|candidate| candidate.pointer("/content/parts"))
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(|part| part.get("text").and_then(Value::as_str))
+        .collect()
+}
+
+/// Why the response stopped early, if it was blocked or cut short.
+fn stop_reason(event: &Value) -> Option<String> {
+    if let Some(reason) = event
+        .pointer("/promptFeedback/blockReason")
+        .and_then(Value::as_str)
+    {
+        return Some(format!("prompt blocked: {reason}"));
+    }
+    let reason = event
+        .pointer("/candidates/0/finishReason")
+        .and_then(Value::as_str)?;
+    (reason != "STOP").then(|| format!("finish reason: {reason}"))
+}
+
+/// `error.message` from a non-streamed error body, or the body itself.
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| {
+            v.pointer("/error/message")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| body.to_string())
+}
+
+pub(super) fn stream_generate_content<F>(
+    api_key: &str,
+    model: &str,
+    prompt: &str,
+    cancel: &AtomicBool,
+    callback: &F,
+) where
+    F: Fn(ChatEvent),
+{
+    let spawn = Command::new("curl")
+        .args(["-sS", "-N", "--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawn {
+        Ok(child) => child,
+        Err(err) => {
+            callback(ChatEvent::Failed(format!(
+                "Failed to start curl for the Gemini API: {}",
+                err
+            )));
+            callback(ChatEvent::Done);
+            return;
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(curl_config(api_key, model, prompt).as_bytes());
+    }
+
+    let stderr_handle = child.stderr.take().map(|mut stderr| {
+        thread::spawn(move || {
+            let mut stderr_text = String::new();
+            let _ = stderr.read_to_string(&mut stderr_text);
+            stderr_text
+        })
+    });
+
+    let mut emitted = false;
+    let mut other = String::new();
+    if let Some(stdout) = child.stdout.take() {
+        let reader = BufReader::new(stdout);
+        for line in reader.lines().map_while(Result::ok) {
+            if kill_if_cancelled(&mut child, cancel) {
+                break;
+            }
+            let Some(data) = line.strip_prefix("data:") else {
+                other.push_str(&line);
+                other.push('\n');
+                continue;
+            };
+            let Ok(event) = serde_json::from_str::<Value>(data.trim()) else {
+                callback(ChatEvent::Meta(format!("gemini event: {}", data.trim())));
+                continue;
+            };
+            let text = response_text(&event);
+            if !text.is_empty() {
+                emitted = true;
+                callback(ChatEvent::Token(text));
+            }
+            if let Some(reason) = stop_reason(&event) {
+                callback(ChatEvent::Meta(format!("Gemini API {reason}")));
+            }
+        }
+    }
+
+    if cancel.load(Ordering::Relaxed) {
+        let _ = child.wait();
+        callback(ChatEvent::Done);
+        return;
+    }
+    let status = child.wait().ok();
+    let stderr_text = stderr_handle
+        .and_then(|h| h.join().ok())
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    let other = other.trim();
+    if !other.is_empty() {
+        callback(ChatEvent::Failed(format!(
+            "Gemini API error: {}",
+            error_message(other)
+        )));
+    } else if !status.is_some_and(|s| s.success()) {
+        let msg = if stderr_text.is_empty() {
+            "Gemini API request failed.".to_string()
+        } else {
+            format!("Gemini API request failed: {}", stderr_text)
+        };
+        callback(ChatEvent::Failed(msg));
+    } else if !emitted {
+        callback(ChatEvent::Token("[assistant] (empty response)".to_string()));
+    }
+    callback(ChatEvent::Done);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn streamed_chunks_yield_text_and_stop_reasons() {
+        let chunk: Value = serde_json::from_str(
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Hel"},{"text":"lo"}]}}]}"#,
+        )
+        .expect("chunk");
+        assert_eq!(response_text(&chunk), "Hello");
+        assert_eq!(stop_reason(&chunk), None);
+
+        let last: Value = serde_json::from_str(
+            r#"{"candidates":[{"content":{"parts":[{"text":"!"}]},"finishReason":"STOP"}]}"#,
+        )
+        .expect("last");
+        assert_eq!(response_text(&last), "!");
+        assert_eq!(stop_reason(&last), None);
+
+        let blocked: Value = serde_json::from_str(r#"{"promptFeedback":{"blockReason":"SAFETY"}}"#)
+            .expect("blocked");
+        assert_eq!(response_text(&blocked), "");
+        assert_eq!(
+            stop_reason(&blocked).as_deref(),
+            Some("prompt blocked: SAFETY")
+        );
+    }
+
+    #[test]
+    fn error_bodies_surface_the_api_message() {
+        let body =
+            r#"{"error":{"code":400,"message":"API key not valid.","status":"INVALID_ARGUMENT"}}"#;
+        assert_eq!(error_message(body), "API key not valid.");
+        assert_eq!(error_message("Bad Gateway"), "Bad Gateway");
+    }
+
+    #[test]
+    fn curl_config_escapes_the_model_and_quotes_every_value() {
+        let config = curl_config("k\"ey", "gemini-2.0 flash/../x", "line\n\"quoted\" \\ end");
+        let lines: Vec<_> = config.lines().collect();
+        assert_eq!(
+            lines[0],
+            format!(
+                "url = \"{API_BASE}/gemini-2.0%20flash%2F..%2Fx:streamGenerateContent?alt=sse\""
+            )
+        );
+        assert_eq!(lines[1], r#"header = "x-goog-api-key: k\"ey""#);
+        assert_eq!(lines[2], r#"header = "Content-Type: application/json""#);
+        assert_eq!(
+            lines[3],
+            r#"data-binary = "{\"contents\":[{\"parts\":[{\"text\":\"line\\n\\\"quoted\\\" \\\\ end\"}],\"role\":\"user\"}]}""#
+        );
+        assert_eq!(lines.len(), 4);
+    }
+
+    #[test]
+    fn request_body_wraps_the_prompt_as_a_user_turn() {
+        let body: Value = serde_json::from_str(&request_body("say \"hi\"")).expect("body");
+        assert_eq!(body["contents"][0]["role"], "user");
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "say \"hi\"");
+    }
+}
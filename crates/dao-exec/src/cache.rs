@@ -0,0 +1,254 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::contracts::ToolInvocation;
+use crate::contracts::ToolInvocationStatus;
+use crate::contracts::ToolResult;
+use crate::executor::ToolExecutionContext;
+use crate::executor::ToolExecutionOutcome;
+use crate::executor::ToolExecutor;
+
+/// Tools whose outcome depends only on the repository contents. Plan
+/// generation (model output) and commits (side effects) are never cached.
+const CACHEABLE_TOOLS: &[&str] = &["scan_repo", "compute_diff", "verify"];
+
+/// Wraps an executor and reuses successful outcomes of read-only tools when
+/// the repository, tool and context are unchanged since they were recorded.
+#[derive(Debug, Clone)]
+pub struct CachingToolExecutor<E> {
+    inner: E,
+    dir: PathBuf,
+}
+
+impl<E> CachingToolExecutor<E> {
+    pub fn new(inner: E, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+        }
+    }
+}
+
+impl<E: ToolExecutor> ToolExecutor for CachingToolExecutor<E> {
+    fn execute(
+        &self,
+        invocation: ToolInvocation,
+        context: &ToolExecutionContext<'_>,
+    ) -> ToolExecutionOutcome {
+        if !CACHEABLE_TOOLS.contains(&invocation.tool_id.as_str()) {
+            return self.inner.execute(invocation, context);
+        }
+        let Ok(key) = cache_key(invocation.tool_id.as_str(), context) else {
+            return self.inner.execute(invocation, context);
+        };
+        let path = self.dir.join(format!("{key:016x}.json"));
+
+        if let Some(cached) = read_cached(&path) {
+            let mut logs = cached.result.logs;
+            logs.push("reused cached result (repository unchanged)".to_string());
+            return ToolExecutionOutcome {
+                result: ToolResult {
+                    run_id: invocation.run_id,
+                    invocation_id: invocation.invocation_id,
+                    logs,
+                    cache_hit: true,
+                    ..cached.result
+                },
+                payload: cached.payload,
+            };
+        }
+
+        let outcome = self.inner.execute(invocation, context);
+        if outcome.result.status == ToolInvocationStatus::Succeeded {
+            if let Ok(encoded) = serde_json::to_vec(&outcome) {
+                let _ = fs::create_dir_all(&self.dir).and_then(|()| fs::write(&path, encoded));
+            }
+        }
+        outcome
+    }
+}
+
+fn read_cached(path: &Path) -> Option<ToolExecutionOutcome> {
+    let bytes = fs::read(path).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+fn cache_key(tool_id: &str, context: &ToolExecutionContext<'_>) -> std::io::Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    tool_id.hash(&mut hasher);
+    context.model.hash(&mut hasher);
+    context.intent.hash(&mut hasher);
+    context.scan_filters.hash(&mut hasher);
+    repo_fingerprint(context.cwd)?.hash(&mut hasher);
+
+    // The working-tree walk skips .git, so fold in HEAD and the index to
+    // notice commits and staging changes.
+    if let Ok(output) = Command::new("git")
+        .current_dir(context.cwd)
+        .args(["rev-parse", "HEAD"])
+        .output()
+    {
+        output.stdout.hash(&mut hasher);
+    }
+    if let Ok(modified) = fs::metadata(context.cwd.join(".git/index")).and_then(|m| m.modified()) {
+        modified.hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+/// Hash of every file's path, size and mtime under `repo`, skipping VCS,
+/// DAO state and build output directories. Only an unreadable `repo` is an
+/// error; a subdirectory that cannot be read or vanishes mid-walk is skipped.
+pub fn repo_fingerprint(repo: &Path) -> std::io::Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    let mut pending = vec![repo.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let read = match fs::read_dir(&dir) {
+            Ok(read) => read,
+            Err(_) if dir != repo => continue,
+            Err(err) => return Err(err),
+        };
+        let mut entries = read.filter_map(|entry| entry.ok()).collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let name = entry.file_name();
+            if matches!(
+                name.to_str(),
+                Some(".git" | ".dao" | "target" | "node_modules")
+            ) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+                continue;
+            }
+            entry.path().hash(&mut hasher);
+            metadata.len().hash(&mut hasher);
+            if let Ok(modified) = metadata.modified() {
+                modified.hash(&mut hasher);
+            }
+        }
+    }
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::executor::ToolExecutionPayload;
+    use crate::scan::ScanFilters;
+
+    struct CountingExecutor {
+        calls: Cell<u32>,
+    }
+
+    impl ToolExecutor for CountingExecutor {
+        fn execute(
+            &self,
+            invocation: ToolInvocation,
+            _context: &ToolExecutionContext<'_>,
+        ) -> ToolExecutionOutcome {
+            self.calls.set(self.calls.get() + 1);
+            ToolExecutionOutcome {
+                result: ToolResult {
+                    run_id: invocation.run_id,
+                    invocation_id: invocation.invocation_id,
+                    tool_id: invocation.tool_id,
+                    status: ToolInvocationStatus::Succeeded,
+                    artifacts_emitted: Vec::new(),
+                    logs: vec![format!("call {}", self.calls.get())],
+                    cache_hit: false,
+                },
+                payload: ToolExecutionPayload::Verify {
+                    checks: Vec::new(),
+                    passing: true,
+                },
+            }
+        }
+    }
+
+    fn invocation(tool_id: &str, run_id: u64) -> ToolInvocation {
+        ToolInvocation {
+            run_id,
+            invocation_id: 4,
+            tool_id: tool_id.to_string(),
+            requested_tier: "balanced".to_string(),
+        }
+    }
+
+    #[test]
+    fn repeated_invocation_on_unchanged_repo_is_served_from_cache() {
+        let repo = tempfile::tempdir().expect("tempdir");
+        fs::write(repo.path().join("a.txt"), "a\n").expect("write");
+        let cache_dir = repo.path().join(".dao/cache");
+        let executor = CachingToolExecutor::new(
+            CountingExecutor {
+                calls: Cell::new(0),
+            },
+            &cache_dir,
+        );
+        let context = ToolExecutionContext {
+            cwd: repo.path(),
+            model: None,
+            intent: None,
+            scan_filters: &ScanFilters::default(),
+            external_tools: &[],
+            cancel: None,
+        };
+
+        let first = executor.execute(invocation("verify", 1), &context);
+        let second = executor.execute(invocation("verify", 2), &context);
+        assert_eq!(executor.inner.calls.get(), 1);
+        assert!(!first.result.cache_hit);
+        assert!(second.result.cache_hit);
+        assert_eq!(second.result.run_id, 2);
+        assert_eq!(
+            second.result.logs,
+            vec![
+                "call 1".to_string(),
+                "reused cached result (repository unchanged)".to_string()
+            ]
+        );
+
+        fs::write(repo.path().join("b.txt"), "b\n").expect("write");
+        let third = executor.execute(invocation("verify", 3), &context);
+        assert_eq!(executor.inner.calls.get(), 2);
+        assert!(!third.result.cache_hit);
+    }
+
+    #[test]
+    fn side_effecting_tools_bypass_cache() {
+        let repo = tempfile::tempdir().expect("tempdir");
+        let executor = CachingToolExecutor::new(
+            CountingExecutor {
+                calls: Cell::new(0),
+            },
+            repo.path().join(".dao/cache"),
+        );
+        let context = ToolExecutionContext {
+            cwd: repo.path(),
+            model: None,
+            intent: None,
+            scan_filters: &ScanFilters::default(),
+            external_tools: &[],
+            cancel: None,
+        };
+
+        executor.execute(invocation("git_commit", 1), &context);
+        let second = executor.execute(invocation("git_commit", 1), &context);
+        assert_eq!(executor.inner.calls.get(), 2);
+        assert!(!second.result.cache_hit);
+    }
+}
@@ -25,4 +25,6 @@ pub struct ToolResult {
     pub status: ToolInvocationStatus,
     pub artifacts_emitted: Vec<String>,
     pub logs: Vec<String>,
+    #[serde(default)]
+    pub cache_hit: bool,
 }
@@ -1,38 +1,34 @@
 use std::ffi::OsStr;
+use std::io::Read;
 use std::path::Path;
 use std::process::Command;
 use std::process::Output;
+use std::process::Stdio;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::adapters::ShellAdapter;
 use crate::contracts::ToolInvocation;
 use crate::contracts::ToolInvocationStatus;
 use crate::contracts::ToolResult;
+use crate::external::execute_external;
+use crate::external::ExternalTool;
+use crate::scan::scan_repository;
+use crate::scan::RepoScan;
+use crate::scan::ScanFilters;
+use crate::scan::ScanLimits;
+use crate::test_runner::TestRunner;
+pub use dao_core::conversion::ToolExecutionPayload;
 
-#[derive(Debug, Clone)]
-pub enum ToolExecutionPayload {
-    System {
-        summary: String,
-        detected_stack: Vec<String>,
-        entrypoints: Vec<String>,
-        risk_flags: Vec<String>,
-    },
-    Plan {
-        steps: Vec<String>,
-    },
-    Diff {
-        unified_diff: String,
-    },
-    Verify {
-        checks: Vec<String>,
-        passing: bool,
-    },
-    Commit {
-        hash: String,
-        message: String,
-    },
-}
-
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolExecutionOutcome {
     pub result: ToolResult,
     pub payload: ToolExecutionPayload,
@@ -42,6 +38,12 @@ pub struct ToolExecutionContext<'a> {
     pub cwd: &'a Path,
     pub model: Option<&'a str>,
     pub intent: Option<&'a str>,
+    pub scan_filters: &'a ScanFilters,
+    /// Tools registered in config, dispatched by name after the built-ins.
+    pub external_tools: &'a [ExternalTool],
+    /// Set once the caller stops waiting; tools kill the commands they
+    /// started and return.
+    pub cancel: Option<&'a AtomicBool>,
 }
 
 pub trait ToolExecutor {
@@ -52,6 +54,145 @@ pub trait ToolExecutor {
     ) -> ToolExecutionOutcome;
 }
 
+/// Runs `executor` on a worker thread and returns `None` when it has not
+/// finished within `timeout`. A worker that misses the deadline is cancelled,
+/// which kills the command its tool is running; its outcome is discarded. A
+/// worker that panics yields a `Failed` outcome.
+pub fn execute_with_timeout<E>(
+    executor: &Arc<E>,
+    invocation: ToolInvocation,
+    context: &ToolExecutionContext<'_>,
+    timeout: Duration,
+) -> Option<ToolExecutionOutcome>
+where
+    E: ToolExecutor + Send + Sync + ?Sized + 'static,
+{
+    let executor = Arc::clone(executor);
+    let cwd = context.cwd.to_path_buf();
+    let model = context.model.map(str::to_string);
+    let intent = context.intent.map(str::to_string);
+    let scan_filters = context.scan_filters.clone();
+    let external_tools = context.external_tools.to_vec();
+    let cancel = Arc::new(AtomicBool::new(false));
+    let worker_cancel = Arc::clone(&cancel);
+    let worker_invocation = invocation.clone();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let context = ToolExecutionContext {
+            cwd: &cwd,
+            model: model.as_deref(),
+            intent: intent.as_deref(),
+            scan_filters: &scan_filters,
+            external_tools: &external_tools,
+            cancel: Some(&worker_cancel),
+        };
+        // The receiver is gone once the deadline passed; nothing to report.
+        let _ = sender.send(executor.execute(worker_invocation, &context));
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(outcome) => Some(outcome),
+        Err(RecvTimeoutError::Timeout) => {
+            cancel.store(true, Ordering::Relaxed);
+            None
+        }
+        Err(RecvTimeoutError::Disconnected) => Some(ToolExecutionOutcome {
+            result: build_result(
+                invocation,
+                ToolInvocationStatus::Failed,
+                vec!["tool executor thread panicked".to_string()],
+            ),
+            payload: ToolExecutionPayload::Plan { steps: Vec::new() },
+        }),
+    }
+}
+
+/// How often a running command is checked against the cancel flag.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Like [`Command::output`], but kills the child and returns an
+/// `Interrupted` error once `cancel` is set.
+pub(crate) fn output_unless_cancelled(
+    command: &mut Command,
+    cancel: Option<&AtomicBool>,
+) -> std::io::Result<Output> {
+    let Some(cancel) = cancel else {
+        return command.output();
+    };
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = read_to_end_in_background(child.stdout.take());
+    let stderr = read_to_end_in_background(child.stderr.take());
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Output {
+                status,
+                stdout: stdout.join().unwrap_or_default(),
+                stderr: stderr.join().unwrap_or_default(),
+            });
+        }
+        if cancel.load(Ordering::Relaxed) {
+            let _ = child.kill();
+            let _ = child.wait();
+            // The readers are left behind: a process the child started may
+            // still hold the pipes open.
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "cancelled",
+            ));
+        }
+        thread::sleep(CANCEL_POLL_INTERVAL);
+    }
+}
+
+fn read_to_end_in_background<R>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+/// Longest wait between two attempts of [`execute_with_retries`].
+pub const MAX_RETRY_WAIT: Duration = Duration::from_secs(30);
+
+/// Calls `attempt` again while it returns a `Failed` outcome, at most
+/// `retries` more times. Retry `n` (from 1) waits `backoff * 2^(n-1)`, capped
+/// at [`MAX_RETRY_WAIT`], and is announced through `on_retry(n, wait,
+/// &failed)`. Blocked outcomes are final, and `None` (a timed-out attempt) is
+/// passed through without retrying.
+pub fn execute_with_retries(
+    retries: u32,
+    backoff: Duration,
+    mut attempt: impl FnMut() -> Option<ToolExecutionOutcome>,
+    mut on_retry: impl FnMut(u32, Duration, &ToolExecutionOutcome),
+) -> Option<ToolExecutionOutcome> {
+    let mut outcome = attempt()?;
+    for retry in 1..=retries {
+        if outcome.result.status != ToolInvocationStatus::Failed {
+            break;
+        }
+        let wait = retry_wait(backoff, retry);
+        on_retry(retry, wait, &outcome);
+        thread::sleep(wait);
+        outcome = attempt()?;
+    }
+    Some(outcome)
+}
+
+fn retry_wait(backoff: Duration, retry: u32) -> Duration {
+    backoff
+        .saturating_mul(1 << (retry - 1).min(16))
+        .min(MAX_RETRY_WAIT)
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct SimulatedToolExecutor;
 
@@ -88,6 +229,10 @@ impl ToolExecutor for SimulatedToolExecutor {
                 checks: vec!["Simulated check".to_string()],
                 passing: true,
             },
+            "run_tests" => ToolExecutionPayload::Verify {
+                checks: vec!["Simulated tests".to_string()],
+                passing: true,
+            },
             "git_commit" => ToolExecutionPayload::Commit {
                 hash: "a1b2c3d".to_string(),
                 message: "Simulated commit".to_string(),
@@ -113,53 +258,47 @@ impl ToolExecutor for RuntimeToolExecutor {
         context: &ToolExecutionContext<'_>,
     ) -> ToolExecutionOutcome {
         match invocation.tool_id.as_str() {
-            "scan_repo" => execute_scan(invocation, context.cwd),
+            "scan_repo" => execute_scan(invocation, context.cwd, context.scan_filters),
             "generate_plan" => execute_plan(invocation, context.cwd, context.model, context.intent),
             "compute_diff" => execute_diff(invocation, context.cwd),
             "verify" => execute_verify(invocation, context.cwd),
+            "run_tests" => execute_run_tests(invocation, context.cwd, context.cancel),
             "git_commit" => execute_commit(invocation, context.cwd, context.intent),
-            _ => ToolExecutionOutcome {
-                result: build_result(
+            name => match context.external_tools.iter().find(|tool| tool.name == name) {
+                Some(tool) => execute_external(
                     invocation,
-                    ToolInvocationStatus::Failed,
-                    vec!["unknown tool id".to_string()],
+                    tool,
+                    context.cwd,
+                    context.intent,
+                    context.cancel,
                 ),
-                payload: ToolExecutionPayload::Plan { steps: Vec::new() },
+                None => ToolExecutionOutcome {
+                    result: build_result(
+                        invocation,
+                        ToolInvocationStatus::Failed,
+                        vec!["unknown tool id".to_string()],
+                    ),
+                    payload: ToolExecutionPayload::Plan { steps: Vec::new() },
+                },
             },
         }
     }
 }
 
-fn execute_scan(invocation: ToolInvocation, cwd: &Path) -> ToolExecutionOutcome {
-    let mut detected_stack = Vec::new();
-    if cwd.join("Cargo.toml").exists() {
-        detected_stack.push("rust".to_string());
-    }
-    if cwd.join("package.json").exists() {
-        detected_stack.push("node".to_string());
-    }
-    if cwd.join("pyproject.toml").exists() || cwd.join("requirements.txt").exists() {
-        detected_stack.push("python".to_string());
-    }
-    if cwd.join("go.mod").exists() {
-        detected_stack.push("go".to_string());
-    }
-
-    let mut entrypoints = Vec::new();
-    for entrypoint in [
-        "README.md",
-        "Cargo.toml",
-        "package.json",
-        "pyproject.toml",
-        "Makefile",
-        "justfile",
-    ] {
-        if cwd.join(entrypoint).exists() {
-            entrypoints.push(entrypoint.to_string());
-        }
-    }
-
-    let mut risk_flags = Vec::new();
+fn execute_scan(
+    invocation: ToolInvocation,
+    cwd: &Path,
+    filters: &ScanFilters,
+) -> ToolExecutionOutcome {
+    let scan = scan_repository(cwd, &ScanLimits::default(), filters);
+    let RepoScan {
+        languages,
+        detected_stack,
+        entrypoints,
+        mut risk_flags,
+        files_scanned,
+        ..
+    } = scan;
     if let Ok(output) = run_git(cwd, ["status", "--porcelain"]) {
         if !stdout_text(&output).trim().is_empty() {
             risk_flags.push("dirty_worktree".to_string());
@@ -171,11 +310,18 @@ fn execute_scan(invocation: ToolInvocation, cwd: &Path) -> ToolExecutionOutcome
     } else {
         detected_stack.join(", ")
     };
-    let summary = format!(
-        "Scanned {} (stack: {stack_label}, entrypoints: {})",
+    let mut summary = format!(
+        "Scanned {} (stack: {stack_label}, entrypoints: {}, files: {files_scanned})",
         cwd.display(),
         entrypoints.len()
     );
+    if !languages.is_empty() {
+        let counts = languages
+            .iter()
+            .map(|(language, count)| format!("{language} {count}"))
+            .collect::<Vec<_>>();
+        summary.push_str(&format!("\nLanguages: {}", counts.join(", ")));
+    }
 
     ToolExecutionOutcome {
         result: build_result(
@@ -213,31 +359,16 @@ fn execute_plan(
 }
 
 fn execute_diff(invocation: ToolInvocation, cwd: &Path) -> ToolExecutionOutcome {
-    let diff_output = run_git_allow_diff_exit(cwd, ["diff", "--no-color"]);
-    let untracked_output = run_git(cwd, ["ls-files", "--others", "--exclude-standard"]);
-
-    match (diff_output, untracked_output) {
-        (Ok(diff), Ok(untracked)) => {
-            let mut unified_diff = stdout_text(&diff);
-            let untracked_files = stdout_text(&untracked);
-            for file in untracked_files
-                .lines()
-                .map(str::trim)
-                .filter(|line| !line.is_empty())
-            {
-                unified_diff.push_str(&format!("\n+++ b/{file}\n@@\n+<untracked file>\n"));
-            }
-
-            ToolExecutionOutcome {
-                result: build_result(
-                    invocation,
-                    ToolInvocationStatus::Succeeded,
-                    vec!["diff computed".to_string()],
-                ),
-                payload: ToolExecutionPayload::Diff { unified_diff },
-            }
-        }
-        (Err(err), _) | (_, Err(err)) => ToolExecutionOutcome {
+    match working_tree_diff(cwd, None) {
+        Ok(unified_diff) => ToolExecutionOutcome {
+            result: build_result(
+                invocation,
+                ToolInvocationStatus::Succeeded,
+                vec!["diff computed".to_string()],
+            ),
+            payload: ToolExecutionPayload::Diff { unified_diff },
+        },
+        Err(err) => ToolExecutionOutcome {
             result: build_result(
                 invocation,
                 ToolInvocationStatus::Failed,
@@ -250,6 +381,80 @@ fn execute_diff(invocation: ToolInvocation, cwd: &Path) -> ToolExecutionOutcome
     }
 }
 
+/// Unified diff of the working tree against `commit`, including untracked files.
+pub fn diff_since_commit(cwd: &Path, commit: &str) -> std::io::Result<String> {
+    let spec = format!("{commit}^{{commit}}");
+    let verified = run_git(cwd, ["rev-parse", "--verify", "--quiet", spec.as_str()])?;
+    if !verified.status.success() {
+        return Err(std::io::Error::other(format!("unknown commit {commit}")));
+    }
+    working_tree_diff(cwd, Some(commit))
+}
+
+/// Unified diff of one working-tree file against the index. An untracked file
+/// is shown as wholly added.
+pub fn diff_path(cwd: &Path, path: &Path) -> std::io::Result<String> {
+    let path = path.strip_prefix(cwd).unwrap_or(path);
+    let diff = run_git_allow_diff_exit(
+        cwd,
+        [
+            OsStr::new("diff"),
+            OsStr::new("--no-color"),
+            OsStr::new("--"),
+            path.as_os_str(),
+        ],
+    )?;
+    let unified_diff = stdout_text(&diff);
+    if !unified_diff.trim().is_empty() {
+        return Ok(unified_diff);
+    }
+    let untracked = run_git(
+        cwd,
+        [
+            OsStr::new("ls-files"),
+            OsStr::new("--others"),
+            OsStr::new("--exclude-standard"),
+            OsStr::new("--"),
+            path.as_os_str(),
+        ],
+    )?;
+    if stdout_text(&untracked).trim().is_empty() {
+        return Ok(String::new());
+    }
+    let added = run_git_allow_diff_exit(
+        cwd,
+        [
+            OsStr::new("diff"),
+            OsStr::new("--no-color"),
+            OsStr::new("--no-index"),
+            OsStr::new("--"),
+            OsStr::new("/dev/null"),
+            path.as_os_str(),
+        ],
+    )?;
+    Ok(stdout_text(&added))
+}
+
+fn working_tree_diff(cwd: &Path, base: Option<&str>) -> std::io::Result<String> {
+    let mut args = vec!["diff", "--no-color"];
+    args.extend(base);
+    let diff = run_git_allow_diff_exit(cwd, args)?;
+    let untracked = run_git(cwd, ["ls-files", "--others", "--exclude-standard"])?;
+
+    let mut unified_diff = stdout_text(&diff);
+    let untracked_files = stdout_text(&untracked);
+    for file in untracked_files
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        unified_diff.push_str(&format!(
+            "\n--- /dev/null\n+++ b/{file}\n@@\n+<untracked file>\n"
+        ));
+    }
+    Ok(unified_diff)
+}
+
 fn execute_verify(invocation: ToolInvocation, cwd: &Path) -> ToolExecutionOutcome {
     match run_git_allow_diff_exit(cwd, ["diff", "--check"]) {
         Ok(output) => {
@@ -283,6 +488,69 @@ fn execute_verify(invocation: ToolInvocation, cwd: &Path) -> ToolExecutionOutcom
     }
 }
 
+/// Trailing test output lines kept in the tool result logs.
+const TEST_OUTPUT_LOG_LINES: usize = 20;
+
+fn execute_run_tests(
+    invocation: ToolInvocation,
+    cwd: &Path,
+    cancel: Option<&AtomicBool>,
+) -> ToolExecutionOutcome {
+    let Some(runner) = TestRunner::detect(cwd) else {
+        return ToolExecutionOutcome {
+            result: build_result(
+                invocation,
+                ToolInvocationStatus::Failed,
+                vec![
+                    "no test runner found (expected Cargo.toml or package.json at the repository root)"
+                        .to_string(),
+                ],
+            ),
+            payload: ToolExecutionPayload::Verify {
+                checks: vec!["run tests".to_string()],
+                passing: false,
+            },
+        };
+    };
+    match ShellAdapter::run_tests(cwd, runner, cancel) {
+        Ok((report, output)) => {
+            let mut logs = vec![format!("ran {}", runner.label())];
+            let lines = output
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .collect::<Vec<_>>();
+            logs.extend(
+                lines[lines.len().saturating_sub(TEST_OUTPUT_LOG_LINES)..]
+                    .iter()
+                    .map(|line| line.to_string()),
+            );
+            logs.push(if report.passing {
+                "tests passed".to_string()
+            } else {
+                "tests failed".to_string()
+            });
+            ToolExecutionOutcome {
+                result: build_result(invocation, ToolInvocationStatus::Succeeded, logs),
+                payload: ToolExecutionPayload::Verify {
+                    checks: report.checks,
+                    passing: report.passing,
+                },
+            }
+        }
+        Err(err) => ToolExecutionOutcome {
+            result: build_result(
+                invocation,
+                ToolInvocationStatus::Failed,
+                vec![format!("{} failed to start: {err}", runner.label())],
+            ),
+            payload: ToolExecutionPayload::Verify {
+                checks: vec![runner.label().to_string()],
+                passing: false,
+            },
+        },
+    }
+}
+
 fn execute_commit(
     invocation: ToolInvocation,
     cwd: &Path,
@@ -356,7 +624,7 @@ fn emitted_artifacts(tool_id: &str) -> Vec<String> {
         "scan_repo" => vec!["system".to_string(), "logs".to_string()],
         "generate_plan" => vec!["plan".to_string(), "logs".to_string()],
         "compute_diff" => vec!["diff".to_string(), "logs".to_string()],
-        "verify" => vec!["verify".to_string(), "logs".to_string()],
+        "verify" | "run_tests" => vec!["verify".to_string(), "logs".to_string()],
         "git_commit" => vec!["system".to_string(), "logs".to_string()],
         _ => Vec::new(),
     }
@@ -374,6 +642,7 @@ fn build_result(
         status,
         artifacts_emitted: emitted_artifacts(invocation.tool_id.as_str()),
         logs,
+        cache_hit: false,
     }
 }
 
@@ -470,6 +739,9 @@ mod tests {
             cwd: Path::new("."),
             model: None,
             intent: None,
+            scan_filters: &ScanFilters::default(),
+            external_tools: &[],
+            cancel: None,
         };
         let executor = SimulatedToolExecutor;
         let first = executor.execute(invocation.clone(), &context);
@@ -486,6 +758,190 @@ mod tests {
         }
     }
 
+    struct SleepingExecutor(Duration);
+
+    impl ToolExecutor for SleepingExecutor {
+        fn execute(
+            &self,
+            invocation: ToolInvocation,
+            context: &ToolExecutionContext<'_>,
+        ) -> ToolExecutionOutcome {
+            thread::sleep(self.0);
+            SimulatedToolExecutor.execute(invocation, context)
+        }
+    }
+
+    #[test]
+    fn execute_with_timeout_gives_up_on_tools_past_the_deadline() {
+        let context = ToolExecutionContext {
+            cwd: Path::new("."),
+            model: None,
+            intent: None,
+            scan_filters: &ScanFilters::default(),
+            external_tools: &[],
+            cancel: None,
+        };
+        let slow = Arc::new(SleepingExecutor(Duration::from_secs(2)));
+        let timed_out = execute_with_timeout(
+            &slow,
+            invocation("verify"),
+            &context,
+            Duration::from_millis(50),
+        );
+        assert!(timed_out.is_none());
+
+        let fast = Arc::new(SleepingExecutor(Duration::ZERO));
+        let outcome = execute_with_timeout(
+            &fast,
+            invocation("verify"),
+            &context,
+            Duration::from_secs(5),
+        )
+        .expect("fast tool should finish before the deadline");
+        assert_eq!(outcome.result.status, ToolInvocationStatus::Succeeded);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn execute_with_timeout_kills_the_command_past_the_deadline() {
+        let repo = TempDir::new().expect("tempdir");
+        let external_tools = [ExternalTool {
+            name: "slow".to_string(),
+            command: "sleep 1; touch finished".to_string(),
+            output: crate::external::ExternalToolOutput::Verify,
+        }];
+        let context = ToolExecutionContext {
+            cwd: repo.path(),
+            model: None,
+            intent: None,
+            scan_filters: &ScanFilters::default(),
+            external_tools: &external_tools,
+            cancel: None,
+        };
+        let timed_out = execute_with_timeout(
+            &Arc::new(RuntimeToolExecutor),
+            invocation("slow"),
+            &context,
+            Duration::from_millis(50),
+        );
+        assert!(timed_out.is_none());
+
+        thread::sleep(Duration::from_millis(1500));
+        assert!(!repo.path().join("finished").exists());
+    }
+
+    struct PanickingExecutor;
+
+    impl ToolExecutor for PanickingExecutor {
+        fn execute(
+            &self,
+            _invocation: ToolInvocation,
+            _context: &ToolExecutionContext<'_>,
+        ) -> ToolExecutionOutcome {
+            panic!("tool bug");
+        }
+    }
+
+    #[test]
+    fn execute_with_timeout_reports_a_panicking_tool_as_failed() {
+        let context = ToolExecutionContext {
+            cwd: Path::new("."),
+            model: None,
+            intent: None,
+            scan_filters: &ScanFilters::default(),
+            external_tools: &[],
+            cancel: None,
+        };
+        let outcome = execute_with_timeout(
+            &Arc::new(PanickingExecutor),
+            invocation("verify"),
+            &context,
+            Duration::from_secs(5),
+        )
+        .expect("a panic is not a timeout");
+        assert_eq!(outcome.result.status, ToolInvocationStatus::Failed);
+        assert_eq!(outcome.result.logs, vec!["tool executor thread panicked"]);
+    }
+
+    fn outcome_with(status: ToolInvocationStatus) -> ToolExecutionOutcome {
+        ToolExecutionOutcome {
+            result: build_result(invocation("flaky"), status, vec![format!("{status:?}")]),
+            payload: ToolExecutionPayload::Plan { steps: Vec::new() },
+        }
+    }
+
+    #[test]
+    fn execute_with_retries_backs_off_until_the_tool_stops_failing() {
+        let flaky = |failures: u32, then: ToolInvocationStatus, retries: u32| {
+            let mut calls = 0;
+            let mut waits = Vec::new();
+            let outcome = execute_with_retries(
+                retries,
+                Duration::from_millis(1),
+                || {
+                    calls += 1;
+                    Some(outcome_with(if calls <= failures {
+                        ToolInvocationStatus::Failed
+                    } else {
+                        then
+                    }))
+                },
+                |retry, wait, failed| {
+                    assert_eq!(failed.result.status, ToolInvocationStatus::Failed);
+                    waits.push((retry, wait.as_millis()));
+                },
+            )
+            .expect("outcome");
+            (outcome.result.status, calls, waits)
+        };
+
+        assert_eq!(
+            flaky(2, ToolInvocationStatus::Succeeded, 3),
+            (ToolInvocationStatus::Succeeded, 3, vec![(1, 1), (2, 2)])
+        );
+        assert_eq!(
+            flaky(5, ToolInvocationStatus::Succeeded, 3),
+            (
+                ToolInvocationStatus::Failed,
+                4,
+                vec![(1, 1), (2, 2), (3, 4)]
+            )
+        );
+        assert_eq!(
+            flaky(1, ToolInvocationStatus::Succeeded, 0),
+            (ToolInvocationStatus::Failed, 1, vec![])
+        );
+        assert_eq!(
+            flaky(0, ToolInvocationStatus::Blocked, 3),
+            (ToolInvocationStatus::Blocked, 1, vec![])
+        );
+    }
+
+    #[test]
+    fn retry_waits_are_capped() {
+        let backoff = Duration::from_millis(500);
+        assert_eq!(retry_wait(backoff, 1), backoff);
+        assert_eq!(retry_wait(backoff, 6), Duration::from_secs(16));
+        assert_eq!(retry_wait(backoff, 7), MAX_RETRY_WAIT);
+        assert_eq!(retry_wait(backoff, u32::MAX), MAX_RETRY_WAIT);
+    }
+
+    #[test]
+    fn execute_with_retries_stops_at_a_timed_out_attempt() {
+        let mut calls = 0;
+        let outcome = execute_with_retries(
+            3,
+            Duration::ZERO,
+            || {
+                calls += 1;
+                (calls == 1).then(|| outcome_with(ToolInvocationStatus::Failed))
+            },
+            |_, _, _| {},
+        );
+        assert!(outcome.is_none());
+        assert_eq!(calls, 2);
+    }
+
     #[test]
     fn executors_preserve_contract_shape_for_all_workflow_tools() {
         let fixture = make_repo_fixture();
@@ -493,6 +949,9 @@ mod tests {
             cwd: fixture.path(),
             model: None,
             intent: None,
+            scan_filters: &ScanFilters::default(),
+            external_tools: &[],
+            cancel: None,
         };
         let simulated = SimulatedToolExecutor;
         let runtime = RuntimeToolExecutor;
@@ -543,6 +1002,9 @@ mod tests {
             cwd: temp.path(),
             model: None,
             intent: None,
+            scan_filters: &ScanFilters::default(),
+            external_tools: &[],
+            cancel: None,
         };
         let executor = RuntimeToolExecutor;
         let invocation = invocation("compute_diff");
@@ -558,4 +1020,82 @@ mod tests {
             _ => panic!("expected diff payload"),
         }
     }
+
+    #[test]
+    fn runtime_run_tests_fails_without_a_test_runner() {
+        let temp = tempfile::tempdir().expect("tempdir");
+        let context = ToolExecutionContext {
+            cwd: temp.path(),
+            model: None,
+            intent: None,
+            scan_filters: &ScanFilters::default(),
+            external_tools: &[],
+            cancel: None,
+        };
+
+        let outcome = RuntimeToolExecutor.execute(invocation("run_tests"), &context);
+        assert_eq!(outcome.result.status, ToolInvocationStatus::Failed);
+        assert_eq!(
+            outcome.result.artifacts_emitted,
+            vec!["verify".to_string(), "logs".to_string()]
+        );
+        match outcome.payload {
+            ToolExecutionPayload::Verify { passing, .. } => assert!(!passing),
+            _ => panic!("expected verify payload"),
+        }
+    }
+
+    #[test]
+    fn diff_since_commit_includes_committed_and_working_changes() {
+        let fixture = make_repo_fixture();
+        let base = stdout_text(
+            &run_git(fixture.path(), ["rev-parse", "HEAD"]).expect("rev-parse should run"),
+        )
+        .trim()
+        .to_string();
+        run_git_ok(fixture.path(), &["add", "README.md"]);
+        run_git_ok(fixture.path(), &["commit", "-m", "second"]);
+        fs::write(
+            fixture.path().join("Cargo.toml"),
+            "[package]\nname = \"renamed\"\n",
+        )
+        .expect("modify Cargo.toml");
+
+        let diff = diff_since_commit(fixture.path(), &base).expect("diff should succeed");
+        assert!(diff.contains("+++ b/README.md"));
+        assert!(diff.contains("+++ b/Cargo.toml"));
+        assert!(diff.contains("+++ b/untracked.txt"));
+    }
+
+    #[test]
+    fn diff_path_limits_the_diff_to_one_file() {
+        let fixture = make_repo_fixture();
+        fs::write(
+            fixture.path().join("Cargo.toml"),
+            "[package]\nname = \"renamed\"\n",
+        )
+        .expect("modify Cargo.toml");
+
+        let diff =
+            diff_path(fixture.path(), &fixture.path().join("README.md")).expect("diff README");
+        assert!(diff.contains("+++ b/README.md"));
+        assert!(diff.contains("+changed"));
+        assert!(!diff.contains("Cargo.toml"));
+
+        let untracked =
+            diff_path(fixture.path(), Path::new("untracked.txt")).expect("diff untracked");
+        assert!(untracked.contains("+++ b/untracked.txt"));
+        assert!(untracked.contains("+hello"));
+
+        run_git_ok(fixture.path(), &["checkout", "--", "README.md"]);
+        let clean = diff_path(fixture.path(), Path::new("README.md")).expect("diff clean");
+        assert_eq!(clean, "");
+    }
+
+    #[test]
+    fn diff_since_commit_rejects_unknown_commit() {
+        let fixture = make_repo_fixture();
+        let err = diff_since_commit(fixture.path(), "deadbeef").expect_err("should fail");
+        assert!(err.to_string().contains("unknown commit deadbeef"));
+    }
 }
@@ -0,0 +1,223 @@
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+
+use crate::contracts::ToolInvocation;
+use crate::contracts::ToolInvocationStatus;
+use crate::contracts::ToolResult;
+use crate::executor::output_unless_cancelled;
+use crate::executor::ToolExecutionOutcome;
+use crate::executor::ToolExecutionPayload;
+
+/// Trailing output lines kept in the tool result logs.
+const OUTPUT_LOG_LINES: usize = 20;
+
+/// How the output of an external tool becomes an artifact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExternalToolOutput {
+    /// Stdout is a unified diff.
+    Diff,
+    /// The exit status is the verdict; the output is kept in the logs.
+    Verify,
+}
+
+/// A user-registered command run as a workflow tool.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExternalTool {
+    pub name: String,
+    /// Shell command; `{cwd}` and `{intent}` are replaced with quoted values.
+    pub command: String,
+    pub output: ExternalToolOutput,
+}
+
+impl ExternalTool {
+    pub fn render_command(&self, cwd: &Path, intent: Option<&str>) -> String {
+        self.command
+            .replace("{cwd}", &shell_quote(&cwd.display().to_string()))
+            .replace("{intent}", &shell_quote(intent.unwrap_or_default()))
+    }
+}
+
+pub fn execute_external(
+    invocation: ToolInvocation,
+    tool: &ExternalTool,
+    cwd: &Path,
+    intent: Option<&str>,
+    cancel: Option<&AtomicBool>,
+) -> ToolExecutionOutcome {
+    let command = tool.render_command(cwd, intent);
+    let output = output_unless_cancelled(shell(&command).current_dir(cwd), cancel);
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            return outcome(
+                invocation,
+                ToolInvocationStatus::Failed,
+                vec![format!("failed to start {}: {err}", tool.name)],
+                empty_payload(tool.output),
+            );
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut logs = vec![format!("ran {}: {command}", tool.name)];
+    let tail = stdout
+        .lines()
+        .chain(stderr.lines())
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    logs.extend(
+        tail[tail.len().saturating_sub(OUTPUT_LOG_LINES)..]
+            .iter()
+            .map(|line| line.to_string()),
+    );
+
+    match tool.output {
+        ExternalToolOutput::Diff => {
+            let status = if output.status.success() {
+                ToolInvocationStatus::Succeeded
+            } else {
+                logs.push(format!("{} exited with {}", tool.name, output.status));
+                ToolInvocationStatus::Failed
+            };
+            outcome(
+                invocation,
+                status,
+                logs,
+                ToolExecutionPayload::Diff {
+                    unified_diff: stdout,
+                },
+            )
+        }
+        ExternalToolOutput::Verify => {
+            let passing = output.status.success();
+            logs.push(if passing {
+                format!("{} passed", tool.name)
+            } else {
+                format!("{} failed ({})", tool.name, output.status)
+            });
+            outcome(
+                invocation,
+                ToolInvocationStatus::Succeeded,
+                logs,
+                ToolExecutionPayload::Verify {
+                    checks: vec![tool.name.clone()],
+                    passing,
+                },
+            )
+        }
+    }
+}
+
+fn outcome(
+    invocation: ToolInvocation,
+    status: ToolInvocationStatus,
+    logs: Vec<String>,
+    payload: ToolExecutionPayload,
+) -> ToolExecutionOutcome {
+    ToolExecutionOutcome {
+        result: ToolResult {
+            run_id: invocation.run_id,
+            invocation_id: invocation.invocation_id,
+            tool_id: invocation.tool_id,
+            status,
+            artifacts_emitted: Vec::new(),
+            logs,
+            cache_hit: false,
+        },
+        payload,
+    }
+}
+
+fn empty_payload(output: ExternalToolOutput) -> ToolExecutionPayload {
+    match output {
+        ExternalToolOutput::Diff => ToolExecutionPayload::Diff {
+            unified_diff: String::new(),
+        },
+        ExternalToolOutput::Verify => ToolExecutionPayload::Verify {
+            checks: Vec::new(),
+            passing: false,
+        },
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.args(["-c", command]);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.args(["/C", command]);
+    shell
+}
+
+#[cfg(unix)]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(windows)]
+fn shell_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn invocation(tool_id: &str) -> ToolInvocation {
+        ToolInvocation {
+            run_id: 1,
+            invocation_id: 5,
+            tool_id: tool_id.to_string(),
+            requested_tier: "balanced".to_string(),
+        }
+    }
+
+    #[test]
+    fn external_tools_produce_diff_and_verify_payloads() {
+        let repo = tempfile::tempdir().expect("tempdir");
+        let formatter = ExternalTool {
+            name: "fmt".to_string(),
+            command: "printf '+++ b/%s\\n@@\\n+x\\n' {intent}".to_string(),
+            output: ExternalToolOutput::Diff,
+        };
+        let outcome = execute_external(
+            invocation("fmt"),
+            &formatter,
+            repo.path(),
+            Some("it's.txt"),
+            None,
+        );
+        assert_eq!(outcome.result.status, ToolInvocationStatus::Succeeded);
+        match outcome.payload {
+            ToolExecutionPayload::Diff { unified_diff } => {
+                assert_eq!(unified_diff, "+++ b/it's.txt\n@@\n+x\n");
+            }
+            other => panic!("unexpected payload: {other:?}"),
+        }
+
+        let lint = ExternalTool {
+            name: "lint".to_string(),
+            command: "test -d {cwd} && echo checked && exit 3".to_string(),
+            output: ExternalToolOutput::Verify,
+        };
+        let outcome = execute_external(invocation("lint"), &lint, repo.path(), None, None);
+        assert_eq!(outcome.result.status, ToolInvocationStatus::Succeeded);
+        assert!(outcome.result.logs.contains(&"checked".to_string()));
+        match outcome.payload {
+            ToolExecutionPayload::Verify { checks, passing } => {
+                assert_eq!(checks, vec!["lint".to_string()]);
+                assert!(!passing);
+            }
+            other => panic!("unexpected payload: {other:?}"),
+        }
+    }
+}
@@ -1,7 +1,15 @@
 pub mod adapters;
+pub mod cache;
 pub mod contracts;
 pub mod executor;
+pub mod external;
+pub mod scan;
+pub mod test_runner;
 
 pub use adapters::*;
+pub use cache::*;
 pub use contracts::*;
 pub use executor::*;
+pub use external::*;
+pub use scan::*;
+pub use test_runner::*;
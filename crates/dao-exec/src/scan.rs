@@ -0,0 +1,575 @@
+use dao_core::glob::glob_matches;
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Read;
+use std::num::NonZeroUsize;
+use std::panic::resume_unwind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Bounds on how much of a repository a scan may visit.
+#[derive(Debug, Clone)]
+pub struct ScanLimits {
+    pub max_depth: usize,
+    pub max_files: usize,
+    pub time_budget: Duration,
+}
+
+impl Default for ScanLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            max_files: 20_000,
+            time_budget: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Glob filters applied to repository-relative paths. A pattern containing `/`
+/// matches the path or one of its parent directories; any other pattern matches
+/// a single path component. `*` and `?` stay within a component, `**` spans
+/// directories. Excludes win over includes; an empty include list keeps all.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ScanFilters {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl ScanFilters {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn allows(&self, path: &str) -> bool {
+        if self.excludes(path) {
+            return false;
+        }
+        self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| path_matches(pattern, path))
+    }
+
+    fn excludes(&self, path: &str) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| path_matches(pattern, path))
+    }
+}
+
+fn path_matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+    if pattern.is_empty() {
+        return false;
+    }
+    if !pattern.contains('/') {
+        return path
+            .split('/')
+            .any(|component| glob_matches(pattern, component));
+    }
+    let mut prefix_end = path.len();
+    loop {
+        if glob_matches(pattern, &path[..prefix_end]) {
+            return true;
+        }
+        match path[..prefix_end].rfind('/') {
+            Some(slash) => prefix_end = slash,
+            None => return false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoScan {
+    /// Source file count per language.
+    pub languages: BTreeMap<String, usize>,
+    pub detected_stack: Vec<String>,
+    pub entrypoints: Vec<String>,
+    pub risk_flags: Vec<String>,
+    pub files_scanned: usize,
+    pub truncated: bool,
+}
+
+/// Files at or above this size that look binary are flagged.
+const LARGE_BINARY_BYTES: u64 = 1024 * 1024;
+const SKIPPED_DIRS: &[&str] = &[".git", ".dao", "target", "node_modules"];
+/// Upper bound on scan threads, whatever the core count.
+const SCAN_WORKERS_MAX: usize = 8;
+/// Files a worker gets at minimum, so small repositories stay on one thread.
+const SCAN_CHUNK_FILES: usize = 256;
+/// Directories a walk worker lists at minimum.
+const WALK_CHUNK_DIRS: usize = 16;
+
+const TOP_LEVEL_ENTRYPOINTS: &[&str] = &[
+    "README.md",
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "Makefile",
+    "justfile",
+];
+
+/// Walks `root` and detects languages, build systems, entrypoints and risky
+/// files. Inside a git work tree the file list comes from git, so ignore rules
+/// are honored; otherwise a bounded directory walk skips VCS and build output.
+/// Only paths allowed by `filters` are considered. Both the walk and the
+/// per-file checks are split across up to [`SCAN_WORKERS_MAX`] threads; the
+/// results are merged in sorted order, so they do not depend on scheduling.
+pub fn scan_repository(root: &Path, limits: &ScanLimits, filters: &ScanFilters) -> RepoScan {
+    let started = Instant::now();
+    let (files, truncated) = git_listed_files(root, limits, filters)
+        .unwrap_or_else(|| walk_files(root, limits, filters, started));
+
+    let mut partial = ScanPartial {
+        truncated,
+        ..ScanPartial::default()
+    };
+    for chunk in in_parallel(&files, SCAN_CHUNK_FILES, |paths| {
+        classify_files(root, paths, limits, started)
+    }) {
+        partial.merge(chunk);
+    }
+    if partial.truncated {
+        partial.risk_flags.insert("scan_truncated".to_string());
+    }
+
+    RepoScan {
+        languages: partial.languages,
+        detected_stack: partial.stack.into_iter().collect(),
+        entrypoints: partial.entrypoints.into_iter().collect(),
+        risk_flags: partial.risk_flags.into_iter().collect(),
+        files_scanned: partial.files_scanned,
+        truncated: partial.truncated,
+    }
+}
+
+/// What one worker found in its share of the files.
+#[derive(Debug, Default)]
+struct ScanPartial {
+    files_scanned: usize,
+    languages: BTreeMap<String, usize>,
+    stack: BTreeSet<String>,
+    entrypoints: BTreeSet<String>,
+    risk_flags: BTreeSet<String>,
+    truncated: bool,
+}
+
+impl ScanPartial {
+    fn merge(&mut self, other: ScanPartial) {
+        self.files_scanned += other.files_scanned;
+        for (language, count) in other.languages {
+            *self.languages.entry(language).or_default() += count;
+        }
+        self.stack.extend(other.stack);
+        self.entrypoints.extend(other.entrypoints);
+        self.risk_flags.extend(other.risk_flags);
+        self.truncated |= other.truncated;
+    }
+}
+
+fn classify_files(
+    root: &Path,
+    paths: &[String],
+    limits: &ScanLimits,
+    started: Instant,
+) -> ScanPartial {
+    let mut partial = ScanPartial::default();
+    for path in paths {
+        if started.elapsed() > limits.time_budget {
+            partial.truncated = true;
+            break;
+        }
+        partial.files_scanned += 1;
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        if let Some(language) = language_for(name) {
+            *partial.languages.entry(language.to_string()).or_default() += 1;
+            partial.stack.insert(language.to_string());
+        }
+        if let Some(build) = build_system_for(name) {
+            partial.stack.insert(build.to_string());
+        }
+        if TOP_LEVEL_ENTRYPOINTS.contains(&path.as_str()) || is_entrypoint(path) {
+            partial.entrypoints.insert(path.clone());
+        }
+        if is_secrets_file(name) {
+            partial.risk_flags.insert(format!("secrets_file:{path}"));
+        }
+        if is_large_binary(&root.join(path)) {
+            partial.risk_flags.insert(format!("large_binary:{path}"));
+        }
+    }
+    partial
+}
+
+/// Splits `items` into chunks of at least `min_chunk` and runs `work` on each
+/// chunk on its own scoped thread, at most [`SCAN_WORKERS_MAX`] at a time.
+/// Results come back in chunk order.
+fn in_parallel<T, R, F>(items: &[T], min_chunk: usize, work: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&[T]) -> R + Sync,
+{
+    let workers = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(SCAN_WORKERS_MAX)
+        .min(items.len().div_ceil(min_chunk.max(1)))
+        .max(1);
+    if workers == 1 {
+        return vec![work(items)];
+    }
+    let chunk_size = items.len().div_ceil(workers);
+    thread::scope(|scope| {
+        let handles = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| work(chunk)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|panic| resume_unwind(panic)))
+            .collect()
+    })
+}
+
+fn git_listed_files(
+    root: &Path,
+    limits: &ScanLimits,
+    filters: &ScanFilters,
+) -> Option<(Vec<String>, bool)> {
+    let output = Command::new("git")
+        .current_dir(root)
+        .args([
+            "ls-files",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+            "-z",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let mut truncated = false;
+    let mut files = Vec::new();
+    for raw in output.stdout.split(|byte| *byte == 0) {
+        let Ok(path) = std::str::from_utf8(raw) else {
+            continue;
+        };
+        if path.is_empty()
+            || path.split('/').any(|part| SKIPPED_DIRS.contains(&part))
+            || !filters.allows(path)
+        {
+            continue;
+        }
+        if path.matches('/').count() >= limits.max_depth {
+            truncated = true;
+            continue;
+        }
+        if files.len() == limits.max_files {
+            truncated = true;
+            break;
+        }
+        files.push(path.to_string());
+    }
+    files.sort();
+    files.dedup();
+    Some((files, truncated))
+}
+
+/// Directory walk used outside a git work tree. Each depth level is listed in
+/// parallel, and a level that overflows `max_files` keeps its first files in
+/// path order.
+fn walk_files(
+    root: &Path,
+    limits: &ScanLimits,
+    filters: &ScanFilters,
+    started: Instant,
+) -> (Vec<String>, bool) {
+    let mut files = Vec::new();
+    let mut truncated = false;
+    let mut level = vec![root.to_path_buf()];
+    let mut depth = 0usize;
+    while !level.is_empty() {
+        let mut level_files = Vec::new();
+        let mut next = Vec::new();
+        for listing in in_parallel(&level, WALK_CHUNK_DIRS, |dirs| {
+            list_dirs(root, dirs, depth, limits, filters, started)
+        }) {
+            level_files.extend(listing.files);
+            next.extend(listing.dirs);
+            truncated |= listing.truncated;
+        }
+        level_files.sort();
+        let room = limits.max_files - files.len();
+        if level_files.len() > room {
+            level_files.truncate(room);
+            truncated = true;
+        }
+        files.append(&mut level_files);
+        if truncated && (files.len() == limits.max_files || started.elapsed() > limits.time_budget)
+        {
+            break;
+        }
+        next.sort();
+        level = next;
+        depth += 1;
+    }
+    files.sort();
+    (files, truncated)
+}
+
+/// Files and subdirectories found directly inside a set of directories.
+#[derive(Debug, Default)]
+struct DirListing {
+    files: Vec<String>,
+    dirs: Vec<PathBuf>,
+    truncated: bool,
+}
+
+fn list_dirs(
+    root: &Path,
+    dirs: &[PathBuf],
+    depth: usize,
+    limits: &ScanLimits,
+    filters: &ScanFilters,
+    started: Instant,
+) -> DirListing {
+    let mut listing = DirListing::default();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if started.elapsed() > limits.time_budget {
+                listing.truncated = true;
+                return listing;
+            }
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let Ok(relative) = entry
+                .path()
+                .strip_prefix(root)
+                .map(|path| path.to_string_lossy().replace('\\', "/"))
+            else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if SKIPPED_DIRS.contains(&name) || filters.excludes(&relative) {
+                    continue;
+                }
+                if depth + 1 >= limits.max_depth {
+                    listing.truncated = true;
+                    continue;
+                }
+                listing.dirs.push(entry.path());
+            } else if file_type.is_file() && filters.allows(&relative) {
+                listing.files.push(relative);
+            }
+        }
+    }
+    listing
+}
+
+fn language_for(name: &str) -> Option<&'static str> {
+    let extension = name.rsplit_once('.')?.1;
+    Some(match extension {
+        "rs" => "rust",
+        "ts" | "tsx" => "typescript",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "py" => "python",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "rb" => "ruby",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "c++",
+        "cs" => "c#",
+        "swift" => "swift",
+        "php" => "php",
+        "sh" | "bash" => "shell",
+        _ => return None,
+    })
+}
+
+fn build_system_for(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "Cargo.toml" => "rust",
+        "package.json" => "node",
+        "pyproject.toml" | "requirements.txt" | "setup.py" => "python",
+        "go.mod" => "go",
+        "pom.xml" => "maven",
+        "build.gradle" | "build.gradle.kts" => "gradle",
+        "Gemfile" => "ruby",
+        "CMakeLists.txt" => "cmake",
+        "Dockerfile" => "docker",
+        _ => return None,
+    })
+}
+
+fn is_entrypoint(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    matches!(
+        name,
+        "main.rs" | "main.go" | "main.py" | "__main__.py" | "manage.py"
+    ) || (path.contains("/bin/") && name.ends_with(".rs"))
+        || matches!(
+            path,
+            "index.js" | "index.ts" | "src/index.js" | "src/index.ts" | "src/lib.rs" | "app.py"
+        )
+}
+
+fn is_secrets_file(name: &str) -> bool {
+    matches!(
+        name,
+        ".env" | "id_rsa" | "id_ed25519" | "credentials.json" | ".npmrc" | ".pypirc"
+    ) || (name.starts_with(".env.") && !name.ends_with(".example"))
+        || name.ends_with(".pem")
+        || name.ends_with(".key")
+        || name.ends_with(".p12")
+}
+
+fn is_large_binary(path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    if metadata.len() < LARGE_BINARY_BYTES {
+        return false;
+    }
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut head = Vec::with_capacity(8192);
+    if file.take(8192).read_to_end(&mut head).is_err() {
+        return false;
+    }
+    head.contains(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn write(root: &Path, path: &str, contents: &[u8]) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        fs::write(path, contents).expect("write");
+    }
+
+    #[test]
+    fn scan_detects_stack_entrypoints_and_risky_files() {
+        let repo = tempfile::tempdir().expect("tempdir");
+        let root = repo.path();
+        write(root, "Cargo.toml", b"[package]\n");
+        write(root, "src/main.rs", b"fn main() {}\n");
+        write(root, "src/bin/tool.rs", b"fn main() {}\n");
+        write(root, "web/package.json", b"{}\n");
+        write(root, "web/app.ts", b"export {};\n");
+        write(root, "node_modules/dep/index.js", b"\n");
+        write(root, ".env", b"TOKEN=1\n");
+        write(root, ".env.example", b"TOKEN=\n");
+        let mut blob = vec![0u8; LARGE_BINARY_BYTES as usize];
+        blob[10] = 1;
+        write(root, "assets/model.bin", &blob);
+        write(root, "a/b/c/deep.py", b"\n");
+
+        let limits = ScanLimits {
+            max_depth: 3,
+            ..ScanLimits::default()
+        };
+        let scan = scan_repository(root, &limits, &ScanFilters::default());
+        assert_eq!(scan.detected_stack, vec!["node", "rust", "typescript"]);
+        assert_eq!(
+            scan.entrypoints,
+            vec!["Cargo.toml", "src/bin/tool.rs", "src/main.rs"]
+        );
+        assert_eq!(
+            scan.risk_flags,
+            vec![
+                "large_binary:assets/model.bin",
+                "scan_truncated",
+                "secrets_file:.env",
+            ]
+        );
+        assert_eq!(scan.languages.get("rust"), Some(&2));
+        assert!(scan.truncated);
+    }
+
+    #[test]
+    fn parallel_scan_finds_entrypoints_in_a_stable_order() {
+        let repo = tempfile::tempdir().expect("tempdir");
+        let root = repo.path();
+        let mut expected = Vec::new();
+        for package in (0..40).rev() {
+            write(
+                root,
+                &format!("pkg{package:02}/src/main.rs"),
+                b"fn main() {}\n",
+            );
+            write(
+                root,
+                &format!("pkg{package:02}/src/bin/cli.rs"),
+                b"fn main() {}\n",
+            );
+            for module in 0..20 {
+                write(root, &format!("pkg{package:02}/src/m{module:02}.rs"), b"\n");
+            }
+            expected.push(format!("pkg{package:02}/src/bin/cli.rs"));
+            expected.push(format!("pkg{package:02}/src/main.rs"));
+        }
+        write(root, "target/debug/build/main.rs", b"\n");
+        write(root, "node_modules/dep/main.py", b"\n");
+        write(root, ".git/hooks/main.py", b"\n");
+        expected.sort();
+
+        let first = scan_repository(root, &ScanLimits::default(), &ScanFilters::default());
+        assert_eq!(first.entrypoints, expected);
+        assert_eq!(first.files_scanned, 40 * 22);
+        assert_eq!(first.languages.get("rust"), Some(&(40 * 22)));
+        assert_eq!(first.detected_stack, vec!["rust"]);
+        assert!(!first.truncated);
+        for _ in 0..3 {
+            let again = scan_repository(root, &ScanLimits::default(), &ScanFilters::default());
+            assert_eq!(again, first);
+        }
+    }
+
+    #[test]
+    fn scan_filters_keep_included_paths_and_drop_excluded_ones() {
+        let repo = tempfile::tempdir().expect("tempdir");
+        let root = repo.path();
+        write(root, "Cargo.toml", b"[package]\n");
+        write(root, "src/main.rs", b"fn main() {}\n");
+        write(root, "src/gen/schema.generated.rs", b"\n");
+        write(root, "vendor/lib/index.js", b"\n");
+        write(root, "web/app.ts", b"export {};\n");
+
+        let filters = ScanFilters {
+            include: vec!["src/**".to_string(), "vendor".to_string()],
+            exclude: vec!["vendor/lib".to_string(), "*.generated.rs".to_string()],
+        };
+        let scan = scan_repository(root, &ScanLimits::default(), &filters);
+        assert_eq!(scan.detected_stack, vec!["rust"]);
+        assert_eq!(scan.entrypoints, vec!["src/main.rs"]);
+        assert_eq!(scan.files_scanned, 1);
+
+        assert!(filters.allows("src/main.rs"));
+        assert!(!filters.allows("web/app.ts"));
+        assert!(!filters.allows("src/gen/schema.generated.rs"));
+    }
+}
@@ -0,0 +1,208 @@
+use std::path::Path;
+
+/// Test command run by the `run_tests` tool, picked from the manifests at the
+/// repository root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestRunner {
+    Cargo,
+    Npm,
+}
+
+impl TestRunner {
+    /// `cargo test` for a `Cargo.toml`, otherwise `npm test` for a
+    /// `package.json`; `None` when the root has neither. Subdirectories are not
+    /// searched, so a workspace with its manifest below the root has no runner.
+    pub fn detect(cwd: &Path) -> Option<Self> {
+        if cwd.join("Cargo.toml").is_file() {
+            Some(Self::Cargo)
+        } else if cwd.join("package.json").is_file() {
+            Some(Self::Npm)
+        } else {
+            None
+        }
+    }
+
+    pub fn program(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Npm => "npm",
+        }
+    }
+
+    pub fn args(self) -> &'static [&'static str] {
+        &["test"]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo test",
+            Self::Npm => "npm test",
+        }
+    }
+
+    /// Turns the combined output and exit status of the test command into
+    /// verify checks.
+    pub fn report(self, output: &str, success: bool) -> TestReport {
+        match self {
+            Self::Cargo => parse_cargo_test_output(output, success),
+            Self::Npm => parse_npm_test_output(output, success),
+        }
+    }
+}
+
+/// Verify checks derived from one test run. The first check summarizes the
+/// counts; failing tests follow by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestReport {
+    pub checks: Vec<String>,
+    pub passing: bool,
+}
+
+/// Sums every `test result:` line of `cargo test` output, one per test binary,
+/// and lists the tests reported as `FAILED`.
+pub fn parse_cargo_test_output(output: &str, success: bool) -> TestReport {
+    let mut totals: Option<[u64; 3]> = None;
+    let mut failed_tests = Vec::new();
+    for line in output.lines().map(str::trim) {
+        if let Some(summary) = line.strip_prefix("test result: ") {
+            let counts = totals.get_or_insert([0; 3]);
+            for part in summary.split(';') {
+                let mut words = part.split_whitespace().rev();
+                let (Some(kind), Some(count)) = (words.next(), words.next()) else {
+                    continue;
+                };
+                let Ok(count) = count.parse::<u64>() else {
+                    continue;
+                };
+                match kind {
+                    "passed" => counts[0] += count,
+                    "failed" => counts[1] += count,
+                    "ignored" => counts[2] += count,
+                    _ => {}
+                }
+            }
+        } else if let Some(name) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.strip_suffix(" ... FAILED"))
+        {
+            failed_tests.push(name.to_string());
+        }
+    }
+
+    let Some([passed, failed, ignored]) = totals else {
+        return TestReport {
+            checks: vec!["cargo test: no test results (see logs)".to_string()],
+            passing: false,
+        };
+    };
+    let mut checks = vec![format!(
+        "cargo test: {passed} passed, {failed} failed, {ignored} ignored"
+    )];
+    checks.extend(failed_tests);
+    TestReport {
+        checks,
+        passing: success && failed == 0,
+    }
+}
+
+/// npm leaves the output format to the test framework, so the exit status is
+/// the verdict. A Jest-style `Tests:` summary is used as the check name when
+/// present.
+pub fn parse_npm_test_output(output: &str, success: bool) -> TestReport {
+    let summary = output
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find_map(|line| line.strip_prefix("Tests:"))
+        .map(str::trim);
+    let check = match summary {
+        Some(summary) => format!("npm test: {summary}"),
+        None => "npm test".to_string(),
+    };
+    TestReport {
+        checks: vec![check],
+        passing: success,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn cargo_summary_lines_become_checks() {
+        let output = "\
+running 3 tests
+test parser::tests::empty ... ok
+test parser::tests::nested ... FAILED
+test parser::tests::slow ... ignored
+
+failures:
+    parser::tests::nested
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.01s
+
+running 2 tests
+test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.00s
+";
+        assert_eq!(
+            parse_cargo_test_output(output, false),
+            TestReport {
+                checks: vec![
+                    "cargo test: 3 passed, 1 failed, 1 ignored".to_string(),
+                    "parser::tests::nested".to_string(),
+                ],
+                passing: false,
+            }
+        );
+
+        let passing = parse_cargo_test_output(
+            "test result: ok. 12 passed; 0 failed; 2 ignored; 0 measured; 0 filtered out; finished in 0.40s\n",
+            true,
+        );
+        assert_eq!(
+            passing.checks,
+            vec!["cargo test: 12 passed, 0 failed, 2 ignored"]
+        );
+        assert!(passing.passing);
+    }
+
+    #[test]
+    fn cargo_output_without_a_summary_fails() {
+        let report = parse_cargo_test_output("error[E0425]: cannot find value `x`\n", false);
+        assert_eq!(
+            report.checks,
+            vec!["cargo test: no test results (see logs)"]
+        );
+        assert!(!report.passing);
+    }
+
+    #[test]
+    fn npm_uses_the_exit_status_and_a_jest_summary() {
+        let report = parse_npm_test_output(
+            "PASS src/app.test.js\nTests:       1 failed, 5 passed, 6 total\n",
+            false,
+        );
+        assert_eq!(report.checks, vec!["npm test: 1 failed, 5 passed, 6 total"]);
+        assert!(!report.passing);
+        assert_eq!(
+            parse_npm_test_output("ok\n", true),
+            TestReport {
+                checks: vec!["npm test".to_string()],
+                passing: true,
+            }
+        );
+    }
+
+    #[test]
+    fn runner_follows_the_root_manifest() {
+        let repo = tempfile::tempdir().expect("tempdir");
+        assert_eq!(TestRunner::detect(repo.path()), None);
+        std::fs::write(repo.path().join("package.json"), "{}").expect("write");
+        assert_eq!(TestRunner::detect(repo.path()), Some(TestRunner::Npm));
+        std::fs::write(repo.path().join("Cargo.toml"), "[package]\n").expect("write");
+        assert_eq!(TestRunner::detect(repo.path()), Some(TestRunner::Cargo));
+    }
+}